@@ -1,13 +1,14 @@
 //! Transaction application logic
 //!
-//! Handles filtering EOA→EOA transfers and applying them to the state store.
+//! Handles filtering EOA→EOA transfers and applying them to the block's write batch.
 //! Updates balances and nonces for watched addresses.
 
+use crate::batch::BlockWriteBatch;
 use crate::cache::ContractCache;
-use crate::fee::{calculate_effective_gas_price, calculate_fee};
+use crate::config::ChainProfile;
+use crate::fee::calculate_fee_breakdown;
 use crate::rpc::RpcClient;
-use crate::records::BlockDelta;
-use crate::store::StateStore;
+use crate::records::{BlockDelta, TxDirection, TxRecord, TxSource};
 use crate::types::{Block, Receipt, Transaction};
 use alloy_primitives::{Address, U256};
 use anyhow::{Context, Result};
@@ -51,7 +52,7 @@ pub async fn check_receiver_is_eoa(
     Ok(is_eoa)
 }
 
-/// Apply a transaction to the state store.
+/// Apply a transaction to the block's write batch.
 ///
 /// Updates balances and nonces for watched addresses.
 /// Only processes transactions that affect addresses in the watchlist.
@@ -59,14 +60,21 @@ pub async fn check_receiver_is_eoa(
 /// For watched senders: Always processes to deduct fees and update nonce.
 /// For watched receivers: Only processes EOA→EOA transfers (value > 0, no input data).
 ///
-/// Also accumulates deltas in the provided accumulator for per-block tracking.
+/// With `ChainProfile::OpStack`, senders also pay the L1 data fee, and the
+/// `mint` of a deposit transaction is credited to the sender even if the
+/// deposit reverts.
+///
+/// Also accumulates deltas in the provided accumulator for per-block tracking,
+/// and writes a `TxRecord` for every watched address whose balance it touches.
 pub async fn apply_transaction(
-    store: &dyn StateStore,
+    batch: &mut BlockWriteBatch<'_>,
     _rpc: &RpcClient,
     _cache: &mut ContractCache,
     tx: &Transaction,
+    tx_index: u32,
     receipt: &Receipt,
     block: &Block,
+    profile: ChainProfile,
     watchlist: &HashSet<Address>,
     delta_accumulator: &mut HashMap<Address, BlockDelta>,
 ) -> Result<()> {
@@ -76,15 +84,26 @@ pub async fn apply_transaction(
     let tx_succeeded = receipt.is_success();
     let is_simple_transfer = is_eoa_to_eoa_transfer(tx);
 
-    // Calculate effective gas price and fee
-    let effective_gas_price = calculate_effective_gas_price(tx, receipt, block)
-        .context("Failed to calculate effective gas price")?;
-    let fee = calculate_fee(receipt.gas_used, effective_gas_price);
+    // Calculate effective gas price and fees (execution gas + EIP-4844 blob gas + L1 data fee)
+    let fees = calculate_fee_breakdown(tx, receipt, block, profile)
+        .context("Failed to calculate transaction fee")?;
+    let effective_gas_price = fees.effective_gas_price;
+    let fee = fees.execution_fee;
+    let blob_fee = fees.blob_fee;
+    let l1_fee = fees.l1_fee;
+    // Blob and L1 data fees are charged whether or not the transaction succeeds
+    let unconditional_fees = blob_fee.saturating_add(l1_fee);
+    // OP Stack deposits mint value to the sender before execution; it persists on failure
+    let minted = if profile == ChainProfile::OpStack && tx.is_deposit() {
+        tx.mint.unwrap_or(U256::ZERO)
+    } else {
+        U256::ZERO
+    };
 
     // Update sender if in watchlist (ALWAYS process sender transactions)
     if watchlist.contains(&sender) {
         // Get current account state
-        let mut account = store
+        let mut account = batch
             .get_account(sender)
             .context("Failed to get sender account")?
             .unwrap_or_else(|| {
@@ -100,23 +119,27 @@ pub async fn apply_transaction(
         let balance_before = account.balance;
         let nonce_before = account.nonce;
 
-        // Calculate changes for delta tracking
+        // Credit minted value first (zero outside OP Stack deposits)
+        account.balance = account.balance.saturating_add(minted);
+
+        // Calculate changes for delta tracking.
         let (delta_minus, sent_value, fee_paid, failed_fee) = if tx_succeeded {
-            // Success: sender pays value + fee
-            let total_deducted = value.saturating_add(fee);
+            // Success: sender pays value + fee + blob/L1 fees
+            let total_deducted = value.saturating_add(fee).saturating_add(unconditional_fees);
             account.balance = account.balance.saturating_sub(total_deducted);
             (total_deducted, value, fee, U256::ZERO)
         } else {
-            // Failure: sender only pays fee
-            account.balance = account.balance.saturating_sub(fee);
-            (fee, U256::ZERO, U256::ZERO, fee)
+            // Failure: sender only pays fee + blob/L1 fees
+            let total_deducted = fee.saturating_add(unconditional_fees);
+            account.balance = account.balance.saturating_sub(total_deducted);
+            (total_deducted, U256::ZERO, U256::ZERO, fee)
         };
 
         // Update nonce (always increments, even on failure)
         account.nonce += 1;
 
         // Save updated account
-        store
+        batch
             .put_account(sender, &account)
             .context("Failed to save sender account")?;
 
@@ -124,16 +147,42 @@ pub async fn apply_transaction(
         let delta = delta_accumulator
             .entry(sender)
             .or_insert_with(|| crate::records::BlockDelta::new(block.number));
+        delta.delta_plus = delta.delta_plus.saturating_add(minted);
+        delta.minted_value = delta.minted_value.saturating_add(minted);
         delta.delta_minus = delta.delta_minus.saturating_add(delta_minus);
         delta.sent_value = delta.sent_value.saturating_add(sent_value);
         delta.fee_paid = delta.fee_paid.saturating_add(fee_paid);
         delta.failed_fee = delta.failed_fee.saturating_add(failed_fee);
+        delta.blob_fee = delta.blob_fee.saturating_add(blob_fee);
+        delta.l1_fee = delta.l1_fee.saturating_add(l1_fee);
         delta.nonce_delta += 1;
         delta.tx_count += 1;
 
+        let direction = if receiver == Some(sender) {
+            TxDirection::SelfTransfer
+        } else {
+            TxDirection::Out
+        };
+        batch
+            .put_tx_record(
+                sender,
+                &TxRecord {
+                    block: block.number,
+                    tx_index,
+                    tx_hash: Some(tx.hash),
+                    counterparty: receiver,
+                    direction,
+                    value: sent_value,
+                    fee: delta_minus.saturating_sub(sent_value),
+                    success: tx_succeeded,
+                    source: TxSource::TopLevel,
+                },
+            )
+            .context("Failed to save sender tx record")?;
+
         info!(
-            "TX {:?}: sender {:?} balance {} -> {} (value={}, fee={}, gas_used={}, egp={}), nonce {} -> {}",
-            tx.hash, sender, balance_before, account.balance, value, fee, receipt.gas_used, effective_gas_price, nonce_before, account.nonce
+            "TX {:?}: sender {:?} balance {} -> {} (value={}, fee={}, blob_fee={}, l1_fee={}, minted={}, gas_used={}, egp={}), nonce {} -> {}",
+            tx.hash, sender, balance_before, account.balance, value, fee, blob_fee, l1_fee, minted, receipt.gas_used, effective_gas_price, nonce_before, account.nonce
         );
     }
 
@@ -142,7 +191,7 @@ pub async fn apply_transaction(
     if let Some(recv) = receiver {
         if watchlist.contains(&recv) && tx_succeeded && is_simple_transfer && value > U256::ZERO {
             // Get current account state
-            let mut account = store
+            let mut account = batch
                 .get_account(recv)
                 .context("Failed to get receiver account")?
                 .unwrap_or_else(|| {
@@ -159,7 +208,7 @@ pub async fn apply_transaction(
             account.balance = account.balance.saturating_add(value);
 
             // Save updated account
-            store
+            batch
                 .put_account(recv, &account)
                 .context("Failed to save receiver account")?;
 
@@ -171,6 +220,26 @@ pub async fn apply_transaction(
             delta.received_value = delta.received_value.saturating_add(value);
             delta.tx_count += 1;
 
+            // A watched self-transfer is already recorded on the sender side
+            if recv != sender || !watchlist.contains(&sender) {
+                batch
+                    .put_tx_record(
+                        recv,
+                        &TxRecord {
+                            block: block.number,
+                            tx_index,
+                            tx_hash: Some(tx.hash),
+                            counterparty: Some(sender),
+                            direction: TxDirection::In,
+                            value,
+                            fee: U256::ZERO,
+                            success: true,
+                            source: TxSource::TopLevel,
+                        },
+                    )
+                    .context("Failed to save receiver tx record")?;
+            }
+
             info!(
                 "TX {:?}: receiver {:?} balance {} -> {} (value={})",
                 tx.hash, recv, balance_before, account.balance, value
@@ -193,7 +262,7 @@ pub async fn apply_transaction(
 /// Contracts are *not* debited here – tracking contract balances is out of
 /// scope for the active state store.
 pub fn apply_internal_credit(
-    store: &dyn StateStore,
+    batch: &mut BlockWriteBatch<'_>,
     addr: Address,
     value: U256,
     block_number: u64,
@@ -204,7 +273,7 @@ pub fn apply_internal_credit(
     }
 
     // Load or create the receiver account.
-    let mut account = store
+    let mut account = batch
         .get_account(addr)
         .context("Failed to get receiver account for internal credit")?
         .unwrap_or_else(|| crate::records::AccountRecord {
@@ -216,7 +285,7 @@ pub fn apply_internal_credit(
     let balance_before = account.balance;
     account.balance = account.balance.saturating_add(value);
 
-    store
+    batch
         .put_account(addr, &account)
         .context("Failed to save receiver account for internal credit")?;
 
@@ -236,11 +305,111 @@ pub fn apply_internal_credit(
     Ok(())
 }
 
+/// Credit `value` to a watched account's balance without touching its nonce.
+///
+/// Returns the balance before and after the credit.
+fn credit_balance(
+    batch: &mut BlockWriteBatch<'_>,
+    addr: Address,
+    value: U256,
+) -> Result<(U256, U256)> {
+    let mut account = batch
+        .get_account(addr)
+        .context("Failed to get account for credit")?
+        .unwrap_or(crate::records::AccountRecord {
+            nonce: 0,
+            balance: U256::ZERO,
+            code_hash: alloy_primitives::B256::ZERO,
+        });
+
+    let balance_before = account.balance;
+    account.balance = account.balance.saturating_add(value);
+
+    batch
+        .put_account(addr, &account)
+        .context("Failed to save account for credit")?;
+
+    Ok((balance_before, account.balance))
+}
+
+/// Apply a validator withdrawal (EIP-4895) credited to a watched address.
+///
+/// Withdrawals are system-level balance increases, not transactions:
+/// - Increases the receiver's balance by `value`
+/// - Does **not** change the nonce or `tx_count`
+/// - Updates the per-block `BlockDelta` accumulator: `delta_plus` and
+///   `withdrawal_value` are incremented
+pub fn apply_withdrawal_credit(
+    batch: &mut BlockWriteBatch<'_>,
+    addr: Address,
+    value: U256,
+    block_number: u64,
+    delta_accumulator: &mut HashMap<Address, BlockDelta>,
+) -> Result<()> {
+    if value == U256::ZERO {
+        return Ok(()); // Nothing to do
+    }
+
+    let (balance_before, balance_after) =
+        credit_balance(batch, addr, value).context("Failed to apply withdrawal")?;
+
+    let delta = delta_accumulator
+        .entry(addr)
+        .or_insert_with(|| BlockDelta::new(block_number));
+    delta.delta_plus = delta.delta_plus.saturating_add(value);
+    delta.withdrawal_value = delta.withdrawal_value.saturating_add(value);
+
+    info!(
+        "Withdrawal: addr {:?} balance {} -> {} (value={}) at block {}",
+        addr, balance_before, balance_after, value, block_number
+    );
+
+    Ok(())
+}
+
+/// Apply priority-fee income earned by a watched block fee recipient.
+///
+/// `value` is the sum of `(effective_gas_price - base_fee) * gas_used` over
+/// every transaction in the block (failed transactions pay priority fees too).
+/// Like withdrawals, this does not change the nonce or `tx_count`; it
+/// increments `delta_plus` and `priority_fee_income`.
+///
+/// Direct MEV payments to the fee recipient are ordinary value transfers and
+/// are picked up by `apply_transaction` / internal transfer tracing. Static
+/// block and uncle rewards (pre-Merge only) are not credited.
+pub fn apply_priority_fee_credit(
+    batch: &mut BlockWriteBatch<'_>,
+    addr: Address,
+    value: U256,
+    block_number: u64,
+    delta_accumulator: &mut HashMap<Address, BlockDelta>,
+) -> Result<()> {
+    if value == U256::ZERO {
+        return Ok(()); // Nothing to do
+    }
+
+    let (balance_before, balance_after) =
+        credit_balance(batch, addr, value).context("Failed to apply priority fee income")?;
+
+    let delta = delta_accumulator
+        .entry(addr)
+        .or_insert_with(|| BlockDelta::new(block_number));
+    delta.delta_plus = delta.delta_plus.saturating_add(value);
+    delta.priority_fee_income = delta.priority_fee_income.saturating_add(value);
+
+    info!(
+        "Priority fees: fee recipient {:?} balance {} -> {} (value={}) at block {}",
+        addr, balance_before, balance_after, value, block_number
+    );
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::records::AccountRecord;
-    use crate::store::RocksStateStore;
+    use crate::store::{RocksStateStore, StateStore};
     use alloy_primitives::{address, b256};
     use tempfile::TempDir;
 
@@ -252,6 +421,7 @@ mod tests {
     ) -> Transaction {
         Transaction {
             hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
+            tx_type: crate::types::TxType::Legacy,
             from,
             to,
             value,
@@ -261,6 +431,7 @@ mod tests {
             gas: U256::from(21000),
             input,
             nonce: 0,
+            mint: None,
         }
     }
 
@@ -269,6 +440,11 @@ mod tests {
             status,
             gas_used,
             effective_gas_price: None,
+            logs: vec![],
+            blob_gas_used: None,
+            blob_gas_price: None,
+            l1_fee: None,
+            transaction_hash: None,
         }
     }
 
@@ -276,8 +452,11 @@ mod tests {
         Block {
             number: 12345,
             hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
+            parent_hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
             base_fee_per_gas: None,
+            miner: Address::ZERO,
             transactions: vec![],
+            withdrawals: vec![],
         }
     }
 
@@ -303,6 +482,155 @@ mod tests {
         assert!(!is_eoa_to_eoa_transfer(&tx4));
     }
 
+    #[test]
+    fn test_apply_withdrawal_credit() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let addr = address!("742d35Cc6634C0532925a3b844Bc9e7595f0bEb0");
+        store
+            .put_account(
+                addr,
+                &AccountRecord {
+                    nonce: 5,
+                    balance: U256::from(1000u64),
+                    code_hash: alloy_primitives::B256::ZERO,
+                },
+            )
+            .unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 12345);
+        let mut deltas = HashMap::new();
+        apply_withdrawal_credit(&mut batch, addr, U256::from(500u64), 12345, &mut deltas).unwrap();
+
+        let account = batch.get_account(addr).unwrap().unwrap();
+        assert_eq!(account.balance, U256::from(1500u64));
+        assert_eq!(account.nonce, 5); // Withdrawals never touch the nonce
+
+        let delta = &deltas[&addr];
+        assert_eq!(delta.delta_plus, U256::from(500u64));
+        assert_eq!(delta.withdrawal_value, U256::from(500u64));
+        assert_eq!(delta.received_value, U256::ZERO);
+        assert_eq!(delta.tx_count, 0);
+        assert!(delta.has_changes());
+    }
+
+    #[tokio::test]
+    async fn test_op_stack_deposit_mints_and_l1_fee_is_charged() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        // apply_transaction never calls the RPC client; it only needs a handle
+        let rpc = RpcClient::new("http://127.0.0.1:0".to_string());
+        let mut cache = ContractCache::new();
+        let sender = address!("742d35Cc6634C0532925a3b844Bc9e7595f0bEb0");
+        let receiver = address!("0000000000000000000000000000000000000001");
+        let watchlist: HashSet<Address> = [sender].into_iter().collect();
+        let block = create_test_block();
+
+        let mut batch = BlockWriteBatch::new(&store, block.number);
+        let mut deltas = HashMap::new();
+
+        // Deposit: mints 1000 to the sender, sends 300, pays no fee (even with l1Fee set)
+        let deposit = Transaction {
+            tx_type: crate::types::TxType::Deposit,
+            mint: Some(U256::from(1000u64)),
+            ..create_test_tx(sender, Some(receiver), U256::from(300u64), vec![])
+        };
+        let receipt = Receipt {
+            l1_fee: Some(U256::from(7u64)),
+            ..create_test_receipt(1, U256::from(21000))
+        };
+        apply_transaction(
+            &mut batch, &rpc, &mut cache, &deposit, 0, &receipt, &block,
+            ChainProfile::OpStack, &watchlist, &mut deltas,
+        )
+        .await
+        .unwrap();
+        assert_eq!(batch.get_account(sender).unwrap().unwrap().balance, U256::from(700u64));
+
+        // Regular L2 transaction: pays execution fee (zero gas price here) + l1Fee
+        let tx = create_test_tx(sender, Some(receiver), U256::ZERO, vec![0x01]);
+        let receipt = Receipt {
+            l1_fee: Some(U256::from(7u64)),
+            effective_gas_price: Some(U256::from(0u64)),
+            ..create_test_receipt(1, U256::from(21000))
+        };
+        apply_transaction(
+            &mut batch, &rpc, &mut cache, &tx, 1, &receipt, &block,
+            ChainProfile::OpStack, &watchlist, &mut deltas,
+        )
+        .await
+        .unwrap();
+
+        let account = batch.get_account(sender).unwrap().unwrap();
+        assert_eq!(account.balance, U256::from(693u64));
+        assert_eq!(account.nonce, 2);
+
+        let delta = &deltas[&sender];
+        assert_eq!(delta.minted_value, U256::from(1000u64));
+        assert_eq!(delta.delta_plus, U256::from(1000u64));
+        assert_eq!(delta.l1_fee, U256::from(7u64));
+        assert_eq!(delta.delta_minus, U256::from(307u64));
+    }
+
+    #[tokio::test]
+    async fn test_apply_transaction_writes_tx_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let rpc = RpcClient::new("http://127.0.0.1:0".to_string());
+        let mut cache = ContractCache::new();
+        let sender = address!("742d35Cc6634C0532925a3b844Bc9e7595f0bEb0");
+        let receiver = address!("0000000000000000000000000000000000000001");
+        let watchlist: HashSet<Address> = [sender, receiver].into_iter().collect();
+        for addr in [sender, receiver] {
+            store
+                .put_account(
+                    addr,
+                    &AccountRecord {
+                        nonce: 0,
+                        balance: U256::from(10u64).pow(U256::from(18u64)),
+                        code_hash: alloy_primitives::B256::ZERO,
+                    },
+                )
+                .unwrap();
+        }
+        let block = create_test_block();
+        let mut batch = BlockWriteBatch::new(&store, block.number);
+        let mut deltas = HashMap::new();
+
+        // Successful transfer between two watched EOAs, then a failed one
+        let tx = create_test_tx(sender, Some(receiver), U256::from(1000u64), vec![]);
+        for (tx_index, status) in [(3, 1), (4, 0)] {
+            apply_transaction(
+                &mut batch, &rpc, &mut cache, &tx, tx_index, &create_test_receipt(status, U256::from(21000)),
+                &block, ChainProfile::Ethereum, &watchlist, &mut deltas,
+            )
+            .await
+            .unwrap();
+        }
+        batch.commit().unwrap();
+
+        let fee = U256::from(21000u64 * 20_000_000_000u64);
+        let sent = store.get_tx_records_in_range(sender, 0, 20000).unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].tx_index, 3);
+        assert_eq!(sent[0].direction, TxDirection::Out);
+        assert_eq!(sent[0].counterparty, Some(receiver));
+        assert_eq!(sent[0].value, U256::from(1000u64));
+        assert_eq!(sent[0].fee, fee);
+        assert!(sent[0].success);
+        assert_eq!(sent[1].value, U256::ZERO);
+        assert_eq!(sent[1].fee, fee);
+        assert!(!sent[1].success);
+
+        // Only the successful transfer credited the receiver
+        let received = store.get_tx_records_in_range(receiver, 0, 20000).unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].direction, TxDirection::In);
+        assert_eq!(received[0].counterparty, Some(sender));
+        assert_eq!(received[0].fee, U256::ZERO);
+        assert_eq!(received[0].source, TxSource::TopLevel);
+    }
+
     // Note: Integration tests for apply_transaction would require a mock RPC client
     // For now, we test the filter logic which is the most critical part
 }
@@ -0,0 +1,428 @@
+//! Historical backfill from an archive node
+//!
+//! `WatchMeta.start_block` is the block at which the watcher first saw an
+//! address, and every query before it is clamped away. Backfill extends that
+//! coverage backwards: it anchors the address (or token/owner pair) at a
+//! historical block in a scratch store, replays every block up to the current
+//! `start_block` through the regular `Watcher` pipeline (apply, trace, ERC20
+//! tracker), and then joins the result onto the existing data.
+//!
+//! The join is only accepted if the replayed balance at `start_block` equals
+//! the snapshot already stored there. Deltas, snapshots, transaction history
+//! and the moved `start_block` are then committed to the main store in one
+//! batch amending the head block, so an interrupted or rejected backfill
+//! never changes what queries return.
+
+use crate::batch::BlockWriteBatch;
+use crate::config::ChainProfile;
+use crate::records::{TokenWatchMeta, WatchMeta};
+use crate::rpc::RpcClient;
+use crate::store::{RocksStateStore, StateStore};
+use crate::watcher::Watcher;
+use alloy_primitives::Address;
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::info;
+
+/// What to backfill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackfillTarget {
+    /// ETH balance history of an address.
+    Eth(Address),
+    /// ERC20 balance history of a (token, owner) pair.
+    Erc20 { token: Address, owner: Address },
+}
+
+impl BackfillTarget {
+    /// The watched address (the owner for ERC20 targets).
+    pub fn owner(&self) -> Address {
+        match self {
+            BackfillTarget::Eth(addr) => *addr,
+            BackfillTarget::Erc20 { owner, .. } => *owner,
+        }
+    }
+}
+
+/// Outcome of a successful backfill.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackfillReport {
+    /// New coverage start (the historical anchor block).
+    pub from_block: u64,
+    /// Previous coverage start, where the backfilled range joined existing data.
+    pub join_block: u64,
+    /// Number of deltas copied into the main store.
+    pub deltas_copied: usize,
+}
+
+/// Current coverage start for a target, or an error if it is not tracked.
+fn coverage_start(store: &dyn StateStore, target: BackfillTarget) -> Result<u64> {
+    match target {
+        BackfillTarget::Eth(addr) => store
+            .get_watch_meta(addr)?
+            .map(|m| m.start_block)
+            .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr)),
+        BackfillTarget::Erc20 { token, owner } => store
+            .get_token_watch_meta(token, owner)?
+            .map(|m| m.start_block)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Token {:?} owner {:?} is not being tracked", token, owner)
+            }),
+    }
+}
+
+/// Backfill `target` from historical block `from` up to its current coverage start.
+///
+/// Blocks are replayed into a scratch store at `scratch_path`, which is
+/// removed again afterwards. `rpc` must point at an archive node.
+pub async fn backfill(
+    store: &RocksStateStore,
+    rpc: RpcClient,
+    scratch_path: &Path,
+    target: BackfillTarget,
+    from: u64,
+    chain_profile: ChainProfile,
+) -> Result<BackfillReport> {
+    let join_block = coverage_start(store, target)?;
+    if from >= join_block {
+        anyhow::bail!(
+            "Backfill start block {} must be before the current coverage start {}",
+            from,
+            join_block
+        );
+    }
+
+    // Start from an empty scratch store
+    if scratch_path.exists() {
+        std::fs::remove_dir_all(scratch_path).with_context(|| {
+            format!("Failed to remove stale scratch store at {:?}", scratch_path)
+        })?;
+    }
+    let scratch = RocksStateStore::open(scratch_path)
+        .with_context(|| format!("Failed to open scratch store at {:?}", scratch_path))?;
+
+    info!(
+        "Backfilling {:?} from block {} to {} (scratch store {:?})",
+        target, from, join_block, scratch_path
+    );
+
+    let tokens = match target {
+        BackfillTarget::Eth(_) => Vec::new(),
+        BackfillTarget::Erc20 { token, .. } => vec![token],
+    };
+    let mut watcher = Watcher::new(scratch, rpc).with_chain_profile(chain_profile);
+    watcher
+        .initialize_at_block(vec![target.owner()], tokens, from)
+        .await
+        .with_context(|| format!("Failed to initialize backfill at block {}", from))?;
+    watcher
+        .process_block_range(from + 1, join_block)
+        .await
+        .context("Failed to replay backfill range")?;
+
+    let report = join_backfill(store, watcher.store(), target, from, join_block)?;
+
+    drop(watcher);
+    std::fs::remove_dir_all(scratch_path)
+        .with_context(|| format!("Failed to remove scratch store at {:?}", scratch_path))?;
+
+    info!(
+        "Backfill complete: coverage now starts at block {} ({} deltas copied)",
+        report.from_block, report.deltas_copied
+    );
+    Ok(report)
+}
+
+/// Join replayed history in `scratch` onto `store` and move the coverage start back.
+///
+/// `scratch` must hold the anchor snapshot at `from` and the replayed blocks
+/// `from + 1..=join_block`. Fails without writing anything if the replayed
+/// balance at `join_block` does not match the stored snapshot there.
+pub fn join_backfill(
+    store: &dyn StateStore,
+    scratch: &dyn StateStore,
+    target: BackfillTarget,
+    from: u64,
+    join_block: u64,
+) -> Result<BackfillReport> {
+    let head = store
+        .get_head()?
+        .context("Store has no head block to join backfill onto")?;
+    let mut batch = BlockWriteBatch::amend(store, head)?;
+    let deltas_copied = match target {
+        BackfillTarget::Eth(addr) => {
+            let (_, replayed) = scratch
+                .get_latest_snapshot_at_or_before(addr, join_block)?
+                .context("Backfill produced no snapshot")?;
+            let stored = store.get_snapshot(addr, join_block)?.ok_or_else(|| {
+                anyhow::anyhow!("No stored snapshot at coverage start {}", join_block)
+            })?;
+            if replayed != stored {
+                anyhow::bail!(
+                    "Backfilled balance {} at block {} does not match stored snapshot {}; \
+                    coverage left unchanged",
+                    replayed,
+                    join_block,
+                    stored
+                );
+            }
+
+            let anchor = scratch
+                .get_snapshot(addr, from)?
+                .context("Backfill anchor snapshot missing")?;
+            batch.put_snapshot_at(addr, from, anchor)?;
+
+            let deltas = scratch.get_deltas_in_range(addr, from + 1, join_block)?;
+            for (block, delta) in &deltas {
+                batch.put_delta_at(addr, *block, delta)?;
+                // The snapshot at the join block already exists (and matches)
+                if *block < join_block {
+                    if let Some(balance) = scratch.get_snapshot(addr, *block)? {
+                        batch.put_snapshot_at(addr, *block, balance)?;
+                    }
+                }
+            }
+
+            // Per-transaction history; the batch numbers records that share a transaction
+            for record in scratch.get_tx_records_in_range(addr, from + 1, join_block)? {
+                batch.put_tx_record_at(addr, record.block, &record)?;
+            }
+
+            let mut meta = batch
+                .get_watch_meta(addr)?
+                .unwrap_or_else(|| WatchMeta::new(from));
+            meta.start_block = from;
+            batch.put_watch_meta(addr, &meta)?;
+            deltas.len()
+        }
+        BackfillTarget::Erc20 { token, owner } => {
+            let (_, replayed) = scratch
+                .get_latest_erc20_snapshot_at_or_before(token, owner, join_block)?
+                .context("Backfill produced no ERC20 snapshot")?;
+            let stored = match store.get_latest_erc20_snapshot_at_or_before(token, owner, join_block)? {
+                Some((block, balance)) if block == join_block => balance,
+                _ => anyhow::bail!("No stored ERC20 snapshot at coverage start {}", join_block),
+            };
+            if replayed != stored {
+                anyhow::bail!(
+                    "Backfilled ERC20 balance {} at block {} does not match stored snapshot {}; \
+                    coverage left unchanged",
+                    replayed,
+                    join_block,
+                    stored
+                );
+            }
+
+            let deltas = scratch.get_erc20_deltas_in_range(token, owner, from + 1, join_block)?;
+            let snapshot_blocks = std::iter::once(from)
+                .chain(deltas.iter().map(|(block, _)| *block))
+                .filter(|block| *block < join_block);
+            for block in snapshot_blocks {
+                if let Some((b, balance)) =
+                    scratch.get_latest_erc20_snapshot_at_or_before(token, owner, block)?
+                {
+                    if b == block {
+                        batch.put_erc20_snapshot_at(token, owner, block, balance)?;
+                    }
+                }
+            }
+            for (block, delta) in &deltas {
+                batch.put_erc20_delta_at(token, owner, *block, delta)?;
+            }
+
+            // Keep the rest of the metadata (e.g. the auto-discovered flag)
+            let meta = batch
+                .get_token_watch_meta(token, owner)?
+                .context("Token watch meta disappeared during backfill")?;
+            batch.put_token_watch_meta(
+                token,
+                owner,
+                &TokenWatchMeta {
+                    start_block: from,
+                    ..meta
+                },
+            )?;
+            deltas.len()
+        }
+    };
+    batch.commit().context("Failed to commit backfill join")?;
+
+    Ok(BackfillReport {
+        from_block: from,
+        join_block,
+        deltas_copied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{BlockDelta, Erc20Delta};
+    use alloy_primitives::U256;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (RocksStateStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        (store, temp_dir)
+    }
+
+    /// Main store watching `addr` from block 200 with 1000 wei.
+    fn main_store(addr: Address) -> (RocksStateStore, TempDir) {
+        let (store, temp_dir) = create_test_store();
+        store.put_watch_meta(addr, &WatchMeta::new(200)).unwrap();
+        store.put_snapshot(addr, 200, U256::from(1000u64)).unwrap();
+        store.set_head(205).unwrap();
+        (store, temp_dir)
+    }
+
+    /// Scratch store replayed from block 100 (anchor 500 wei) to block 200.
+    fn scratch_store(addr: Address, final_credit: u64) -> (RocksStateStore, TempDir) {
+        let (scratch, temp_dir) = create_test_store();
+        scratch.put_snapshot(addr, 100, U256::from(500u64)).unwrap();
+        for (block, credit, balance) in [(150, 300, 800), (200, final_credit, 800 + final_credit)] {
+            let mut delta = BlockDelta::new(block);
+            delta.delta_plus = U256::from(credit);
+            scratch.put_delta(addr, block, &delta).unwrap();
+            scratch.put_snapshot(addr, block, U256::from(balance)).unwrap();
+        }
+        (scratch, temp_dir)
+    }
+
+    #[test]
+    fn test_join_backfill_extends_coverage() {
+        let addr = Address::repeat_byte(0x01);
+        let (store, _main_dir) = main_store(addr);
+        let (scratch, _scratch_dir) = scratch_store(addr, 200);
+
+        let report = join_backfill(&store, &scratch, BackfillTarget::Eth(addr), 100, 200).unwrap();
+        assert_eq!(report.deltas_copied, 2);
+        assert_eq!(store.get_watch_meta(addr).unwrap().unwrap().start_block, 100);
+
+        let balances = store.get_balances_in_range(addr, 100, 201).unwrap();
+        assert_eq!(balances[0], (100, U256::from(500u64)));
+        assert_eq!(balances[50], (150, U256::from(800u64)));
+        assert_eq!(balances[100], (200, U256::from(1000u64)));
+        assert_eq!(balances[101], (201, U256::from(1000u64)));
+    }
+
+    #[test]
+    fn test_join_backfill_rejects_mismatch() {
+        let addr = Address::repeat_byte(0x01);
+        let (store, _main_dir) = main_store(addr);
+        // Replay ends at 900 wei instead of the stored 1000
+        let (scratch, _scratch_dir) = scratch_store(addr, 100);
+
+        assert!(join_backfill(&store, &scratch, BackfillTarget::Eth(addr), 100, 200).is_err());
+        assert_eq!(store.get_watch_meta(addr).unwrap().unwrap().start_block, 200);
+        assert!(store.get_deltas_in_range(addr, 100, 200).unwrap().is_empty());
+    }
+
+    /// Main store tracking (token, owner) from block 200 with 1000 units, and the
+    /// scratch store replayed from block 100 (anchor 500 units) to block 200.
+    fn erc20_stores(
+        token: Address,
+        owner: Address,
+        final_credit: u64,
+    ) -> ((RocksStateStore, TempDir), (RocksStateStore, TempDir)) {
+        let (store, main_dir) = main_store(owner);
+        let meta = TokenWatchMeta {
+            auto_discovered: true,
+            ..TokenWatchMeta::new(200)
+        };
+        store.put_token_watch_meta(token, owner, &meta).unwrap();
+        store.put_erc20_snapshot(token, owner, 200, U256::from(1000u64)).unwrap();
+
+        let (scratch, scratch_dir) = create_test_store();
+        scratch.put_erc20_snapshot(token, owner, 100, U256::from(500u64)).unwrap();
+        for (block, credit, balance) in [(150, 300, 800), (200, final_credit, 800 + final_credit)] {
+            let mut delta = Erc20Delta::new(block);
+            delta.delta_plus = U256::from(credit);
+            scratch.put_erc20_delta(token, owner, block, &delta).unwrap();
+            scratch.put_erc20_snapshot(token, owner, block, U256::from(balance)).unwrap();
+        }
+        ((store, main_dir), (scratch, scratch_dir))
+    }
+
+    #[test]
+    fn test_join_backfill_erc20_extends_pair_coverage() {
+        let token = Address::repeat_byte(0x02);
+        let owner = Address::repeat_byte(0x01);
+        let target = BackfillTarget::Erc20 { token, owner };
+        let ((store, _main_dir), (scratch, _scratch_dir)) = erc20_stores(token, owner, 200);
+
+        let report = join_backfill(&store, &scratch, target, 100, 200).unwrap();
+        assert_eq!(report.deltas_copied, 2);
+        // Coverage moved back, keeping the auto-discovered flag
+        assert_eq!(
+            store.get_token_watch_meta(token, owner).unwrap(),
+            Some(TokenWatchMeta {
+                auto_discovered: true,
+                ..TokenWatchMeta::new(100)
+            })
+        );
+        assert_eq!(
+            store.get_latest_erc20_snapshot_at_or_before(token, owner, 120).unwrap(),
+            Some((100, U256::from(500u64)))
+        );
+        assert_eq!(
+            store.get_latest_erc20_snapshot_at_or_before(token, owner, 199).unwrap(),
+            Some((150, U256::from(800u64)))
+        );
+        let deltas = store.get_erc20_deltas_in_range(token, owner, 100, 200).unwrap();
+        assert_eq!(deltas.iter().map(|(block, _)| *block).collect::<Vec<_>>(), vec![150, 200]);
+
+        // The join amended the head block, so rolling the head back reverts all of it
+        let undo = store.get_block_undo(205).unwrap().unwrap();
+        crate::reorg::rollback_block(&store, &undo).unwrap();
+        assert_eq!(
+            store.get_token_watch_meta(token, owner).unwrap().unwrap().start_block,
+            200
+        );
+        assert!(store.get_erc20_deltas_in_range(token, owner, 100, 200).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_erc20_queries_cover_backfilled_range() {
+        let token = Address::repeat_byte(0x02);
+        let owner = Address::repeat_byte(0x01);
+        let target = BackfillTarget::Erc20 { token, owner };
+        let ((store, _main_dir), (scratch, _scratch_dir)) = erc20_stores(token, owner, 200);
+        join_backfill(&store, &scratch, target, 100, 200).unwrap();
+
+        // The owner's ETH coverage still starts at 200; the pair's now starts at 100
+        let balances = store
+            .get_erc20_balances_in_range_with_metadata(token, owner, 100, 200)
+            .unwrap();
+        assert_eq!(balances.effective_start, 100);
+        assert_eq!(balances.watch_start_block, 100);
+        assert!(balances.message.is_none());
+        assert_eq!(balances.data[0], (100, U256::from(500u64)));
+        assert_eq!(balances.data[50], (150, U256::from(800u64)));
+        assert_eq!(balances.data[100], (200, U256::from(1000u64)));
+
+        let deltas = store
+            .get_erc20_deltas_in_range_with_metadata(token, owner, 100, 200)
+            .unwrap();
+        assert_eq!(deltas.effective_start, 100);
+        assert_eq!(deltas.data.len(), 2);
+    }
+
+    #[test]
+    fn test_join_backfill_erc20_rejects_mismatch() {
+        let token = Address::repeat_byte(0x02);
+        let owner = Address::repeat_byte(0x01);
+        let target = BackfillTarget::Erc20 { token, owner };
+        let ((store, _main_dir), (scratch, _scratch_dir)) = erc20_stores(token, owner, 100);
+
+        assert!(join_backfill(&store, &scratch, target, 100, 200).is_err());
+        assert_eq!(
+            store.get_token_watch_meta(token, owner).unwrap().unwrap().start_block,
+            200
+        );
+        assert!(store.get_erc20_deltas_in_range(token, owner, 100, 200).unwrap().is_empty());
+        assert_eq!(
+            store.get_latest_erc20_snapshot_at_or_before(token, owner, 199).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,829 @@
+//! Atomic per-block write batch
+//!
+//! Collects every mutation produced while processing one block (accounts,
+//! deltas, snapshots, transaction history, ERC20 state, block hash and head) and commits them to
+//! the store in a single RocksDB `WriteBatch`. A crash mid-block therefore
+//! leaves the store at the previous head, and the block is simply re-processed
+//! on restart.
+//!
+//! Reads through the batch see its own pending writes first, then fall back
+//! to the store. The value each key had before its first write is journaled
+//! and committed alongside the block as its `BlockUndo` record.
+//!
+//! Writes made between blocks (watchlist additions initialized at the head,
+//! backfilled history joined with the `_at` writers) reopen the head block
+//! with `amend`, so a rollback of the head reverts them.
+
+use crate::config::{FollowMode, TokenStrategy};
+use crate::keys::{
+    encode_account_key, encode_allowance_change_key, encode_allowance_key, encode_block_hash_key,
+    encode_block_undo_key, encode_conversion_key, encode_delta_key, encode_drift_key,
+    encode_erc1155_delta_key, encode_erc1155_snapshot_key, encode_erc1155_watch_meta_key,
+    encode_erc20_delta_key, encode_erc20_snapshot_key, encode_event_key, encode_meta_key,
+    encode_nft_holding_key, encode_nft_transfer_key, encode_snapshot_key, encode_token_metadata_key,
+    encode_token_watch_meta_key, encode_tx_record_key, encode_watch_meta_key,
+    encode_watched_address_key, encode_watched_token_key,
+};
+use crate::records::{
+    decode_u256, encode_u256, AccountRecord, Allowance, BalanceSnapshot, BlockDelta, BlockUndo,
+    Conversion, DriftRecord, Erc1155Delta, Erc1155Snapshot, Erc20Delta, Erc20Snapshot, EventRecord,
+    NftHolding, NftTransfer, RawWrite, TokenMetadata, TokenWatchMeta, TxRecord, WatchMeta,
+};
+use crate::store::StateStore;
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// Pending writes for a single block.
+///
+/// Values are encoded exactly as `RocksStateStore` encodes them, so a
+/// committed batch is indistinguishable from individual `put_*` calls.
+pub struct BlockWriteBatch<'a> {
+    store: &'a dyn StateStore,
+    block: u64,
+    /// Pending writes: (column family, key) -> value (None = delete)
+    writes: BTreeMap<(&'static str, Vec<u8>), Option<Vec<u8>>>,
+    /// Values in the store before the first write to each key
+    priors: BTreeMap<(String, Vec<u8>), Option<Vec<u8>>>,
+    /// Writes committed with the batch but left out of its undo record
+    unjournaled: BTreeMap<(&'static str, Vec<u8>), Option<Vec<u8>>>,
+}
+
+impl<'a> BlockWriteBatch<'a> {
+    /// Start a new batch for `block` on top of `store`.
+    pub fn new(store: &'a dyn StateStore, block: u64) -> Self {
+        Self {
+            store,
+            block,
+            writes: BTreeMap::new(),
+            priors: BTreeMap::new(),
+            unjournaled: BTreeMap::new(),
+        }
+    }
+
+    /// Reopen the already committed `block` (usually the head) for more writes.
+    ///
+    /// The block's undo record is extended rather than replaced: keys it
+    /// already journals keep their older prior value.
+    pub fn amend(store: &'a dyn StateStore, block: u64) -> Result<Self> {
+        let mut batch = Self::new(store, block);
+        if let Some(undo) = store.get_block_undo(block)? {
+            for entry in undo.entries {
+                batch.priors.insert((entry.cf, entry.key), entry.value);
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Block number this batch belongs to.
+    pub fn block(&self) -> u64 {
+        self.block
+    }
+
+    /// Underlying store (reads bypass pending writes).
+    pub fn store(&self) -> &'a dyn StateStore {
+        self.store
+    }
+
+    /// Number of distinct keys written so far.
+    pub fn len(&self) -> usize {
+        self.writes.len() + self.unjournaled.len()
+    }
+
+    /// Whether the batch has no pending writes.
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty() && self.unjournaled.is_empty()
+    }
+
+    /// Read a raw value, preferring pending writes over the store.
+    fn read(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let pending_key = (cf, key.to_vec());
+        match self
+            .writes
+            .get(&pending_key)
+            .or_else(|| self.unjournaled.get(&pending_key))
+        {
+            Some(pending) => Ok(pending.clone()),
+            None => self.store.get_raw(cf, key),
+        }
+    }
+
+    /// Record a raw write, journaling the key's prior value on first touch.
+    fn write(&mut self, cf: &'static str, key: Vec<u8>, value: Option<Vec<u8>>) -> Result<()> {
+        let journal_id = (cf.to_string(), key.clone());
+        if !self.priors.contains_key(&journal_id) {
+            let prior = self.store.get_raw(cf, &key)?;
+            self.priors.insert(journal_id, prior);
+        }
+        self.writes.insert((cf, key), value);
+        Ok(())
+    }
+
+    /// Record a raw write that a rollback of this block leaves in place.
+    fn write_unjournaled(&mut self, cf: &'static str, key: Vec<u8>, value: Option<Vec<u8>>) {
+        self.unjournaled.insert((cf, key), value);
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ETH state
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Get an account record, including pending writes.
+    pub fn get_account(&self, addr: Address) -> Result<Option<AccountRecord>> {
+        match self.read("accounts", &encode_account_key(addr))? {
+            Some(bytes) => {
+                let record = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize account record")?;
+                Ok(Some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put an account record.
+    pub fn put_account(&mut self, addr: Address, acc: &AccountRecord) -> Result<()> {
+        let value = postcard::to_allocvec(acc).context("Failed to serialize account record")?;
+        self.write("accounts", encode_account_key(addr), Some(value))
+    }
+
+    /// Put a block delta for an address.
+    pub fn put_delta(&mut self, addr: Address, delta: &BlockDelta) -> Result<()> {
+        self.put_delta_at(addr, self.block, delta)
+    }
+
+    /// Put a block delta for an address at an earlier `block` (backfill).
+    pub fn put_delta_at(&mut self, addr: Address, block: u64, delta: &BlockDelta) -> Result<()> {
+        let value = postcard::to_allocvec(delta).context("Failed to serialize delta")?;
+        self.write("block_deltas", encode_delta_key(addr, block), Some(value))
+    }
+
+    /// Put a balance snapshot for an address.
+    pub fn put_snapshot(&mut self, addr: Address, balance: BalanceSnapshot) -> Result<()> {
+        self.put_snapshot_at(addr, self.block, balance)
+    }
+
+    /// Put a balance snapshot for an address at an earlier `block` (backfill).
+    pub fn put_snapshot_at(
+        &mut self,
+        addr: Address,
+        block: u64,
+        balance: BalanceSnapshot,
+    ) -> Result<()> {
+        let value = encode_u256(balance).to_vec();
+        self.write("balance_snapshots", encode_snapshot_key(addr, block), Some(value))
+    }
+
+    /// Get watch metadata for an address, including pending writes.
+    pub fn get_watch_meta(&self, addr: Address) -> Result<Option<WatchMeta>> {
+        match self.read("watch_meta", &encode_watch_meta_key(addr))? {
+            Some(bytes) => {
+                let meta =
+                    postcard::from_bytes(&bytes).context("Failed to deserialize watch meta")?;
+                Ok(Some(meta))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put watch metadata for an address.
+    pub fn put_watch_meta(&mut self, addr: Address, meta: &WatchMeta) -> Result<()> {
+        let value = postcard::to_allocvec(meta).context("Failed to serialize watch meta")?;
+        self.write("watch_meta", encode_watch_meta_key(addr), Some(value))
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC20 state
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Put an ERC20 delta for (token, owner).
+    pub fn put_erc20_delta(
+        &mut self,
+        token: Address,
+        owner: Address,
+        delta: &Erc20Delta,
+    ) -> Result<()> {
+        self.put_erc20_delta_at(token, owner, self.block, delta)
+    }
+
+    /// Put an ERC20 delta for (token, owner) at an earlier `block` (backfill).
+    pub fn put_erc20_delta_at(
+        &mut self,
+        token: Address,
+        owner: Address,
+        block: u64,
+        delta: &Erc20Delta,
+    ) -> Result<()> {
+        let value = postcard::to_allocvec(delta).context("Failed to serialize ERC20 delta")?;
+        self.write(
+            "erc20_deltas",
+            encode_erc20_delta_key(token, owner, block),
+            Some(value),
+        )
+    }
+
+    /// Get this block's ERC20 delta for (token, owner), including pending writes.
+    pub fn get_erc20_delta(&self, token: Address, owner: Address) -> Result<Option<Erc20Delta>> {
+        match self.read("erc20_deltas", &encode_erc20_delta_key(token, owner, self.block))? {
+            Some(bytes) => {
+                let delta =
+                    postcard::from_bytes(&bytes).context("Failed to deserialize ERC20 delta")?;
+                Ok(Some(delta))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put an ERC20 snapshot for (token, owner).
+    pub fn put_erc20_snapshot(
+        &mut self,
+        token: Address,
+        owner: Address,
+        balance: Erc20Snapshot,
+    ) -> Result<()> {
+        self.put_erc20_snapshot_at(token, owner, self.block, balance)
+    }
+
+    /// Put an ERC20 snapshot for (token, owner) at an earlier `block` (backfill).
+    pub fn put_erc20_snapshot_at(
+        &mut self,
+        token: Address,
+        owner: Address,
+        block: u64,
+        balance: Erc20Snapshot,
+    ) -> Result<()> {
+        let value = encode_u256(balance).to_vec();
+        self.write(
+            "erc20_snapshots",
+            encode_erc20_snapshot_key(token, owner, block),
+            Some(value),
+        )
+    }
+
+    /// Get the current ERC20 balance for (token, owner), including pending writes.
+    pub fn get_erc20_balance(&self, token: Address, owner: Address) -> Result<Option<U256>> {
+        match self.read("erc20_balances", &encode_token_watch_meta_key(token, owner))? {
+            Some(bytes) => {
+                let balance = decode_u256(&bytes).context("Failed to decode ERC20 balance")?;
+                Ok(Some(balance))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put the current ERC20 balance for (token, owner).
+    pub fn put_erc20_balance(&mut self, token: Address, owner: Address, balance: U256) -> Result<()> {
+        let value = encode_u256(balance).to_vec();
+        self.write("erc20_balances", encode_token_watch_meta_key(token, owner), Some(value))
+    }
+
+    /// Get the current share balance of (token, owner), including pending writes.
+    pub fn get_erc20_shares(&self, token: Address, owner: Address) -> Result<Option<U256>> {
+        match self.read("erc20_shares", &encode_token_watch_meta_key(token, owner))? {
+            Some(bytes) => {
+                let shares = decode_u256(&bytes).context("Failed to decode ERC20 shares")?;
+                Ok(Some(shares))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put the current share balance of (token, owner).
+    pub fn put_erc20_shares(&mut self, token: Address, owner: Address, shares: U256) -> Result<()> {
+        let value = encode_u256(shares).to_vec();
+        self.write("erc20_shares", encode_token_watch_meta_key(token, owner), Some(value))
+    }
+
+    /// Get token watch metadata for (token, owner), including pending writes.
+    pub fn get_token_watch_meta(
+        &self,
+        token: Address,
+        owner: Address,
+    ) -> Result<Option<TokenWatchMeta>> {
+        match self.read("erc20_watch_meta", &encode_token_watch_meta_key(token, owner))? {
+            Some(bytes) => {
+                let meta = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize token watch meta")?;
+                Ok(Some(meta))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put token watch metadata for (token, owner).
+    pub fn put_token_watch_meta(
+        &mut self,
+        token: Address,
+        owner: Address,
+        meta: &TokenWatchMeta,
+    ) -> Result<()> {
+        let value = postcard::to_allocvec(meta).context("Failed to serialize token watch meta")?;
+        self.write("erc20_watch_meta", encode_token_watch_meta_key(token, owner), Some(value))
+    }
+
+    /// Get the metadata of a token, including pending writes.
+    pub fn get_token_metadata(&self, token: Address) -> Result<Option<TokenMetadata>> {
+        match self.read("token_metadata", &encode_token_metadata_key(token))? {
+            Some(bytes) => {
+                let metadata = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize token metadata")?;
+                Ok(Some(metadata))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put the metadata of a token.
+    pub fn put_token_metadata(&mut self, token: Address, metadata: &TokenMetadata) -> Result<()> {
+        let value =
+            postcard::to_allocvec(metadata).context("Failed to serialize token metadata")?;
+        self.write("token_metadata", encode_token_metadata_key(token), Some(value))
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC1155 state
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Put an ERC1155 delta for (contract, id, owner).
+    pub fn put_erc1155_delta(
+        &mut self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        delta: &Erc1155Delta,
+    ) -> Result<()> {
+        let value = postcard::to_allocvec(delta).context("Failed to serialize ERC1155 delta")?;
+        self.write(
+            "erc1155_deltas",
+            encode_erc1155_delta_key(contract, id, owner, self.block),
+            Some(value),
+        )
+    }
+
+    /// Put an ERC1155 snapshot for (contract, id, owner).
+    pub fn put_erc1155_snapshot(
+        &mut self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        balance: Erc1155Snapshot,
+    ) -> Result<()> {
+        let value = encode_u256(balance).to_vec();
+        self.write(
+            "erc1155_snapshots",
+            encode_erc1155_snapshot_key(contract, id, owner, self.block),
+            Some(value),
+        )
+    }
+
+    /// Get the current ERC1155 balance for (contract, id, owner), including pending writes.
+    pub fn get_erc1155_balance(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+    ) -> Result<Option<U256>> {
+        match self.read("erc1155_balances", &encode_erc1155_watch_meta_key(contract, id, owner))? {
+            Some(bytes) => {
+                let balance = decode_u256(&bytes).context("Failed to decode ERC1155 balance")?;
+                Ok(Some(balance))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put the current ERC1155 balance for (contract, id, owner).
+    pub fn put_erc1155_balance(
+        &mut self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        balance: U256,
+    ) -> Result<()> {
+        let value = encode_u256(balance).to_vec();
+        self.write(
+            "erc1155_balances",
+            encode_erc1155_watch_meta_key(contract, id, owner),
+            Some(value),
+        )
+    }
+
+    /// Get watch metadata for (contract, id, owner), including pending writes.
+    pub fn get_erc1155_watch_meta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+    ) -> Result<Option<TokenWatchMeta>> {
+        let key = encode_erc1155_watch_meta_key(contract, id, owner);
+        match self.read("erc1155_watch_meta", &key)? {
+            Some(bytes) => {
+                let meta = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize ERC1155 watch meta")?;
+                Ok(Some(meta))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Put watch metadata for (contract, id, owner).
+    pub fn put_erc1155_watch_meta(
+        &mut self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        meta: &TokenWatchMeta,
+    ) -> Result<()> {
+        let value =
+            postcard::to_allocvec(meta).context("Failed to serialize ERC1155 watch meta")?;
+        self.write(
+            "erc1155_watch_meta",
+            encode_erc1155_watch_meta_key(contract, id, owner),
+            Some(value),
+        )
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Per-transaction history
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Put a transaction record for `addr` in this block.
+    ///
+    /// Records of the same address and transaction get increasing sequence numbers.
+    pub fn put_tx_record(&mut self, addr: Address, record: &TxRecord) -> Result<()> {
+        self.put_tx_record_at(addr, self.block, record)
+    }
+
+    /// Put a transaction record for `addr` at an earlier `block` (backfill).
+    ///
+    /// Takes the first sequence number free both in the batch and in the
+    /// store, so records already committed for the block are kept.
+    pub fn put_tx_record_at(&mut self, addr: Address, block: u64, record: &TxRecord) -> Result<()> {
+        let value = postcard::to_allocvec(record).context("Failed to serialize tx record")?;
+        let mut seq = 0u16;
+        loop {
+            let key = encode_tx_record_key(addr, block, record.tx_index, seq);
+            if self.read("tx_history", &key)?.is_none() {
+                return self.write("tx_history", key, Some(value));
+            }
+            seq = seq
+                .checked_add(1)
+                .context("Too many tx records for one transaction")?;
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Decoded events
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Put a decoded event emitted in this block.
+    pub fn put_event_record(&mut self, record: &EventRecord) -> Result<()> {
+        let value = postcard::to_allocvec(record).context("Failed to serialize event record")?;
+        self.write(
+            "events",
+            encode_event_key(self.block, record.log_ordinal),
+            Some(value),
+        )
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC721 ownership
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Add an NFT to `owner`'s current holdings.
+    pub fn put_nft_holding(&mut self, owner: Address, holding: &NftHolding) -> Result<()> {
+        let value = postcard::to_allocvec(holding).context("Failed to serialize NFT holding")?;
+        self.write(
+            "nft_holdings",
+            encode_nft_holding_key(owner, holding.contract, holding.token_id),
+            Some(value),
+        )
+    }
+
+    /// Remove an NFT from `owner`'s current holdings.
+    pub fn delete_nft_holding(
+        &mut self,
+        owner: Address,
+        contract: Address,
+        token_id: U256,
+    ) -> Result<()> {
+        self.write(
+            "nft_holdings",
+            encode_nft_holding_key(owner, contract, token_id),
+            None,
+        )
+    }
+
+    /// Put an NFT transfer of `owner` in this block.
+    pub fn put_nft_transfer(&mut self, owner: Address, transfer: &NftTransfer) -> Result<()> {
+        let value = postcard::to_allocvec(transfer).context("Failed to serialize NFT transfer")?;
+        self.write(
+            "nft_transfers",
+            encode_nft_transfer_key(owner, self.block, transfer.log_ordinal),
+            Some(value),
+        )
+    }
+
+    /// Put a wrap or unwrap by `owner` in this block.
+    pub fn put_conversion(&mut self, owner: Address, conversion: &Conversion) -> Result<()> {
+        let value = postcard::to_allocvec(conversion).context("Failed to serialize conversion")?;
+        self.write(
+            "conversions",
+            encode_conversion_key(owner, self.block, conversion.log_ordinal),
+            Some(value),
+        )
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Token approvals
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Set `owner`'s current allowance for (token, spender).
+    pub fn put_allowance(&mut self, owner: Address, allowance: &Allowance) -> Result<()> {
+        let value = postcard::to_allocvec(allowance).context("Failed to serialize allowance")?;
+        self.write(
+            "allowances",
+            encode_allowance_key(owner, allowance.token, allowance.spender),
+            Some(value),
+        )
+    }
+
+    /// Remove `owner`'s current allowance for (token, spender).
+    pub fn delete_allowance(
+        &mut self,
+        owner: Address,
+        token: Address,
+        spender: Address,
+    ) -> Result<()> {
+        self.write("allowances", encode_allowance_key(owner, token, spender), None)
+    }
+
+    /// Put an approval by `owner` in this block into its history.
+    pub fn put_allowance_change(&mut self, owner: Address, allowance: &Allowance) -> Result<()> {
+        let value = postcard::to_allocvec(allowance).context("Failed to serialize allowance")?;
+        self.write(
+            "allowance_history",
+            encode_allowance_change_key(owner, self.block, allowance.log_ordinal),
+            Some(value),
+        )
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Reconciliation
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Put a drift record found while reconciling this block.
+    pub fn put_drift_record(&mut self, record: &DriftRecord) -> Result<()> {
+        let value = postcard::to_allocvec(record).context("Failed to serialize drift record")?;
+        self.write(
+            "drift",
+            encode_drift_key(self.block, record.address, record.token),
+            Some(value),
+        )
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Persisted watchlist
+    // ─────────────────────────────────────────────────────────────────
+    //
+    // Watchlist edits are not journaled: a rollback keeps the watchlist as
+    // it is and only reverts the coverage of its entries.
+
+    /// Add an address to the persisted watchlist.
+    pub fn put_watched_address(&mut self, addr: Address) {
+        self.write_unjournaled("watchlist", encode_watched_address_key(addr), Some(Vec::new()));
+    }
+
+    /// Remove an address from the persisted watchlist.
+    pub fn delete_watched_address(&mut self, addr: Address) {
+        self.write_unjournaled("watchlist", encode_watched_address_key(addr), None);
+    }
+
+    /// Add an ERC20 token (or change its strategy) in the persisted watchlist.
+    pub fn put_watched_token(&mut self, token: Address, strategy: TokenStrategy) {
+        let value = strategy.to_string().into_bytes();
+        self.write_unjournaled("watchlist", encode_watched_token_key(token), Some(value));
+    }
+
+    /// Remove an ERC20 token from the persisted watchlist.
+    pub fn delete_watched_token(&mut self, token: Address) {
+        self.write_unjournaled("watchlist", encode_watched_token_key(token), None);
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Chain bookkeeping
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Put the hash of this batch's block.
+    pub fn put_block_hash(&mut self, hash: B256) -> Result<()> {
+        self.write(
+            "block_hashes",
+            encode_block_hash_key(self.block),
+            Some(hash.as_slice().to_vec()),
+        )
+    }
+
+    /// Set the head block number.
+    pub fn set_head(&mut self, block: u64) -> Result<()> {
+        self.write("meta", encode_meta_key(0x01), Some(block.to_be_bytes().to_vec()))
+    }
+
+    /// Record the follow mode this block was processed under.
+    pub fn set_head_finality(&mut self, mode: FollowMode) -> Result<()> {
+        let value = postcard::to_allocvec(&mode).context("Failed to serialize head finality")?;
+        self.write("meta", encode_meta_key(0x02), Some(value))
+    }
+
+    /// Delete the undo record of an older block.
+    ///
+    /// The delete is not journaled: journaling it would copy the pruned record
+    /// (and every record it pruned in turn) into this block's undo, and a
+    /// rollback must not bring back undo beyond the reorg depth anyway.
+    pub fn delete_block_undo(&mut self, block: u64) {
+        self.write_unjournaled("block_undo", encode_block_undo_key(block), None);
+    }
+
+    /// Commit all pending writes plus this block's undo record atomically.
+    pub fn commit(self) -> Result<()> {
+        let undo = BlockUndo {
+            block: self.block,
+            entries: self
+                .priors
+                .into_iter()
+                .map(|((cf, key), value)| RawWrite { cf, key, value })
+                .collect(),
+        };
+        let undo_value =
+            postcard::to_allocvec(&undo).context("Failed to serialize block undo")?;
+
+        let mut writes: Vec<RawWrite> = self
+            .writes
+            .into_iter()
+            .chain(self.unjournaled)
+            .map(|((cf, key), value)| RawWrite {
+                cf: cf.to_string(),
+                key,
+                value,
+            })
+            .collect();
+        writes.push(RawWrite {
+            cf: "block_undo".to_string(),
+            key: encode_block_undo_key(self.block),
+            value: Some(undo_value),
+        });
+
+        self.store
+            .write_batch(&writes)
+            .with_context(|| format!("Failed to commit batch for block {}", self.block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{TxDirection, TxSource};
+    use crate::store::RocksStateStore;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (RocksStateStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        (store, temp_dir)
+    }
+
+    fn account(nonce: u64, balance: u64) -> AccountRecord {
+        AccountRecord {
+            nonce,
+            balance: U256::from(balance),
+            code_hash: B256::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_batch_reads_own_writes_and_defers_to_commit() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        store.put_account(addr, &account(1, 1000)).unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        batch.put_account(addr, &account(2, 900)).unwrap();
+        batch.set_head(101).unwrap();
+
+        // Pending writes are visible through the batch only
+        assert_eq!(batch.get_account(addr).unwrap(), Some(account(2, 900)));
+        assert_eq!(store.get_account(addr).unwrap(), Some(account(1, 1000)));
+        assert_eq!(store.get_head().unwrap(), None);
+
+        batch.commit().unwrap();
+        assert_eq!(store.get_account(addr).unwrap(), Some(account(2, 900)));
+        assert_eq!(store.get_head().unwrap(), Some(101));
+    }
+
+    #[test]
+    fn test_dropped_batch_writes_nothing() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+
+        {
+            let mut batch = BlockWriteBatch::new(&store, 101);
+            batch.put_account(addr, &account(1, 1000)).unwrap();
+            batch.put_snapshot(addr, U256::from(1000u64)).unwrap();
+            batch.set_head(101).unwrap();
+            // Simulates a failure mid-block: the batch is never committed
+        }
+
+        assert_eq!(store.get_account(addr).unwrap(), None);
+        assert_eq!(store.get_snapshot(addr, 101).unwrap(), None);
+        assert_eq!(store.get_head().unwrap(), None);
+        assert_eq!(store.get_block_undo(101).unwrap(), None);
+    }
+
+    #[test]
+    fn test_commit_journals_prior_values() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0xaa);
+        store.put_account(addr, &account(1, 1000)).unwrap();
+        store.set_head(100).unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        batch.put_account(addr, &account(2, 900)).unwrap();
+        batch.put_account(addr, &account(3, 800)).unwrap();
+        batch.put_erc20_balance(token, addr, U256::from(5u64)).unwrap();
+        batch.set_head(101).unwrap();
+        batch.commit().unwrap();
+
+        let undo = store.get_block_undo(101).unwrap().unwrap();
+        assert_eq!(undo.block, 101);
+        assert_eq!(undo.entries.len(), 3);
+
+        // Only the value before the first write is journaled
+        let acc_entry = undo.entries.iter().find(|e| e.cf == "accounts").unwrap();
+        assert_eq!(
+            acc_entry.value,
+            Some(postcard::to_allocvec(&account(1, 1000)).unwrap())
+        );
+        let bal_entry = undo.entries.iter().find(|e| e.cf == "erc20_balances").unwrap();
+        assert_eq!(bal_entry.value, None);
+        let head_entry = undo.entries.iter().find(|e| e.cf == "meta").unwrap();
+        assert_eq!(head_entry.value, Some(100u64.to_be_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_amend_extends_undo_record() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        let other = Address::repeat_byte(0x02);
+        store.put_account(addr, &account(1, 1000)).unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        batch.put_account(addr, &account(2, 900)).unwrap();
+        batch.commit().unwrap();
+
+        let mut batch = BlockWriteBatch::amend(&store, 101).unwrap();
+        batch.put_account(addr, &account(3, 800)).unwrap();
+        batch.put_account(other, &account(0, 5)).unwrap();
+        batch.commit().unwrap();
+
+        // The first prior of a key is kept; new keys are added
+        let undo = store.get_block_undo(101).unwrap().unwrap();
+        assert_eq!(undo.entries.len(), 2);
+        let prior = |key: Vec<u8>| {
+            let entry = undo.entries.iter().find(|e| e.key == key).unwrap();
+            entry.value.clone()
+        };
+        assert_eq!(
+            prior(encode_account_key(addr)),
+            Some(postcard::to_allocvec(&account(1, 1000)).unwrap())
+        );
+        assert_eq!(prior(encode_account_key(other)), None);
+    }
+
+    #[test]
+    fn test_tx_record_keeps_committed_sequence_numbers() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        let record = |value: u64| TxRecord {
+            block: 101,
+            tx_index: 3,
+            tx_hash: Some(B256::repeat_byte(0x33)),
+            counterparty: None,
+            direction: TxDirection::In,
+            value: U256::from(value),
+            fee: U256::ZERO,
+            success: true,
+            source: TxSource::Internal,
+        };
+
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        batch.put_tx_record(addr, &record(1)).unwrap();
+        batch.commit().unwrap();
+
+        // A later write to the same transaction takes the next free sequence number
+        let mut batch = BlockWriteBatch::amend(&store, 101).unwrap();
+        batch.put_tx_record(addr, &record(2)).unwrap();
+        batch.commit().unwrap();
+
+        let records = store.get_tx_records_in_range(addr, 101, 101).unwrap();
+        assert_eq!(records, vec![record(1), record(2)]);
+    }
+}
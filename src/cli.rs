@@ -3,13 +3,23 @@
 //! Provides a developer-friendly command-line interface for interacting
 //! with the state store. All commands output pretty JSON.
 
-use crate::records::{AccountRecord, HeaderRecord};
-use crate::{RocksStateStore, StateStore};
-use alloy_primitives::{Address, B256, U256};
+use crate::config::{load_watchlist, TokenStrategy};
+use crate::records::{
+    AccountRecord, Allowance, ApprovalKind, BlockDelta, Conversion, ConversionKind, DecodedValue,
+    DriftRecord, Erc1155Delta, Erc20Delta, EventRecord, HeaderRecord, NftHolding, NftTransfer,
+    TxDirection, TxRecord, TxSource,
+};
+use crate::{QueryResult, RocksStateStore, StateStore};
+use crate::token_metadata::format_units;
+use crate::watchlist::{
+    apply_queued_changes, pending_changes, queue_change, watch_queue_path, WatchlistChange,
+};
+use alloy_primitives::{Address, B256, I256, U256};
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use serde_json::json;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// State store CLI tool
 #[derive(Parser)]
@@ -26,6 +36,18 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
+    #[command(flatten)]
+    Store(StoreCommand),
+    /// Edit or list the persisted watchlist (a running watcher picks up changes between blocks)
+    Watch {
+        #[command(subcommand)]
+        action: WatchAction,
+    },
+}
+
+/// Commands that read or write the store directly
+#[derive(Subcommand)]
+pub enum StoreCommand {
     /// Set the head block number
     SetHead {
         /// Block number
@@ -157,6 +179,149 @@ pub enum Commands {
         #[arg(long)]
         dense: bool,
     },
+    /// Get ERC1155 balances for (contract, id, owner) in a range
+    Erc1155Balances {
+        /// ERC1155 contract address
+        contract: String,
+        /// Token ID (decimal or 0x-prefixed hex)
+        id: String,
+        /// Owner address
+        owner: String,
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+    },
+    /// Get ERC1155 deltas for (contract, id, owner) in a range
+    Erc1155Deltas {
+        /// ERC1155 contract address
+        contract: String,
+        /// Token ID (decimal or 0x-prefixed hex)
+        id: String,
+        /// Owner address
+        owner: String,
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+        /// Include all blocks in range, even with zero deltas
+        #[arg(long)]
+        dense: bool,
+    },
+    /// List the transactions that moved an address's ETH balance in a range
+    Txs {
+        /// Ethereum address (hex, with or without 0x prefix)
+        address: String,
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+    },
+    /// List an address's ETH ⇄ WETH conversions in a range, with the linked ETH transfer
+    Conversions {
+        /// Ethereum address (hex, with or without 0x prefix)
+        address: String,
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+    },
+    /// List the ERC721 NFTs an address holds (watcher --nfts)
+    Nfts {
+        /// Owner address
+        owner: String,
+        /// Holdings at the end of this block instead of at the head
+        #[arg(long)]
+        at: Option<u64>,
+    },
+    /// List an address's ERC721 transfers in a range (watcher --nfts)
+    NftHistory {
+        /// Owner address
+        owner: String,
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+    },
+    /// List an address's current token approvals and flag risky ones (watcher --approvals)
+    Allowances {
+        /// Owner address
+        owner: String,
+        /// File of trusted spender addresses (one per line); others are flagged
+        #[arg(long)]
+        allowlist: Option<PathBuf>,
+    },
+    /// List an address's approvals and revocations in a range (watcher --approvals)
+    AllowanceHistory {
+        /// Owner address
+        owner: String,
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+    },
+    /// List decoded events (from watcher --events definitions) in a range
+    Events {
+        /// Start block number (inclusive)
+        start: u64,
+        /// End block number (inclusive)
+        end: u64,
+        /// Only show events emitted by this contract
+        #[arg(long)]
+        contract: Option<String>,
+        /// Only show events with this name (e.g. Swap)
+        #[arg(long)]
+        event: Option<String>,
+        /// Only show events with this address as a parameter
+        #[arg(long)]
+        address: Option<String>,
+    },
+    /// List reconciliation drift records
+    Drift {
+        /// Only show records for this address
+        #[arg(long)]
+        address: Option<String>,
+        /// Start block number (inclusive, default 0)
+        #[arg(long)]
+        start: Option<u64>,
+        /// End block number (inclusive, default head)
+        #[arg(long)]
+        end: Option<u64>,
+    },
+    /// List known ERC20 tokens with their name, symbol and decimals
+    Tokens,
+    /// List ERC20 (token, owner) pairs registered by token discovery
+    DiscoveredTokens {
+        /// Only show tokens discovered for this owner
+        #[arg(long)]
+        owner: Option<String>,
+    },
+}
+
+/// `statectl watch` subcommands
+#[derive(Subcommand)]
+pub enum WatchAction {
+    /// Start watching an address (or an ERC20 token with --token)
+    Add {
+        /// Address to watch (token contract with --token)
+        address: String,
+        /// Watch an ERC20 token instead of an address
+        #[arg(long)]
+        token: bool,
+        /// Token accounting strategy: transfers, poll, poll:N or shares
+        #[arg(long, requires = "token")]
+        strategy: Option<TokenStrategy>,
+    },
+    /// Stop watching an address (or an ERC20 token with --token); history is kept
+    Remove {
+        /// Address to stop watching (token contract with --token)
+        address: String,
+        /// Remove an ERC20 token instead of an address
+        #[arg(long)]
+        token: bool,
+    },
+    /// List watched addresses and tokens, and changes still queued for the watcher
+    List,
 }
 
 /// Pad an odd-length hex string with a leading zero.
@@ -210,24 +375,338 @@ fn parse_u256(s: &str) -> Result<U256> {
     Ok(U256::from_be_slice(&bytes))
 }
 
+/// JSON representation of a block delta (shared by dense and sparse output).
+fn delta_json(block: u64, delta: &BlockDelta) -> serde_json::Value {
+    json!({
+        "block": block,
+        "delta_plus": format!("0x{:x}", delta.delta_plus),
+        "delta_minus": format!("0x{:x}", delta.delta_minus),
+        "received_value": format!("0x{:x}", delta.received_value),
+        "sent_value": format!("0x{:x}", delta.sent_value),
+        "fee_paid": format!("0x{:x}", delta.fee_paid),
+        "failed_fee": format!("0x{:x}", delta.failed_fee),
+        "blob_fee": format!("0x{:x}", delta.blob_fee),
+        "l1_fee": format!("0x{:x}", delta.l1_fee),
+        "minted_value": format!("0x{:x}", delta.minted_value),
+        "withdrawal_value": format!("0x{:x}", delta.withdrawal_value),
+        "priority_fee_income": format!("0x{:x}", delta.priority_fee_income),
+        "correction_plus": format!("0x{:x}", delta.correction_plus),
+        "correction_minus": format!("0x{:x}", delta.correction_minus),
+        "nonce_delta": delta.nonce_delta,
+        "tx_count": delta.tx_count,
+    })
+}
+
+/// JSON representation of an ERC20 or ERC1155 delta (shared by dense and sparse output).
+fn erc20_delta_json(block: u64, delta: &Erc20Delta) -> serde_json::Value {
+    json!({
+        "block": block,
+        "delta_plus": format!("0x{:x}", delta.delta_plus),
+        "delta_minus": format!("0x{:x}", delta.delta_minus),
+        "correction_plus": format!("0x{:x}", delta.correction_plus),
+        "correction_minus": format!("0x{:x}", delta.correction_minus),
+        "rebase_plus": format!("0x{:x}", delta.rebase_plus),
+        "rebase_minus": format!("0x{:x}", delta.rebase_minus),
+        "adjustment_plus": format!("0x{:x}", delta.adjustment_plus),
+        "adjustment_minus": format!("0x{:x}", delta.adjustment_minus),
+        "tx_count": delta.tx_count,
+    })
+}
+
+/// JSON representation of a drift record.
+///
+/// `difference` is node minus stored, as a signed hex string.
+fn drift_json(record: &DriftRecord) -> serde_json::Value {
+    let difference = if record.node_balance >= record.stored_balance {
+        format!("+0x{:x}", record.node_balance - record.stored_balance)
+    } else {
+        format!("-0x{:x}", record.stored_balance - record.node_balance)
+    };
+    json!({
+        "block": record.block,
+        "address": format!("0x{:x}", record.address),
+        "asset": match record.token {
+            Some(token) => format!("0x{:x}", token),
+            None => "ETH".to_string(),
+        },
+        "stored_balance": format!("0x{:x}", record.stored_balance),
+        "node_balance": format!("0x{:x}", record.node_balance),
+        "difference": difference,
+        "stored_nonce": record.stored_nonce,
+        "node_nonce": record.node_nonce,
+        "corrected": record.corrected,
+    })
+}
+
+/// JSON representation of a transaction history record.
+fn tx_record_json(record: &TxRecord) -> serde_json::Value {
+    json!({
+        "block": record.block,
+        "tx_index": record.tx_index,
+        "tx_hash": record.tx_hash.map(|h| format!("0x{:x}", h)),
+        "counterparty": record.counterparty.map(|a| format!("0x{:x}", a)),
+        "direction": direction_str(record.direction),
+        "value": format!("0x{:x}", record.value),
+        "fee": format!("0x{:x}", record.fee),
+        "status": if record.success { "success" } else { "failed" },
+        "source": match record.source {
+            TxSource::TopLevel => "top-level",
+            TxSource::Internal => "internal",
+            TxSource::Withdrawal => "withdrawal",
+        },
+    })
+}
+
+/// JSON representation of an ETH ⇄ token conversion.
+fn conversion_json(conversion: &Conversion) -> serde_json::Value {
+    json!({
+        "block": conversion.block,
+        "log_ordinal": conversion.log_ordinal,
+        "tx_hash": format!("0x{:x}", conversion.tx_hash),
+        "token": format!("0x{:x}", conversion.token),
+        "kind": match conversion.kind {
+            ConversionKind::Wrap => "wrap",
+            ConversionKind::Unwrap => "unwrap",
+        },
+        "amount": format!("0x{:x}", conversion.amount),
+    })
+}
+
+/// Whether `record` is the ETH side of `conversion`.
+///
+/// Wrapping sends ETH to the token in the same transaction; unwrapping
+/// receives ETH back from it (usually as an internal transfer).
+fn is_conversion_eth_side(record: &TxRecord, conversion: &Conversion) -> bool {
+    let direction = match conversion.kind {
+        ConversionKind::Wrap => TxDirection::Out,
+        ConversionKind::Unwrap => TxDirection::In,
+    };
+    record.tx_hash == Some(conversion.tx_hash)
+        && record.counterparty == Some(conversion.token)
+        && record.direction == direction
+}
+
+/// Lowercase name of a transfer direction.
+fn direction_str(direction: TxDirection) -> &'static str {
+    match direction {
+        TxDirection::In => "in",
+        TxDirection::Out => "out",
+        TxDirection::SelfTransfer => "self",
+    }
+}
+
+/// JSON representation of a held NFT.
+fn nft_holding_json(holding: &NftHolding) -> serde_json::Value {
+    json!({
+        "contract": format!("0x{:x}", holding.contract),
+        "token_id": holding.token_id.to_string(),
+        "since_block": holding.since_block,
+    })
+}
+
+/// JSON representation of an NFT transfer.
+fn nft_transfer_json(transfer: &NftTransfer) -> serde_json::Value {
+    json!({
+        "block": transfer.block,
+        "log_ordinal": transfer.log_ordinal,
+        "tx_hash": format!("0x{:x}", transfer.tx_hash),
+        "contract": format!("0x{:x}", transfer.contract),
+        "token_id": transfer.token_id.to_string(),
+        "direction": direction_str(transfer.direction),
+        "counterparty": format!("0x{:x}", transfer.counterparty),
+    })
+}
+
+/// JSON representation of a token approval.
+fn allowance_json(allowance: &Allowance) -> serde_json::Value {
+    json!({
+        "block": allowance.block,
+        "log_ordinal": allowance.log_ordinal,
+        "tx_hash": format!("0x{:x}", allowance.tx_hash),
+        "token": format!("0x{:x}", allowance.token),
+        "spender": format!("0x{:x}", allowance.spender),
+        "kind": match allowance.kind {
+            ApprovalKind::Erc20 => "erc20",
+            ApprovalKind::ForAll => "approval-for-all",
+        },
+        "value": format!("0x{:x}", allowance.value),
+        "unlimited": allowance.is_unlimited(),
+    })
+}
+
+/// JSON representation of a decoded event parameter value.
+///
+/// Unsigned integers are hex like other amounts; signed integers carry a sign.
+fn decoded_value_json(value: &DecodedValue) -> serde_json::Value {
+    match value {
+        DecodedValue::Address(addr) => json!(format!("0x{:x}", addr)),
+        DecodedValue::Bool(b) => json!(b),
+        DecodedValue::Uint(v) => json!(format!("0x{:x}", v)),
+        DecodedValue::Int(raw) => {
+            let v = I256::from_raw(*raw);
+            let sign = if v.is_negative() { "-" } else { "" };
+            json!(format!("{}0x{:x}", sign, v.unsigned_abs()))
+        }
+        DecodedValue::Bytes(bytes) => json!(format!("0x{}", hex::encode(bytes))),
+        DecodedValue::String(s) => json!(s),
+        DecodedValue::Hash(hash) => json!(format!("0x{:x}", hash)),
+    }
+}
+
+/// JSON representation of a decoded event.
+fn event_record_json(record: &EventRecord) -> serde_json::Value {
+    let params: serde_json::Map<String, serde_json::Value> = record
+        .params
+        .iter()
+        .map(|p| (p.name.clone(), decoded_value_json(&p.value)))
+        .collect();
+    json!({
+        "block": record.block,
+        "log_ordinal": record.log_ordinal,
+        "tx_hash": format!("0x{:x}", record.tx_hash),
+        "contract": format!("0x{:x}", record.contract),
+        "event": record.event,
+        "signature": record.signature,
+        "params": params,
+    })
+}
+
+/// JSON output of a range query: `fields` plus the query's coverage metadata.
+fn query_meta_json<T>(
+    query_result: &QueryResult<T>,
+    fields: serde_json::Value,
+) -> serde_json::Value {
+    let mut value = json!({
+        "requestedStart": query_result.requested_start,
+        "requestedEnd": query_result.requested_end,
+        "effectiveStart": query_result.effective_start,
+        "effectiveEnd": query_result.effective_end,
+        "watchStartBlock": query_result.watch_start_block,
+        "watchEndBlock": query_result.watch_end_block,
+        "coverageGaps": query_result.coverage_gaps,
+        "headBlock": query_result.head_block,
+        "headFinality": query_result.head_finality.map(|m| m.to_string()),
+        "message": query_result.message,
+    });
+    if let (Some(object), serde_json::Value::Object(fields)) = (value.as_object_mut(), fields) {
+        object.extend(fields);
+    }
+    value
+}
+
+/// Blocks of a query's effective range outside its coverage gaps, in order.
+fn covered_blocks<T>(query_result: &QueryResult<T>) -> impl Iterator<Item = u64> + '_ {
+    (query_result.effective_start..=query_result.effective_end).filter(move |block| {
+        !query_result
+            .coverage_gaps
+            .iter()
+            .any(|(start, end)| (start..=end).contains(&block))
+    })
+}
+
+/// Apply a `statectl watch` command, or queue it if a watcher holds the database.
+fn run_watch(db_path: &Path, action: WatchAction) -> Result<serde_json::Value> {
+    let queue = watch_queue_path(db_path);
+    let change = match action {
+        WatchAction::Add {
+            address,
+            token: false,
+            ..
+        } => WatchlistChange::AddAddress(parse_address(&address)?),
+        WatchAction::Add {
+            address,
+            token: true,
+            strategy,
+        } => WatchlistChange::AddToken(parse_address(&address)?, strategy.unwrap_or_default()),
+        WatchAction::Remove {
+            address,
+            token: false,
+        } => WatchlistChange::RemoveAddress(parse_address(&address)?),
+        WatchAction::Remove {
+            address,
+            token: true,
+        } => WatchlistChange::RemoveToken(parse_address(&address)?),
+        WatchAction::List => {
+            let store = RocksStateStore::open(db_path)
+                .with_context(|| format!("Failed to open database at {:?}", db_path))?;
+            let addresses: Vec<_> = store
+                .get_watched_addresses()?
+                .iter()
+                .map(|addr| format!("0x{:x}", addr))
+                .collect();
+            let tokens: Vec<_> = store
+                .get_watched_tokens()?
+                .iter()
+                .map(|(token, strategy)| {
+                    json!({
+                        "token": format!("0x{:x}", token),
+                        "strategy": strategy.to_string(),
+                    })
+                })
+                .collect();
+            let queued: Vec<_> = pending_changes(&queue)?
+                .iter()
+                .map(|change| change.to_string())
+                .collect();
+            return Ok(json!({
+                "addresses": addresses,
+                "tokens": tokens,
+                "queued": queued
+            }));
+        }
+    };
+
+    // RocksDB allows one writer: while a watcher runs, hand the change over through the queue
+    match RocksStateStore::open(db_path) {
+        Ok(store) => {
+            apply_queued_changes(&store, &queue)?;
+            change.apply(&store)?;
+            Ok(json!({ "status": "applied", "change": change.to_string() }))
+        }
+        Err(e) if format!("{:#}", e).contains("lock") => {
+            queue_change(&queue, &change)?;
+            Ok(json!({
+                "status": "queued",
+                "change": change.to_string(),
+                "message": "The database is in use by a watcher; it applies the change before its next block."
+            }))
+        }
+        Err(e) => Err(e.context(format!("Failed to open database at {:?}", db_path))),
+    }
+}
+
 /// Run the CLI command and print JSON output.
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
-    let store = RocksStateStore::open(&cli.db_path)
-        .with_context(|| format!("Failed to open database at {:?}", cli.db_path))?;
-
     let result = match cli.command {
-        Commands::SetHead { block } => {
+        Commands::Store(command) => {
+            let store = RocksStateStore::open(&cli.db_path)
+                .with_context(|| format!("Failed to open database at {:?}", cli.db_path))?;
+            run_store_command(&store, command)?
+        }
+        Commands::Watch { action } => run_watch(&cli.db_path, action)?,
+    };
+
+    // Pretty print JSON
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
+
+/// Run a command against the opened store.
+fn run_store_command(store: &RocksStateStore, command: StoreCommand) -> Result<serde_json::Value> {
+    let result = match command {
+        StoreCommand::SetHead { block } => {
             store.set_head(block)?;
             json!({ "status": "ok", "head_block": block })
         }
-        Commands::GetHead => {
+        StoreCommand::GetHead => {
+            let finality = store.get_head_finality()?.map(|m| m.to_string());
             match store.get_head()? {
-                Some(block) => json!({ "head_block": block }),
-                None => json!({ "head_block": null }),
+                Some(block) => json!({ "head_block": block, "head_finality": finality }),
+                None => json!({ "head_block": null, "head_finality": finality }),
             }
         }
-        Commands::PutAccount {
+        StoreCommand::PutAccount {
             address,
             nonce,
             balance_hex,
@@ -252,7 +731,7 @@ pub fn run() -> Result<()> {
                 }
             })
         }
-        Commands::GetAccount { address } => {
+        StoreCommand::GetAccount { address } => {
             let addr = parse_address(&address)?;
             match store.get_account(addr)? {
                 Some(acc) => json!({
@@ -269,7 +748,7 @@ pub fn run() -> Result<()> {
                 }),
             }
         }
-        Commands::PutCode { code_hash, hex_bytecode } => {
+        StoreCommand::PutCode { code_hash, hex_bytecode } => {
             let code_hash_val = parse_hash(&code_hash)?;
             let code_hex = hex_bytecode.strip_prefix("0x").unwrap_or(&hex_bytecode);
             let code_hex = pad_hex_string(code_hex);
@@ -282,7 +761,7 @@ pub fn run() -> Result<()> {
                 "code_length": code.len(),
             })
         }
-        Commands::GetCode { code_hash } => {
+        StoreCommand::GetCode { code_hash } => {
             let code_hash_val = parse_hash(&code_hash)?;
             match store.get_code(code_hash_val)? {
                 Some(code) => json!({
@@ -296,7 +775,7 @@ pub fn run() -> Result<()> {
                 }),
             }
         }
-        Commands::PutStorage { address, slot, value_hex } => {
+        StoreCommand::PutStorage { address, slot, value_hex } => {
             let addr = parse_address(&address)?;
             let slot_val = parse_hash(&slot)?;
             let value = parse_u256(&value_hex)?;
@@ -308,7 +787,7 @@ pub fn run() -> Result<()> {
                 "value": format!("0x{:x}", value),
             })
         }
-        Commands::GetStorage { address, slot } => {
+        StoreCommand::GetStorage { address, slot } => {
             let addr = parse_address(&address)?;
             let slot_val = parse_hash(&slot)?;
             let value = store.get_storage(addr, slot_val)?;
@@ -318,7 +797,7 @@ pub fn run() -> Result<()> {
                 "value": format!("0x{:x}", value),
             })
         }
-        Commands::PutHeader {
+        StoreCommand::PutHeader {
             number,
             timestamp,
             basefee_hex,
@@ -354,7 +833,7 @@ pub fn run() -> Result<()> {
                 }
             })
         }
-        Commands::GetHeader { number } => {
+        StoreCommand::GetHeader { number } => {
             match store.get_header(number)? {
                 Some(header) => json!({
                     "block": number,
@@ -374,7 +853,7 @@ pub fn run() -> Result<()> {
                 }),
             }
         }
-        Commands::PutBlockHash { number, hash } => {
+        StoreCommand::PutBlockHash { number, hash } => {
             let hash_val = parse_hash(&hash)?;
             store.put_block_hash(number, hash_val)?;
             json!({
@@ -383,7 +862,7 @@ pub fn run() -> Result<()> {
                 "hash": format!("0x{:x}", hash_val),
             })
         }
-        Commands::GetBlockHash { number } => {
+        StoreCommand::GetBlockHash { number } => {
             match store.get_block_hash(number)? {
                 Some(hash) => json!({
                     "block": number,
@@ -395,7 +874,7 @@ pub fn run() -> Result<()> {
                 }),
             }
         }
-        Commands::Deltas {
+        StoreCommand::Deltas {
             address,
             start,
             end,
@@ -406,82 +885,33 @@ pub fn run() -> Result<()> {
                 .get_deltas_in_range_with_metadata(addr, start, end)
                 .context("Failed to get deltas")?;
 
-            if dense {
-                // Dense output: include all blocks in effective range, even with zero deltas
-                let mut result = Vec::new();
-                let mut delta_map: std::collections::HashMap<u64, _> =
-                    query_result.data.into_iter().collect();
-                for block in query_result.effective_start..=query_result.effective_end {
-                    if let Some(delta) = delta_map.remove(&block) {
-                        result.push(json!({
-                            "block": block,
-                            "delta_plus": format!("0x{:x}", delta.delta_plus),
-                            "delta_minus": format!("0x{:x}", delta.delta_minus),
-                            "received_value": format!("0x{:x}", delta.received_value),
-                            "sent_value": format!("0x{:x}", delta.sent_value),
-                            "fee_paid": format!("0x{:x}", delta.fee_paid),
-                            "failed_fee": format!("0x{:x}", delta.failed_fee),
-                            "nonce_delta": delta.nonce_delta,
-                            "tx_count": delta.tx_count,
-                        }));
-                    } else {
-                        result.push(json!({
-                            "block": block,
-                            "delta_plus": "0x0",
-                            "delta_minus": "0x0",
-                            "received_value": "0x0",
-                            "sent_value": "0x0",
-                            "fee_paid": "0x0",
-                            "failed_fee": "0x0",
-                            "nonce_delta": 0,
-                            "tx_count": 0,
-                        }));
-                    }
-                }
-                json!({
-                    "address": format!("0x{:x}", addr),
-                    "requestedStart": query_result.requested_start,
-                    "requestedEnd": query_result.requested_end,
-                    "effectiveStart": query_result.effective_start,
-                    "effectiveEnd": query_result.effective_end,
-                    "watchStartBlock": query_result.watch_start_block,
-                    "headBlock": query_result.head_block,
-                    "message": query_result.message,
-                    "deltas": result
-                })
+            let result: Vec<_> = if dense {
+                // Dense output: all covered blocks in the effective range, even with zero deltas
+                let deltas: HashMap<u64, &BlockDelta> =
+                    query_result.data.iter().map(|(block, delta)| (*block, delta)).collect();
+                covered_blocks(&query_result)
+                    .map(|block| match deltas.get(&block) {
+                        Some(delta) => delta_json(block, delta),
+                        None => delta_json(block, &BlockDelta::new(block)),
+                    })
+                    .collect()
             } else {
                 // Sparse output: only blocks with deltas
-                let result: Vec<_> = query_result
+                query_result
                     .data
-                    .into_iter()
-                    .map(|(block, delta)| {
-                        json!({
-                            "block": block,
-                            "delta_plus": format!("0x{:x}", delta.delta_plus),
-                            "delta_minus": format!("0x{:x}", delta.delta_minus),
-                            "received_value": format!("0x{:x}", delta.received_value),
-                            "sent_value": format!("0x{:x}", delta.sent_value),
-                            "fee_paid": format!("0x{:x}", delta.fee_paid),
-                            "failed_fee": format!("0x{:x}", delta.failed_fee),
-                            "nonce_delta": delta.nonce_delta,
-                            "tx_count": delta.tx_count,
-                        })
-                    })
-                    .collect();
+                    .iter()
+                    .map(|(block, delta)| delta_json(*block, delta))
+                    .collect()
+            };
+            query_meta_json(
+                &query_result,
                 json!({
                     "address": format!("0x{:x}", addr),
-                    "requestedStart": query_result.requested_start,
-                    "requestedEnd": query_result.requested_end,
-                    "effectiveStart": query_result.effective_start,
-                    "effectiveEnd": query_result.effective_end,
-                    "watchStartBlock": query_result.watch_start_block,
-                    "headBlock": query_result.head_block,
-                    "message": query_result.message,
                     "deltas": result
-                })
-            }
+                }),
+            )
         }
-        Commands::Balances {
+        StoreCommand::Balances {
             address,
             start,
             end,
@@ -493,7 +923,7 @@ pub fn run() -> Result<()> {
 
             let result: Vec<_> = query_result
                 .data
-                .into_iter()
+                .iter()
                 .map(|(block, balance)| {
                     json!({
                         "block": block,
@@ -502,19 +932,15 @@ pub fn run() -> Result<()> {
                 })
                 .collect();
 
-            json!({
-                "address": format!("0x{:x}", addr),
-                "requestedStart": query_result.requested_start,
-                "requestedEnd": query_result.requested_end,
-                "effectiveStart": query_result.effective_start,
-                "effectiveEnd": query_result.effective_end,
-                "watchStartBlock": query_result.watch_start_block,
-                "headBlock": query_result.head_block,
-                "message": query_result.message,
-                "balances": result
-            })
+            query_meta_json(
+                &query_result,
+                json!({
+                    "address": format!("0x{:x}", addr),
+                    "balances": result
+                }),
+            )
         }
-        Commands::Erc20Balances {
+        StoreCommand::Erc20Balances {
             token,
             owner,
             start,
@@ -530,32 +956,32 @@ pub fn run() -> Result<()> {
                     end,
                 )
                 .context("Failed to get ERC20 balances")?;
+            let metadata = store.get_token_metadata(token_addr)?.unwrap_or_default();
 
             let result: Vec<_> = query_result
                 .data
-                .into_iter()
+                .iter()
                 .map(|(block, balance)| {
                     json!({
                         "block": block,
                         "balance": format!("0x{:x}", balance),
+                        "formatted": metadata.decimals.and_then(|d| format_units(*balance, d)),
                     })
                 })
                 .collect();
 
-            json!({
-                "token": format!("0x{:x}", token_addr),
-                "owner": format!("0x{:x}", owner_addr),
-                "requestedStart": query_result.requested_start,
-                "requestedEnd": query_result.requested_end,
-                "effectiveStart": query_result.effective_start,
-                "effectiveEnd": query_result.effective_end,
-                "watchStartBlock": query_result.watch_start_block,
-                "headBlock": query_result.head_block,
-                "message": query_result.message,
-                "balances": result
-            })
+            query_meta_json(
+                &query_result,
+                json!({
+                    "token": format!("0x{:x}", token_addr),
+                    "symbol": metadata.symbol,
+                    "decimals": metadata.decimals,
+                    "owner": format!("0x{:x}", owner_addr),
+                    "balances": result
+                }),
+            )
         }
-        Commands::Erc20Deltas {
+        StoreCommand::Erc20Deltas {
             token,
             owner,
             start,
@@ -572,70 +998,461 @@ pub fn run() -> Result<()> {
                     end,
                 )
                 .context("Failed to get ERC20 deltas")?;
+            let metadata = store.get_token_metadata(token_addr)?.unwrap_or_default();
 
-            if dense {
-                let mut result = Vec::new();
-                let mut delta_map: std::collections::HashMap<u64, _> =
-                    query_result.data.into_iter().collect();
-                for block in query_result.effective_start..=query_result.effective_end {
-                    if let Some(delta) = delta_map.remove(&block) {
-                        result.push(json!({
-                            "block": block,
-                            "delta_plus": format!("0x{:x}", delta.delta_plus),
-                            "delta_minus": format!("0x{:x}", delta.delta_minus),
-                            "tx_count": delta.tx_count,
-                        }));
-                    } else {
-                        result.push(json!({
-                            "block": block,
-                            "delta_plus": "0x0",
-                            "delta_minus": "0x0",
-                            "tx_count": 0,
-                        }));
-                    }
-                }
+            let result: Vec<_> = if dense {
+                let deltas: HashMap<u64, &Erc20Delta> =
+                    query_result.data.iter().map(|(block, delta)| (*block, delta)).collect();
+                covered_blocks(&query_result)
+                    .map(|block| match deltas.get(&block) {
+                        Some(delta) => erc20_delta_json(block, delta),
+                        None => erc20_delta_json(block, &Erc20Delta::new(block)),
+                    })
+                    .collect()
+            } else {
+                query_result
+                    .data
+                    .iter()
+                    .map(|(block, delta)| erc20_delta_json(*block, delta))
+                    .collect()
+            };
+            query_meta_json(
+                &query_result,
                 json!({
                     "token": format!("0x{:x}", token_addr),
+                    "symbol": metadata.symbol,
+                    "decimals": metadata.decimals,
                     "owner": format!("0x{:x}", owner_addr),
-                    "requestedStart": query_result.requested_start,
-                    "requestedEnd": query_result.requested_end,
-                    "effectiveStart": query_result.effective_start,
-                    "effectiveEnd": query_result.effective_end,
-                    "watchStartBlock": query_result.watch_start_block,
-                    "headBlock": query_result.head_block,
-                    "message": query_result.message,
                     "deltas": result
+                }),
+            )
+        }
+        StoreCommand::Erc1155Balances {
+            contract,
+            id,
+            owner,
+            start,
+            end,
+        } => {
+            let contract_addr = parse_address(&contract)?;
+            let token_id: U256 = id
+                .parse()
+                .with_context(|| format!("Invalid token ID: {}", id))?;
+            let owner_addr = parse_address(&owner)?;
+            let query_result = store
+                .get_erc1155_balances_in_range_with_metadata(
+                    contract_addr,
+                    token_id,
+                    owner_addr,
+                    start,
+                    end,
+                )
+                .context("Failed to get ERC1155 balances")?;
+
+            let result: Vec<_> = query_result
+                .data
+                .iter()
+                .map(|(block, balance)| {
+                    json!({
+                        "block": block,
+                        "balance": format!("0x{:x}", balance),
+                    })
                 })
+                .collect();
+
+            query_meta_json(
+                &query_result,
+                json!({
+                    "contract": format!("0x{:x}", contract_addr),
+                    "id": token_id.to_string(),
+                    "owner": format!("0x{:x}", owner_addr),
+                    "balances": result
+                }),
+            )
+        }
+        StoreCommand::Erc1155Deltas {
+            contract,
+            id,
+            owner,
+            start,
+            end,
+            dense,
+        } => {
+            let contract_addr = parse_address(&contract)?;
+            let token_id: U256 = id
+                .parse()
+                .with_context(|| format!("Invalid token ID: {}", id))?;
+            let owner_addr = parse_address(&owner)?;
+            let query_result = store
+                .get_erc1155_deltas_in_range_with_metadata(
+                    contract_addr,
+                    token_id,
+                    owner_addr,
+                    start,
+                    end,
+                )
+                .context("Failed to get ERC1155 deltas")?;
+
+            let result: Vec<_> = if dense {
+                let deltas: HashMap<u64, &Erc1155Delta> =
+                    query_result.data.iter().map(|(block, delta)| (*block, delta)).collect();
+                covered_blocks(&query_result)
+                    .map(|block| match deltas.get(&block) {
+                        Some(delta) => erc20_delta_json(block, delta),
+                        None => erc20_delta_json(block, &Erc1155Delta::new(block)),
+                    })
+                    .collect()
             } else {
-                let result: Vec<_> = query_result
+                query_result
                     .data
-                    .into_iter()
-                    .map(|(block, delta)| {
-                        json!({
-                            "block": block,
-                            "delta_plus": format!("0x{:x}", delta.delta_plus),
-                            "delta_minus": format!("0x{:x}", delta.delta_minus),
-                            "tx_count": delta.tx_count,
-                        })
-                    })
-                    .collect();
+                    .iter()
+                    .map(|(block, delta)| erc20_delta_json(*block, delta))
+                    .collect()
+            };
+
+            query_meta_json(
+                &query_result,
                 json!({
-                    "token": format!("0x{:x}", token_addr),
+                    "contract": format!("0x{:x}", contract_addr),
+                    "id": token_id.to_string(),
                     "owner": format!("0x{:x}", owner_addr),
-                    "requestedStart": query_result.requested_start,
-                    "requestedEnd": query_result.requested_end,
-                    "effectiveStart": query_result.effective_start,
-                    "effectiveEnd": query_result.effective_end,
-                    "watchStartBlock": query_result.watch_start_block,
-                    "headBlock": query_result.head_block,
-                    "message": query_result.message,
                     "deltas": result
+                }),
+            )
+        }
+        StoreCommand::Txs {
+            address,
+            start,
+            end,
+        } => {
+            let addr = parse_address(&address)?;
+            let query_result = store
+                .get_tx_records_in_range_with_metadata(addr, start, end)
+                .context("Failed to get transactions")?;
+            let conversions = store
+                .get_conversions_in_range(addr, start, end)
+                .context("Failed to get conversions")?;
+
+            // Link ETH transfers to the WETH deposit/withdrawal they funded
+            let result: Vec<_> = query_result
+                .data
+                .iter()
+                .map(|(_, record)| {
+                    let mut value = tx_record_json(record);
+                    if let Some(conversion) =
+                        conversions.iter().find(|c| is_conversion_eth_side(record, c))
+                    {
+                        value["conversion"] = conversion_json(conversion);
+                    }
+                    value
                 })
-            }
+                .collect();
+
+            query_meta_json(
+                &query_result,
+                json!({
+                    "address": format!("0x{:x}", addr),
+                    "count": result.len(),
+                    "transactions": result
+                }),
+            )
         }
-    };
+        StoreCommand::Conversions {
+            address,
+            start,
+            end,
+        } => {
+            let addr = parse_address(&address)?;
+            let conversions = store
+                .get_conversions_in_range(addr, start, end)
+                .context("Failed to get conversions")?;
+            let records = store
+                .get_tx_records_in_range(addr, start, end)
+                .context("Failed to get transactions")?;
 
-    // Pretty print JSON
-    println!("{}", serde_json::to_string_pretty(&result)?);
-    Ok(())
+            let result: Vec<_> = conversions
+                .iter()
+                .map(|conversion| {
+                    let mut value = conversion_json(conversion);
+                    value["ethTransfer"] = records
+                        .iter()
+                        .find(|record| is_conversion_eth_side(record, conversion))
+                        .map(tx_record_json)
+                        .unwrap_or(serde_json::Value::Null);
+                    value
+                })
+                .collect();
+
+            json!({
+                "address": format!("0x{:x}", addr),
+                "start": start,
+                "end": end,
+                "headBlock": store.get_head()?,
+                "count": result.len(),
+                "conversions": result
+            })
+        }
+        StoreCommand::Nfts { owner, at } => {
+            let addr = parse_address(&owner)?;
+            let watch_meta = store
+                .get_watch_meta(addr)?
+                .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
+            let head = store.get_head()?;
+
+            // Holdings before the watch start are unknown
+            let (holdings, message) = match at {
+                Some(block) if block < watch_meta.start_block => (
+                    Vec::new(),
+                    Some(format!(
+                        "Earliest known NFT ownership starts at block {}.",
+                        watch_meta.start_block
+                    )),
+                ),
+                Some(block) if !watch_meta.covers(block) => (
+                    Vec::new(),
+                    Some(format!("Address was not watched at block {}.", block)),
+                ),
+                Some(block) => match store.get_nft_holdings_at(addr, block)? {
+                    Some(holdings) => (holdings, None),
+                    None => {
+                        let gaps: Vec<String> = watch_meta
+                            .gaps_in(watch_meta.start_block, block)
+                            .iter()
+                            .map(|(start, end)| format!("{}-{}", start, end))
+                            .collect();
+                        (
+                            Vec::new(),
+                            Some(format!(
+                                "Address was not watched in blocks {}; NFT ownership at block {} \
+                                is unknown.",
+                                gaps.join(", "),
+                                block
+                            )),
+                        )
+                    }
+                },
+                None => (store.get_nft_holdings(addr)?, None),
+            };
+            let holdings: Vec<_> = holdings.iter().map(nft_holding_json).collect();
+
+            json!({
+                "owner": format!("0x{:x}", addr),
+                "block": at.or(head),
+                "watchStartBlock": watch_meta.start_block,
+                "watchEndBlock": watch_meta.end_block,
+                "headBlock": head,
+                "message": message,
+                "count": holdings.len(),
+                "nfts": holdings
+            })
+        }
+        StoreCommand::NftHistory { owner, start, end } => {
+            let addr = parse_address(&owner)?;
+            let watch_meta = store
+                .get_watch_meta(addr)?
+                .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
+            let transfers: Vec<_> = store
+                .get_nft_transfers_in_range(addr, start, end)
+                .context("Failed to get NFT transfers")?
+                .iter()
+                .map(nft_transfer_json)
+                .collect();
+
+            json!({
+                "owner": format!("0x{:x}", addr),
+                "start": start,
+                "end": end,
+                "watchStartBlock": watch_meta.start_block,
+                "watchEndBlock": watch_meta.end_block,
+                "headBlock": store.get_head()?,
+                "count": transfers.len(),
+                "transfers": transfers
+            })
+        }
+        StoreCommand::Allowances { owner, allowlist } => {
+            let addr = parse_address(&owner)?;
+            let watch_meta = store
+                .get_watch_meta(addr)?
+                .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
+            let allowlist = allowlist
+                .map(|path| {
+                    load_watchlist(&path).with_context(|| {
+                        format!("Failed to load spender allowlist from {:?}", path)
+                    })
+                })
+                .transpose()?;
+
+            let mut flagged = 0;
+            let allowances: Vec<_> = store
+                .get_allowances(addr)
+                .context("Failed to get allowances")?
+                .iter()
+                .map(|allowance| -> Result<serde_json::Value> {
+                    let allowlisted = allowlist
+                        .as_ref()
+                        .map(|list| list.contains(&allowance.spender));
+                    let mut risks = Vec::new();
+                    if allowance.is_unlimited() {
+                        risks.push("unlimited");
+                    }
+                    if allowlisted == Some(false) {
+                        risks.push("not-allowlisted");
+                    }
+                    if !risks.is_empty() {
+                        flagged += 1;
+                    }
+
+                    let mut value = allowance_json(allowance);
+                    value["symbol"] =
+                        json!(store.get_token_metadata(allowance.token)?.and_then(|m| m.symbol));
+                    value["allowlisted"] = json!(allowlisted);
+                    value["risks"] = json!(risks);
+                    Ok(value)
+                })
+                .collect::<Result<_>>()?;
+
+            json!({
+                "owner": format!("0x{:x}", addr),
+                "watchStartBlock": watch_meta.start_block,
+                "watchEndBlock": watch_meta.end_block,
+                "headBlock": store.get_head()?,
+                "message": "Values are the last approved amounts; allowances spent by transferFrom \
+                    are not observed.",
+                "count": allowances.len(),
+                "flagged": flagged,
+                "allowances": allowances
+            })
+        }
+        StoreCommand::AllowanceHistory { owner, start, end } => {
+            let addr = parse_address(&owner)?;
+            let watch_meta = store
+                .get_watch_meta(addr)?
+                .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
+            let history: Vec<_> = store
+                .get_allowance_history_in_range(addr, start, end)
+                .context("Failed to get allowance history")?
+                .iter()
+                .map(allowance_json)
+                .collect();
+
+            json!({
+                "owner": format!("0x{:x}", addr),
+                "start": start,
+                "end": end,
+                "watchStartBlock": watch_meta.start_block,
+                "watchEndBlock": watch_meta.end_block,
+                "headBlock": store.get_head()?,
+                "count": history.len(),
+                "approvals": history
+            })
+        }
+        StoreCommand::Events {
+            start,
+            end,
+            contract,
+            event,
+            address,
+        } => {
+            let contract = contract.as_deref().map(parse_address).transpose()?;
+            let addr = address.as_deref().map(parse_address).transpose()?;
+            let records: Vec<_> = store
+                .get_event_records_in_range(start, end)
+                .context("Failed to get events")?
+                .into_iter()
+                .filter(|r| contract.is_none() || contract == Some(r.contract))
+                .filter(|r| event.is_none() || event.as_deref() == Some(r.event.as_str()))
+                .filter(|r| match addr {
+                    Some(addr) => r
+                        .params
+                        .iter()
+                        .any(|p| p.value == DecodedValue::Address(addr)),
+                    None => true,
+                })
+                .map(|r| event_record_json(&r))
+                .collect();
+
+            json!({
+                "start": start,
+                "end": end,
+                "contract": contract.map(|a| format!("0x{:x}", a)),
+                "event": event,
+                "address": addr.map(|a| format!("0x{:x}", a)),
+                "headBlock": store.get_head()?,
+                "count": records.len(),
+                "events": records
+            })
+        }
+        StoreCommand::Drift {
+            address,
+            start,
+            end,
+        } => {
+            let addr = address.as_deref().map(parse_address).transpose()?;
+            let head = store.get_head()?;
+            let start = start.unwrap_or(0);
+            let end = end.or(head).unwrap_or(u64::MAX);
+            let records: Vec<_> = store
+                .get_drift_records_in_range(start, end)
+                .context("Failed to get drift records")?
+                .into_iter()
+                .filter(|r| addr.is_none() || addr == Some(r.address))
+                .map(|r| drift_json(&r))
+                .collect();
+
+            json!({
+                "address": addr.map(|a| format!("0x{:x}", a)),
+                "start": start,
+                "end": end,
+                "headBlock": head,
+                "count": records.len(),
+                "drift": records
+            })
+        }
+        StoreCommand::Tokens => {
+            let tokens: Vec<_> = store
+                .get_all_token_metadata()
+                .context("Failed to get token metadata")?
+                .into_iter()
+                .map(|(token, metadata)| {
+                    json!({
+                        "token": format!("0x{:x}", token),
+                        "name": metadata.name,
+                        "symbol": metadata.symbol,
+                        "decimals": metadata.decimals,
+                        "nonStandard": metadata.non_standard,
+                    })
+                })
+                .collect();
+
+            json!({
+                "count": tokens.len(),
+                "tokens": tokens
+            })
+        }
+        StoreCommand::DiscoveredTokens { owner } => {
+            let owner_addr = owner.as_deref().map(parse_address).transpose()?;
+            let tokens: Vec<_> = store
+                .get_token_watch_metas()
+                .context("Failed to get token watch metadata")?
+                .into_iter()
+                .filter(|(_, owner, meta)| {
+                    meta.auto_discovered && (owner_addr.is_none() || owner_addr == Some(*owner))
+                })
+                .map(|(token, owner, meta)| {
+                    json!({
+                        "token": format!("0x{:x}", token),
+                        "owner": format!("0x{:x}", owner),
+                        "startBlock": meta.start_block,
+                    })
+                })
+                .collect();
+
+            json!({
+                "owner": owner_addr.map(|a| format!("0x{:x}", a)),
+                "count": tokens.len(),
+                "tokens": tokens
+            })
+        }
+    };
+    Ok(result)
 }
@@ -2,11 +2,142 @@
 //!
 //! Handles loading the watchlist from a file.
 //! Each line should contain one Ethereum address in hex format.
+//! Also defines the chain profile that selects chain-specific fee rules,
+//! the follow mode that selects how far behind the tip the watcher stays,
+//! and the accounting strategy of each ERC20 token.
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, U256};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
+
+/// Chain profile selecting chain-specific transaction and fee rules.
+///
+/// A watched fee recipient is credited priority fees only: neither profile
+/// applies static block or uncle rewards, which only pre-Merge Ethereum
+/// blocks pay on the execution layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ChainProfile {
+    /// Ethereum mainnet and testnets (L1 rules)
+    #[default]
+    Ethereum,
+    /// OP Stack L2s (Optimism, Base, ...): senders also pay the receipt's `l1Fee`,
+    /// and deposit transactions (type 0x7e) pay no fee and mint value to the sender
+    OpStack,
+}
+
+/// Which block the watcher follows, i.e. how final processed blocks are.
+///
+/// Parsed from `latest`, `latest-N` (N confirmations), `safe` or `finalized`.
+/// The mode used for the last committed block is stored with the head, so
+/// queries can report the finality of the data they return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FollowMode {
+    /// The chain tip (may be reorganized)
+    #[default]
+    Latest,
+    /// The chain tip minus N confirmations
+    Confirmations(u64),
+    /// The consensus-layer `safe` block
+    Safe,
+    /// The consensus-layer `finalized` block (cannot be reorganized)
+    Finalized,
+}
+
+impl FromStr for FollowMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "latest" => Ok(FollowMode::Latest),
+            "safe" => Ok(FollowMode::Safe),
+            "finalized" => Ok(FollowMode::Finalized),
+            _ => {
+                let n = s
+                    .strip_prefix("latest-")
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .with_context(|| {
+                        format!(
+                            "Invalid follow mode '{}' (expected latest, latest-N, safe or finalized)",
+                            s
+                        )
+                    })?;
+                Ok(if n == 0 {
+                    FollowMode::Latest
+                } else {
+                    FollowMode::Confirmations(n)
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for FollowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FollowMode::Latest => write!(f, "latest"),
+            FollowMode::Confirmations(n) => write!(f, "latest-{}", n),
+            FollowMode::Safe => write!(f, "safe"),
+            FollowMode::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+/// How the balances of an ERC20 token are derived.
+///
+/// Parsed from `transfers`, `poll`, `poll:N` or `shares` (the optional
+/// second column of the token watchlist).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenStrategy {
+    /// Apply `Transfer` events only (standard ERC20)
+    #[default]
+    Transfers,
+    /// Apply `Transfer` events, and every N blocks poll `balanceOf` and record
+    /// the remaining change as a rebase (aTokens and other rebasing tokens)
+    Poll(u64),
+    /// Apply `Transfer` events, follow share balances through `TransferShares`
+    /// events and convert them with the token's share rate every block (stETH)
+    Shares,
+}
+
+impl FromStr for TokenStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "transfers" => Ok(TokenStrategy::Transfers),
+            "poll" => Ok(TokenStrategy::Poll(1)),
+            "shares" => Ok(TokenStrategy::Shares),
+            _ => {
+                let n = s
+                    .strip_prefix("poll:")
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .filter(|n| *n > 0)
+                    .with_context(|| {
+                        format!(
+                            "Invalid token strategy '{}' (expected transfers, poll, poll:N or shares)",
+                            s
+                        )
+                    })?;
+                Ok(TokenStrategy::Poll(n))
+            }
+        }
+    }
+}
+
+impl fmt::Display for TokenStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenStrategy::Transfers => write!(f, "transfers"),
+            TokenStrategy::Poll(1) => write!(f, "poll"),
+            TokenStrategy::Poll(n) => write!(f, "poll:{}", n),
+            TokenStrategy::Shares => write!(f, "shares"),
+        }
+    }
+}
 
 /// Load a watchlist from a file.
 ///
@@ -66,7 +197,7 @@ fn pad_hex_string(s: &str) -> String {
 /// Parse an address from a hex string.
 ///
 /// Accepts addresses with or without 0x prefix.
-fn parse_address(s: &str) -> Result<Address> {
+pub(crate) fn parse_address(s: &str) -> Result<Address> {
     let s = s.strip_prefix("0x").unwrap_or(s);
     let s = pad_hex_string(s);
     let bytes = hex::decode(&s)
@@ -81,33 +212,107 @@ fn parse_address(s: &str) -> Result<Address> {
 
 /// Load a token watchlist from a file.
 ///
-/// Each line should contain one ERC20 token contract address in hex format.
+/// Each line should contain one ERC20 token contract address in hex format,
+/// optionally followed by its `TokenStrategy` (see `load_token_entries`).
 /// Empty lines and lines starting with '#' are ignored.
 ///
 /// # Example file format:
-/// ```
+/// ```text
 /// # USDT on mainnet
 /// 0xdAC17F958D2ee523a2206206994597C13D831ec7
 /// # USDC on mainnet
 /// 0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48
 /// ```
 pub fn load_token_watchlist(path: &Path) -> Result<Vec<Address>> {
+    Ok(load_token_entries(path)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
+}
+
+/// Load a token watchlist with the accounting strategy of each token.
+///
+/// The strategy column is optional and defaults to `transfers`.
+///
+/// # Example file format:
+/// ```text
+/// 0xdAC17F958D2ee523a2206206994597C13D831ec7
+/// # stETH: share-based
+/// 0xae7ab96520DE3A18E5e111B5EaAb095312D7fE84 shares
+/// # Aave v3 aUSDC: poll balanceOf every 10 blocks
+/// 0x98C23E9d8f34FEFb1B7BD6a91B7FF122F4e16F5c poll:10
+/// ```
+pub fn load_token_entries(path: &Path) -> Result<Vec<(Address, TokenStrategy)>> {
     let contents = fs::read_to_string(path)
         .with_context(|| format!("Failed to read token watchlist file: {:?}", path))?;
 
-    let mut addresses = Vec::new();
+    let mut entries = Vec::new();
     for (line_num, line) in contents.lines().enumerate() {
         let line = line.trim();
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
-        let addr = parse_address(line).with_context(|| {
+        let mut parts = line.split_whitespace();
+        let (Some(token), strategy, None) = (parts.next(), parts.next(), parts.next()) else {
+            anyhow::bail!(
+                "Expected '<token> [strategy]' on line {}: {}",
+                line_num + 1,
+                line
+            );
+        };
+        let addr = parse_address(token).with_context(|| {
             format!("Invalid token address on line {}: {}", line_num + 1, line)
         })?;
-        addresses.push(addr);
+        let strategy = strategy
+            .map(str::parse::<TokenStrategy>)
+            .transpose()
+            .with_context(|| format!("Invalid token strategy on line {}: {}", line_num + 1, line))?
+            .unwrap_or_default();
+        entries.push((addr, strategy));
     }
 
-    Ok(addresses)
+    Ok(entries)
+}
+
+/// Load an ERC1155 watchlist from a file.
+///
+/// Each line holds a contract address and a token ID (decimal or 0x-prefixed hex),
+/// separated by whitespace. Every (contract, id) is tracked for every watched address.
+///
+/// # Example file format:
+/// ```text
+/// # contract                                 id
+/// 0x76BE3b62873462d2142405439777e971754E8E77 10
+/// 0x76BE3b62873462d2142405439777e971754E8E77 0x2a
+/// ```
+pub fn load_erc1155_watchlist(path: &Path) -> Result<Vec<(Address, U256)>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ERC1155 watchlist file: {:?}", path))?;
+
+    let mut pairs = Vec::new();
+    for (line_num, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(contract), Some(id), None) = (parts.next(), parts.next(), parts.next()) else {
+            anyhow::bail!(
+                "Expected '<contract> <id>' on line {}: {}",
+                line_num + 1,
+                line
+            );
+        };
+        let contract = parse_address(contract).with_context(|| {
+            format!("Invalid ERC1155 contract on line {}: {}", line_num + 1, line)
+        })?;
+        let id = id
+            .parse::<U256>()
+            .with_context(|| format!("Invalid ERC1155 token ID on line {}: {}", line_num + 1, line))?;
+        pairs.push((contract, id));
+    }
+
+    Ok(pairs)
 }
 
 #[cfg(test)]
@@ -142,4 +347,63 @@ mod tests {
         let addr2 = parse_address("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap();
         assert_eq!(addr1, addr2);
     }
+
+    #[test]
+    fn test_load_erc1155_watchlist() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# contract id").unwrap();
+        writeln!(file, "0x76BE3b62873462d2142405439777e971754E8E77 10").unwrap();
+        writeln!(file, "0x76BE3b62873462d2142405439777e971754E8E77  0x2a").unwrap();
+        file.flush().unwrap();
+
+        let pairs = load_erc1155_watchlist(file.path()).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].1, U256::from(10u64));
+        assert_eq!(pairs[1].1, U256::from(42u64));
+
+        writeln!(file, "0x76BE3b62873462d2142405439777e971754E8E77").unwrap();
+        file.flush().unwrap();
+        assert!(load_erc1155_watchlist(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_token_entries() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        writeln!(file, "0xae7ab96520DE3A18E5e111B5EaAb095312D7fE84 shares").unwrap();
+        writeln!(file, "0x98C23E9d8f34FEFb1B7BD6a91B7FF122F4e16F5c poll:10").unwrap();
+        file.flush().unwrap();
+
+        let entries = load_token_entries(file.path()).unwrap();
+        let strategies: Vec<_> = entries.iter().map(|(_, s)| *s).collect();
+        assert_eq!(
+            strategies,
+            vec![TokenStrategy::Transfers, TokenStrategy::Shares, TokenStrategy::Poll(10)]
+        );
+        assert_eq!(load_token_watchlist(file.path()).unwrap().len(), 3);
+        assert_eq!("poll".parse::<TokenStrategy>().unwrap(), TokenStrategy::Poll(1));
+        assert_eq!(TokenStrategy::Poll(10).to_string(), "poll:10");
+        assert!("poll:0".parse::<TokenStrategy>().is_err());
+
+        writeln!(file, "0x98C23E9d8f34FEFb1B7BD6a91B7FF122F4e16F5c rebase").unwrap();
+        file.flush().unwrap();
+        assert!(load_token_entries(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_follow_mode_parse_roundtrip() {
+        for (input, mode) in [
+            ("latest", FollowMode::Latest),
+            ("latest-12", FollowMode::Confirmations(12)),
+            ("safe", FollowMode::Safe),
+            ("finalized", FollowMode::Finalized),
+        ] {
+            let parsed: FollowMode = input.parse().unwrap();
+            assert_eq!(parsed, mode);
+            assert_eq!(parsed.to_string(), input);
+        }
+        assert_eq!("latest-0".parse::<FollowMode>().unwrap(), FollowMode::Latest);
+        assert!("pending".parse::<FollowMode>().is_err());
+        assert!("latest-x".parse::<FollowMode>().is_err());
+    }
 }
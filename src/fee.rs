@@ -1,8 +1,10 @@
 //! Gas fee calculation utilities
 //!
 //! Handles calculation of effective gas price and total fees
-//! for both legacy and EIP-1559 transactions.
+//! for legacy, EIP-1559 and EIP-4844 (blob) transactions, plus the
+//! OP Stack L1 data fee when running with the `OpStack` chain profile.
 
+use crate::config::ChainProfile;
 use crate::types::{Block, Receipt, Transaction};
 use alloy_primitives::U256;
 use anyhow::{Context, Result};
@@ -11,8 +13,10 @@ use anyhow::{Context, Result};
 ///
 /// Priority order:
 /// 1. Use `effective_gas_price` from receipt if available (post-London)
-/// 2. Use `gas_price` for legacy transactions
-/// 3. Calculate for EIP-1559: `min(max_fee, base_fee + max_priority_fee)`
+/// 2. Use `gas_price` for legacy / access list transactions (types 0, 1)
+/// 3. Calculate for EIP-1559 / blob transactions (types 2, 3):
+///    `min(max_fee, base_fee + max_priority_fee)`
+/// 4. Fall back to `gas_price` for unknown types that carry one
 pub fn calculate_effective_gas_price(
     tx: &Transaction,
     receipt: &Receipt,
@@ -55,7 +59,14 @@ pub fn calculate_effective_gas_price(
         return Ok(effective);
     }
 
-    anyhow::bail!("Transaction type not recognized (neither legacy nor EIP-1559)");
+    if let Some(gas_price) = tx.gas_price {
+        return Ok(gas_price);
+    }
+
+    anyhow::bail!(
+        "Transaction type {:?} not recognized and has no gas_price",
+        tx.tx_type
+    );
 }
 
 /// Calculate the total fee paid for a transaction.
@@ -65,17 +76,106 @@ pub fn calculate_fee(gas_used: U256, effective_gas_price: U256) -> U256 {
     gas_used.saturating_mul(effective_gas_price)
 }
 
+/// Calculate the blob fee paid by an EIP-4844 transaction.
+///
+/// Blob fee = blob_gas_used * blob_gas_price. It is charged up front and is
+/// not refunded if the transaction reverts. Zero for non-blob transactions.
+pub fn calculate_blob_fee(receipt: &Receipt) -> U256 {
+    match (receipt.blob_gas_used, receipt.blob_gas_price) {
+        (Some(used), Some(price)) => used.saturating_mul(price),
+        _ => U256::ZERO,
+    }
+}
+
+/// Fee components charged to a transaction's sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeBreakdown {
+    /// Effective gas price used for the execution fee
+    pub effective_gas_price: U256,
+    /// Execution gas fee: gas_used * effective_gas_price
+    pub execution_fee: U256,
+    /// Blob gas fee (EIP-4844): blob_gas_used * blob_gas_price
+    pub blob_fee: U256,
+    /// L1 data fee (OP Stack): the receipt's `l1Fee`
+    pub l1_fee: U256,
+}
+
+impl FeeBreakdown {
+    /// Fee breakdown for a transaction that pays nothing.
+    pub fn zero() -> Self {
+        Self {
+            effective_gas_price: U256::ZERO,
+            execution_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+        }
+    }
+
+    /// Total amount deducted from the sender for fees.
+    pub fn total(&self) -> U256 {
+        self.execution_fee
+            .saturating_add(self.blob_fee)
+            .saturating_add(self.l1_fee)
+    }
+}
+
+/// Calculate all fee components for a transaction under the given chain profile.
+///
+/// With `ChainProfile::OpStack`, deposit transactions (type 0x7e) pay no fee
+/// at all and every other transaction also pays the receipt's `l1Fee`.
+pub fn calculate_fee_breakdown(
+    tx: &Transaction,
+    receipt: &Receipt,
+    block: &Block,
+    profile: ChainProfile,
+) -> Result<FeeBreakdown> {
+    if profile == ChainProfile::OpStack && tx.is_deposit() {
+        // Deposit gas is bought on L1; nothing is charged on L2
+        return Ok(FeeBreakdown::zero());
+    }
+
+    let effective_gas_price = calculate_effective_gas_price(tx, receipt, block)?;
+    let l1_fee = match profile {
+        ChainProfile::Ethereum => U256::ZERO,
+        ChainProfile::OpStack => receipt.l1_fee.unwrap_or(U256::ZERO),
+    };
+    Ok(FeeBreakdown {
+        effective_gas_price,
+        execution_fee: calculate_fee(receipt.gas_used, effective_gas_price),
+        blob_fee: calculate_blob_fee(receipt),
+        l1_fee,
+    })
+}
+
+/// Calculate the priority fee paid to the block's fee recipient.
+///
+/// Priority fee = (effective_gas_price - base_fee) * gas_used.
+/// The base fee is burned (EIP-1559); before London there is no base fee and
+/// the whole fee goes to the fee recipient.
+pub fn calculate_priority_fee(
+    gas_used: U256,
+    effective_gas_price: U256,
+    base_fee: Option<U256>,
+) -> U256 {
+    let tip_per_gas = effective_gas_price.saturating_sub(base_fee.unwrap_or(U256::ZERO));
+    gas_used.saturating_mul(tip_per_gas)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::TxType;
     use alloy_primitives::{address, b256};
 
     fn create_test_block(base_fee: Option<U256>) -> Block {
         Block {
             number: 12345,
             hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
+            parent_hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
             base_fee_per_gas: base_fee,
+            miner: address!("0000000000000000000000000000000000000000"),
             transactions: vec![],
+            withdrawals: vec![],
         }
     }
 
@@ -84,6 +184,7 @@ mod tests {
             hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
             from: address!("0000000000000000000000000000000000000000"),
             to: Some(address!("0000000000000000000000000000000000000001")),
+            tx_type: TxType::Legacy,
             value: U256::ZERO,
             gas_price: Some(gas_price),
             max_fee_per_gas: None,
@@ -91,6 +192,7 @@ mod tests {
             gas: U256::from(21000),
             input: vec![],
             nonce: 0,
+            mint: None,
         }
     }
 
@@ -99,6 +201,7 @@ mod tests {
             hash: b256!("0000000000000000000000000000000000000000000000000000000000000000"),
             from: address!("0000000000000000000000000000000000000000"),
             to: Some(address!("0000000000000000000000000000000000000001")),
+            tx_type: TxType::Eip1559,
             value: U256::ZERO,
             gas_price: None,
             max_fee_per_gas: Some(max_fee),
@@ -106,6 +209,7 @@ mod tests {
             gas: U256::from(21000),
             input: vec![],
             nonce: 0,
+            mint: None,
         }
     }
 
@@ -115,6 +219,26 @@ mod tests {
             gas_used,
             effective_gas_price,
             logs: vec![],
+            blob_gas_used: None,
+            blob_gas_price: None,
+            l1_fee: None,
+            transaction_hash: None,
+        }
+    }
+
+    fn create_blob_tx(max_fee: U256, max_priority_fee: U256) -> Transaction {
+        Transaction {
+            tx_type: TxType::Blob,
+            ..create_eip1559_tx(max_fee, max_priority_fee)
+        }
+    }
+
+    fn create_blob_receipt(status: u64, gas_used: U256, blob_gas_used: U256, blob_gas_price: U256) -> Receipt {
+        Receipt {
+            status,
+            blob_gas_used: Some(blob_gas_used),
+            blob_gas_price: Some(blob_gas_price),
+            ..create_receipt(gas_used, None)
         }
     }
 
@@ -177,4 +301,127 @@ mod tests {
         let effective = calculate_effective_gas_price(&tx, &receipt, &block).unwrap();
         assert_eq!(effective, U256::from(15_000_000_000u64));
     }
+
+    #[test]
+    fn test_priority_fee_excludes_base_fee() {
+        let base_fee = U256::from(10_000_000_000u64); // 10 gwei
+        let block = create_test_block(Some(base_fee));
+        let tx = create_eip1559_tx(
+            U256::from(30_000_000_000u64),
+            U256::from(2_000_000_000u64),
+        );
+        let receipt = create_receipt(U256::from(21000), None);
+
+        let effective = calculate_effective_gas_price(&tx, &receipt, &block).unwrap();
+        let tip = calculate_priority_fee(receipt.gas_used, effective, block.base_fee_per_gas);
+        // (12 gwei - 10 gwei) * 21000 = 42_000_000_000_000
+        assert_eq!(tip, U256::from(42_000_000_000_000u64));
+    }
+
+    #[test]
+    fn test_priority_fee_pre_london_is_full_fee() {
+        let block = create_test_block(None);
+        let tx = create_legacy_tx(U256::from(20_000_000_000u64));
+        let receipt = create_receipt(U256::from(21000), None);
+
+        let effective = calculate_effective_gas_price(&tx, &receipt, &block).unwrap();
+        let tip = calculate_priority_fee(receipt.gas_used, effective, block.base_fee_per_gas);
+        assert_eq!(tip, calculate_fee(receipt.gas_used, effective));
+    }
+
+    #[test]
+    fn test_blob_tx_fee_includes_blob_gas() {
+        let base_fee = U256::from(10_000_000_000u64); // 10 gwei
+        let block = create_test_block(Some(base_fee));
+        let tx = create_blob_tx(
+            U256::from(30_000_000_000u64),
+            U256::from(2_000_000_000u64),
+        );
+        // One blob (131072 blob gas) at 3 wei per blob gas
+        let receipt = create_blob_receipt(1, U256::from(21000), U256::from(131072u64), U256::from(3u64));
+
+        assert!(tx.is_eip1559());
+        assert!(tx.is_blob());
+
+        let fees = calculate_fee_breakdown(&tx, &receipt, &block, ChainProfile::Ethereum).unwrap();
+        // Execution gas is priced like EIP-1559: min(30, 10 + 2) = 12 gwei
+        assert_eq!(fees.effective_gas_price, U256::from(12_000_000_000u64));
+        assert_eq!(fees.execution_fee, U256::from(252_000_000_000_000u64));
+        assert_eq!(fees.blob_fee, U256::from(393_216u64));
+        assert_eq!(fees.total(), U256::from(252_000_000_393_216u64));
+    }
+
+    #[test]
+    fn test_blob_fee_charged_on_failure() {
+        let block = create_test_block(Some(U256::from(10_000_000_000u64)));
+        let tx = create_blob_tx(
+            U256::from(30_000_000_000u64),
+            U256::from(2_000_000_000u64),
+        );
+        let receipt = create_blob_receipt(0, U256::from(21000), U256::from(262144u64), U256::from(1u64));
+
+        let fees = calculate_fee_breakdown(&tx, &receipt, &block, ChainProfile::Ethereum).unwrap();
+        assert_eq!(fees.blob_fee, U256::from(262144u64));
+    }
+
+    #[test]
+    fn test_non_blob_tx_has_no_blob_fee() {
+        let block = create_test_block(None);
+        let tx = create_legacy_tx(U256::from(20_000_000_000u64));
+        let receipt = create_receipt(U256::from(21000), None);
+
+        let fees = calculate_fee_breakdown(&tx, &receipt, &block, ChainProfile::Ethereum).unwrap();
+        assert_eq!(fees.blob_fee, U256::ZERO);
+        assert_eq!(fees.total(), fees.execution_fee);
+    }
+
+    #[test]
+    fn test_tx_type_from_byte() {
+        assert_eq!(TxType::from_byte(0), TxType::Legacy);
+        assert_eq!(TxType::from_byte(1), TxType::AccessList);
+        assert_eq!(TxType::from_byte(2), TxType::Eip1559);
+        assert_eq!(TxType::from_byte(3), TxType::Blob);
+        assert_eq!(TxType::from_byte(0x7e), TxType::Deposit);
+        assert_eq!(TxType::from_byte(0x64), TxType::Unknown(0x64));
+        assert!(TxType::AccessList.uses_gas_price());
+        assert!(TxType::Blob.uses_dynamic_fee());
+    }
+
+    #[test]
+    fn test_op_stack_adds_l1_fee() {
+        let block = create_test_block(Some(U256::from(1_000_000u64)));
+        let tx = create_eip1559_tx(U256::from(2_000_000u64), U256::from(1_000u64));
+        let receipt = Receipt {
+            l1_fee: Some(U256::from(50_000_000_000u64)),
+            ..create_receipt(U256::from(21000), Some(U256::from(1_001_000u64)))
+        };
+
+        let fees = calculate_fee_breakdown(&tx, &receipt, &block, ChainProfile::OpStack).unwrap();
+        assert_eq!(fees.execution_fee, U256::from(21_021_000_000u64)); // 21000 * 1_001_000
+        assert_eq!(fees.l1_fee, U256::from(50_000_000_000u64));
+        assert_eq!(fees.total(), U256::from(71_021_000_000u64));
+
+        // The Ethereum profile ignores l1Fee
+        let fees = calculate_fee_breakdown(&tx, &receipt, &block, ChainProfile::Ethereum).unwrap();
+        assert_eq!(fees.l1_fee, U256::ZERO);
+        assert_eq!(fees.total(), fees.execution_fee);
+    }
+
+    #[test]
+    fn test_op_stack_deposit_pays_no_fee() {
+        let block = create_test_block(Some(U256::from(1_000_000u64)));
+        let tx = Transaction {
+            tx_type: TxType::Deposit,
+            gas_price: None,
+            mint: Some(U256::from(1_000_000_000_000_000_000u64)),
+            ..create_legacy_tx(U256::ZERO)
+        };
+        let receipt = Receipt {
+            l1_fee: Some(U256::from(50_000_000_000u64)),
+            ..create_receipt(U256::from(50000), None)
+        };
+
+        let fees = calculate_fee_breakdown(&tx, &receipt, &block, ChainProfile::OpStack).unwrap();
+        assert_eq!(fees, FeeBreakdown::zero());
+    }
 }
@@ -0,0 +1,325 @@
+//! New-head notifications that drive the watcher loop
+//!
+//! By default the watcher polls for new blocks at a fixed interval. With a
+//! WebSocket URL it instead subscribes to `eth_subscribe("newHeads")` and
+//! polls as soon as a head arrives. The subscription is only a wake-up
+//! signal: blocks are still fetched and checked for reorgs by
+//! `process_block_range`, so a missed or duplicated notification is harmless.
+//!
+//! When the connection drops, the loop falls back to interval polling and
+//! reconnects with exponential backoff. Heads that replace an already seen
+//! head (same number, different hash, or a parent hash that does not match)
+//! are reported as reorgs and logged.
+
+use crate::types::BlockHeader;
+use alloy_primitives::B256;
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{info, warn};
+
+/// Number of recent heads remembered for reorg detection.
+const RECENT_HEADS: usize = 128;
+
+/// First reconnect delay, doubled per failed attempt.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Upper bound for the reconnect delay.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Timeout for connecting and confirming the subscription.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a new head relates to the heads seen before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadObservation {
+    /// Extends the known chain (or is the first head seen).
+    New,
+    /// Same number and hash as a head already seen.
+    Duplicate,
+    /// Replaces a previously seen head at `number`.
+    Reorg { number: u64, replaced: B256 },
+}
+
+/// Remembers recent subscription heads and classifies new ones.
+#[derive(Debug, Default)]
+pub struct HeadTracker {
+    /// Block number -> hash of recently seen heads
+    recent: BTreeMap<u64, B256>,
+}
+
+impl HeadTracker {
+    /// Create an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a head and report whether it reorgs a previously seen one.
+    pub fn observe(&mut self, head: &BlockHeader) -> HeadObservation {
+        let observation = match self.recent.get(&head.number) {
+            Some(hash) if *hash == head.hash => return HeadObservation::Duplicate,
+            Some(hash) => HeadObservation::Reorg {
+                number: head.number,
+                replaced: *hash,
+            },
+            None => match head.number.checked_sub(1).and_then(|n| self.recent.get(&n)) {
+                Some(parent) if *parent != head.parent_hash => HeadObservation::Reorg {
+                    number: head.number - 1,
+                    replaced: *parent,
+                },
+                _ => HeadObservation::New,
+            },
+        };
+
+        // Forget the abandoned branch and record the new one
+        if let HeadObservation::Reorg { number, .. } = observation {
+            self.recent.split_off(&number);
+        }
+        if let Some(parent) = head.number.checked_sub(1) {
+            self.recent.entry(parent).or_insert(head.parent_hash);
+        }
+        self.recent.insert(head.number, head.hash);
+        while self.recent.len() > RECENT_HEADS {
+            self.recent.pop_first();
+        }
+
+        observation
+    }
+}
+
+/// Extract the head from an `eth_subscription` notification.
+///
+/// Returns `None` for any other message (e.g. the subscribe response).
+pub fn parse_new_head(message: &Value) -> Option<BlockHeader> {
+    if message.get("method")?.as_str()? != "eth_subscription" {
+        return None;
+    }
+    let result = message.get("params")?.get("result")?;
+    serde_json::from_value(result.clone()).ok()
+}
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// `eth_subscribe("newHeads")` subscription with reconnect and polling fallback.
+pub struct NewHeadsSubscription {
+    url: String,
+    poll_interval: Duration,
+    stream: Option<WsStream>,
+    reconnect_delay: Duration,
+    next_reconnect: Instant,
+    tracker: HeadTracker,
+}
+
+impl NewHeadsSubscription {
+    /// Create a subscription to `url`; connects lazily on the first wait.
+    ///
+    /// `poll_interval` is used as the fallback while disconnected and as the
+    /// longest wait for a head while connected.
+    pub fn new(url: String, poll_interval: Duration) -> Self {
+        Self {
+            url,
+            poll_interval,
+            stream: None,
+            reconnect_delay: INITIAL_RECONNECT_DELAY,
+            next_reconnect: Instant::now(),
+            tracker: HeadTracker::new(),
+        }
+    }
+
+    /// Wait until the watcher should poll for new blocks.
+    ///
+    /// Returns when a new head arrives, or after the poll interval if the
+    /// subscription is down or quiet.
+    pub async fn wait(&mut self) {
+        if self.stream.is_none() && Instant::now() >= self.next_reconnect {
+            self.reconnect().await;
+        }
+
+        let Some(stream) = self.stream.as_mut() else {
+            // Disconnected: fall back to polling
+            tokio::time::sleep(self.poll_interval).await;
+            return;
+        };
+
+        let deadline = tokio::time::Instant::now() + self.poll_interval;
+        loop {
+            match tokio::time::timeout_at(deadline, next_head(stream)).await {
+                Err(_) => return, // Quiet chain: poll anyway
+                Ok(Err(e)) => {
+                    warn!("newHeads subscription lost, falling back to polling: {:?}", e);
+                    self.disconnect();
+                    return;
+                }
+                Ok(Ok(head)) => match self.tracker.observe(&head) {
+                    HeadObservation::New => return,
+                    HeadObservation::Duplicate => continue,
+                    HeadObservation::Reorg { number, replaced } => {
+                        warn!(
+                            "Reorged head from subscription: block {} {:?} replaces {:?} at block {}; \
+                            rollback is handled when the block is processed",
+                            head.number, head.hash, replaced, number
+                        );
+                        return;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Try to (re)connect and subscribe; on failure schedule the next attempt.
+    async fn reconnect(&mut self) {
+        match tokio::time::timeout(CONNECT_TIMEOUT, subscribe(&self.url)).await {
+            Ok(Ok(stream)) => {
+                info!("Subscribed to newHeads at {}", self.url);
+                self.stream = Some(stream);
+                self.reconnect_delay = INITIAL_RECONNECT_DELAY;
+            }
+            Ok(Err(e)) => self.schedule_reconnect(format!("{:?}", e)),
+            Err(_) => self.schedule_reconnect("connection timed out".to_string()),
+        }
+    }
+
+    fn schedule_reconnect(&mut self, reason: String) {
+        warn!(
+            "newHeads subscription to {} failed ({}), polling every {:?}; reconnecting in {:?}",
+            self.url, reason, self.poll_interval, self.reconnect_delay
+        );
+        self.next_reconnect = Instant::now() + self.reconnect_delay;
+        self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+
+    fn disconnect(&mut self) {
+        self.stream = None;
+        self.next_reconnect = Instant::now();
+    }
+}
+
+/// Connect to `url` and confirm an `eth_subscribe("newHeads")` subscription.
+async fn subscribe(url: &str) -> Result<WsStream> {
+    let (mut stream, _) = connect_async(url)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["newHeads"]
+    });
+    stream
+        .send(Message::Text(request.to_string()))
+        .await
+        .context("Failed to send eth_subscribe")?;
+
+    // Wait for the subscription id
+    loop {
+        let message = next_json(&mut stream).await?;
+        if message.get("id").and_then(|id| id.as_u64()) != Some(1) {
+            continue;
+        }
+        if let Some(error) = message.get("error") {
+            anyhow::bail!("eth_subscribe failed: {}", error);
+        }
+        message
+            .get("result")
+            .and_then(|r| r.as_str())
+            .context("eth_subscribe returned no subscription id")?;
+        return Ok(stream);
+    }
+}
+
+/// Read the next new-head notification, skipping other messages.
+async fn next_head(stream: &mut WsStream) -> Result<BlockHeader> {
+    loop {
+        if let Some(head) = parse_new_head(&next_json(stream).await?) {
+            return Ok(head);
+        }
+    }
+}
+
+/// Read the next JSON text message (pings are answered by the stream itself).
+async fn next_json(stream: &mut WsStream) -> Result<Value> {
+    loop {
+        let message = stream
+            .next()
+            .await
+            .context("WebSocket closed")?
+            .context("WebSocket read failed")?;
+        match message {
+            Message::Text(text) => {
+                return serde_json::from_str(&text).context("Invalid JSON from WebSocket")
+            }
+            Message::Binary(bytes) => {
+                return serde_json::from_slice(&bytes).context("Invalid JSON from WebSocket")
+            }
+            Message::Close(frame) => anyhow::bail!("WebSocket closed by server: {:?}", frame),
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(number: u64, hash: u8, parent: u8) -> BlockHeader {
+        BlockHeader {
+            number,
+            hash: B256::repeat_byte(hash),
+            parent_hash: B256::repeat_byte(parent),
+        }
+    }
+
+    #[test]
+    fn test_head_tracker_detects_reorgs() {
+        let mut tracker = HeadTracker::new();
+        assert_eq!(tracker.observe(&header(100, 0x10, 0x0f)), HeadObservation::New);
+        assert_eq!(tracker.observe(&header(101, 0x11, 0x10)), HeadObservation::New);
+        assert_eq!(tracker.observe(&header(101, 0x11, 0x10)), HeadObservation::Duplicate);
+
+        // Same number, different hash
+        assert_eq!(
+            tracker.observe(&header(101, 0xa1, 0x10)),
+            HeadObservation::Reorg {
+                number: 101,
+                replaced: B256::repeat_byte(0x11)
+            }
+        );
+
+        // Next head on a branch that also replaced 101
+        assert_eq!(
+            tracker.observe(&header(102, 0xb2, 0xb1)),
+            HeadObservation::Reorg {
+                number: 101,
+                replaced: B256::repeat_byte(0xa1)
+            }
+        );
+        assert_eq!(tracker.observe(&header(103, 0xb3, 0xb2)), HeadObservation::New);
+    }
+
+    #[test]
+    fn test_parse_new_head() {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": {
+                "subscription": "0x9ce59a13059e417087c02d3236a0b1cc",
+                "result": {
+                    "number": "0x1b4",
+                    "hash": format!("0x{}", "11".repeat(32)),
+                    "parentHash": format!("0x{}", "10".repeat(32)),
+                    "miner": "0x0000000000000000000000000000000000000000"
+                }
+            }
+        });
+        assert_eq!(parse_new_head(&notification), Some(header(0x1b4, 0x11, 0x10)));
+
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": "0x9ce59a13059e417087c02d3236a0b1cc" });
+        assert_eq!(parse_new_head(&response), None);
+    }
+}
@@ -3,7 +3,7 @@
 //! All keys use a single-byte prefix followed by binary data.
 //! This ensures deterministic, lexicographically ordered keys in RocksDB.
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 use anyhow;
 
 /// Encode an account key.
@@ -62,6 +62,17 @@ pub fn encode_block_hash_key(block: u64) -> Vec<u8> {
     key
 }
 
+/// Encode a block undo key.
+///
+/// Format: byte 'R' (0x52) + block_number (8 bytes, big-endian)
+/// Total length: 9 bytes
+pub fn encode_block_undo_key(block: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(9);
+    key.push(b'R');
+    key.extend_from_slice(&block.to_be_bytes());
+    key
+}
+
 /// Encode a meta key.
 ///
 /// Format: byte 'M' (0x4D) + meta_id (1 byte)
@@ -69,6 +80,8 @@ pub fn encode_block_hash_key(block: u64) -> Vec<u8> {
 ///
 /// Meta IDs:
 /// - 0x01: head_block
+/// - 0x02: head_finality
+/// - 0x03: schema_version
 pub fn encode_meta_key(meta_id: u8) -> Vec<u8> {
     vec![b'M', meta_id]
 }
@@ -257,6 +270,337 @@ pub fn decode_token_watch_meta_key(key: &[u8]) -> Result<(Address, Address), any
     Ok((token, owner))
 }
 
+/// Encode a token metadata key.
+///
+/// Format: byte 'G' (0x47) + token (20 bytes)
+/// Total length: 21 bytes
+pub fn encode_token_metadata_key(token: Address) -> Vec<u8> {
+    let mut key = Vec::with_capacity(21);
+    key.push(b'G');
+    key.extend_from_slice(token.as_slice());
+    key
+}
+
+/// Decode a token metadata key back to the token address.
+pub fn decode_token_metadata_key(key: &[u8]) -> Result<Address, anyhow::Error> {
+    if key.len() != 21 {
+        anyhow::bail!("Token metadata key must be 21 bytes, got {}", key.len());
+    }
+    if key[0] != b'G' {
+        anyhow::bail!("Invalid token metadata key prefix");
+    }
+    Ok(Address::from_slice(&key[1..21]))
+}
+
+// -----------------------------------------------------------------------------
+// ERC1155 keys
+// -----------------------------------------------------------------------------
+
+/// Encode an ERC1155 key with the given prefix: contract + id + owner [+ block].
+fn encode_erc1155_key(
+    prefix: u8,
+    contract: Address,
+    id: U256,
+    owner: Address,
+    block: Option<u64>,
+) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 32 + 20 + 8);
+    key.push(prefix);
+    key.extend_from_slice(contract.as_slice());
+    key.extend_from_slice(&id.to_be_bytes::<32>());
+    key.extend_from_slice(owner.as_slice());
+    if let Some(block) = block {
+        key.extend_from_slice(&block.to_be_bytes());
+    }
+    key
+}
+
+/// Decode an ERC1155 delta or snapshot key back to (contract, id, owner, block).
+fn decode_erc1155_block_key(
+    prefix: u8,
+    key: &[u8],
+) -> Result<(Address, U256, Address, u64), anyhow::Error> {
+    if key.len() != 1 + 20 + 32 + 20 + 8 {
+        anyhow::bail!("ERC1155 key must be 81 bytes, got {}", key.len());
+    }
+    if key[0] != prefix {
+        anyhow::bail!("Invalid ERC1155 key prefix");
+    }
+    let contract = Address::from_slice(&key[1..21]);
+    let id = U256::from_be_slice(&key[21..53]);
+    let owner = Address::from_slice(&key[53..73]);
+    let block = u64::from_be_bytes(
+        key[73..81]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse block number"))?,
+    );
+    Ok((contract, id, owner, block))
+}
+
+/// Encode an ERC1155 delta key.
+///
+/// Format: 'I' (0x49) + contract(20 bytes) + id(32 bytes BE) + owner(20 bytes) + block(u64 BE)
+/// Total length: 81 bytes
+pub fn encode_erc1155_delta_key(contract: Address, id: U256, owner: Address, block: u64) -> Vec<u8> {
+    encode_erc1155_key(b'I', contract, id, owner, Some(block))
+}
+
+/// Decode an ERC1155 delta key back to (contract, id, owner, block).
+pub fn decode_erc1155_delta_key(key: &[u8]) -> Result<(Address, U256, Address, u64), anyhow::Error> {
+    decode_erc1155_block_key(b'I', key)
+}
+
+/// Encode an ERC1155 snapshot key.
+///
+/// Format: 'J' (0x4A) + contract(20 bytes) + id(32 bytes BE) + owner(20 bytes) + block(u64 BE)
+/// Total length: 81 bytes
+pub fn encode_erc1155_snapshot_key(
+    contract: Address,
+    id: U256,
+    owner: Address,
+    block: u64,
+) -> Vec<u8> {
+    encode_erc1155_key(b'J', contract, id, owner, Some(block))
+}
+
+/// Decode an ERC1155 snapshot key back to (contract, id, owner, block).
+pub fn decode_erc1155_snapshot_key(
+    key: &[u8],
+) -> Result<(Address, U256, Address, u64), anyhow::Error> {
+    decode_erc1155_block_key(b'J', key)
+}
+
+/// Encode an ERC1155 watch meta key (also used for the current balance).
+///
+/// Format: 'K' (0x4B) + contract(20 bytes) + id(32 bytes BE) + owner(20 bytes)
+/// Total length: 73 bytes
+pub fn encode_erc1155_watch_meta_key(contract: Address, id: U256, owner: Address) -> Vec<u8> {
+    encode_erc1155_key(b'K', contract, id, owner, None)
+}
+
+/// Encode a drift record key.
+///
+/// Format: 'F' (0x46) + block(u64 BE) + address(20 bytes) + token(20 bytes)
+/// Total length: 49 bytes
+/// Block-first so records can be listed in block order; ETH uses the zero token.
+pub fn encode_drift_key(block: u64, address: Address, token: Option<Address>) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 8 + 20 + 20);
+    key.push(b'F');
+    key.extend_from_slice(&block.to_be_bytes());
+    key.extend_from_slice(address.as_slice());
+    key.extend_from_slice(token.unwrap_or(Address::ZERO).as_slice());
+    key
+}
+
+/// Decode a drift record key back to (block, address, token).
+pub fn decode_drift_key(key: &[u8]) -> Result<(u64, Address, Option<Address>), anyhow::Error> {
+    if key.len() != 1 + 8 + 20 + 20 {
+        anyhow::bail!("Drift key must be 49 bytes, got {}", key.len());
+    }
+    if key[0] != b'F' {
+        anyhow::bail!("Invalid drift key prefix");
+    }
+    let block = u64::from_be_bytes(
+        key[1..9]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse block number"))?,
+    );
+    let address = Address::from_slice(&key[9..29]);
+    let token = Address::from_slice(&key[29..49]);
+    let token = if token == Address::ZERO { None } else { Some(token) };
+    Ok((block, address, token))
+}
+
+/// Encode a transaction history key (address-first for efficient prefix scans).
+///
+/// Format: 'V' (0x56) + address(20 bytes) + block(u64 BE) + tx_index(u32 BE) + seq(u16 BE)
+/// Total length: 35 bytes
+/// `seq` numbers several records of the same address in one transaction
+/// (e.g. a top-level transfer plus internal credits).
+pub fn encode_tx_record_key(addr: Address, block: u64, tx_index: u32, seq: u16) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 8 + 4 + 2);
+    key.push(b'V');
+    key.extend_from_slice(addr.as_slice());
+    key.extend_from_slice(&block.to_be_bytes());
+    key.extend_from_slice(&tx_index.to_be_bytes());
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+/// Decode a transaction history key back to (address, block, tx_index, seq).
+pub fn decode_tx_record_key(key: &[u8]) -> Result<(Address, u64, u32, u16), anyhow::Error> {
+    if key.len() != 35 {
+        anyhow::bail!("Tx record key must be 35 bytes, got {}", key.len());
+    }
+    if key[0] != b'V' {
+        anyhow::bail!("Invalid tx record key prefix");
+    }
+    let addr = Address::from_slice(&key[1..21]);
+    let block = u64::from_be_bytes(
+        key[21..29]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse block number"))?,
+    );
+    let tx_index = u32::from_be_bytes(
+        key[29..33]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse tx index"))?,
+    );
+    let seq = u16::from_be_bytes(
+        key[33..35]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse sequence number"))?,
+    );
+    Ok((addr, block, tx_index, seq))
+}
+
+/// Encode a decoded event key (block-first so events list in chain order).
+///
+/// Format: 'E' (0x45) + block(u64 BE) + log_ordinal(u32 BE)
+/// Total length: 13 bytes
+/// `log_ordinal` is the log's position among the logs of the block's
+/// successful transactions.
+pub fn encode_event_key(block: u64, log_ordinal: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 8 + 4);
+    key.push(b'E');
+    key.extend_from_slice(&block.to_be_bytes());
+    key.extend_from_slice(&log_ordinal.to_be_bytes());
+    key
+}
+
+/// Decode a decoded event key back to (block, log_ordinal).
+pub fn decode_event_key(key: &[u8]) -> Result<(u64, u32), anyhow::Error> {
+    if key.len() != 13 {
+        anyhow::bail!("Event key must be 13 bytes, got {}", key.len());
+    }
+    if key[0] != b'E' {
+        anyhow::bail!("Invalid event key prefix");
+    }
+    let block = u64::from_be_bytes(
+        key[1..9]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse block number"))?,
+    );
+    let log_ordinal = u32::from_be_bytes(
+        key[9..13]
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Failed to parse log ordinal"))?,
+    );
+    Ok((block, log_ordinal))
+}
+
+/// Encode a current NFT holding key (owner-first so holdings list by prefix scan).
+///
+/// Format: 'N' (0x4E) + owner(20 bytes) + contract(20 bytes) + token_id(32 bytes BE)
+/// Total length: 73 bytes
+pub fn encode_nft_holding_key(owner: Address, contract: Address, token_id: U256) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 20 + 32);
+    key.push(b'N');
+    key.extend_from_slice(owner.as_slice());
+    key.extend_from_slice(contract.as_slice());
+    key.extend_from_slice(&token_id.to_be_bytes::<32>());
+    key
+}
+
+/// Decode an NFT holding key back to (owner, contract, token_id).
+pub fn decode_nft_holding_key(key: &[u8]) -> Result<(Address, Address, U256), anyhow::Error> {
+    if key.len() != 73 {
+        anyhow::bail!("NFT holding key must be 73 bytes, got {}", key.len());
+    }
+    if key[0] != b'N' {
+        anyhow::bail!("Invalid NFT holding key prefix");
+    }
+    let owner = Address::from_slice(&key[1..21]);
+    let contract = Address::from_slice(&key[21..41]);
+    let token_id = U256::from_be_slice(&key[41..73]);
+    Ok((owner, contract, token_id))
+}
+
+/// Encode an NFT transfer history key (owner-first, then chain order).
+///
+/// Format: 'O' (0x4F) + owner(20 bytes) + block(u64 BE) + log_ordinal(u32 BE)
+/// Total length: 33 bytes
+pub fn encode_nft_transfer_key(owner: Address, block: u64, log_ordinal: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 8 + 4);
+    key.push(b'O');
+    key.extend_from_slice(owner.as_slice());
+    key.extend_from_slice(&block.to_be_bytes());
+    key.extend_from_slice(&log_ordinal.to_be_bytes());
+    key
+}
+
+/// Encode an ETH ⇄ token conversion key (owner-first, then chain order).
+///
+/// Format: 'P' (0x50) + owner(20 bytes) + block(u64 BE) + log_ordinal(u32 BE)
+/// Total length: 33 bytes
+pub fn encode_conversion_key(owner: Address, block: u64, log_ordinal: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 8 + 4);
+    key.push(b'P');
+    key.extend_from_slice(owner.as_slice());
+    key.extend_from_slice(&block.to_be_bytes());
+    key.extend_from_slice(&log_ordinal.to_be_bytes());
+    key
+}
+
+/// Encode a current allowance key (owner-first so allowances list by prefix scan).
+///
+/// Format: 'L' (0x4C) + owner(20 bytes) + token(20 bytes) + spender(20 bytes)
+/// Total length: 61 bytes
+pub fn encode_allowance_key(owner: Address, token: Address, spender: Address) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 20 + 20);
+    key.push(b'L');
+    key.extend_from_slice(owner.as_slice());
+    key.extend_from_slice(token.as_slice());
+    key.extend_from_slice(spender.as_slice());
+    key
+}
+
+/// Encode an approval history key (owner-first, then chain order).
+///
+/// Format: 'Q' (0x51) + owner(20 bytes) + block(u64 BE) + log_ordinal(u32 BE)
+/// Total length: 33 bytes
+pub fn encode_allowance_change_key(owner: Address, block: u64, log_ordinal: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 8 + 4);
+    key.push(b'Q');
+    key.extend_from_slice(owner.as_slice());
+    key.extend_from_slice(&block.to_be_bytes());
+    key.extend_from_slice(&log_ordinal.to_be_bytes());
+    key
+}
+
+/// Encode a persisted watchlist key for a watched address.
+///
+/// Format: 'Y' (0x59) + 'a' + address(20 bytes)
+/// Total length: 22 bytes
+pub fn encode_watched_address_key(addr: Address) -> Vec<u8> {
+    let mut key = Vec::with_capacity(2 + 20);
+    key.extend_from_slice(b"Ya");
+    key.extend_from_slice(addr.as_slice());
+    key
+}
+
+/// Encode a persisted watchlist key for a watched ERC20 token.
+///
+/// Format: 'Y' (0x59) + 't' + token(20 bytes)
+/// Total length: 22 bytes
+pub fn encode_watched_token_key(token: Address) -> Vec<u8> {
+    let mut key = Vec::with_capacity(2 + 20);
+    key.extend_from_slice(b"Yt");
+    key.extend_from_slice(token.as_slice());
+    key
+}
+
+/// Decode a persisted watchlist key (address or token) back to the address.
+pub fn decode_watchlist_key(key: &[u8]) -> Result<Address, anyhow::Error> {
+    if key.len() != 22 {
+        anyhow::bail!("Watchlist key must be 22 bytes, got {}", key.len());
+    }
+    if key[0] != b'Y' || !matches!(key[1], b'a' | b't') {
+        anyhow::bail!("Invalid watchlist key prefix");
+    }
+    Ok(Address::from_slice(&key[2..22]))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,6 +654,15 @@ mod tests {
         assert_eq!(u64::from_be_bytes(key[1..9].try_into().unwrap()), block);
     }
 
+    #[test]
+    fn test_block_undo_key_encoding() {
+        let block = 4242u64;
+        let key = encode_block_undo_key(block);
+        assert_eq!(key.len(), 9);
+        assert_eq!(key[0], b'R');
+        assert_eq!(u64::from_be_bytes(key[1..9].try_into().unwrap()), block);
+    }
+
     #[test]
     fn test_meta_key_encoding() {
         let key = encode_meta_key(0x01);
@@ -407,5 +760,78 @@ mod tests {
         let (t, o) = decode_token_watch_meta_key(&key).unwrap();
         assert_eq!(token, t);
         assert_eq!(owner, o);
+
+        let key = encode_token_metadata_key(token);
+        assert_eq!(key[0], b'G');
+        assert_eq!(decode_token_metadata_key(&key).unwrap(), token);
+    }
+
+    #[test]
+    fn test_erc1155_key_roundtrip() {
+        let contract = Address::repeat_byte(0xaa);
+        let owner = Address::repeat_byte(0x11);
+        let id = U256::from(7u64);
+
+        let key = encode_erc1155_delta_key(contract, id, owner, 100);
+        assert_eq!(key.len(), 81);
+        assert_eq!(key[0], b'I');
+        assert_eq!(decode_erc1155_delta_key(&key).unwrap(), (contract, id, owner, 100));
+
+        let key = encode_erc1155_snapshot_key(contract, id, owner, 100);
+        assert_eq!(key[0], b'J');
+        assert_eq!(decode_erc1155_snapshot_key(&key).unwrap(), (contract, id, owner, 100));
+        assert!(decode_erc1155_delta_key(&key).is_err());
+
+        assert_eq!(encode_erc1155_watch_meta_key(contract, id, owner).len(), 73);
+    }
+
+    #[test]
+    fn test_drift_key_roundtrip() {
+        let token = Address::from_slice(&hex::decode("dAC17F958D2ee523a2206206994597C13D831ec7").unwrap());
+        let owner = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
+
+        let key = encode_drift_key(100, owner, Some(token));
+        assert_eq!(key.len(), 49);
+        assert_eq!(key[0], b'F');
+        assert_eq!(decode_drift_key(&key).unwrap(), (100, owner, Some(token)));
+
+        let key = encode_drift_key(100, owner, None);
+        assert_eq!(decode_drift_key(&key).unwrap(), (100, owner, None));
+    }
+
+    #[test]
+    fn test_tx_record_key_roundtrip_and_ordering() {
+        let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
+
+        let key = encode_tx_record_key(addr, 100, 7, 1);
+        assert_eq!(key.len(), 35);
+        assert_eq!(key[0], b'V');
+        assert_eq!(decode_tx_record_key(&key).unwrap(), (addr, 100, 7, 1));
+
+        // Records sort by block, then tx index, then sequence
+        assert!(encode_tx_record_key(addr, 100, 7, 1) < encode_tx_record_key(addr, 100, 8, 0));
+        assert!(encode_tx_record_key(addr, 100, 300, 0) < encode_tx_record_key(addr, 101, 0, 0));
+    }
+
+    #[test]
+    fn test_event_key_roundtrip() {
+        let key = encode_event_key(100, 3);
+        assert_eq!(key.len(), 13);
+        assert_eq!(key[0], b'E');
+        assert_eq!(decode_event_key(&key).unwrap(), (100, 3));
+        assert!(encode_event_key(100, 300) < encode_event_key(101, 0));
+    }
+
+    #[test]
+    fn test_nft_keys() {
+        let owner = Address::repeat_byte(0x11);
+        let contract = Address::repeat_byte(0x22);
+        let key = encode_nft_holding_key(owner, contract, U256::from(42u64));
+        assert_eq!(key.len(), 73);
+        assert_eq!(decode_nft_holding_key(&key).unwrap(), (owner, contract, U256::from(42u64)));
+
+        let key = encode_nft_transfer_key(owner, 100, 3);
+        assert_eq!(key.len(), 33);
+        assert!(encode_nft_transfer_key(owner, 100, 300) < encode_nft_transfer_key(owner, 101, 0));
     }
 }
@@ -6,24 +6,41 @@
 
 pub mod keys;
 pub mod records;
+pub mod schema;
 pub mod store;
 pub mod cli;
 pub mod trace;
 pub mod tracker;
+pub mod tracker_approvals;
+pub mod tracker_erc1155;
 pub mod tracker_erc20;
+pub mod tracker_erc721;
+pub mod tracker_events;
 
 // Watcher modules
 pub mod apply;
+pub mod backfill;
+pub mod batch;
 pub mod cache;
 pub mod config;
 pub mod fee;
+pub mod heads;
+pub mod rebasing;
+pub mod reconcile;
+pub mod reorg;
 pub mod rpc;
+pub mod rpc_pool;
+pub mod token_metadata;
 pub mod types;
 pub mod watcher;
+pub mod watchlist;
 
 // Re-export the main types for convenience
 pub use records::{
-    AccountRecord, BalanceSnapshot, BlockDelta, Erc20Delta, Erc20Snapshot, HeaderRecord,
-    TokenWatchMeta, WatchMeta,
+    AccountRecord, Allowance, ApprovalKind, BalanceSnapshot, BlockDelta, BlockUndo, Conversion,
+    ConversionKind, DecodedValue, DriftRecord, Erc20Delta, Erc20Snapshot, EventParamValue,
+    EventRecord, HeaderRecord, NftHolding, NftTransfer, RawWrite, TokenMetadata, TokenWatchMeta,
+    TxDirection, TxRecord, TxSource, WatchMeta,
 };
+pub use batch::BlockWriteBatch;
 pub use store::{QueryResult, RocksStateStore, StateStore};
@@ -0,0 +1,363 @@
+//! Rebasing and share-based ERC20 tokens
+//!
+//! Rebasing tokens (aTokens, stETH, ...) change balances without emitting
+//! `Transfer` events, so log-based accounting alone drifts immediately.
+//! After the ERC20 tracker has applied a block's transfers, every token with
+//! a non-default `TokenStrategy` gets the expected balance of each covered
+//! owner, and whatever the transfers do not explain is written as a rebase
+//! delta (`rebase_plus` / `rebase_minus`, also counted in `delta_plus` /
+//! `delta_minus`):
+//!
+//! - `Poll(n)`: every n blocks, the expected balance is `balanceOf` at the block.
+//! - `Shares`: share balances follow `TransferShares` events and are converted
+//!   every block with `getTotalPooledEther() / getTotalShares()` (Lido stETH).
+
+use crate::batch::BlockWriteBatch;
+use crate::config::TokenStrategy;
+use crate::records::Erc20Delta;
+use crate::rpc::RpcClient;
+use crate::tracker_erc20::parse_address_from_topic;
+use crate::types::{Log, Receipt};
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+
+/// keccak256("TransferShares(address,address,uint256)")
+const TRANSFER_SHARES_TOPIC: [u8; 32] = [
+    0x9d, 0x9c, 0x90, 0x92, 0x96, 0xd9, 0xc6, 0x74, 0x45, 0x1c, 0x0c, 0x24, 0xf0, 0x2c, 0xb6,
+    0x49, 0x81, 0xeb, 0x3b, 0x72, 0x7f, 0x99, 0x86, 0x59, 0x39, 0x19, 0x2f, 0x88, 0x0a, 0x75,
+    0x5d, 0xcb,
+];
+
+/// `sharesOf(address)` selector
+const SHARES_OF_SELECTOR: [u8; 4] = [0xf5, 0xeb, 0x42, 0xdc];
+
+/// `getTotalPooledEther()` selector
+const TOTAL_POOLED_ETHER_SELECTOR: [u8; 4] = [0x37, 0xcf, 0xda, 0xca];
+
+/// `getTotalShares()` selector
+const TOTAL_SHARES_SELECTOR: [u8; 4] = [0xd5, 0x00, 0x2f, 0x2e];
+
+/// Decode a single `uint256` return value.
+fn decode_uint(data: &[u8], what: &str) -> Result<U256> {
+    if data.len() < 32 {
+        anyhow::bail!("{} returned insufficient data: {} bytes", what, data.len());
+    }
+    Ok(U256::from_be_slice(&data[0..32]))
+}
+
+/// Get `sharesOf(owner)` of a share-based token at `block`.
+pub async fn fetch_shares_of(
+    rpc: &RpcClient,
+    token: Address,
+    owner: Address,
+    block: &str,
+) -> Result<U256> {
+    let mut data = SHARES_OF_SELECTOR.to_vec();
+    let mut padded = [0u8; 32];
+    padded[12..32].copy_from_slice(owner.as_slice());
+    data.extend_from_slice(&padded);
+    let result = rpc.eth_call(token, &data, block).await?;
+    decode_uint(&result, "sharesOf")
+}
+
+/// Get (total pooled ether, total shares) of a share-based token at `block`.
+async fn fetch_share_rate(rpc: &RpcClient, token: Address, block: &str) -> Result<(U256, U256)> {
+    let pooled = rpc.eth_call(token, &TOTAL_POOLED_ETHER_SELECTOR, block).await?;
+    let shares = rpc.eth_call(token, &TOTAL_SHARES_SELECTOR, block).await?;
+    Ok((
+        decode_uint(&pooled, "getTotalPooledEther")?,
+        decode_uint(&shares, "getTotalShares")?,
+    ))
+}
+
+/// Convert shares to a token amount, rounding down like stETH's `balanceOf`.
+fn shares_to_balance(shares: U256, total_pooled: U256, total_shares: U256) -> U256 {
+    if total_shares.is_zero() {
+        return U256::ZERO;
+    }
+    shares.saturating_mul(total_pooled) / total_shares
+}
+
+/// Parse from, to and the share amount from a `TransferShares` log.
+fn parse_transfer_shares_log(log: &Log) -> Option<(Address, Address, U256)> {
+    if log.topics.len() != 3 || log.data.len() < 32 {
+        return None;
+    }
+    let topic0 = log.topics[0].strip_prefix("0x").unwrap_or(&log.topics[0]);
+    if hex::decode(topic0).ok()?.as_slice() != TRANSFER_SHARES_TOPIC {
+        return None;
+    }
+    let from = parse_address_from_topic(&log.topics[1]).ok()?;
+    let to = parse_address_from_topic(&log.topics[2]).ok()?;
+    Some((from, to, U256::from_be_slice(&log.data[0..32])))
+}
+
+/// Record the difference between `expected` and the stored balance as a rebase.
+///
+/// The difference is merged into this block's ERC20 delta, and the balance
+/// and snapshot are set to `expected`. Returns false if they already agree.
+fn apply_rebase(
+    batch: &mut BlockWriteBatch<'_>,
+    token: Address,
+    owner: Address,
+    expected: U256,
+) -> Result<bool> {
+    let stored = batch.get_erc20_balance(token, owner)?.unwrap_or(U256::ZERO);
+    if stored == expected {
+        return Ok(false);
+    }
+
+    let mut delta = batch
+        .get_erc20_delta(token, owner)?
+        .unwrap_or_else(|| Erc20Delta::new(batch.block()));
+    if expected > stored {
+        let diff = expected - stored;
+        delta.delta_plus = delta.delta_plus.saturating_add(diff);
+        delta.rebase_plus = delta.rebase_plus.saturating_add(diff);
+    } else {
+        let diff = stored - expected;
+        delta.delta_minus = delta.delta_minus.saturating_add(diff);
+        delta.rebase_minus = delta.rebase_minus.saturating_add(diff);
+    }
+
+    batch
+        .put_erc20_delta(token, owner, &delta)
+        .context("Failed to store rebase delta")?;
+    batch
+        .put_erc20_balance(token, owner, expected)
+        .context("Failed to store rebased balance")?;
+    batch
+        .put_erc20_snapshot(token, owner, expected)
+        .context("Failed to store rebased snapshot")?;
+    Ok(true)
+}
+
+/// Apply `TransferShares` logs of `token` to the share balances of `owners`.
+fn apply_share_transfers(
+    batch: &mut BlockWriteBatch<'_>,
+    token: Address,
+    owners: &HashSet<Address>,
+    receipts: &[(B256, Receipt)],
+) -> Result<()> {
+    for (_tx_hash, receipt) in receipts {
+        for log in receipt.logs.iter().filter(|log| log.address == token) {
+            let Some((from, to, shares)) = parse_transfer_shares_log(log) else {
+                continue;
+            };
+            if owners.contains(&from) {
+                let current = batch.get_erc20_shares(token, from)?.unwrap_or(U256::ZERO);
+                batch.put_erc20_shares(token, from, current.saturating_sub(shares))?;
+            }
+            if owners.contains(&to) {
+                let current = batch.get_erc20_shares(token, to)?.unwrap_or(U256::ZERO);
+                batch.put_erc20_shares(token, to, current.saturating_add(shares))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Owners whose (token, owner) pair is covered at the batch's block.
+///
+/// Uses the same coverage as the ERC20 queries: the pair's start, the earlier
+/// of the pair's and the owner's coverage end, and the gaps of both.
+fn covered_owners(
+    batch: &BlockWriteBatch<'_>,
+    token: Address,
+    owners: &[Address],
+) -> Result<HashSet<Address>> {
+    let block = batch.block();
+    let mut covered = HashSet::new();
+    for &owner in owners {
+        let Some(meta) = batch.get_token_watch_meta(token, owner)? else {
+            continue;
+        };
+        let owner_meta = batch.get_watch_meta(owner)?;
+        if meta.coverage(owner_meta.as_ref()).covers(block) {
+            covered.insert(owner);
+        }
+    }
+    Ok(covered)
+}
+
+/// Apply the accounting strategies of rebasing and share-based tokens at the batch's block.
+///
+/// Must run after the ERC20 tracker. `receipts` are the block's successful
+/// receipts. Only (token, owner) pairs covered at the block, by both the pair
+/// and its owner, are updated.
+/// Returns the number of rebase deltas written.
+pub async fn apply_token_strategies(
+    batch: &mut BlockWriteBatch<'_>,
+    rpc: &RpcClient,
+    strategies: &HashMap<Address, TokenStrategy>,
+    owners: &[Address],
+    receipts: &[(B256, Receipt)],
+) -> Result<usize> {
+    let block = batch.block();
+    let block_tag = format!("0x{:x}", block);
+    let mut rebased = 0;
+
+    for (&token, &strategy) in strategies {
+        let covered = covered_owners(batch, token, owners)?;
+        if covered.is_empty() {
+            continue;
+        }
+
+        match strategy {
+            TokenStrategy::Transfers => {}
+            TokenStrategy::Poll(every) => {
+                if !block.is_multiple_of(every) {
+                    continue;
+                }
+                for &owner in &covered {
+                    let balance = match rpc.erc20_balance_of(token, owner, &block_tag).await {
+                        Ok(balance) => balance,
+                        Err(e) => {
+                            warn!(
+                                "Rebase poll: balanceOf failed for token {:?} owner {:?}: {:?}",
+                                token, owner, e
+                            );
+                            continue;
+                        }
+                    };
+                    if apply_rebase(batch, token, owner, balance)? {
+                        rebased += 1;
+                    }
+                }
+            }
+            TokenStrategy::Shares => {
+                apply_share_transfers(batch, token, &covered, receipts)
+                    .with_context(|| format!("Failed to apply share transfers of {:?}", token))?;
+                let (total_pooled, total_shares) =
+                    match fetch_share_rate(rpc, token, &block_tag).await {
+                        Ok(rate) => rate,
+                        Err(e) => {
+                            warn!("Share rate unavailable for token {:?}: {:?}", token, e);
+                            continue;
+                        }
+                    };
+                for &owner in &covered {
+                    let shares = batch.get_erc20_shares(token, owner)?.unwrap_or(U256::ZERO);
+                    let expected = shares_to_balance(shares, total_pooled, total_shares);
+                    if apply_rebase(batch, token, owner, expected)? {
+                        rebased += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(rebased)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{TokenWatchMeta, WatchMeta};
+    use crate::store::{RocksStateStore, StateStore};
+    use alloy_primitives::keccak256;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_topic_and_selectors() {
+        assert_eq!(
+            keccak256("TransferShares(address,address,uint256)").0,
+            TRANSFER_SHARES_TOPIC
+        );
+        assert_eq!(keccak256("sharesOf(address)")[..4], SHARES_OF_SELECTOR);
+        assert_eq!(keccak256("getTotalPooledEther()")[..4], TOTAL_POOLED_ETHER_SELECTOR);
+        assert_eq!(keccak256("getTotalShares()")[..4], TOTAL_SHARES_SELECTOR);
+    }
+
+    #[test]
+    fn test_covered_owners_respects_pair_and_owner_coverage() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let token = Address::repeat_byte(0xaa);
+        let watched = Address::repeat_byte(0x11);
+        let removed_owner = Address::repeat_byte(0x22);
+        let removed_pair = Address::repeat_byte(0x33);
+        let untracked = Address::repeat_byte(0x44);
+
+        let mut ended_owner = WatchMeta::new(100);
+        ended_owner.unwatch(150);
+        let mut ended_pair = TokenWatchMeta::new(100);
+        ended_pair.unwatch(150);
+        store.put_watch_meta(watched, &WatchMeta::new(100)).unwrap();
+        store.put_watch_meta(removed_owner, &ended_owner).unwrap();
+        store.put_watch_meta(removed_pair, &WatchMeta::new(100)).unwrap();
+        store.put_token_watch_meta(token, watched, &TokenWatchMeta::new(100)).unwrap();
+        store.put_token_watch_meta(token, removed_owner, &TokenWatchMeta::new(100)).unwrap();
+        store.put_token_watch_meta(token, removed_pair, &ended_pair).unwrap();
+
+        let owners = [watched, removed_owner, removed_pair, untracked];
+        let batch = BlockWriteBatch::new(&store, 150);
+        let covered = covered_owners(&batch, token, &owners).unwrap();
+        assert_eq!(covered, [watched, removed_owner, removed_pair].into_iter().collect());
+
+        let batch = BlockWriteBatch::new(&store, 151);
+        let covered = covered_owners(&batch, token, &owners).unwrap();
+        assert_eq!(covered, [watched].into_iter().collect());
+
+        let batch = BlockWriteBatch::new(&store, 99);
+        assert!(covered_owners(&batch, token, &owners).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_share_transfers_and_rebase() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let token = Address::repeat_byte(0xaa);
+        let owner = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        store
+            .put_token_watch_meta(
+                token,
+                owner,
+                &TokenWatchMeta::new(100),
+            )
+            .unwrap();
+        store.put_erc20_balance(token, owner, U256::from(1000u64)).unwrap();
+        store.put_erc20_shares(token, owner, U256::from(900u64)).unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        // owner receives 100 shares from another address
+        let receipt = Receipt {
+            logs: vec![Log {
+                address: token,
+                topics: vec![
+                    format!("0x{}", hex::encode(TRANSFER_SHARES_TOPIC)),
+                    format!("0x{:0>64}", hex::encode(other.as_slice())),
+                    format!("0x{:0>64}", hex::encode(owner.as_slice())),
+                ],
+                data: U256::from(100u64).to_be_bytes::<32>().to_vec(),
+            }],
+            status: 1,
+            gas_used: U256::ZERO,
+            effective_gas_price: None,
+            blob_gas_used: None,
+            blob_gas_price: None,
+            l1_fee: None,
+            transaction_hash: None,
+        };
+        let covered: HashSet<Address> = [owner].into_iter().collect();
+        apply_share_transfers(&mut batch, token, &covered, &[(B256::ZERO, receipt)]).unwrap();
+        let shares = batch.get_erc20_shares(token, owner).unwrap().unwrap();
+        assert_eq!(shares, U256::from(1000u64));
+
+        // 1000 shares at a rate of 1.05 after a positive rebase
+        let expected = shares_to_balance(shares, U256::from(105u64), U256::from(100u64));
+        assert!(apply_rebase(&mut batch, token, owner, expected).unwrap());
+        assert!(!apply_rebase(&mut batch, token, owner, expected).unwrap());
+        let delta = batch.get_erc20_delta(token, owner).unwrap().unwrap();
+        assert_eq!(delta.rebase_plus, U256::from(50u64));
+        assert_eq!(delta.delta_plus, U256::from(50u64));
+        assert_eq!(delta.tx_count, 0);
+        batch.commit().unwrap();
+
+        assert_eq!(store.get_erc20_balance(token, owner).unwrap(), Some(U256::from(1050u64)));
+        assert_eq!(store.get_erc20_shares(token, owner).unwrap(), Some(U256::from(1000u64)));
+    }
+}
@@ -0,0 +1,402 @@
+//! Balance reconciliation against the node
+//!
+//! Kage derives balances incrementally from blocks, so a case the apply logic
+//! misses (self-destructs, new transaction types, ...) silently skews the
+//! stored state. At a configurable cadence the watcher asks the node for the
+//! real ETH balance, nonce and ERC20 balances at the block it just processed
+//! and compares them with the `AccountRecord`s and the `erc20_balances` CF.
+//!
+//! Every mismatch is written to the `drift` column family through the block's
+//! `BlockWriteBatch`, so drift records are rolled back with the block on a
+//! reorg. With auto-correct enabled, a corrective delta is written as well
+//! (`correction_plus` / `correction_minus`) so the stored balance matches the
+//! node from that block on.
+
+use crate::batch::BlockWriteBatch;
+use crate::records::{AccountRecord, BlockDelta, DriftRecord, Erc20Delta};
+use crate::rpc::RpcClient;
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Compare a watched address's stored ETH balance and nonce with the node.
+///
+/// Returns the drift record if they disagree. With `auto_correct`, the
+/// account is set to the node's values and the balance and nonce
+/// differences are added to the address's delta for this block.
+pub fn reconcile_eth(
+    batch: &mut BlockWriteBatch<'_>,
+    addr: Address,
+    node_balance: U256,
+    node_nonce: u64,
+    auto_correct: bool,
+    delta_accumulator: &mut HashMap<Address, BlockDelta>,
+) -> Result<Option<DriftRecord>> {
+    let block = batch.block();
+    let mut account = batch
+        .get_account(addr)
+        .context("Failed to get account for reconciliation")?
+        .unwrap_or(AccountRecord {
+            nonce: 0,
+            balance: U256::ZERO,
+            code_hash: B256::ZERO,
+        });
+
+    let mut record = DriftRecord {
+        block,
+        address: addr,
+        token: None,
+        stored_balance: account.balance,
+        node_balance,
+        stored_nonce: Some(account.nonce),
+        node_nonce: Some(node_nonce),
+        corrected: false,
+    };
+    if !record.has_drift() {
+        return Ok(None);
+    }
+
+    warn!(
+        "ETH drift for {:?} at block {}: stored balance {} nonce {}, node balance {} nonce {}",
+        addr, block, account.balance, account.nonce, node_balance, node_nonce
+    );
+
+    if auto_correct {
+        let delta = delta_accumulator
+            .entry(addr)
+            .or_insert_with(|| BlockDelta::new(block));
+        if node_balance > account.balance {
+            let diff = node_balance - account.balance;
+            delta.delta_plus = delta.delta_plus.saturating_add(diff);
+            delta.correction_plus = delta.correction_plus.saturating_add(diff);
+        } else {
+            let diff = account.balance - node_balance;
+            delta.delta_minus = delta.delta_minus.saturating_add(diff);
+            delta.correction_minus = delta.correction_minus.saturating_add(diff);
+        }
+        // Nonces only grow, so a stored nonce ahead of the node has no delta to record
+        delta.nonce_delta = delta
+            .nonce_delta
+            .saturating_add(node_nonce.saturating_sub(account.nonce));
+
+        account.balance = node_balance;
+        account.nonce = node_nonce;
+        batch
+            .put_account(addr, &account)
+            .context("Failed to store corrected account")?;
+        record.corrected = true;
+    }
+
+    batch
+        .put_drift_record(&record)
+        .context("Failed to store drift record")?;
+    Ok(Some(record))
+}
+
+/// Compare a stored ERC20 balance for (token, owner) with the node.
+///
+/// Returns the drift record if they disagree. With `auto_correct`, the
+/// difference is merged into this block's ERC20 delta and the balance and
+/// snapshot are set to the node's value.
+pub fn reconcile_erc20(
+    batch: &mut BlockWriteBatch<'_>,
+    token: Address,
+    owner: Address,
+    node_balance: U256,
+    auto_correct: bool,
+) -> Result<Option<DriftRecord>> {
+    let block = batch.block();
+    let stored_balance = batch.get_erc20_balance(token, owner)?.unwrap_or(U256::ZERO);
+
+    let mut record = DriftRecord {
+        block,
+        address: owner,
+        token: Some(token),
+        stored_balance,
+        node_balance,
+        stored_nonce: None,
+        node_nonce: None,
+        corrected: false,
+    };
+    if !record.has_drift() {
+        return Ok(None);
+    }
+
+    warn!(
+        "ERC20 drift for token {:?} owner {:?} at block {}: stored {}, node {}",
+        token, owner, block, stored_balance, node_balance
+    );
+
+    if auto_correct {
+        let mut delta = batch
+            .get_erc20_delta(token, owner)?
+            .unwrap_or_else(|| Erc20Delta::new(block));
+        if node_balance > stored_balance {
+            let diff = node_balance - stored_balance;
+            delta.delta_plus = delta.delta_plus.saturating_add(diff);
+            delta.correction_plus = delta.correction_plus.saturating_add(diff);
+        } else {
+            let diff = stored_balance - node_balance;
+            delta.delta_minus = delta.delta_minus.saturating_add(diff);
+            delta.correction_minus = delta.correction_minus.saturating_add(diff);
+        }
+
+        batch
+            .put_erc20_delta(token, owner, &delta)
+            .context("Failed to store corrective ERC20 delta")?;
+        batch
+            .put_erc20_balance(token, owner, node_balance)
+            .context("Failed to store corrected ERC20 balance")?;
+        batch
+            .put_erc20_snapshot(token, owner, node_balance)
+            .context("Failed to store corrected ERC20 snapshot")?;
+        record.corrected = true;
+    }
+
+    batch
+        .put_drift_record(&record)
+        .context("Failed to store drift record")?;
+    Ok(Some(record))
+}
+
+/// Reconcile every covered watched address and (token, owner) pair at the batch's block.
+///
+/// Addresses and pairs not covered at this block are skipped; a pair is
+/// covered only where both it and its owner are. RPC failures are logged and
+/// skipped so a flaky node never stalls block processing.
+pub async fn reconcile_block(
+    batch: &mut BlockWriteBatch<'_>,
+    rpc: &RpcClient,
+    watched_eoas: &[Address],
+    watched_tokens: &[Address],
+    auto_correct: bool,
+    delta_accumulator: &mut HashMap<Address, BlockDelta>,
+) -> Result<Vec<DriftRecord>> {
+    let block = batch.block();
+    let block_tag = format!("0x{:x}", block);
+    let mut drift = Vec::new();
+
+    for &addr in watched_eoas {
+        match batch.store().get_watch_meta(addr)? {
+            Some(meta) if meta.covers(block) => {}
+            _ => continue,
+        }
+
+        let node_balance = match rpc.get_balance(addr, &block_tag).await {
+            Ok(b) => b,
+            Err(e) => {
+                warn!("Reconciliation: eth_getBalance failed for {:?}: {:?}", addr, e);
+                continue;
+            }
+        };
+        let node_nonce = match rpc.get_transaction_count(addr, &block_tag).await {
+            Ok(n) => n,
+            Err(e) => {
+                warn!("Reconciliation: eth_getTransactionCount failed for {:?}: {:?}", addr, e);
+                continue;
+            }
+        };
+
+        if let Some(record) = reconcile_eth(
+            batch,
+            addr,
+            node_balance,
+            node_nonce,
+            auto_correct,
+            delta_accumulator,
+        )? {
+            drift.push(record);
+        }
+    }
+
+    for &token in watched_tokens {
+        for &owner in watched_eoas {
+            let Some(meta) = batch.get_token_watch_meta(token, owner)? else {
+                continue;
+            };
+            let owner_meta = batch.get_watch_meta(owner)?;
+            if !meta.coverage(owner_meta.as_ref()).covers(block) {
+                continue;
+            }
+
+            let node_balance = match rpc.erc20_balance_of(token, owner, &block_tag).await {
+                Ok(b) => b,
+                Err(e) => {
+                    warn!(
+                        "Reconciliation: balanceOf failed for token {:?} owner {:?}: {:?}",
+                        token, owner, e
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(record) = reconcile_erc20(batch, token, owner, node_balance, auto_correct)? {
+                drift.push(record);
+            }
+        }
+    }
+
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{TokenWatchMeta, WatchMeta};
+    use crate::rpc::mock::{mock_rpc, word};
+    use crate::store::{RocksStateStore, StateStore};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (RocksStateStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        (store, temp_dir)
+    }
+
+    fn account(nonce: u64, balance: u64) -> AccountRecord {
+        AccountRecord {
+            nonce,
+            balance: U256::from(balance),
+            code_hash: B256::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_eth_records_drift_without_correcting() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        store.put_account(addr, &account(3, 1000)).unwrap();
+
+        let mut deltas = HashMap::new();
+        let mut batch = BlockWriteBatch::new(&store, 100);
+
+        // Matching state produces no record
+        let none = reconcile_eth(&mut batch, addr, U256::from(1000u64), 3, false, &mut deltas)
+            .unwrap();
+        assert!(none.is_none());
+
+        let record = reconcile_eth(&mut batch, addr, U256::from(1200u64), 3, false, &mut deltas)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.stored_balance, U256::from(1000u64));
+        assert_eq!(record.node_balance, U256::from(1200u64));
+        assert!(!record.corrected);
+        assert!(deltas.is_empty());
+        batch.commit().unwrap();
+
+        assert_eq!(store.get_account(addr).unwrap(), Some(account(3, 1000)));
+        assert_eq!(store.get_drift_records_in_range(100, 100).unwrap(), vec![record]);
+    }
+
+    #[test]
+    fn test_reconcile_eth_auto_correct() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        store.put_account(addr, &account(3, 1000)).unwrap();
+
+        let mut deltas = HashMap::new();
+        let mut batch = BlockWriteBatch::new(&store, 100);
+        let record = reconcile_eth(&mut batch, addr, U256::from(700u64), 4, true, &mut deltas)
+            .unwrap()
+            .unwrap();
+        assert!(record.corrected);
+        assert_eq!(batch.get_account(addr).unwrap(), Some(account(4, 700)));
+
+        let delta = deltas.get(&addr).unwrap();
+        assert_eq!(delta.delta_minus, U256::from(300u64));
+        assert_eq!(delta.correction_minus, U256::from(300u64));
+        assert_eq!(delta.correction_plus, U256::ZERO);
+        assert_eq!(delta.nonce_delta, 1);
+    }
+
+    #[test]
+    fn test_reconcile_eth_auto_correct_nonce_only() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        store.put_account(addr, &account(3, 1000)).unwrap();
+
+        let mut deltas = HashMap::new();
+        let mut batch = BlockWriteBatch::new(&store, 100);
+        let record = reconcile_eth(&mut batch, addr, U256::from(1000u64), 5, true, &mut deltas)
+            .unwrap()
+            .unwrap();
+        assert!(record.corrected);
+        assert_eq!(batch.get_account(addr).unwrap(), Some(account(5, 1000)));
+
+        // The correction is recorded in the block's delta
+        let delta = deltas.get(&addr).unwrap();
+        assert!(delta.has_changes());
+        assert_eq!(delta.nonce_delta, 2);
+        assert_eq!(delta.delta_plus, U256::ZERO);
+        assert_eq!(delta.delta_minus, U256::ZERO);
+    }
+
+    #[test]
+    fn test_reconcile_erc20_auto_correct_merges_delta() {
+        let (store, _temp_dir) = create_test_store();
+        let token = Address::repeat_byte(0xaa);
+        let owner = Address::repeat_byte(0x01);
+        store.put_erc20_balance(token, owner, U256::from(500u64)).unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 100);
+        // Transfer already applied by the tracker in this block
+        let mut tracked = Erc20Delta::new(100);
+        tracked.delta_plus = U256::from(50u64);
+        tracked.tx_count = 1;
+        batch.put_erc20_delta(token, owner, &tracked).unwrap();
+        batch.put_erc20_balance(token, owner, U256::from(550u64)).unwrap();
+
+        let record = reconcile_erc20(&mut batch, token, owner, U256::from(600u64), true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.token, Some(token));
+        assert_eq!(record.stored_balance, U256::from(550u64));
+        batch.commit().unwrap();
+
+        let deltas = store.get_erc20_deltas_in_range(token, owner, 100, 100).unwrap();
+        assert_eq!(deltas.len(), 1);
+        let delta = &deltas[0].1;
+        assert_eq!(delta.delta_plus, U256::from(100u64));
+        assert_eq!(delta.correction_plus, U256::from(50u64));
+        assert_eq!(delta.tx_count, 1);
+        assert_eq!(store.get_erc20_balance(token, owner).unwrap(), Some(U256::from(600u64)));
+        assert_eq!(
+            store.get_latest_erc20_snapshot_at_or_before(token, owner, 100).unwrap(),
+            Some((100, U256::from(600u64)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_block_skips_uncovered_pairs() {
+        let (store, _temp_dir) = create_test_store();
+        let owner = Address::repeat_byte(0x01);
+        let (covered, ended) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        store.put_watch_meta(owner, &WatchMeta::new(100)).unwrap();
+        store.put_account(owner, &account(1, 100)).unwrap();
+        let mut ended_meta = TokenWatchMeta::new(100);
+        ended_meta.unwatch(120);
+        store.put_token_watch_meta(covered, owner, &TokenWatchMeta::new(100)).unwrap();
+        store.put_token_watch_meta(ended, owner, &ended_meta).unwrap();
+
+        // The node reports a balance of 5 for every token; both pairs store none
+        let rpc = mock_rpc(|method, _| match method {
+            "eth_getBalance" => Ok(json!("0x64")),
+            "eth_getTransactionCount" => Ok(json!("0x1")),
+            "eth_call" => Ok(word(5)),
+            _ => Err(json!({"code": -32601, "message": "unexpected"})),
+        });
+        let mut deltas = HashMap::new();
+        let mut batch = BlockWriteBatch::new(&store, 150);
+        let tokens = [covered, ended];
+        let drift = reconcile_block(&mut batch, &rpc, &[owner], &tokens, true, &mut deltas)
+            .await
+            .unwrap();
+
+        assert_eq!(drift.len(), 1);
+        assert_eq!(drift[0].token, Some(covered));
+        assert!(batch.get_erc20_delta(ended, owner).unwrap().is_none());
+        assert!(deltas.is_empty());
+    }
+}
@@ -60,6 +60,20 @@ pub struct BlockDelta {
     pub fee_paid: U256,
     /// Total fees paid for failed transactions
     pub failed_fee: U256,
+    /// Total EIP-4844 blob fees paid (successful and failed transactions)
+    pub blob_fee: U256,
+    /// Total L1 data fees paid (OP Stack chain profile)
+    pub l1_fee: U256,
+    /// Total value minted by deposit transactions (OP Stack chain profile)
+    pub minted_value: U256,
+    /// Total value credited by validator withdrawals (EIP-4895)
+    pub withdrawal_value: U256,
+    /// Priority fees earned as the block's fee recipient
+    pub priority_fee_income: U256,
+    /// Balance increase written by reconciliation auto-correct
+    pub correction_plus: U256,
+    /// Balance decrease written by reconciliation auto-correct
+    pub correction_minus: U256,
     /// Nonce increment (usually 0 or 1, but could be more if multiple txs in block)
     pub nonce_delta: u64,
     /// Number of transactions affecting this address in this block
@@ -77,6 +91,13 @@ impl BlockDelta {
             sent_value: U256::ZERO,
             fee_paid: U256::ZERO,
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 0,
             tx_count: 0,
         }
@@ -98,12 +119,94 @@ pub type BalanceSnapshot = U256;
 
 /// Watch metadata for an address.
 ///
-/// Tracks when we started watching this address.
+/// Tracks when we started (and stopped) watching this address. An address
+/// that is unwatched and watched again has several coverage segments; the
+/// blocks between them are recorded as gaps.
 /// Used to enforce coverage boundaries in queries.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WatchMeta {
     /// Block number at which we started tracking this address.
     pub start_block: u64,
+    /// Last covered block once the address is unwatched (None while watched).
+    pub end_block: Option<u64>,
+    /// Uncovered block ranges [start, end] between coverage segments, in order.
+    pub gaps: Vec<(u64, u64)>,
+}
+
+impl WatchMeta {
+    /// Coverage starting at `start_block`, without an end.
+    pub fn new(start_block: u64) -> Self {
+        Self {
+            start_block,
+            end_block: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    /// Whether the address is currently watched.
+    pub fn is_watched(&self) -> bool {
+        self.end_block.is_none()
+    }
+
+    /// Whether `block` is covered (an open coverage segment has no upper bound).
+    pub fn covers(&self, block: u64) -> bool {
+        block >= self.start_block
+            && self.end_block.is_none_or(|end| block <= end)
+            && !self.gaps.iter().any(|&(start, end)| (start..=end).contains(&block))
+    }
+
+    /// Stop watching: `block` is the last covered block.
+    pub fn unwatch(&mut self, block: u64) {
+        if self.end_block.is_none() {
+            self.end_block = Some(block);
+        }
+    }
+
+    /// Start a new coverage segment at `block`.
+    ///
+    /// Blocks between the end of the previous segment and `block` become a gap.
+    pub fn rewatch(&mut self, block: u64) {
+        reopen_coverage(&mut self.end_block, &mut self.gaps, block);
+    }
+
+    /// Gaps overlapping [start, end], clipped to it.
+    pub fn gaps_in(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        self.gaps
+            .iter()
+            .filter(|&&(gap_start, gap_end)| gap_start <= end && gap_end >= start)
+            .map(|&(gap_start, gap_end)| (gap_start.max(start), gap_end.min(end)))
+            .collect()
+    }
+
+    /// Covered segments within [start, end], in order.
+    pub fn segments_in(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let start = start.max(self.start_block);
+        let end = self.end_block.map_or(end, |last| end.min(last));
+        let mut segments = Vec::new();
+        if start > end {
+            return segments;
+        }
+        let mut from = start;
+        for (gap_start, gap_end) in self.gaps_in(start, end) {
+            if gap_start > from {
+                segments.push((from, gap_start - 1));
+            }
+            from = gap_end.saturating_add(1);
+        }
+        if from <= end {
+            segments.push((from, end));
+        }
+        segments
+    }
+}
+
+/// Clear `end_block` and record the blocks between it and `block` as a gap.
+fn reopen_coverage(end_block: &mut Option<u64>, gaps: &mut Vec<(u64, u64)>, block: u64) {
+    if let Some(end) = end_block.take() {
+        if block > end.saturating_add(1) {
+            gaps.push((end + 1, block - 1));
+        }
+    }
 }
 
 /// Per-block ERC20 delta for a specific (token, owner) at a given block.
@@ -118,6 +221,18 @@ pub struct Erc20Delta {
     pub delta_plus: U256,
     /// Tokens sent/burned in this block.
     pub delta_minus: U256,
+    /// Balance increase written by reconciliation auto-correct.
+    pub correction_plus: U256,
+    /// Balance decrease written by reconciliation auto-correct.
+    pub correction_minus: U256,
+    /// Balance increase without a Transfer event (rebasing or share-rate change).
+    pub rebase_plus: U256,
+    /// Balance decrease without a Transfer event (negative rebase).
+    pub rebase_minus: U256,
+    /// Balance increase found by `balanceOf` verification (inaccurate events).
+    pub adjustment_plus: U256,
+    /// Balance decrease found by `balanceOf` verification (e.g. transfer fees).
+    pub adjustment_minus: U256,
     /// Number of Transfer events that affected this (token, owner) in this block.
     pub tx_count: u32,
 }
@@ -129,6 +244,12 @@ impl Erc20Delta {
             block,
             delta_plus: U256::ZERO,
             delta_minus: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
+            rebase_plus: U256::ZERO,
+            rebase_minus: U256::ZERO,
+            adjustment_plus: U256::ZERO,
+            adjustment_minus: U256::ZERO,
             tx_count: 0,
         }
     }
@@ -145,14 +266,433 @@ impl Erc20Delta {
 ///   'U' + token(20) + owner(20) + block(u64 BE)
 pub type Erc20Snapshot = U256;
 
+/// Per-block ERC1155 delta for a specific (contract, id, owner) at a given block.
+///
+/// Same fields as `Erc20Delta`; `tx_count` counts `TransferSingle` events and
+/// entries of `TransferBatch` events.
+///
+/// Keyed as:
+///   'I' + contract(20) + id(32 BE) + owner(20) + block(u64 BE)
+pub type Erc1155Delta = Erc20Delta;
+
+/// ERC1155 balance snapshot for (contract, id, owner) at a specific block.
+///
+/// Keyed as:
+///   'J' + contract(20) + id(32 BE) + owner(20) + block(u64 BE)
+pub type Erc1155Snapshot = U256;
+
 /// Coverage metadata for a (token, owner) pair.
 ///
 /// This is similar to `WatchMeta` for ETH addresses, but scoped to
-/// a token+owner combination.
+/// a token+owner combination. Also used for ERC1155 (contract, id, owner).
+/// The pair's own end and gaps come from removing and re-adding the token;
+/// the owner's are recorded in its `WatchMeta`.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TokenWatchMeta {
     /// Block number at which we started tracking this (token, owner).
     pub start_block: u64,
+    /// Registered by token discovery (a Transfer touched the owner) rather
+    /// than from the token watchlist.
+    pub auto_discovered: bool,
+    /// Last covered block once the token is unwatched (None while watched).
+    pub end_block: Option<u64>,
+    /// Uncovered block ranges [start, end] between coverage segments, in order.
+    pub gaps: Vec<(u64, u64)>,
+}
+
+impl TokenWatchMeta {
+    /// Coverage of a watchlist pair starting at `start_block`, without an end.
+    pub fn new(start_block: u64) -> Self {
+        Self {
+            start_block,
+            auto_discovered: false,
+            end_block: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    /// Whether the pair is currently tracked.
+    pub fn is_watched(&self) -> bool {
+        self.end_block.is_none()
+    }
+
+    /// Whether the pair's own coverage includes `block` (ignoring the owner's).
+    pub fn covers(&self, block: u64) -> bool {
+        block >= self.start_block
+            && self.end_block.is_none_or(|end| block <= end)
+            && !self.gaps.iter().any(|&(start, end)| (start..=end).contains(&block))
+    }
+
+    /// Stop tracking: `block` is the last covered block.
+    pub fn unwatch(&mut self, block: u64) {
+        if self.end_block.is_none() {
+            self.end_block = Some(block);
+        }
+    }
+
+    /// Start a new coverage segment at `block`.
+    ///
+    /// Blocks between the end of the previous segment and `block` become a gap.
+    pub fn rewatch(&mut self, block: u64) {
+        reopen_coverage(&mut self.end_block, &mut self.gaps, block);
+    }
+
+    /// Effective coverage of the pair, given its owner's coverage.
+    ///
+    /// The pair keeps its own start, which a backfill may have moved before
+    /// the owner's. It is only updated while both the token and its owner
+    /// are watched, so it ends at the earlier end and skips both their gaps.
+    pub fn coverage(&self, owner: Option<&WatchMeta>) -> WatchMeta {
+        let owner_end = owner.and_then(|meta| meta.end_block);
+        let end_block = match (self.end_block, owner_end) {
+            (Some(pair), Some(owner)) => Some(pair.min(owner)),
+            (pair, owner) => pair.or(owner),
+        };
+        let mut all_gaps: Vec<(u64, u64)> = self
+            .gaps
+            .iter()
+            .chain(owner.into_iter().flat_map(|meta| &meta.gaps))
+            .copied()
+            .collect();
+        all_gaps.sort_unstable();
+        let mut gaps: Vec<(u64, u64)> = Vec::with_capacity(all_gaps.len());
+        for (start, end) in all_gaps {
+            match gaps.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => gaps.push((start, end)),
+            }
+        }
+        WatchMeta {
+            start_block: self.start_block,
+            end_block,
+            gaps,
+        }
+    }
+}
+
+/// ERC20 token metadata read from the contract.
+///
+/// Each field is `None` when the call reverted or returned something that
+/// could not be decoded (the functions are optional in ERC20).
+///
+/// Keyed as:
+///   'G' + token(20)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    /// `name()`
+    pub name: Option<String>,
+    /// `symbol()`
+    pub symbol: Option<String>,
+    /// `decimals()`
+    pub decimals: Option<u8>,
+    /// `balanceOf` disagreed with Transfer events (fee-on-transfer or
+    /// inaccurate events); balances of touched owners are always verified.
+    pub non_standard: bool,
+}
+
+impl TokenMetadata {
+    /// Whether any of the name, symbol or decimals was decoded.
+    pub fn is_known(&self) -> bool {
+        self.name.is_some() || self.symbol.is_some() || self.decimals.is_some()
+    }
+}
+
+/// Difference between a stored balance and the node's balance at a block.
+///
+/// Written by reconciliation whenever the two disagree. `token` is `None`
+/// for ETH (in which case the nonces are compared as well).
+///
+/// Keyed as:
+///   'F' + block(u64 BE) + address(20) + token(20, zero for ETH)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriftRecord {
+    /// Block at which the balances were compared.
+    pub block: u64,
+    /// Watched address.
+    pub address: Address,
+    /// Token contract (None = ETH).
+    pub token: Option<Address>,
+    /// Balance derived by Kage.
+    pub stored_balance: U256,
+    /// Balance reported by the node.
+    pub node_balance: U256,
+    /// Nonce derived by Kage (ETH only).
+    pub stored_nonce: Option<u64>,
+    /// Nonce reported by the node (ETH only).
+    pub node_nonce: Option<u64>,
+    /// Whether auto-correct wrote a corrective delta for this drift.
+    pub corrected: bool,
+}
+
+impl DriftRecord {
+    /// Whether stored and node state disagree.
+    pub fn has_drift(&self) -> bool {
+        self.stored_balance != self.node_balance || self.stored_nonce != self.node_nonce
+    }
+}
+
+/// Which side of a balance change the watched address was on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxDirection {
+    /// The address received value.
+    In,
+    /// The address sent value and/or paid fees.
+    Out,
+    /// The address sent value to itself (only fees change the balance).
+    SelfTransfer,
+}
+
+/// Where a balance change of a watched address came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxSource {
+    /// The transaction itself (sender fees and value, EOA→EOA receipts).
+    TopLevel,
+    /// An internal contract → EOA transfer found by tracing.
+    Internal,
+    /// A validator withdrawal (EIP-4895).
+    Withdrawal,
+}
+
+/// One transaction (or withdrawal) that moved a watched address's ETH balance.
+///
+/// `BlockDelta` aggregates per block; these records keep the per-transaction
+/// detail. Withdrawals are not transactions: they have no hash and use
+/// `tx_index = transaction count + withdrawal position`, so they sort after
+/// the block's transactions.
+///
+/// Keyed as:
+///   'V' + address(20) + block(u64 BE) + tx_index(u32 BE) + seq(u16 BE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxRecord {
+    /// Block containing the transaction.
+    pub block: u64,
+    /// Position of the transaction in the block.
+    pub tx_index: u32,
+    /// Transaction hash (None for withdrawals).
+    pub tx_hash: Option<B256>,
+    /// The other side of the transfer (None for withdrawals and contract creations).
+    pub counterparty: Option<Address>,
+    /// Direction relative to the watched address.
+    pub direction: TxDirection,
+    /// Value transferred (zero when the transaction failed).
+    pub value: U256,
+    /// Fees paid by the watched address (execution, blob and L1 data fees).
+    pub fee: U256,
+    /// Whether the transaction succeeded.
+    pub success: bool,
+    /// Where the balance change came from.
+    pub source: TxSource,
+}
+
+/// An ERC721 token currently held by a watched address.
+///
+/// Keyed as:
+///   'N' + owner(20) + contract(20) + token_id(32 BE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NftHolding {
+    /// NFT contract.
+    pub contract: Address,
+    /// Token ID.
+    pub token_id: U256,
+    /// Block in which the owner received the token.
+    pub since_block: u64,
+}
+
+/// One ERC721 transfer involving a watched address.
+///
+/// Mints have the zero address as `counterparty` on an `In` transfer, burns
+/// on an `Out` transfer.
+///
+/// Keyed as:
+///   'O' + owner(20) + block(u64 BE) + log_ordinal(u32 BE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NftTransfer {
+    /// Block containing the transfer.
+    pub block: u64,
+    /// Position of the log among the logs of the block's successful transactions.
+    pub log_ordinal: u32,
+    /// Transaction that emitted the log.
+    pub tx_hash: B256,
+    /// NFT contract.
+    pub contract: Address,
+    /// Token ID.
+    pub token_id: U256,
+    /// Direction relative to the watched address.
+    pub direction: TxDirection,
+    /// The other side of the transfer.
+    pub counterparty: Address,
+}
+
+/// Direction of an ETH ⇄ token conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConversionKind {
+    /// ETH wrapped into the token (`Deposit`).
+    Wrap,
+    /// Token unwrapped into ETH (`Withdrawal`).
+    Unwrap,
+}
+
+/// One ETH ⇄ token conversion by a watched address (WETH-style `Deposit` /
+/// `Withdrawal` event of a watched token).
+///
+/// The ETH side is the transaction record with the same `tx_hash` and the
+/// token as counterparty (a top-level send for wraps, an internal transfer
+/// for unwraps).
+///
+/// Keyed as:
+///   'P' + owner(20) + block(u64 BE) + log_ordinal(u32 BE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conversion {
+    /// Block containing the conversion.
+    pub block: u64,
+    /// Position of the log among the logs of the block's successful transactions.
+    pub log_ordinal: u32,
+    /// Transaction that emitted the log.
+    pub tx_hash: B256,
+    /// Wrapped-ETH token contract.
+    pub token: Address,
+    /// Wrap or unwrap.
+    pub kind: ConversionKind,
+    /// Amount of ETH (and token) converted.
+    pub amount: U256,
+}
+
+/// Which approval event granted an allowance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApprovalKind {
+    /// ERC20 `Approval(owner, spender, value)`: the spender may move up to `value`.
+    Erc20,
+    /// ERC721/ERC1155 `ApprovalForAll(owner, operator, approved)`: the operator
+    /// may move every token the owner holds in the contract.
+    ForAll,
+}
+
+/// Allowances at or above this are treated as unlimited.
+///
+/// Wallets approve `type(uint256).max`, but tokens with narrower balances
+/// (e.g. `uint96` in UNI and COMP) emit their own maximum instead.
+pub const UNLIMITED_ALLOWANCE: U256 = U256::from_limbs([u64::MAX, u32::MAX as u64, 0, 0]);
+
+/// A token approval granted by a watched address.
+///
+/// Stored both as the owner's current allowance for (token, spender) and in
+/// its approval history. `value` is the last approved amount: most tokens
+/// spend allowances in `transferFrom` without an event, so less may remain.
+/// For `ForAll` approvals it is 1 when approved and 0 when revoked.
+///
+/// Keyed as:
+///   'L' + owner(20) + token(20) + spender(20) (current; revoked ones are deleted)
+///   'Q' + owner(20) + block(u64 BE) + log_ordinal(u32 BE) (history)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Allowance {
+    /// Block of the approval.
+    pub block: u64,
+    /// Position of the log among the logs of the block's successful transactions.
+    pub log_ordinal: u32,
+    /// Transaction that emitted the log.
+    pub tx_hash: B256,
+    /// Token contract.
+    pub token: Address,
+    /// Spender (ERC20) or operator (`ApprovalForAll`).
+    pub spender: Address,
+    /// Approval event kind.
+    pub kind: ApprovalKind,
+    /// Approved amount (1 / 0 for `ForAll`).
+    pub value: U256,
+}
+
+impl Allowance {
+    /// Whether the approval was revoked (zero allowance or `approved = false`).
+    pub fn is_revoked(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    /// Whether the spender can move an unlimited amount.
+    pub fn is_unlimited(&self) -> bool {
+        match self.kind {
+            ApprovalKind::Erc20 => self.value >= UNLIMITED_ALLOWANCE,
+            ApprovalKind::ForAll => !self.is_revoked(),
+        }
+    }
+}
+
+/// A decoded event parameter value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecodedValue {
+    Address(Address),
+    Bool(bool),
+    /// Unsigned integer of any size.
+    Uint(U256),
+    /// Signed integer as raw two's complement (sign-extended to 256 bits).
+    Int(U256),
+    /// Fixed-size or dynamic byte array.
+    Bytes(Vec<u8>),
+    String(String),
+    /// keccak256 of an indexed dynamic value (the value itself is not logged).
+    Hash(B256),
+}
+
+/// A named, decoded event parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventParamValue {
+    /// Parameter name from the definition (`arg{i}` if unnamed).
+    pub name: String,
+    /// Canonical ABI type (e.g. "uint256").
+    pub kind: String,
+    /// Decoded value.
+    pub value: DecodedValue,
+}
+
+/// A log matched and decoded by `EventTracker`.
+///
+/// Keyed as:
+///   'E' + block(u64 BE) + log_ordinal(u32 BE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Block containing the log.
+    pub block: u64,
+    /// Position of the log among the logs of the block's successful transactions.
+    pub log_ordinal: u32,
+    /// Transaction that emitted the log.
+    pub tx_hash: B256,
+    /// Emitting contract.
+    pub contract: Address,
+    /// Event name (e.g. "Swap").
+    pub event: String,
+    /// Canonical signature (e.g. "Swap(address,uint256,uint256,uint256,uint256,address)").
+    pub signature: String,
+    /// Decoded parameters in declaration order.
+    pub params: Vec<EventParamValue>,
+}
+
+/// A single raw write to a column family.
+///
+/// `value` of `None` deletes the key. Used for atomic batches and for
+/// the undo journal of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawWrite {
+    /// Column family name
+    pub cf: String,
+    /// Encoded key
+    pub key: Vec<u8>,
+    /// Encoded value (None = delete)
+    pub value: Option<Vec<u8>>,
+}
+
+/// Undo information for a processed block.
+///
+/// Journals the value every key had before the block's batch was committed,
+/// so the block can be rolled back exactly if it is later reorganized out of
+/// the canonical chain.
+///
+/// Keyed as:
+///   'R' + block(u64 BE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockUndo {
+    /// Block number (also encoded in the key).
+    pub block: u64,
+    /// Writes that restore every touched key to its pre-block value.
+    pub entries: Vec<RawWrite>,
 }
 
 /// Encode a U256 value as a fixed 32-byte big-endian byte array.
@@ -204,4 +744,56 @@ mod tests {
         let decoded = decode_u256(&encoded).unwrap();
         assert_eq!(value, decoded);
     }
+
+    #[test]
+    fn test_watch_meta_segments() {
+        let mut meta = WatchMeta::new(100);
+        assert!(meta.covers(1000) && !meta.covers(99));
+
+        meta.unwatch(150);
+        assert!(!meta.is_watched());
+        assert!(meta.covers(150) && !meta.covers(151));
+
+        // Re-watching right after the end continues the segment
+        meta.rewatch(151);
+        meta.unwatch(160);
+        assert!(meta.gaps.is_empty());
+
+        meta.rewatch(200);
+        assert!(meta.is_watched());
+        assert_eq!(meta.gaps, vec![(161, 199)]);
+        assert!(meta.covers(160) && !meta.covers(161) && !meta.covers(199) && meta.covers(200));
+
+        assert_eq!(meta.gaps_in(50, 170), vec![(161, 170)]);
+        assert_eq!(meta.segments_in(50, 250), vec![(100, 160), (200, 250)]);
+        assert_eq!(meta.segments_in(170, 180), vec![]);
+
+        meta.unwatch(220);
+        assert_eq!(meta.segments_in(190, 250), vec![(200, 220)]);
+    }
+
+    #[test]
+    fn test_token_watch_meta_coverage_combines_owner() {
+        let mut pair = TokenWatchMeta::new(50);
+        pair.unwatch(120);
+        pair.rewatch(140);
+        assert_eq!(pair.gaps, vec![(121, 139)]);
+        assert!(pair.covers(120) && !pair.covers(130) && pair.covers(140));
+
+        let mut owner = WatchMeta::new(100);
+        owner.unwatch(135);
+        owner.rewatch(150);
+        owner.unwatch(300);
+
+        // The pair's start, the earlier end and both gaps merged
+        let coverage = pair.coverage(Some(&owner));
+        assert_eq!(coverage.start_block, 50);
+        assert_eq!(coverage.end_block, Some(300));
+        assert_eq!(coverage.gaps, vec![(121, 149)]);
+        assert_eq!(coverage.segments_in(0, 400), vec![(50, 120), (150, 300)]);
+
+        pair.unwatch(200);
+        assert_eq!(pair.coverage(Some(&owner)).end_block, Some(200));
+        assert_eq!(pair.coverage(None).end_block, Some(200));
+    }
 }
@@ -0,0 +1,219 @@
+//! Chain reorganization handling
+//!
+//! Every block is committed through a `BlockWriteBatch`, which journals the
+//! prior value of every key it writes into the block's `BlockUndo` record.
+//! When the node's canonical chain no longer matches the block hashes we have
+//! stored, those records are used to roll the store back to the common
+//! ancestor before the canonical blocks are re-applied.
+//!
+//! Rollback is exact: account records, ERC20 balances and the head are
+//! restored to their pre-block values, and every delta/snapshot row written
+//! for a reorganized block is deleted.
+
+use crate::batch::BlockWriteBatch;
+use crate::keys::{encode_block_undo_key, encode_meta_key};
+use crate::records::{BlockUndo, RawWrite};
+use crate::rpc::RpcClient;
+use crate::store::StateStore;
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+/// Maximum reorg depth we can recover from.
+///
+/// Undo information older than this many blocks behind the head is pruned.
+pub const MAX_REORG_DEPTH: u64 = 64;
+
+/// Revert a single block using its undo record.
+///
+/// Restores every journaled key, drops the undo record and moves the head to
+/// the parent block, all in one atomic batch.
+pub fn rollback_block(store: &dyn StateStore, undo: &BlockUndo) -> Result<()> {
+    let mut writes = undo.entries.clone();
+    writes.push(RawWrite {
+        cf: "block_undo".to_string(),
+        key: encode_block_undo_key(undo.block),
+        value: None,
+    });
+    writes.push(RawWrite {
+        cf: "meta".to_string(),
+        key: encode_meta_key(0x01),
+        value: Some(undo.block.saturating_sub(1).to_be_bytes().to_vec()),
+    });
+    store.write_batch(&writes)
+}
+
+/// Roll the store back so that `ancestor` becomes the head block.
+///
+/// Every block above `ancestor` must have undo information; otherwise the
+/// rollback stops with an error and the head is left at the last reverted block.
+pub fn rollback_to(store: &dyn StateStore, ancestor: u64) -> Result<()> {
+    let head = store.get_head()?.unwrap_or(0);
+    for block in (ancestor + 1..=head).rev() {
+        let undo = store.get_block_undo(block)?.with_context(|| {
+            format!(
+                "No undo information for block {}; cannot roll back to {}",
+                block, ancestor
+            )
+        })?;
+        rollback_block(store, &undo)
+            .with_context(|| format!("Failed to roll back block {}", block))?;
+        info!("Rolled back block {}", block);
+    }
+    Ok(())
+}
+
+/// Drop undo information that is too old to be needed for a reorg.
+///
+/// The delete commits with the block's batch but is not journaled, so a
+/// rolled-back block does not restore the undo record it pruned.
+pub fn prune_undo(batch: &mut BlockWriteBatch<'_>) -> Result<()> {
+    let head = batch.block();
+    if head > MAX_REORG_DEPTH {
+        batch.delete_block_undo(head - MAX_REORG_DEPTH);
+    }
+    Ok(())
+}
+
+/// Walk back from `from` until the stored hash matches the node's canonical hash.
+///
+/// Returns the highest block number that both chains agree on.
+pub async fn find_common_ancestor(
+    store: &dyn StateStore,
+    rpc: &RpcClient,
+    from: u64,
+) -> Result<u64> {
+    let head = store.get_head()?.unwrap_or(from);
+    let mut block = from;
+    loop {
+        let stored = store.get_block_hash(block)?.with_context(|| {
+            format!("No stored hash for block {}; cannot locate common ancestor", block)
+        })?;
+        let canonical = rpc
+            .get_block_hash(&format!("0x{:x}", block))
+            .await
+            .with_context(|| format!("Failed to fetch canonical hash for block {}", block))?;
+
+        if stored == canonical {
+            return Ok(block);
+        }
+
+        warn!(
+            "Block {} was reorganized: stored {:?}, canonical {:?}",
+            block, stored, canonical
+        );
+
+        if block == 0 || head - block >= MAX_REORG_DEPTH {
+            anyhow::bail!(
+                "Reorg deeper than {} blocks below head {}; reinitialize the store",
+                MAX_REORG_DEPTH,
+                head
+            );
+        }
+        block -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{AccountRecord, BlockDelta, Erc20Delta};
+    use crate::store::RocksStateStore;
+    use alloy_primitives::{address, B256, U256};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (RocksStateStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        (store, temp_dir)
+    }
+
+    fn account(nonce: u64, balance: u64) -> AccountRecord {
+        AccountRecord {
+            nonce,
+            balance: U256::from(balance),
+            code_hash: B256::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_rollback_restores_state_and_head() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = address!("0000000000000000000000000000000000000001");
+        let token = address!("00000000000000000000000000000000000000aa");
+
+        // Block 100 is the ancestor.
+        store.put_account(addr, &account(1, 1000)).unwrap();
+        store.put_erc20_balance(token, addr, U256::from(50u64)).unwrap();
+        store.set_head(100).unwrap();
+
+        // Apply block 101.
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        batch.put_account(addr, &account(2, 900)).unwrap();
+        let mut delta = BlockDelta::new(101);
+        delta.delta_minus = U256::from(100u64);
+        delta.nonce_delta = 1;
+        batch.put_delta(addr, &delta).unwrap();
+        batch.put_snapshot(addr, U256::from(900u64)).unwrap();
+        let mut erc20_delta = Erc20Delta::new(101);
+        erc20_delta.delta_plus = U256::from(5u64);
+        batch.put_erc20_delta(token, addr, &erc20_delta).unwrap();
+        batch.put_erc20_snapshot(token, addr, U256::from(55u64)).unwrap();
+        batch.put_erc20_balance(token, addr, U256::from(55u64)).unwrap();
+        batch.put_block_hash(B256::repeat_byte(0x11)).unwrap();
+        batch.set_head(101).unwrap();
+        batch.commit().unwrap();
+
+        rollback_to(&store, 100).unwrap();
+
+        assert_eq!(store.get_head().unwrap(), Some(100));
+        assert_eq!(store.get_account(addr).unwrap(), Some(account(1, 1000)));
+        assert_eq!(store.get_delta(addr, 101).unwrap(), None);
+        assert_eq!(store.get_snapshot(addr, 101).unwrap(), None);
+        assert_eq!(
+            store.get_erc20_balance(token, addr).unwrap(),
+            Some(U256::from(50u64))
+        );
+        assert!(store
+            .get_erc20_deltas_in_range(token, addr, 101, 101)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.get_latest_erc20_snapshot_at_or_before(token, addr, 101).unwrap(),
+            None
+        );
+        assert_eq!(store.get_block_hash(101).unwrap(), None);
+        assert_eq!(store.get_block_undo(101).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rollback_keeps_pruned_undo_deleted() {
+        let (store, _temp_dir) = create_test_store();
+        let old = 200 - MAX_REORG_DEPTH;
+        store.put_block_undo(old, &BlockUndo { block: old, entries: vec![] }).unwrap();
+        store.set_head(199).unwrap();
+
+        let mut batch = BlockWriteBatch::new(&store, 200);
+        batch.set_head(200).unwrap();
+        prune_undo(&mut batch).unwrap();
+        batch.commit().unwrap();
+        assert_eq!(store.get_block_undo(old).unwrap(), None);
+
+        // The pruned record is not journaled into block 200's undo
+        let undo = store.get_block_undo(200).unwrap().unwrap();
+        assert!(undo.entries.iter().all(|e| e.cf != "block_undo"));
+
+        rollback_to(&store, 199).unwrap();
+        assert_eq!(store.get_head().unwrap(), Some(199));
+        assert_eq!(store.get_block_undo(old).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rollback_without_undo_fails() {
+        let (store, _temp_dir) = create_test_store();
+        store.set_head(105).unwrap();
+
+        let result = rollback_to(&store, 100);
+        assert!(result.is_err());
+        assert!(format!("{:#}", result.unwrap_err()).contains("No undo information for block 105"));
+    }
+}
@@ -2,24 +2,419 @@
 //!
 //! Provides a typed interface to Ethereum JSON-RPC endpoints.
 //! Handles hex string parsing and error handling.
+//!
+//! Every request goes through a token-bucket rate limiter and is retried with
+//! exponential backoff and jitter when it fails with a retryable `RpcError`
+//! (transport errors, timeouts, HTTP 429/5xx, rate-limit JSON-RPC codes).
+//! Fatal errors (invalid params, unknown method, reverts) are returned at once.
+//!
+//! A client can be given several endpoints (see `rpc_pool`): retryable failures
+//! fail over to the next healthy endpoint before backing off, and balance reads
+//! during initialization can require several endpoints to agree.
 
+use crate::rpc_pool::{quorum_value, EndpointPool};
 use crate::types::{Block, CallTrace, Receipt};
 use alloy_primitives::{Address, B256, U256};
 use anyhow::{Context, Result};
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A classified JSON-RPC failure.
+///
+/// Returned inside `anyhow::Error`; use `err.downcast_ref::<RpcError>()` to
+/// inspect it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcError {
+    /// The request could not be sent or the connection failed.
+    Transport(String),
+    /// The request exceeded its timeout.
+    Timeout,
+    /// The endpoint answered with a non-success HTTP status.
+    Http { status: u16, body: String },
+    /// The node returned a JSON-RPC error object.
+    JsonRpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    /// The response was not valid JSON-RPC.
+    InvalidResponse(String),
+}
+
+impl RpcError {
+    /// Build an error from a JSON-RPC `error` object.
+    pub fn from_error_object(error: &Value) -> Self {
+        RpcError::JsonRpc {
+            code: error.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+            message: error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            data: error.get("data").cloned(),
+        }
+    }
+
+    /// Whether retrying the same request may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RpcError::Transport(_) | RpcError::Timeout => true,
+            RpcError::Http { status, .. } => *status == 408 || *status == 429 || *status >= 500,
+            RpcError::JsonRpc { code, message, .. } => match code {
+                // Limit exceeded / internal error / resource unavailable
+                -32005 | -32603 | -32002 => true,
+                // Generic server error: only transient conditions
+                -32000 => {
+                    let message = message.to_lowercase();
+                    ["rate limit", "too many requests", "timeout", "timed out", "try again", "header not found"]
+                        .iter()
+                        .any(|m| message.contains(m))
+                }
+                _ => false,
+            },
+            RpcError::InvalidResponse(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Transport(e) => write!(f, "RPC transport error: {}", e),
+            RpcError::Timeout => write!(f, "RPC request timed out"),
+            RpcError::Http { status, body } => write!(f, "RPC HTTP error {}: {}", status, body),
+            RpcError::JsonRpc { code, message, .. } => {
+                write!(f, "RPC error {}: {}", code, message)
+            }
+            RpcError::InvalidResponse(e) => write!(f, "Invalid RPC response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// Whether an error chain contains a retryable `RpcError`.
+///
+/// Used by long-running loops to ride out node outages that outlast the
+/// client's own retries.
+pub fn is_retryable_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|e| e.downcast_ref::<RpcError>().is_some_and(RpcError::is_retryable))
+}
+
+/// Retry, timeout and rate-limit settings for `RpcClient`.
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    /// Retries after the first attempt for retryable errors.
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubled on every further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the backoff between retries.
+    pub max_backoff: Duration,
+    /// Timeout for methods without an entry in `method_timeouts`.
+    pub default_timeout: Duration,
+    /// Per-method timeouts (e.g. longer ones for `debug_*` tracing).
+    pub method_timeouts: HashMap<String, Duration>,
+    /// Sustained request rate in requests per second (None = unlimited).
+    pub requests_per_second: Option<f64>,
+    /// Maximum burst size of the rate limiter.
+    pub burst: u32,
+    /// Blocks an endpoint may trail the best known head before it is skipped.
+    pub max_head_lag: u64,
+    /// Endpoints that must agree on quorum reads (1 = no quorum).
+    pub quorum: usize,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        let mut method_timeouts = HashMap::new();
+        method_timeouts.insert("debug_traceTransaction".to_string(), Duration::from_secs(60));
+        method_timeouts.insert("debug_traceBlockByNumber".to_string(), Duration::from_secs(120));
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            default_timeout: Duration::from_secs(30),
+            method_timeouts,
+            requests_per_second: None,
+            burst: 10,
+            max_head_lag: 5,
+            quorum: 1,
+        }
+    }
+}
+
+impl RpcConfig {
+    /// Timeout for a method.
+    pub fn timeout_for(&self, method: &str) -> Duration {
+        self.method_timeouts
+            .get(method)
+            .copied()
+            .unwrap_or(self.default_timeout)
+    }
+
+    /// Backoff before retry number `attempt` (0-based), without jitter.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Token-bucket rate limiter.
+#[derive(Debug)]
+struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(refill_per_sec: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take a token at `now`, or return how long to wait for the next one.
+    fn try_acquire(&mut self, now: Instant) -> std::result::Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+        }
+    }
+}
+
+/// Add up to 50% random jitter to a backoff.
+fn with_jitter(backoff: Duration) -> Duration {
+    // Sub-second clock noise is plenty to de-synchronize retrying clients
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let fraction = f64::from(nanos % 1000) / 1000.0;
+    backoff + backoff.mul_f64(fraction * 0.5)
+}
+
+/// Extract `result` from a JSON-RPC response, classifying an `error` object.
+fn extract_result(json: Value) -> std::result::Result<Value, RpcError> {
+    // Check for RPC error
+    if let Some(error) = json.get("error") {
+        return Err(RpcError::from_error_object(error));
+    }
+
+    // Extract result
+    json.get("result")
+        .cloned()
+        .ok_or_else(|| RpcError::InvalidResponse("missing 'result' field".to_string()))
+}
 
 /// JSON-RPC client for Ethereum nodes.
 pub struct RpcClient {
     client: reqwest::Client,
-    url: String,
+    pool: Mutex<EndpointPool>,
+    config: RpcConfig,
+    rate_limiter: Option<Mutex<RateLimiter>>,
 }
 
 impl RpcClient {
-    /// Create a new RPC client.
+    /// Create a new RPC client with the default retry and timeout settings.
     pub fn new(url: String) -> Self {
+        Self::with_config(url, RpcConfig::default())
+    }
+
+    /// Create a new RPC client with explicit retry, timeout and rate-limit settings.
+    pub fn with_config(url: String, config: RpcConfig) -> Self {
+        Self::build(vec![url], config)
+    }
+
+    /// Create a client over several endpoints, in order of preference.
+    ///
+    /// Fails if `urls` is empty or `config.quorum` exceeds the number of endpoints.
+    pub fn with_endpoints(urls: Vec<String>, config: RpcConfig) -> Result<Self> {
+        if urls.is_empty() {
+            anyhow::bail!("At least one RPC endpoint is required");
+        }
+        if config.quorum > urls.len() {
+            anyhow::bail!(
+                "Quorum of {} needs at least {} endpoints, got {}",
+                config.quorum,
+                config.quorum,
+                urls.len()
+            );
+        }
+        Ok(Self::build(urls, config))
+    }
+
+    fn build(urls: Vec<String>, config: RpcConfig) -> Self {
+        let rate_limiter = config
+            .requests_per_second
+            .filter(|rps| *rps > 0.0)
+            .map(|rps| Mutex::new(RateLimiter::new(rps, config.burst)));
         Self {
             client: reqwest::Client::new(),
-            url,
+            pool: Mutex::new(EndpointPool::new(urls, config.max_head_lag)),
+            config,
+            rate_limiter,
+        }
+    }
+
+    /// Number of configured endpoints.
+    pub fn endpoint_count(&self) -> usize {
+        self.with_pool(|pool| pool.len())
+    }
+
+    /// Run `f` on the endpoint pool.
+    fn with_pool<T>(&self, f: impl FnOnce(&mut EndpointPool) -> T) -> T {
+        // The pool only holds health counters, so a poisoned lock is still usable
+        let mut pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut pool)
+    }
+
+    /// Wait until the rate limiter grants a request.
+    async fn acquire_permit(&self) {
+        let Some(limiter) = &self.rate_limiter else {
+            return;
+        };
+        loop {
+            let wait = match limiter.lock() {
+                Ok(mut limiter) => match limiter.try_acquire(Instant::now()) {
+                    Ok(()) => return,
+                    Err(wait) => wait,
+                },
+                Err(_) => return, // Poisoned lock: don't block requests
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Send one HTTP request to an endpoint with a timeout and classify any failure.
+    async fn send_once(
+        &self,
+        endpoint: usize,
+        body: &Value,
+        timeout: Duration,
+    ) -> std::result::Result<Value, RpcError> {
+        self.acquire_permit().await;
+
+        let url = self.with_pool(|pool| pool.url(endpoint).to_string());
+        let response = self
+            .client
+            .post(&url)
+            .timeout(timeout)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    RpcError::Timeout
+                } else {
+                    RpcError::Transport(e.to_string())
+                }
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(RpcError::Http {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        response.json().await.map_err(|e| {
+            if e.is_timeout() {
+                RpcError::Timeout
+            } else {
+                RpcError::InvalidResponse(e.to_string())
+            }
+        })
+    }
+
+    /// Send a request, retrying retryable failures with failover, backoff and jitter.
+    ///
+    /// `check` turns the raw response into the final value, so JSON-RPC errors
+    /// are retried (or not) under the same policy as transport errors. A failed
+    /// request moves on to the next untried endpoint right away; once every
+    /// endpoint has failed, the client backs off before starting over. With
+    /// `pinned`, only that endpoint is used.
+    async fn send_with_retry<T>(
+        &self,
+        label: &str,
+        body: &Value,
+        timeout: Duration,
+        pinned: Option<usize>,
+        check: impl Fn(Value) -> std::result::Result<T, RpcError>,
+    ) -> Result<T> {
+        let mut attempt = 0;
+        let mut tried = Vec::new();
+        loop {
+            let endpoint = match pinned {
+                Some(idx) => idx,
+                None => self.with_pool(|pool| {
+                    pool.select(Instant::now(), &tried)
+                        .or_else(|| pool.select(Instant::now(), &[]))
+                        .unwrap_or(0)
+                }),
+            };
+
+            let result = self.send_once(endpoint, body, timeout).await.and_then(&check);
+            match result {
+                Ok(value) => {
+                    self.with_pool(|pool| pool.record_success(endpoint));
+                    return Ok(value);
+                }
+                Err(e) if e.is_retryable() => {
+                    let url = self.with_pool(|pool| {
+                        pool.record_failure(endpoint, Instant::now());
+                        pool.url(endpoint).to_string()
+                    });
+                    if attempt >= self.config.max_retries {
+                        return Err(e.into());
+                    }
+                    attempt += 1;
+                    tried.push(endpoint);
+
+                    let untried = pinned.is_none()
+                        && self.with_pool(|pool| pool.select(Instant::now(), &tried).is_some());
+                    if untried {
+                        tracing::warn!("{} failed on {} ({}), failing over", label, url, e);
+                        continue;
+                    }
+
+                    let backoff = with_jitter(self.config.backoff(attempt - 1));
+                    tracing::warn!(
+                        "{} failed on {} ({}), retry {}/{} in {:?}",
+                        label,
+                        url,
+                        e,
+                        attempt,
+                        self.config.max_retries,
+                        backoff
+                    );
+                    tokio::time::sleep(backoff).await;
+                    tried.clear();
+                }
+                Err(e) => {
+                    // The endpoint answered; the request itself is bad
+                    self.with_pool(|pool| pool.record_success(endpoint));
+                    return Err(e.into());
+                }
+            }
         }
     }
 
@@ -32,28 +427,143 @@ impl RpcClient {
             "params": params
         });
 
-        let response = self
-            .client
-            .post(&self.url)
-            .json(&request)
-            .send()
+        self.send_with_retry(method, &request, self.config.timeout_for(method), None, extract_result)
             .await
-            .context("Failed to send RPC request")?;
+    }
 
-        let json: Value = response
-            .json()
-            .await
-            .context("Failed to parse RPC response")?;
+    /// Make a JSON-RPC call that `quorum` endpoints must answer identically.
+    ///
+    /// Endpoints are asked one at a time (each with retries) in order of
+    /// preference until enough identical results are collected. Fails if the
+    /// endpoints disagree or too many of them fail.
+    async fn call_quorum(&self, method: &str, params: Value, quorum: usize) -> Result<Value> {
+        if quorum <= 1 {
+            return self.call(method, params).await;
+        }
 
-        // Check for RPC error
-        if let Some(error) = json.get("error") {
-            anyhow::bail!("RPC error: {}", error);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        });
+        let timeout = self.config.timeout_for(method);
+        let order = self.with_pool(|pool| pool.preference_order(Instant::now()));
+
+        let mut responses = Vec::new();
+        let mut failures = Vec::new();
+        for endpoint in order {
+            let url = self.with_pool(|pool| pool.url(endpoint).to_string());
+            match self
+                .send_with_retry(method, &request, timeout, Some(endpoint), extract_result)
+                .await
+            {
+                Ok(value) => {
+                    responses.push(value);
+                    if let Some(value) = quorum_value(&responses, quorum) {
+                        return Ok(value.clone());
+                    }
+                }
+                Err(e) => failures.push(format!("{}: {}", url, e)),
+            }
         }
 
-        // Extract result
-        json.get("result")
-            .cloned()
-            .context("RPC response missing 'result' field")
+        anyhow::bail!(
+            "No quorum of {} endpoints for {}: responses {:?}, failures {:?}",
+            quorum,
+            method,
+            responses,
+            failures
+        )
+    }
+
+    /// Query every endpoint's `eth_blockNumber` and update the pool's head tracking.
+    ///
+    /// Endpoints that fail are put in cooldown; endpoints trailing the best
+    /// head by more than `max_head_lag` are skipped until they catch up.
+    pub async fn refresh_heads(&self) {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_blockNumber",
+            "params": []
+        });
+        let timeout = self.config.timeout_for("eth_blockNumber");
+
+        for endpoint in 0..self.endpoint_count() {
+            let head = self
+                .send_once(endpoint, &request, timeout)
+                .await
+                .and_then(extract_result)
+                .and_then(|result| {
+                    result
+                        .as_str()
+                        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+                        .ok_or_else(|| RpcError::InvalidResponse(format!("bad block number {}", result)))
+                });
+            self.with_pool(|pool| match head {
+                Ok(head) => {
+                    pool.record_success(endpoint);
+                    pool.record_head(endpoint, head);
+                    if pool.is_lagging(endpoint) {
+                        tracing::warn!(
+                            "RPC endpoint {} is lagging: head {} vs best {:?}",
+                            pool.url(endpoint),
+                            head,
+                            pool.best_head()
+                        );
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("RPC endpoint {} failed head check: {}", pool.url(endpoint), e);
+                    pool.record_failure(endpoint, Instant::now());
+                }
+            });
+        }
+    }
+
+    /// Make a JSON-RPC batch call.
+    ///
+    /// Sends all `calls` as one JSON array and returns one result per call,
+    /// in request order (responses are matched by id). Fails as a whole if the
+    /// node does not answer with an array, i.e. does not support batches.
+    /// Only failures of the whole request are retried.
+    async fn call_batch(&self, calls: &[(&str, Value)]) -> Result<Vec<Result<Value>>> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| {
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": method,
+                    "params": params
+                })
+            })
+            .collect();
+        let request = Value::Array(request);
+
+        // The batch takes as long as its slowest method
+        let timeout = calls
+            .iter()
+            .map(|(method, _)| self.config.timeout_for(method))
+            .max()
+            .unwrap_or(self.config.default_timeout);
+
+        let json = self
+            .send_with_retry("RPC batch", &request, timeout, None, |json| match &json {
+                Value::Object(obj) if obj.contains_key("error") => {
+                    Err(RpcError::from_error_object(&obj["error"]))
+                }
+                _ => Ok(json),
+            })
+            .await
+            .context("RPC batch request failed")?;
+
+        parse_batch_response(json, calls.len())
     }
 
     /// Get a block by number with full transaction details.
@@ -88,6 +598,26 @@ impl RpcClient {
             .context("Failed to parse block number")
     }
 
+    /// Get just the block hash for a given block number or tag.
+    ///
+    /// Used for reorg detection, where only the canonical hash at a height is needed.
+    pub async fn get_block_hash(&self, block: &str) -> Result<B256> {
+        let params = json!([block, false]);
+        let result = self.call("eth_getBlockByNumber", params).await?;
+
+        let hash_str = result
+            .get("hash")
+            .and_then(|v| v.as_str())
+            .with_context(|| format!("Block {} missing 'hash' field", block))?;
+
+        let hash_str = hash_str.strip_prefix("0x").unwrap_or(hash_str);
+        let bytes = hex::decode(hash_str).context("Failed to decode block hash hex")?;
+        if bytes.len() != 32 {
+            anyhow::bail!("Expected 32 bytes for block hash, got {}", bytes.len());
+        }
+        Ok(B256::from_slice(&bytes))
+    }
+
     /// Get the current finalized block number.
     ///
     /// Tries "finalized" first, then falls back to "latest" if finalized is not available
@@ -146,9 +676,18 @@ impl RpcClient {
     ///
     /// `block` can be a block number (u64) or "finalized", "latest", etc.
     pub async fn get_balance(&self, address: Address, block: &str) -> Result<U256> {
+        self.fetch_balance(address, block, 1).await
+    }
+
+    /// Get the balance of an address, requiring `RpcConfig::quorum` endpoints to agree.
+    pub async fn get_balance_quorum(&self, address: Address, block: &str) -> Result<U256> {
+        self.fetch_balance(address, block, self.config.quorum).await
+    }
+
+    async fn fetch_balance(&self, address: Address, block: &str, quorum: usize) -> Result<U256> {
         let addr_str = format!("0x{:x}", address);
         let params = json!([addr_str, block]);
-        let result = self.call("eth_getBalance", params).await?;
+        let result = self.call_quorum("eth_getBalance", params, quorum).await?;
         
         let balance_str_raw = result
             .as_str()
@@ -227,6 +766,16 @@ impl RpcClient {
     /// `to` is the contract address, `data` is the ABI-encoded call data (hex string).
     /// `block` can be "latest", "0x123", etc.
     pub async fn eth_call(&self, to: Address, data: &[u8], block: &str) -> Result<Vec<u8>> {
+        self.eth_call_with_quorum(to, data, block, 1).await
+    }
+
+    async fn eth_call_with_quorum(
+        &self,
+        to: Address,
+        data: &[u8],
+        block: &str,
+        quorum: usize,
+    ) -> Result<Vec<u8>> {
         let to_str = format!("0x{:x}", to);
         let data_str = format!("0x{}", hex::encode(data));
         let params = json!([{
@@ -234,7 +783,7 @@ impl RpcClient {
             "data": data_str
         }, block]);
 
-        let result = self.call("eth_call", params).await?;
+        let result = self.call_quorum("eth_call", params, quorum).await?;
         let hex_str = result
             .as_str()
             .context("eth_call result is not a string")?;
@@ -260,6 +809,27 @@ impl RpcClient {
         token: Address,
         owner: Address,
         block: &str,
+    ) -> Result<U256> {
+        self.fetch_erc20_balance(token, owner, block, 1).await
+    }
+
+    /// Get ERC20 balanceOf(owner), requiring `RpcConfig::quorum` endpoints to agree.
+    pub async fn erc20_balance_of_quorum(
+        &self,
+        token: Address,
+        owner: Address,
+        block: &str,
+    ) -> Result<U256> {
+        self.fetch_erc20_balance(token, owner, block, self.config.quorum)
+            .await
+    }
+
+    async fn fetch_erc20_balance(
+        &self,
+        token: Address,
+        owner: Address,
+        block: &str,
+        quorum: usize,
     ) -> Result<U256> {
         // balanceOf(address) selector: 0x70a08231
         let mut data = vec![0x70, 0xa0, 0x82, 0x31];
@@ -268,13 +838,94 @@ impl RpcClient {
         padded[12..32].copy_from_slice(owner.as_slice());
         data.extend_from_slice(&padded);
 
-        let result = self.eth_call(token, &data, block).await?;
+        let result = self.eth_call_with_quorum(token, &data, block, quorum).await?;
         if result.len() < 32 {
             anyhow::bail!("balanceOf returned insufficient data: {} bytes", result.len());
         }
         Ok(U256::from_be_slice(&result[0..32]))
     }
 
+    /// Get ERC1155 balanceOf(owner, id), requiring `RpcConfig::quorum` endpoints to agree.
+    pub async fn erc1155_balance_of_quorum(
+        &self,
+        contract: Address,
+        owner: Address,
+        id: U256,
+        block: &str,
+    ) -> Result<U256> {
+        // balanceOf(address,uint256) selector: 0x00fdd58e
+        let mut data = vec![0x00, 0xfd, 0xd5, 0x8e];
+        let mut padded = [0u8; 32];
+        padded[12..32].copy_from_slice(owner.as_slice());
+        data.extend_from_slice(&padded);
+        data.extend_from_slice(&id.to_be_bytes::<32>());
+
+        let result = self
+            .eth_call_with_quorum(contract, &data, block, self.config.quorum)
+            .await?;
+        if result.len() < 32 {
+            anyhow::bail!("balanceOf returned insufficient data: {} bytes", result.len());
+        }
+        Ok(U256::from_be_slice(&result[0..32]))
+    }
+
+    /// Get several transaction receipts in one batch request.
+    ///
+    /// Receipts are returned in the order of `tx_hashes`.
+    pub async fn get_transaction_receipts_batch(&self, tx_hashes: &[B256]) -> Result<Vec<Receipt>> {
+        let calls: Vec<(&str, Value)> = tx_hashes
+            .iter()
+            .map(|hash| ("eth_getTransactionReceipt", json!([format!("0x{:x}", hash)])))
+            .collect();
+
+        self.call_batch(&calls)
+            .await?
+            .into_iter()
+            .zip(tx_hashes)
+            .map(|(result, hash)| {
+                let value = result.with_context(|| format!("Failed to fetch receipt for tx {:?}", hash))?;
+                serde_json::from_value(value).context("Failed to deserialize receipt")
+            })
+            .collect()
+    }
+
+    /// Get all receipts of a block with `eth_getBlockReceipts`.
+    ///
+    /// `block` can be a block number ("0x123") or a tag. Receipts are in
+    /// transaction order. Not every node implements this method.
+    pub async fn get_block_receipts(&self, block: &str) -> Result<Vec<Receipt>> {
+        let result = self.call("eth_getBlockReceipts", json!([block])).await?;
+        if result.is_null() {
+            anyhow::bail!("eth_getBlockReceipts returned null for block {}", block);
+        }
+        serde_json::from_value(result).context("Failed to deserialize block receipts")
+    }
+
+    /// Trace every transaction of a block using the `callTracer`.
+    ///
+    /// Uses `debug_traceBlockByNumber`. Returns one entry per transaction in
+    /// block order; an entry is `None` if the tracer failed for that transaction.
+    pub async fn debug_trace_block_by_number_calltracer(
+        &self,
+        block: &str,
+        timeout: &str,
+    ) -> Result<Vec<Option<CallTrace>>> {
+        let params = json!([
+            block,
+            {
+                "tracer": "callTracer",
+                "timeout": timeout,
+            }
+        ]);
+
+        let result = self
+            .call("debug_traceBlockByNumber", params)
+            .await
+            .context("debug_traceBlockByNumber RPC call failed")?;
+
+        parse_block_traces(result)
+    }
+
     /// Trace a transaction using the `callTracer`.
     ///
     /// This uses `debug_traceTransaction` with the built-in `callTracer`
@@ -302,10 +953,182 @@ impl RpcClient {
     }
 }
 
+/// Split a batch response into per-request results, ordered by request id.
+fn parse_batch_response(json: Value, len: usize) -> Result<Vec<Result<Value>>> {
+    let items = match json {
+        Value::Array(items) => items,
+        other => anyhow::bail!("RPC batch not supported: {}", other),
+    };
+
+    let mut results: Vec<Option<Result<Value>>> = (0..len).map(|_| None).collect();
+    for item in items {
+        let id = item
+            .get("id")
+            .and_then(|v| v.as_u64())
+            .context("RPC batch response item missing 'id'")? as usize;
+        let slot = results
+            .get_mut(id)
+            .with_context(|| format!("RPC batch response has unknown id {}", id))?;
+        *slot = Some(match (item.get("error"), item.get("result")) {
+            (Some(error), _) => Err(RpcError::from_error_object(error).into()),
+            (None, Some(result)) => Ok(result.clone()),
+            (None, None) => Err(anyhow::anyhow!("RPC response missing 'result' field")),
+        });
+    }
+
+    Ok(results
+        .into_iter()
+        .enumerate()
+        .map(|(id, r)| r.unwrap_or_else(|| Err(anyhow::anyhow!("No response for batch id {}", id))))
+        .collect())
+}
+
+/// Parse a `debug_traceBlockByNumber` result into per-transaction call traces.
+///
+/// Each entry is `{"txHash": ..., "result": {...}}` (or `{"error": ...}` when
+/// the tracer failed for that transaction).
+fn parse_block_traces(result: Value) -> Result<Vec<Option<CallTrace>>> {
+    let items = match result {
+        Value::Array(items) => items,
+        other => anyhow::bail!("debug_traceBlockByNumber result is not an array: {}", other),
+    };
+
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            item.get("result")
+                .cloned()
+                .and_then(|trace| serde_json::from_value(trace).ok())
+        })
+        .collect())
+}
+
+/// Local JSON-RPC server for tests of RPC-driven code.
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::RpcClient;
+    use alloy_primitives::U256;
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve JSON-RPC requests on a local port; `handler` returns the result or error object.
+    pub(crate) fn mock_rpc(
+        handler: impl Fn(&str, &Value) -> std::result::Result<Value, Value> + Send + 'static,
+    ) -> RpcClient {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let method = request["method"].as_str().unwrap();
+                let response = match handler(method, &request["params"]) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+                    Err(error) => json!({"jsonrpc": "2.0", "id": request["id"], "error": error}),
+                }
+                .to_string();
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        RpcClient::new(url)
+    }
+
+    /// ABI-encode a uint256 return value.
+    pub(crate) fn word(value: u64) -> Value {
+        json!(format!("0x{}", hex::encode(U256::from(value).to_be_bytes::<32>())))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rpc_error_classification() {
+        let err = |code: i64, message: &str| {
+            RpcError::from_error_object(&json!({ "code": code, "message": message }))
+        };
+
+        assert!(RpcError::Timeout.is_retryable());
+        assert!(RpcError::Transport("connection reset".to_string()).is_retryable());
+        assert!(RpcError::Http { status: 429, body: String::new() }.is_retryable());
+        assert!(RpcError::Http { status: 503, body: String::new() }.is_retryable());
+        assert!(!RpcError::Http { status: 401, body: String::new() }.is_retryable());
+        assert!(err(-32005, "limit exceeded").is_retryable());
+        assert!(err(-32000, "Rate limit reached, try again later").is_retryable());
+        assert!(!err(-32000, "insufficient funds").is_retryable());
+        assert!(!err(-32601, "the method does not exist").is_retryable());
+        assert!(!err(-32602, "invalid argument").is_retryable());
+        assert!(!err(3, "execution reverted").is_retryable());
+
+        // Retryable errors are still found behind anyhow context
+        let wrapped = anyhow::Error::from(RpcError::Timeout).context("Failed to get block");
+        assert!(is_retryable_error(&wrapped));
+        assert!(!is_retryable_error(&anyhow::anyhow!("Block not found")));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = RpcConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            ..RpcConfig::default()
+        };
+        assert_eq!(config.backoff(0), Duration::from_millis(100));
+        assert_eq!(config.backoff(1), Duration::from_millis(200));
+        assert_eq!(config.backoff(3), Duration::from_millis(800));
+        assert_eq!(config.backoff(4), Duration::from_secs(1));
+        assert_eq!(config.backoff(40), Duration::from_secs(1));
+
+        let jittered = with_jitter(Duration::from_millis(100));
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(150));
+
+        assert_eq!(config.timeout_for("eth_getBalance"), Duration::from_secs(30));
+        assert_eq!(config.timeout_for("debug_traceBlockByNumber"), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_rate_limiter_token_bucket() {
+        let mut limiter = RateLimiter::new(10.0, 2);
+        let start = limiter.last_refill;
+
+        // Burst of two, then wait for a refill
+        assert!(limiter.try_acquire(start).is_ok());
+        assert!(limiter.try_acquire(start).is_ok());
+        let wait = limiter.try_acquire(start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        assert!(limiter.try_acquire(start + Duration::from_millis(100)).is_ok());
+        assert!(limiter.try_acquire(start + Duration::from_millis(100)).is_err());
+
+        // Refill never exceeds the burst size
+        let later = start + Duration::from_secs(10);
+        assert!(limiter.try_acquire(later).is_ok());
+        assert!(limiter.try_acquire(later).is_ok());
+        assert!(limiter.try_acquire(later).is_err());
+    }
+
     #[test]
     fn test_address_formatting() {
         // Test that address formatting works correctly
@@ -313,4 +1136,41 @@ mod tests {
         let addr = Address::from_slice(&addr_bytes);
         assert_eq!(format!("0x{:x}", addr), "0x0742d35cc6634c0532925a3b844bc9e7595f0beb");
     }
+
+    #[test]
+    fn test_parse_batch_response_orders_by_id() {
+        let json = json!([
+            { "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "not found" } },
+            { "jsonrpc": "2.0", "id": 0, "result": "0x1" },
+        ]);
+        let results = parse_batch_response(json, 3).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &json!("0x1"));
+        assert!(results[1].is_err());
+        assert!(results[2].is_err()); // No response for id 2
+
+        // Nodes without batch support answer with a single error object
+        let json = json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32600 } });
+        assert!(parse_batch_response(json, 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_block_traces() {
+        let result = json!([
+            {
+                "txHash": "0x01",
+                "result": {
+                    "type": "CALL",
+                    "from": "0x0742d35cc6634c0532925a3b844bc9e7595f0beb",
+                    "to": "0xdac17f958d2ee523a2206206994597c13d831ec7",
+                    "value": "0x10"
+                }
+            },
+            { "txHash": "0x02", "error": "execution timeout" },
+        ]);
+        let traces = parse_block_traces(result).unwrap();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].as_ref().unwrap().value, U256::from(16u64));
+        assert!(traces[1].is_none());
+    }
 }
@@ -0,0 +1,200 @@
+//! Endpoint health and head tracking for multi-endpoint RPC
+//!
+//! `RpcClient` can be configured with several endpoints. The pool decides
+//! which one a request goes to:
+//!
+//! - Endpoints are preferred in the order they were configured
+//! - A failed endpoint is put in a cooldown (growing with consecutive
+//!   failures) and skipped until it expires or every other endpoint is down
+//! - An endpoint whose head lags the best known head by more than
+//!   `max_head_lag` blocks is skipped as well
+//!
+//! The pool only holds state; sending requests is up to `RpcClient`.
+
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Cooldown after the first failure, doubled per consecutive failure.
+const BASE_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Upper bound for the cooldown multiplier (5s * 16 = 80s).
+const MAX_COOLDOWN_FACTOR: u32 = 16;
+
+/// Health state of one endpoint.
+#[derive(Debug, Clone)]
+struct EndpointState {
+    url: String,
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+    head: Option<u64>,
+}
+
+/// Set of RPC endpoints with health and head tracking.
+#[derive(Debug, Clone)]
+pub struct EndpointPool {
+    endpoints: Vec<EndpointState>,
+    max_head_lag: u64,
+}
+
+impl EndpointPool {
+    /// Create a pool from endpoint URLs in order of preference.
+    pub fn new(urls: Vec<String>, max_head_lag: u64) -> Self {
+        Self {
+            endpoints: urls
+                .into_iter()
+                .map(|url| EndpointState {
+                    url,
+                    consecutive_failures: 0,
+                    unhealthy_until: None,
+                    head: None,
+                })
+                .collect(),
+            max_head_lag,
+        }
+    }
+
+    /// Number of endpoints.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether the pool has no endpoints.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// URL of an endpoint.
+    pub fn url(&self, idx: usize) -> &str {
+        &self.endpoints[idx].url
+    }
+
+    /// Highest head reported by any endpoint.
+    pub fn best_head(&self) -> Option<u64> {
+        self.endpoints.iter().filter_map(|e| e.head).max()
+    }
+
+    /// Last head reported by an endpoint.
+    pub fn head(&self, idx: usize) -> Option<u64> {
+        self.endpoints[idx].head
+    }
+
+    /// Whether an endpoint is behind the best known head by more than the allowed lag.
+    pub fn is_lagging(&self, idx: usize) -> bool {
+        match (self.endpoints[idx].head, self.best_head()) {
+            (Some(head), Some(best)) => head.saturating_add(self.max_head_lag) < best,
+            _ => false,
+        }
+    }
+
+    /// Whether an endpoint is out of cooldown and not lagging.
+    pub fn is_available(&self, idx: usize, now: Instant) -> bool {
+        let cooling_down = self.endpoints[idx]
+            .unhealthy_until
+            .is_some_and(|until| now < until);
+        !cooling_down && !self.is_lagging(idx)
+    }
+
+    /// Endpoint indices in the order they should be tried.
+    ///
+    /// Available endpoints come first in configured order, followed by the
+    /// unavailable ones (so a request is still attempted when all are down).
+    pub fn preference_order(&self, now: Instant) -> Vec<usize> {
+        let (mut order, unavailable): (Vec<usize>, Vec<usize>) =
+            (0..self.endpoints.len()).partition(|&idx| self.is_available(idx, now));
+        order.extend(unavailable);
+        order
+    }
+
+    /// Best endpoint that has not been tried yet, if any.
+    pub fn select(&self, now: Instant, tried: &[usize]) -> Option<usize> {
+        self.preference_order(now)
+            .into_iter()
+            .find(|idx| !tried.contains(idx))
+    }
+
+    /// Record a successful response; clears the endpoint's cooldown.
+    pub fn record_success(&mut self, idx: usize) {
+        let endpoint = &mut self.endpoints[idx];
+        endpoint.consecutive_failures = 0;
+        endpoint.unhealthy_until = None;
+    }
+
+    /// Record a failed request and start (or extend) the endpoint's cooldown.
+    pub fn record_failure(&mut self, idx: usize, now: Instant) {
+        let endpoint = &mut self.endpoints[idx];
+        endpoint.consecutive_failures = endpoint.consecutive_failures.saturating_add(1);
+        let factor = 1u32
+            .checked_shl(endpoint.consecutive_failures - 1)
+            .unwrap_or(MAX_COOLDOWN_FACTOR)
+            .min(MAX_COOLDOWN_FACTOR);
+        endpoint.unhealthy_until = Some(now + BASE_COOLDOWN * factor);
+    }
+
+    /// Record the head block reported by an endpoint.
+    pub fn record_head(&mut self, idx: usize, head: u64) {
+        self.endpoints[idx].head = Some(head);
+    }
+}
+
+/// Value returned by at least `quorum` of `responses`, if any.
+pub fn quorum_value(responses: &[Value], quorum: usize) -> Option<&Value> {
+    responses
+        .iter()
+        .find(|candidate| responses.iter().filter(|r| r == candidate).count() >= quorum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pool() -> EndpointPool {
+        EndpointPool::new(
+            vec!["http://a".to_string(), "http://b".to_string(), "http://c".to_string()],
+            5,
+        )
+    }
+
+    #[test]
+    fn test_failover_and_cooldown() {
+        let mut pool = pool();
+        let now = Instant::now();
+        assert_eq!(pool.select(now, &[]), Some(0));
+        assert_eq!(pool.select(now, &[0]), Some(1));
+        assert_eq!(pool.select(now, &[0, 1, 2]), None);
+
+        // A failed primary is skipped until its cooldown expires
+        pool.record_failure(0, now);
+        assert_eq!(pool.preference_order(now), vec![1, 2, 0]);
+        assert_eq!(pool.select(now + BASE_COOLDOWN, &[]), Some(0));
+
+        // Consecutive failures extend the cooldown; success clears it
+        pool.record_failure(0, now);
+        assert!(!pool.is_available(0, now + BASE_COOLDOWN));
+        assert!(pool.is_available(0, now + BASE_COOLDOWN * 2));
+        pool.record_success(0);
+        assert!(pool.is_available(0, now));
+    }
+
+    #[test]
+    fn test_lagging_endpoint_is_skipped() {
+        let mut pool = pool();
+        let now = Instant::now();
+        pool.record_head(0, 100);
+        pool.record_head(1, 110);
+        pool.record_head(2, 106);
+
+        assert_eq!(pool.best_head(), Some(110));
+        assert!(pool.is_lagging(0));
+        assert!(!pool.is_lagging(2));
+        assert_eq!(pool.preference_order(now), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_quorum_value() {
+        let responses = vec![json!("0x10"), json!("0x11"), json!("0x10")];
+        assert_eq!(quorum_value(&responses, 2), Some(&json!("0x10")));
+        assert_eq!(quorum_value(&responses, 3), None);
+        assert_eq!(quorum_value(&responses[..2], 2), None);
+    }
+}
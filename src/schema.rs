@@ -0,0 +1,275 @@
+//! Store schema version and record migrations
+//!
+//! Records are encoded with postcard, which is neither self-describing nor
+//! tolerant of added fields: a record written with an older layout does not
+//! decode with the current one. The store therefore keeps the schema version
+//! of its values in `meta` (id 0x03). Opening an older store rewrites every
+//! affected value to the current layout in bounded batches, and records the
+//! new version once all of them are committed.
+//!
+//! Version 0 is every store written before the version was recorded, with
+//! the original record layouts. Those stores only have the original column
+//! families: undo records and token metadata were introduced with version 1.
+//! Adding a field to a stored record requires bumping `SCHEMA_VERSION` and a
+//! migration step here.
+
+use crate::records::{BlockDelta, Erc20Delta, TokenWatchMeta, WatchMeta};
+use alloy_primitives::U256;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Schema version of the records written by this build.
+///
+/// - 0: original layouts
+/// - 1: fee, withdrawal and correction fields in `BlockDelta`; correction,
+///   rebase and adjustment fields in `Erc20Delta`; `TokenWatchMeta.auto_discovered`;
+///   coverage end and gaps in `WatchMeta` and `TokenWatchMeta`
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Column families whose value layout changed since version 0.
+pub(crate) const MIGRATED_CFS: &[&str] = &[
+    "block_deltas",
+    "erc20_deltas",
+    "watch_meta",
+    "erc20_watch_meta",
+];
+
+/// Maximum number of rewritten records committed in one migration batch.
+pub(crate) const MIGRATION_BATCH_SIZE: usize = 1000;
+
+/// Progress of an interrupted migration: the last key committed in `cf`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MigrationCursor {
+    pub cf: String,
+    pub key: Vec<u8>,
+}
+
+/// `BlockDelta` in schema version 0.
+#[derive(Serialize, Deserialize)]
+struct BlockDeltaV0 {
+    block: u64,
+    delta_plus: U256,
+    delta_minus: U256,
+    received_value: U256,
+    sent_value: U256,
+    fee_paid: U256,
+    failed_fee: U256,
+    nonce_delta: u64,
+    tx_count: u32,
+}
+
+/// `Erc20Delta` in schema version 0.
+#[derive(Serialize, Deserialize)]
+struct Erc20DeltaV0 {
+    block: u64,
+    delta_plus: U256,
+    delta_minus: U256,
+    tx_count: u32,
+}
+
+/// `WatchMeta` and `TokenWatchMeta` in schema version 0.
+#[derive(Serialize, Deserialize)]
+struct WatchMetaV0 {
+    start_block: u64,
+}
+
+fn decode<T: DeserializeOwned>(cf: &str, value: &[u8]) -> Result<T> {
+    postcard::from_bytes(value)
+        .with_context(|| format!("Failed to decode version 0 value in '{}'", cf))
+}
+
+fn encode<T: Serialize>(cf: &str, record: &T) -> Result<Vec<u8>> {
+    postcard::to_allocvec(record).with_context(|| format!("Failed to encode value in '{}'", cf))
+}
+
+/// Rewrite a version 0 value of column family `cf` in the current layout.
+///
+/// Returns None if the value's layout is unchanged.
+pub(crate) fn upgrade_v0_value(cf: &str, value: &[u8]) -> Result<Option<Vec<u8>>> {
+    let upgraded = match cf {
+        "block_deltas" => {
+            let old: BlockDeltaV0 = decode(cf, value)?;
+            let delta = BlockDelta {
+                delta_plus: old.delta_plus,
+                delta_minus: old.delta_minus,
+                received_value: old.received_value,
+                sent_value: old.sent_value,
+                fee_paid: old.fee_paid,
+                failed_fee: old.failed_fee,
+                nonce_delta: old.nonce_delta,
+                tx_count: old.tx_count,
+                ..BlockDelta::new(old.block)
+            };
+            encode(cf, &delta)?
+        }
+        "erc20_deltas" => {
+            let old: Erc20DeltaV0 = decode(cf, value)?;
+            let delta = Erc20Delta {
+                delta_plus: old.delta_plus,
+                delta_minus: old.delta_minus,
+                tx_count: old.tx_count,
+                ..Erc20Delta::new(old.block)
+            };
+            encode(cf, &delta)?
+        }
+        "watch_meta" => {
+            let old: WatchMetaV0 = decode(cf, value)?;
+            encode(cf, &WatchMeta::new(old.start_block))?
+        }
+        "erc20_watch_meta" => {
+            let old: WatchMetaV0 = decode(cf, value)?;
+            let meta = TokenWatchMeta::new(old.start_block);
+            encode(cf, &meta)?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(upgraded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{
+        encode_delta_key, encode_meta_key, encode_token_watch_meta_key, encode_watch_meta_key,
+    };
+    use crate::records::RawWrite;
+    use crate::store::{RocksStateStore, StateStore};
+    use alloy_primitives::Address;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_open_migrates_version_0_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = Address::repeat_byte(0x11);
+        let old_delta = BlockDeltaV0 {
+            block: 101,
+            delta_plus: U256::from(500u64),
+            delta_minus: U256::from(20u64),
+            received_value: U256::from(500u64),
+            sent_value: U256::ZERO,
+            fee_paid: U256::from(20u64),
+            failed_fee: U256::ZERO,
+            nonce_delta: 1,
+            tx_count: 2,
+        };
+        let token = Address::repeat_byte(0x22);
+        let old_meta = postcard::to_allocvec(&WatchMetaV0 { start_block: 100 }).unwrap();
+
+        // A store written before versioning: old layouts and no version
+        {
+            let store = RocksStateStore::open(temp_dir.path()).unwrap();
+            let raw = |cf: &str, key: Vec<u8>, value: Option<Vec<u8>>| RawWrite {
+                cf: cf.to_string(),
+                key,
+                value,
+            };
+            store
+                .write_batch(&[
+                    raw(
+                        "block_deltas",
+                        encode_delta_key(addr, 101),
+                        Some(postcard::to_allocvec(&old_delta).unwrap()),
+                    ),
+                    raw(
+                        "watch_meta",
+                        encode_watch_meta_key(addr),
+                        Some(old_meta.clone()),
+                    ),
+                    raw(
+                        "erc20_watch_meta",
+                        encode_token_watch_meta_key(token, addr),
+                        Some(old_meta),
+                    ),
+                    raw("meta", encode_meta_key(0x03), None),
+                ])
+                .unwrap();
+            assert!(store.get_watch_meta(addr).is_err());
+        }
+
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        assert_eq!(
+            store.get_watch_meta(addr).unwrap(),
+            Some(WatchMeta::new(100))
+        );
+        let deltas = store.get_deltas_in_range(addr, 101, 101).unwrap();
+        let mut expected = BlockDelta::new(101);
+        expected.delta_plus = U256::from(500u64);
+        expected.delta_minus = U256::from(20u64);
+        expected.received_value = U256::from(500u64);
+        expected.fee_paid = U256::from(20u64);
+        expected.nonce_delta = 1;
+        expected.tx_count = 2;
+        assert_eq!(deltas, vec![(101, expected)]);
+
+        assert_eq!(
+            store.get_token_watch_meta(token, addr).unwrap(),
+            Some(TokenWatchMeta::new(100))
+        );
+
+        // Already migrated: reopening leaves the records alone
+        drop(store);
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        assert_eq!(
+            store.get_watch_meta(addr).unwrap(),
+            Some(WatchMeta::new(100))
+        );
+    }
+
+    #[test]
+    fn test_open_resumes_interrupted_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let done = Address::repeat_byte(0x11);
+        let pending = Address::repeat_byte(0x22);
+        let mut done_meta = WatchMeta::new(100);
+        done_meta.end_block = Some(150);
+
+        // Interrupted after the batch holding `done`: its value is already in the current
+        // layout and the cursor points at it, with no version recorded yet
+        {
+            let store = RocksStateStore::open(temp_dir.path()).unwrap();
+            let cursor = MigrationCursor {
+                cf: "watch_meta".to_string(),
+                key: encode_watch_meta_key(done),
+            };
+            let raw = |cf: &str, key: Vec<u8>, value: Option<Vec<u8>>| RawWrite {
+                cf: cf.to_string(),
+                key,
+                value,
+            };
+            store
+                .write_batch(&[
+                    raw(
+                        "watch_meta",
+                        encode_watch_meta_key(done),
+                        Some(postcard::to_allocvec(&done_meta).unwrap()),
+                    ),
+                    raw(
+                        "watch_meta",
+                        encode_watch_meta_key(pending),
+                        Some(postcard::to_allocvec(&WatchMetaV0 { start_block: 200 }).unwrap()),
+                    ),
+                    raw(
+                        "meta",
+                        encode_meta_key(0x04),
+                        Some(postcard::to_allocvec(&cursor).unwrap()),
+                    ),
+                    raw("meta", encode_meta_key(0x03), None),
+                ])
+                .unwrap();
+        }
+
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        // The committed record is not migrated twice (which would drop its end block)
+        assert_eq!(store.get_watch_meta(done).unwrap(), Some(done_meta));
+        assert_eq!(
+            store.get_watch_meta(pending).unwrap(),
+            Some(WatchMeta::new(200))
+        );
+        assert_eq!(store.get_raw("meta", &encode_meta_key(0x04)).unwrap(), None);
+        assert_eq!(
+            store.get_raw("meta", &encode_meta_key(0x03)).unwrap(),
+            Some(SCHEMA_VERSION.to_be_bytes().to_vec())
+        );
+    }
+}
@@ -3,20 +3,34 @@
 //! Provides a persistent key-value store for Ethereum state data.
 //! Uses RocksDB with column families for efficient organization.
 
+use crate::config::{FollowMode, TokenStrategy};
 use crate::keys::{
-    decode_delta_key, decode_erc20_delta_key, decode_erc20_snapshot_key, decode_snapshot_key,
-    encode_account_key, encode_block_hash_key, encode_code_key, encode_delta_key,
-    encode_erc20_delta_key, encode_erc20_snapshot_key, encode_header_key, encode_meta_key,
-    encode_snapshot_key, encode_storage_key, encode_token_watch_meta_key, encode_watch_meta_key,
+    decode_delta_key, decode_erc1155_delta_key, decode_erc1155_snapshot_key, decode_erc20_delta_key,
+    decode_erc20_snapshot_key, decode_snapshot_key, decode_token_metadata_key,
+    decode_token_watch_meta_key, decode_watch_meta_key, decode_watchlist_key, encode_account_key,
+    encode_allowance_change_key, encode_allowance_key, encode_block_hash_key, encode_block_undo_key,
+    encode_code_key, encode_conversion_key, encode_delta_key, encode_drift_key,
+    encode_erc1155_delta_key, encode_erc1155_snapshot_key, encode_erc1155_watch_meta_key,
+    encode_erc20_delta_key, encode_erc20_snapshot_key, encode_event_key, encode_header_key,
+    encode_meta_key, encode_nft_holding_key, encode_nft_transfer_key, encode_snapshot_key,
+    encode_storage_key, encode_token_metadata_key, encode_token_watch_meta_key,
+    encode_tx_record_key, encode_watch_meta_key, encode_watched_address_key,
+    encode_watched_token_key,
 };
 use crate::records::{
-    decode_u256, encode_u256, AccountRecord, BalanceSnapshot, BlockDelta, Erc20Delta, Erc20Snapshot,
-    HeaderRecord, TokenWatchMeta, WatchMeta,
+    decode_u256, encode_u256, AccountRecord, Allowance, BalanceSnapshot, BlockDelta, BlockUndo,
+    Conversion, DriftRecord, Erc1155Delta, Erc1155Snapshot, Erc20Delta, Erc20Snapshot, EventRecord,
+    HeaderRecord, NftHolding, NftTransfer, RawWrite, TokenMetadata, TokenWatchMeta, TxDirection,
+    TxRecord, WatchMeta,
+};
+use crate::schema::{
+    upgrade_v0_value, MigrationCursor, MIGRATED_CFS, MIGRATION_BATCH_SIZE, SCHEMA_VERSION,
 };
 use alloy_primitives::{Address, B256, U256};
 use anyhow::{Context, Result};
-use rocksdb::{ColumnFamilyDescriptor, Options, DB};
+use rocksdb::{ColumnFamilyDescriptor, Options, WriteBatch, DB};
 use std::path::Path;
+use tracing::info;
 
 /// Trait defining the interface for Ethereum state storage.
 ///
@@ -29,6 +43,9 @@ pub trait StateStore {
     /// Store an account record.
     fn put_account(&self, addr: Address, acc: &AccountRecord) -> Result<()>;
 
+    /// Delete an account record.
+    fn delete_account(&self, addr: Address) -> Result<()>;
+
     /// Get contract bytecode by code hash.
     fn get_code(&self, code_hash: B256) -> Result<Option<Vec<u8>>>;
 
@@ -55,18 +72,30 @@ pub trait StateStore {
     /// Store a block hash.
     fn put_block_hash(&self, block: u64, hash: B256) -> Result<()>;
 
+    /// Delete a block hash.
+    fn delete_block_hash(&self, block: u64) -> Result<()>;
+
     /// Get the current head block number.
     fn get_head(&self) -> Result<Option<u64>>;
 
     /// Set the current head block number.
     fn set_head(&self, block: u64) -> Result<()>;
 
+    /// Get the follow mode the head block was processed under.
+    fn get_head_finality(&self) -> Result<Option<FollowMode>>;
+
+    /// Set the follow mode the head block was processed under.
+    fn set_head_finality(&self, mode: FollowMode) -> Result<()>;
+
     /// Store a block delta for an address.
     fn put_delta(&self, addr: Address, block: u64, delta: &BlockDelta) -> Result<()>;
 
     /// Get a block delta for an address.
     fn get_delta(&self, addr: Address, block: u64) -> Result<Option<BlockDelta>>;
 
+    /// Delete a block delta for an address.
+    fn delete_delta(&self, addr: Address, block: u64) -> Result<()>;
+
     /// Get all deltas for an address in a block range.
     fn get_deltas_in_range(
         &self,
@@ -81,6 +110,9 @@ pub trait StateStore {
     /// Get a balance snapshot for an address at a block.
     fn get_snapshot(&self, addr: Address, block: u64) -> Result<Option<BalanceSnapshot>>;
 
+    /// Delete a balance snapshot for an address at a block.
+    fn delete_snapshot(&self, addr: Address, block: u64) -> Result<()>;
+
     /// Get the latest snapshot at or before a given block.
     fn get_latest_snapshot_at_or_before(
         &self,
@@ -102,6 +134,9 @@ pub trait StateStore {
     /// Get watch metadata for an address.
     fn get_watch_meta(&self, addr: Address) -> Result<Option<WatchMeta>>;
 
+    /// Get every address with watch metadata, watched or not.
+    fn get_watch_metas(&self) -> Result<Vec<(Address, WatchMeta)>>;
+
     /// Get deltas for an address in a block range with coverage metadata.
     fn get_deltas_in_range_with_metadata(
         &self,
@@ -140,6 +175,9 @@ pub trait StateStore {
         end_block: u64,
     ) -> Result<Vec<(u64, Erc20Delta)>>;
 
+    /// Delete an ERC20 delta for (token, owner) at a block.
+    fn delete_erc20_delta(&self, token: Address, owner: Address, block: u64) -> Result<()>;
+
     /// Store an ERC20 snapshot for (token, owner) at a block.
     fn put_erc20_snapshot(
         &self,
@@ -157,12 +195,24 @@ pub trait StateStore {
         block: u64,
     ) -> Result<Option<(u64, Erc20Snapshot)>>;
 
+    /// Delete an ERC20 snapshot for (token, owner) at a block.
+    fn delete_erc20_snapshot(&self, token: Address, owner: Address, block: u64) -> Result<()>;
+
     /// Store current ERC20 balance (for internal tracking).
     fn put_erc20_balance(&self, token: Address, owner: Address, balance: U256) -> Result<()>;
 
     /// Get current ERC20 balance.
     fn get_erc20_balance(&self, token: Address, owner: Address) -> Result<Option<U256>>;
 
+    /// Delete current ERC20 balance.
+    fn delete_erc20_balance(&self, token: Address, owner: Address) -> Result<()>;
+
+    /// Store the current share balance of (token, owner) for share-based tokens.
+    fn put_erc20_shares(&self, token: Address, owner: Address, shares: U256) -> Result<()>;
+
+    /// Get the current share balance of (token, owner).
+    fn get_erc20_shares(&self, token: Address, owner: Address) -> Result<Option<U256>>;
+
     /// Store token watch metadata for (token, owner).
     fn put_token_watch_meta(
         &self,
@@ -178,6 +228,18 @@ pub trait StateStore {
         owner: Address,
     ) -> Result<Option<TokenWatchMeta>>;
 
+    /// Get every (token, owner) pair with token watch metadata.
+    fn get_token_watch_metas(&self) -> Result<Vec<(Address, Address, TokenWatchMeta)>>;
+
+    /// Store name, symbol and decimals of a token.
+    fn put_token_metadata(&self, token: Address, metadata: &TokenMetadata) -> Result<()>;
+
+    /// Get the stored metadata of a token.
+    fn get_token_metadata(&self, token: Address) -> Result<Option<TokenMetadata>>;
+
+    /// Get the metadata of every token, ordered by address.
+    fn get_all_token_metadata(&self) -> Result<Vec<(Address, TokenMetadata)>>;
+
     /// Get ERC20 deltas in range with coverage metadata.
     fn get_erc20_deltas_in_range_with_metadata(
         &self,
@@ -195,12 +257,307 @@ pub trait StateStore {
         requested_start: u64,
         requested_end: u64,
     ) -> Result<QueryResult<U256>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC1155 token tracking
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Store an ERC1155 delta for (contract, id, owner) at a block.
+    fn put_erc1155_delta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        block: u64,
+        delta: &Erc1155Delta,
+    ) -> Result<()>;
+
+    /// Get ERC1155 deltas for (contract, id, owner) in a block range.
+    fn get_erc1155_deltas_in_range(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<(u64, Erc1155Delta)>>;
+
+    /// Store an ERC1155 snapshot for (contract, id, owner) at a block.
+    fn put_erc1155_snapshot(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        block: u64,
+        balance: Erc1155Snapshot,
+    ) -> Result<()>;
+
+    /// Get the latest ERC1155 snapshot at or before a block.
+    fn get_latest_erc1155_snapshot_at_or_before(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        block: u64,
+    ) -> Result<Option<(u64, Erc1155Snapshot)>>;
+
+    /// Store current ERC1155 balance (for internal tracking).
+    fn put_erc1155_balance(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        balance: U256,
+    ) -> Result<()>;
+
+    /// Get current ERC1155 balance.
+    fn get_erc1155_balance(&self, contract: Address, id: U256, owner: Address)
+        -> Result<Option<U256>>;
+
+    /// Store watch metadata for (contract, id, owner).
+    fn put_erc1155_watch_meta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        meta: &TokenWatchMeta,
+    ) -> Result<()>;
+
+    /// Get watch metadata for (contract, id, owner).
+    fn get_erc1155_watch_meta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+    ) -> Result<Option<TokenWatchMeta>>;
+
+    /// Get ERC1155 deltas in range with coverage metadata.
+    fn get_erc1155_deltas_in_range_with_metadata(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<Erc1155Delta>>;
+
+    /// Get ERC1155 balances in range with coverage metadata (fill-forward).
+    fn get_erc1155_balances_in_range_with_metadata(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<U256>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Reorg undo information
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Store undo information for a processed block.
+    fn put_block_undo(&self, block: u64, undo: &BlockUndo) -> Result<()>;
+
+    /// Get undo information for a processed block.
+    fn get_block_undo(&self, block: u64) -> Result<Option<BlockUndo>>;
+
+    /// Delete undo information for a block.
+    fn delete_block_undo(&self, block: u64) -> Result<()>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Reconciliation drift records
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Store a drift record.
+    fn put_drift_record(&self, record: &DriftRecord) -> Result<()>;
+
+    /// Get all drift records in [start_block, end_block] (inclusive), in block order.
+    fn get_drift_records_in_range(
+        &self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<DriftRecord>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Per-transaction history
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Store a transaction record for an address.
+    ///
+    /// `seq` distinguishes several records of the address in the same transaction.
+    fn put_tx_record(&self, addr: Address, seq: u16, record: &TxRecord) -> Result<()>;
+
+    /// Get an address's transaction records in [start_block, end_block] (inclusive),
+    /// ordered by block, transaction index and sequence.
+    fn get_tx_records_in_range(
+        &self,
+        addr: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<TxRecord>>;
+
+    /// Get an address's transaction records in a block range with coverage metadata.
+    fn get_tx_records_in_range_with_metadata(
+        &self,
+        addr: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<TxRecord>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Decoded events
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Store a decoded event.
+    fn put_event_record(&self, record: &EventRecord) -> Result<()>;
+
+    /// Get all decoded events in [start_block, end_block] (inclusive), in chain order.
+    fn get_event_records_in_range(
+        &self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<EventRecord>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC721 ownership
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Record that `owner` currently holds an NFT.
+    fn put_nft_holding(&self, owner: Address, holding: &NftHolding) -> Result<()>;
+
+    /// Remove an NFT from `owner`'s current holdings.
+    fn delete_nft_holding(&self, owner: Address, contract: Address, token_id: U256) -> Result<()>;
+
+    /// Get the NFTs `owner` currently holds, ordered by contract and token ID.
+    fn get_nft_holdings(&self, owner: Address) -> Result<Vec<NftHolding>>;
+
+    /// Store an NFT transfer involving `owner`.
+    fn put_nft_transfer(&self, owner: Address, transfer: &NftTransfer) -> Result<()>;
+
+    /// Get `owner`'s NFT transfers in [start_block, end_block] (inclusive), in chain order.
+    fn get_nft_transfers_in_range(
+        &self,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<NftTransfer>>;
+
+    /// Get the NFTs `owner` held at the end of `block`, replayed from its transfer history.
+    ///
+    /// Only transfers observed since the owner's watch start are known, so
+    /// tokens received earlier are missing. Returns None if `block` is not
+    /// covered or a coverage gap precedes it: tokens may have moved unobserved.
+    fn get_nft_holdings_at(&self, owner: Address, block: u64) -> Result<Option<Vec<NftHolding>>> {
+        let Some(meta) = self.get_watch_meta(owner)? else {
+            return Ok(None);
+        };
+        if !meta.covers(block) || !meta.gaps_in(meta.start_block, block).is_empty() {
+            return Ok(None);
+        }
+        let mut holdings = std::collections::BTreeMap::new();
+        for transfer in self.get_nft_transfers_in_range(owner, meta.start_block, block)? {
+            let id = (transfer.contract, transfer.token_id);
+            match transfer.direction {
+                TxDirection::In => {
+                    holdings.insert(id, transfer.block);
+                }
+                TxDirection::Out => {
+                    holdings.remove(&id);
+                }
+                TxDirection::SelfTransfer => {}
+            }
+        }
+        Ok(Some(
+            holdings
+                .into_iter()
+                .map(|((contract, token_id), since_block)| NftHolding {
+                    contract,
+                    token_id,
+                    since_block,
+                })
+                .collect(),
+        ))
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ETH ⇄ token conversions
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Store a wrap or unwrap by `owner`.
+    fn put_conversion(&self, owner: Address, conversion: &Conversion) -> Result<()>;
+
+    /// Get `owner`'s conversions in [start_block, end_block] (inclusive), in chain order.
+    fn get_conversions_in_range(
+        &self,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<Conversion>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Token approvals
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Set `owner`'s current allowance for (token, spender).
+    fn put_allowance(&self, owner: Address, allowance: &Allowance) -> Result<()>;
+
+    /// Remove `owner`'s current allowance for (token, spender).
+    fn delete_allowance(&self, owner: Address, token: Address, spender: Address) -> Result<()>;
+
+    /// Get `owner`'s current (non-revoked) allowances, ordered by token and spender.
+    fn get_allowances(&self, owner: Address) -> Result<Vec<Allowance>>;
+
+    /// Store an approval (or revocation) by `owner` in its history.
+    fn put_allowance_change(&self, owner: Address, allowance: &Allowance) -> Result<()>;
+
+    /// Get `owner`'s approvals in [start_block, end_block] (inclusive), in chain order.
+    fn get_allowance_history_in_range(
+        &self,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<Allowance>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Persisted watchlist
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Add an address to the persisted watchlist.
+    fn put_watched_address(&self, addr: Address) -> Result<()>;
+
+    /// Remove an address from the persisted watchlist.
+    fn delete_watched_address(&self, addr: Address) -> Result<()>;
+
+    /// Get the persisted watched addresses, in address order.
+    fn get_watched_addresses(&self) -> Result<Vec<Address>>;
+
+    /// Add an ERC20 token (or change its strategy) in the persisted watchlist.
+    fn put_watched_token(&self, token: Address, strategy: TokenStrategy) -> Result<()>;
+
+    /// Remove an ERC20 token from the persisted watchlist.
+    fn delete_watched_token(&self, token: Address) -> Result<()>;
+
+    /// Get the persisted watched tokens with their strategies, in address order.
+    fn get_watched_tokens(&self) -> Result<Vec<(Address, TokenStrategy)>>;
+
+    // ─────────────────────────────────────────────────────────────────
+    // Raw access and atomic batches
+    // ─────────────────────────────────────────────────────────────────
+
+    /// Read the raw bytes stored under `key` in column family `cf`.
+    fn get_raw(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Apply a list of raw writes atomically: either all are persisted or none are.
+    ///
+    /// Later writes to the same key take precedence over earlier ones.
+    fn write_batch(&self, writes: &[RawWrite]) -> Result<()>;
 }
 
 /// Query result with coverage metadata.
 ///
 /// Contains information about how the requested range was clamped
-/// to respect coverage boundaries (watch_start_block and head_block).
+/// to respect coverage boundaries (watch_start_block, watch_end_block and
+/// head_block).
 #[derive(Debug, Clone)]
 pub struct QueryResult<T> {
     /// The originally requested start block.
@@ -213,8 +570,14 @@ pub struct QueryResult<T> {
     pub effective_end: u64,
     /// The block at which we started watching this address.
     pub watch_start_block: u64,
+    /// The last covered block if the address is no longer watched.
+    pub watch_end_block: Option<u64>,
+    /// Ranges [start, end] within the effective range in which the address was not watched.
+    pub coverage_gaps: Vec<(u64, u64)>,
     /// The current head block (if available).
     pub head_block: Option<u64>,
+    /// The follow mode the head block was processed under (if recorded).
+    pub head_finality: Option<FollowMode>,
     /// Optional message explaining any clamping that occurred.
     pub message: Option<String>,
     /// The actual query results (block, value) pairs.
@@ -230,6 +593,12 @@ pub struct QueryResult<T> {
 /// - headers: block headers
 /// - block_hashes: block hashes
 /// - meta: metadata (head block, etc.)
+/// - block_undo: per-block undo information for reorg rollback
+/// - drift: reconciliation drift records
+/// - tx_history: per-transaction records of watched addresses
+/// - events: logs decoded from user-supplied event definitions
+/// - nft_holdings: current ERC721 holdings of watched addresses
+/// - nft_transfers: ERC721 transfer history of watched addresses
 pub struct RocksStateStore {
     db: DB,
 }
@@ -237,7 +606,8 @@ pub struct RocksStateStore {
 impl RocksStateStore {
     /// Open or create a RocksDB database at the given path.
     ///
-    /// Creates all required column families if they don't exist.
+    /// Creates all required column families if they don't exist, and
+    /// migrates records written by an older schema version.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
@@ -259,12 +629,207 @@ impl RocksStateStore {
             ColumnFamilyDescriptor::new("erc20_snapshots", Options::default()),
             ColumnFamilyDescriptor::new("erc20_watch_meta", Options::default()),
             ColumnFamilyDescriptor::new("erc20_balances", Options::default()),
+            ColumnFamilyDescriptor::new("token_metadata", Options::default()),
+            ColumnFamilyDescriptor::new("erc20_shares", Options::default()),
+            // ERC1155 token tracking
+            ColumnFamilyDescriptor::new("erc1155_deltas", Options::default()),
+            ColumnFamilyDescriptor::new("erc1155_snapshots", Options::default()),
+            ColumnFamilyDescriptor::new("erc1155_watch_meta", Options::default()),
+            ColumnFamilyDescriptor::new("erc1155_balances", Options::default()),
+            // Reorg handling
+            ColumnFamilyDescriptor::new("block_undo", Options::default()),
+            // Reconciliation
+            ColumnFamilyDescriptor::new("drift", Options::default()),
+            // Per-transaction history
+            ColumnFamilyDescriptor::new("tx_history", Options::default()),
+            // Decoded events
+            ColumnFamilyDescriptor::new("events", Options::default()),
+            // ERC721 ownership
+            ColumnFamilyDescriptor::new("nft_holdings", Options::default()),
+            ColumnFamilyDescriptor::new("nft_transfers", Options::default()),
+            // ETH ⇄ token conversions (WETH Deposit/Withdrawal)
+            ColumnFamilyDescriptor::new("conversions", Options::default()),
+            // Token approvals: current allowances and history
+            ColumnFamilyDescriptor::new("allowances", Options::default()),
+            ColumnFamilyDescriptor::new("allowance_history", Options::default()),
+            // Persisted watchlist (addresses and tokens)
+            ColumnFamilyDescriptor::new("watchlist", Options::default()),
         ];
 
         let db = DB::open_cf_descriptors(&opts, path, column_families)
             .context("Failed to open RocksDB database")?;
 
-        Ok(Self { db })
+        let store = Self { db };
+        store.migrate_schema()?;
+        Ok(store)
+    }
+
+    /// Bring the store's records to `SCHEMA_VERSION`.
+    ///
+    /// A store without a recorded version is new if none of the migrated
+    /// column families hold data, and version 0 otherwise. Nothing is
+    /// rewritten for a store at the current version. Records are rewritten
+    /// in batches of at most `MIGRATION_BATCH_SIZE`, each committed together
+    /// with a cursor (meta 0x04) so an interrupted migration resumes after
+    /// the last committed key; the new version is written last.
+    fn migrate_schema(&self) -> Result<()> {
+        let key = encode_meta_key(0x03); // 0x03 = schema_version
+        let version = match self.get_raw("meta", &key)? {
+            Some(bytes) => u32::from_be_bytes(
+                bytes
+                    .as_slice()
+                    .try_into()
+                    .context("Schema version must be 4 bytes (u32)")?,
+            ),
+            None => {
+                let mut has_records = false;
+                for cf in MIGRATED_CFS {
+                    let mut iter =
+                        self.db.iterator_cf(self.get_cf(cf)?, rocksdb::IteratorMode::Start);
+                    if iter.next().transpose().context("Failed to read iterator")?.is_some() {
+                        has_records = true;
+                        break;
+                    }
+                }
+                if !has_records {
+                    return self.write_batch(&[RawWrite {
+                        cf: "meta".to_string(),
+                        key,
+                        value: Some(SCHEMA_VERSION.to_be_bytes().to_vec()),
+                    }]);
+                }
+                0
+            }
+        };
+        if version > SCHEMA_VERSION {
+            anyhow::bail!(
+                "Database schema version {} is newer than this build supports ({})",
+                version,
+                SCHEMA_VERSION
+            );
+        }
+        if version == SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        let cursor_key = encode_meta_key(0x04); // 0x04 = migration cursor
+        let cursor: Option<MigrationCursor> = match self.get_raw("meta", &cursor_key)? {
+            Some(bytes) => {
+                Some(postcard::from_bytes(&bytes).context("Failed to decode migration cursor")?)
+            }
+            None => None,
+        };
+        info!(
+            "Migrating database schema from version {} to {}{}",
+            version,
+            SCHEMA_VERSION,
+            if cursor.is_some() { " (resuming)" } else { "" }
+        );
+
+        // Column families before the cursor's are done; the cursor's resumes after its key
+        let first = match &cursor {
+            Some(cursor) => MIGRATED_CFS
+                .iter()
+                .position(|cf| *cf == cursor.cf)
+                .with_context(|| format!("Unknown migration cursor column '{}'", cursor.cf))?,
+            None => 0,
+        };
+        let mut migrated = 0usize;
+        for (i, cf) in MIGRATED_CFS.iter().enumerate().skip(first) {
+            let resume_after = match &cursor {
+                Some(cursor) if i == first => Some(cursor.key.as_slice()),
+                _ => None,
+            };
+            let mode = match resume_after {
+                Some(key) => rocksdb::IteratorMode::From(key, rocksdb::Direction::Forward),
+                None => rocksdb::IteratorMode::Start,
+            };
+            let mut writes = Vec::new();
+            for item in self.db.iterator_cf(self.get_cf(cf)?, mode) {
+                let (key, value) = item.context("Failed to read iterator")?;
+                if resume_after == Some(&*key) {
+                    continue;
+                }
+                if let Some(upgraded) = upgrade_v0_value(cf, &value)? {
+                    writes.push(RawWrite {
+                        cf: cf.to_string(),
+                        key: key.to_vec(),
+                        value: Some(upgraded),
+                    });
+                }
+                if writes.len() >= MIGRATION_BATCH_SIZE {
+                    migrated += writes.len();
+                    self.commit_migration_batch(&mut writes, &cursor_key, cf, &key)?;
+                }
+            }
+            if !writes.is_empty() {
+                migrated += writes.len();
+                let last = writes.last().map(|w| w.key.clone()).unwrap_or_default();
+                self.commit_migration_batch(&mut writes, &cursor_key, cf, &last)?;
+            }
+        }
+
+        self.write_batch(&[
+            RawWrite {
+                cf: "meta".to_string(),
+                key,
+                value: Some(SCHEMA_VERSION.to_be_bytes().to_vec()),
+            },
+            RawWrite {
+                cf: "meta".to_string(),
+                key: cursor_key,
+                value: None,
+            },
+        ])
+        .context("Failed to record database schema version")?;
+        info!(
+            "Migrated {} records to schema version {}",
+            migrated, SCHEMA_VERSION
+        );
+        Ok(())
+    }
+
+    /// Commit one batch of migrated records together with the cursor after `last_key` in `cf`.
+    fn commit_migration_batch(
+        &self,
+        writes: &mut Vec<RawWrite>,
+        cursor_key: &[u8],
+        cf: &str,
+        last_key: &[u8],
+    ) -> Result<()> {
+        let cursor = MigrationCursor {
+            cf: cf.to_string(),
+            key: last_key.to_vec(),
+        };
+        writes.push(RawWrite {
+            cf: "meta".to_string(),
+            key: cursor_key.to_vec(),
+            value: Some(
+                postcard::to_allocvec(&cursor).context("Failed to encode migration cursor")?,
+            ),
+        });
+        self.write_batch(writes)
+            .context("Failed to migrate database schema")?;
+        writes.clear();
+        Ok(())
+    }
+
+    /// Scan the persisted watchlist entries under `prefix` (`Ya` or `Yt`).
+    fn scan_watchlist(&self, prefix: &[u8]) -> Result<Vec<(Address, Vec<u8>)>> {
+        let cf = self.get_cf("watchlist")?;
+        let mut entries = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(prefix, rocksdb::Direction::Forward),
+        );
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            entries.push((decode_watchlist_key(&key)?, value.to_vec()));
+        }
+        Ok(entries)
     }
 
     /// Get a column family handle by name.
@@ -273,6 +838,77 @@ impl RocksStateStore {
             .cf_handle(name)
             .with_context(|| format!("Column family '{}' not found", name))
     }
+
+    /// Coverage of a token pair of `owner` with metadata `pair` (see `TokenWatchMeta::coverage`).
+    fn token_coverage(&self, owner: Address, pair: &TokenWatchMeta) -> Result<WatchMeta> {
+        Ok(pair.coverage(self.get_watch_meta(owner)?.as_ref()))
+    }
+
+    /// Clamp a requested range to an address's coverage and describe any clamping.
+    ///
+    /// Coverage runs from `watch_start_block` to the head, or to `end_block`
+    /// once the address is unwatched, without its gaps. `what` names the data
+    /// in the message (e.g. "balance"). Returns a `QueryResult` with empty
+    /// `data` for the caller to fill in.
+    fn clamp_to_watch_coverage<T>(
+        &self,
+        watch_meta: &WatchMeta,
+        what: &str,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<T>> {
+        let head_block = self.get_head()?;
+        let head_finality = self.get_head_finality()?;
+        let effective_start = requested_start.max(watch_meta.start_block);
+        let effective_end = [head_block, watch_meta.end_block]
+            .into_iter()
+            .flatten()
+            .fold(requested_end, u64::min);
+        let coverage_gaps = watch_meta.gaps_in(effective_start, effective_end);
+
+        let mut message_parts = Vec::new();
+        if effective_start > requested_start {
+            message_parts.push(format!(
+                "Earliest known {} starts at block {}.",
+                what, watch_meta.start_block
+            ));
+        }
+        for (gap_start, gap_end) in &coverage_gaps {
+            message_parts.push(format!(
+                "Address was not watched in blocks {}-{}.",
+                gap_start, gap_end
+            ));
+        }
+        if effective_end < requested_end {
+            if watch_meta.end_block == Some(effective_end) {
+                message_parts.push(format!(
+                    "Address was unwatched after block {}.",
+                    effective_end
+                ));
+            } else {
+                message_parts.push(format!("Latest available block is {}.", effective_end));
+            }
+        }
+        let message = if message_parts.is_empty() {
+            None
+        } else {
+            Some(message_parts.join(" "))
+        };
+
+        Ok(QueryResult {
+            requested_start,
+            requested_end,
+            effective_start,
+            effective_end,
+            watch_start_block: watch_meta.start_block,
+            watch_end_block: watch_meta.end_block,
+            coverage_gaps,
+            head_block,
+            head_finality,
+            message,
+            data: Vec::new(),
+        })
+    }
 }
 
 impl StateStore for RocksStateStore {
@@ -299,6 +935,15 @@ impl StateStore for RocksStateStore {
         Ok(())
     }
 
+    fn delete_account(&self, addr: Address) -> Result<()> {
+        let cf = self.get_cf("accounts")?;
+        let key = encode_account_key(addr);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete account")?;
+        Ok(())
+    }
+
     fn get_code(&self, code_hash: B256) -> Result<Option<Vec<u8>>> {
         let cf = self.get_cf("code")?;
         let key = encode_code_key(code_hash);
@@ -382,6 +1027,15 @@ impl StateStore for RocksStateStore {
         Ok(())
     }
 
+    fn delete_block_hash(&self, block: u64) -> Result<()> {
+        let cf = self.get_cf("block_hashes")?;
+        let key = encode_block_hash_key(block);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete block hash")?;
+        Ok(())
+    }
+
     fn get_head(&self) -> Result<Option<u64>> {
         let cf = self.get_cf("meta")?;
         let key = encode_meta_key(0x01); // 0x01 = head_block
@@ -408,14 +1062,37 @@ impl StateStore for RocksStateStore {
         Ok(())
     }
 
-    fn put_delta(&self, addr: Address, block: u64, delta: &BlockDelta) -> Result<()> {
-        let cf = self.get_cf("block_deltas")?;
-        let key = encode_delta_key(addr, block);
-        let value = postcard::to_allocvec(delta).context("Failed to serialize delta")?;
-        self.db
-            .put_cf(cf, &key, &value)
-            .context("Failed to put delta")?;
-        Ok(())
+    fn get_head_finality(&self) -> Result<Option<FollowMode>> {
+        let cf = self.get_cf("meta")?;
+        let key = encode_meta_key(0x02); // 0x02 = head_finality
+        match self.db.get_cf(cf, &key).context("Failed to get head finality")? {
+            Some(bytes) => {
+                let mode = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize head finality")?;
+                Ok(Some(mode))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set_head_finality(&self, mode: FollowMode) -> Result<()> {
+        let cf = self.get_cf("meta")?;
+        let key = encode_meta_key(0x02); // 0x02 = head_finality
+        let value = postcard::to_allocvec(&mode).context("Failed to serialize head finality")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to set head finality")?;
+        Ok(())
+    }
+
+    fn put_delta(&self, addr: Address, block: u64, delta: &BlockDelta) -> Result<()> {
+        let cf = self.get_cf("block_deltas")?;
+        let key = encode_delta_key(addr, block);
+        let value = postcard::to_allocvec(delta).context("Failed to serialize delta")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put delta")?;
+        Ok(())
     }
 
     fn get_delta(&self, addr: Address, block: u64) -> Result<Option<BlockDelta>> {
@@ -431,6 +1108,15 @@ impl StateStore for RocksStateStore {
         }
     }
 
+    fn delete_delta(&self, addr: Address, block: u64) -> Result<()> {
+        let cf = self.get_cf("block_deltas")?;
+        let key = encode_delta_key(addr, block);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete delta")?;
+        Ok(())
+    }
+
     fn get_deltas_in_range(
         &self,
         addr: Address,
@@ -494,6 +1180,15 @@ impl StateStore for RocksStateStore {
         }
     }
 
+    fn delete_snapshot(&self, addr: Address, block: u64) -> Result<()> {
+        let cf = self.get_cf("balance_snapshots")?;
+        let key = encode_snapshot_key(addr, block);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete snapshot")?;
+        Ok(())
+    }
+
     fn get_latest_snapshot_at_or_before(
         &self,
         addr: Address,
@@ -556,112 +1251,74 @@ impl StateStore for RocksStateStore {
             .get_watch_meta(addr)?
             .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
 
-        // Get head block
-        let head_block = self.get_head()?;
-
-        // Clamp start and end blocks
-        let effective_start = requested_start.max(watch_meta.start_block);
-        let effective_end = if let Some(head) = head_block {
-            requested_end.min(head)
-        } else {
-            requested_end
-        };
-
-        // Build message if clamping occurred
-        let mut message_parts = Vec::new();
-        if effective_start > requested_start {
-            message_parts.push(format!(
-                "Earliest known balance starts at block {}.",
-                watch_meta.start_block
-            ));
-        }
-        if let Some(head) = head_block {
-            if effective_end < requested_end {
-                message_parts.push(format!("Latest available block is {}.", head));
-            }
-        }
-        let message = if message_parts.is_empty() {
-            None
-        } else {
-            Some(message_parts.join(" "))
-        };
-
-        // If effective range is invalid, return empty results
-        if effective_start > effective_end {
-            return Ok(QueryResult {
-                requested_start,
-                requested_end,
-                effective_start,
-                effective_end,
-                watch_start_block: watch_meta.start_block,
-                head_block,
-                message,
-                data: Vec::new(),
-            });
-        }
-
-        // Find the anchor snapshot
-        // We require a snapshot at watch_start_block or later, not before
-        let anchor = self
-            .get_latest_snapshot_at_or_before(addr, effective_start)
-            .context("Failed to get anchor snapshot")?;
-
-        let mut balance = match anchor {
-            Some((snapshot_block, bal)) => {
-                // Reject snapshots from before watch_start_block
-                if snapshot_block < watch_meta.start_block {
+        // Clamp start and end blocks to coverage
+        let mut result =
+            self.clamp_to_watch_coverage(&watch_meta, "balance", requested_start, requested_end)?;
+
+        // Each coverage segment is anchored at its own snapshot; gap blocks are skipped
+        for (segment_start, segment_end) in
+            watch_meta.segments_in(result.effective_start, result.effective_end)
+        {
+            let coverage_start = watch_meta
+                .segments_in(watch_meta.start_block, segment_start)
+                .last()
+                .map_or(watch_meta.start_block, |&(start, _)| start);
+
+            // Find the anchor snapshot
+            // We require a snapshot at the segment's watch start or later, not before
+            let anchor = self
+                .get_latest_snapshot_at_or_before(addr, segment_start)
+                .context("Failed to get anchor snapshot")?;
+
+            let (anchor_block, mut balance) = match anchor {
+                Some((snapshot_block, bal)) => {
+                    // Reject snapshots from before the watch start
+                    if snapshot_block < coverage_start {
+                        anyhow::bail!(
+                            "No snapshot found at or after watch_start_block {} for address {:?}. \
+                            Found snapshot at block {} which is before coverage started. \
+                            Please reinitialize/backfill snapshots.",
+                            coverage_start, addr, snapshot_block
+                        );
+                    }
+                    (snapshot_block, bal)
+                }
+                None => {
+                    // No snapshot found - we need a snapshot at the watch start
                     anyhow::bail!(
-                        "No snapshot found at or after watch_start_block {} for address {:?}. \
-                        Found snapshot at block {} which is before coverage started. \
+                        "No snapshot found at watch_start_block {} for address {:?}. \
                         Please reinitialize/backfill snapshots.",
-                        watch_meta.start_block, addr, snapshot_block
+                        coverage_start, addr
                     );
                 }
-                bal
-            }
-            None => {
-                // No snapshot found - we need a snapshot at watch_start_block
-                anyhow::bail!(
-                    "No snapshot found at watch_start_block {} for address {:?}. \
-                    Please reinitialize/backfill snapshots.",
-                    watch_meta.start_block, addr
-                );
-            }
-        };
+            };
+
+            // Get all deltas in the segment
+            let deltas = self
+                .get_deltas_in_range(addr, segment_start, segment_end)
+                .context("Failed to get deltas in range")?;
+
+            // Create a map of block -> delta for efficient lookup
+            let delta_map: std::collections::HashMap<u64, BlockDelta> =
+                deltas.into_iter().collect();
+
+            // Build the result by iterating through each block in the segment
+            for block in segment_start..=segment_end {
+                // Apply delta if it exists for this block. The anchor snapshot is the
+                // balance after its block, so that block's delta is already included.
+                if let Some(delta) = delta_map.get(&block).filter(|_| block > anchor_block) {
+                    // Apply the delta: balance = balance + delta_plus - delta_minus
+                    balance = balance
+                        .saturating_add(delta.delta_plus)
+                        .saturating_sub(delta.delta_minus);
+                }
+                // If no delta, balance stays the same (fill-forward)
 
-        // Get all deltas in the effective range
-        let deltas = self
-            .get_deltas_in_range(addr, effective_start, effective_end)
-            .context("Failed to get deltas in range")?;
-
-        // Create a map of block -> delta for efficient lookup
-        let delta_map: std::collections::HashMap<u64, BlockDelta> = deltas.into_iter().collect();
-
-        // Build the result by iterating through each block in the effective range
-        let mut results = Vec::new();
-        for block in effective_start..=effective_end {
-            // Apply delta if it exists for this block
-            if let Some(delta) = delta_map.get(&block) {
-                // Apply the delta: balance = balance + delta_plus - delta_minus
-                balance = balance
-                    .saturating_add(delta.delta_plus)
-                    .saturating_sub(delta.delta_minus);
+                result.data.push((block, balance));
             }
-            // If no delta, balance stays the same (fill-forward)
-
-            results.push((block, balance));
         }
 
-        Ok(QueryResult {
-            requested_start,
-            requested_end,
-            effective_start,
-            effective_end,
-            watch_start_block: watch_meta.start_block,
-            head_block,
-            message,
-            data: results,
-        })
+        Ok(result)
     }
 
     fn put_watch_meta(&self, addr: Address, meta: &WatchMeta) -> Result<()> {
@@ -687,6 +1344,19 @@ impl StateStore for RocksStateStore {
         }
     }
 
+    fn get_watch_metas(&self) -> Result<Vec<(Address, WatchMeta)>> {
+        let cf = self.get_cf("watch_meta")?;
+        let mut metas = Vec::new();
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, value) = item.context("Failed to read iterator")?;
+            let addr = decode_watch_meta_key(&key)?;
+            let meta: WatchMeta =
+                postcard::from_bytes(&value).context("Failed to deserialize watch meta")?;
+            metas.push((addr, meta));
+        }
+        Ok(metas)
+    }
+
     fn get_deltas_in_range_with_metadata(
         &self,
         addr: Address,
@@ -698,48 +1368,14 @@ impl StateStore for RocksStateStore {
             .get_watch_meta(addr)?
             .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
 
-        // Get head block
-        let head_block = self.get_head()?;
-
-        // Clamp start and end blocks
-        let effective_start = requested_start.max(watch_meta.start_block);
-        let effective_end = if let Some(head) = head_block {
-            requested_end.min(head)
-        } else {
-            requested_end
-        };
-
-        // Build message if clamping occurred
-        let mut message_parts = Vec::new();
-        if effective_start > requested_start {
-            message_parts.push(format!(
-                "Earliest known balance starts at block {}.",
-                watch_meta.start_block
-            ));
-        }
-        if let Some(head) = head_block {
-            if effective_end < requested_end {
-                message_parts.push(format!("Latest available block is {}.", head));
-            }
-        }
-        let message = if message_parts.is_empty() {
-            None
-        } else {
-            Some(message_parts.join(" "))
-        };
+        // Clamp start and end blocks to coverage
+        let mut result =
+            self.clamp_to_watch_coverage(&watch_meta, "balance", requested_start, requested_end)?;
+        let (effective_start, effective_end) = (result.effective_start, result.effective_end);
 
         // If effective range is invalid, return empty results
         if effective_start > effective_end {
-            return Ok(QueryResult {
-                requested_start,
-                requested_end,
-                effective_start,
-                effective_end,
-                watch_start_block: watch_meta.start_block,
-                head_block,
-                message,
-                data: Vec::new(),
-            });
+            return Ok(result);
         }
 
         // Get deltas in the effective range
@@ -762,8 +1398,8 @@ impl StateStore for RocksStateStore {
             let (key_addr, block) = decode_delta_key(&key)
                 .context("Failed to decode delta key")?;
 
-            // Only include deltas for this address (safety check)
-            if key_addr != addr {
+            // Only include deltas for this address in covered blocks (safety check)
+            if key_addr != addr || !watch_meta.covers(block) {
                 continue;
             }
 
@@ -774,16 +1410,8 @@ impl StateStore for RocksStateStore {
             deltas.push((block, delta));
         }
 
-        Ok(QueryResult {
-            requested_start,
-            requested_end,
-            effective_start,
-            effective_end,
-            watch_start_block: watch_meta.start_block,
-            head_block,
-            message,
-            data: deltas,
-        })
+        result.data = deltas;
+        Ok(result)
     }
 
     // ─────────────────────────────────────────────────────────────────
@@ -840,6 +1468,15 @@ impl StateStore for RocksStateStore {
         Ok(deltas)
     }
 
+    fn delete_erc20_delta(&self, token: Address, owner: Address, block: u64) -> Result<()> {
+        let cf = self.get_cf("erc20_deltas")?;
+        let key = encode_erc20_delta_key(token, owner, block);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete ERC20 delta")?;
+        Ok(())
+    }
+
     fn put_erc20_snapshot(
         &self,
         token: Address,
@@ -882,240 +1519,1020 @@ impl StateStore for RocksStateStore {
                 return Ok(Some((key_block, balance)));
             }
         }
-        Ok(None)
+        Ok(None)
+    }
+
+    fn delete_erc20_snapshot(&self, token: Address, owner: Address, block: u64) -> Result<()> {
+        let cf = self.get_cf("erc20_snapshots")?;
+        let key = encode_erc20_snapshot_key(token, owner, block);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete ERC20 snapshot")?;
+        Ok(())
+    }
+
+    fn put_erc20_balance(&self, token: Address, owner: Address, balance: U256) -> Result<()> {
+        let cf = self.get_cf("erc20_balances")?;
+        let key = encode_token_watch_meta_key(token, owner); // Reuse same key layout: token+owner
+        let value = encode_u256(balance);
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put ERC20 balance")?;
+        Ok(())
+    }
+
+    fn get_erc20_balance(&self, token: Address, owner: Address) -> Result<Option<U256>> {
+        let cf = self.get_cf("erc20_balances")?;
+        let key = encode_token_watch_meta_key(token, owner);
+        match self.db.get_cf(cf, &key).context("Failed to get ERC20 balance")? {
+            Some(bytes) => {
+                let balance = decode_u256(&bytes).context("Failed to decode ERC20 balance")?;
+                Ok(Some(balance))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn delete_erc20_balance(&self, token: Address, owner: Address) -> Result<()> {
+        let cf = self.get_cf("erc20_balances")?;
+        let key = encode_token_watch_meta_key(token, owner);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete ERC20 balance")?;
+        Ok(())
+    }
+
+    fn put_erc20_shares(&self, token: Address, owner: Address, shares: U256) -> Result<()> {
+        let cf = self.get_cf("erc20_shares")?;
+        let key = encode_token_watch_meta_key(token, owner);
+        let value = encode_u256(shares);
+        self.db
+            .put_cf(cf, &key, value)
+            .context("Failed to put ERC20 shares")?;
+        Ok(())
+    }
+
+    fn get_erc20_shares(&self, token: Address, owner: Address) -> Result<Option<U256>> {
+        let cf = self.get_cf("erc20_shares")?;
+        let key = encode_token_watch_meta_key(token, owner);
+        match self.db.get_cf(cf, &key).context("Failed to get ERC20 shares")? {
+            Some(bytes) => {
+                let shares = decode_u256(&bytes).context("Failed to decode ERC20 shares")?;
+                Ok(Some(shares))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put_token_watch_meta(
+        &self,
+        token: Address,
+        owner: Address,
+        meta: &TokenWatchMeta,
+    ) -> Result<()> {
+        let cf = self.get_cf("erc20_watch_meta")?;
+        let key = encode_token_watch_meta_key(token, owner);
+        let value =
+            postcard::to_allocvec(meta).context("Failed to serialize token watch meta")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put token watch meta")?;
+        Ok(())
+    }
+
+    fn get_token_watch_meta(
+        &self,
+        token: Address,
+        owner: Address,
+    ) -> Result<Option<TokenWatchMeta>> {
+        let cf = self.get_cf("erc20_watch_meta")?;
+        let key = encode_token_watch_meta_key(token, owner);
+        match self.db.get_cf(cf, &key).context("Failed to get token watch meta")? {
+            Some(bytes) => {
+                let meta =
+                    postcard::from_bytes(&bytes).context("Failed to deserialize token watch meta")?;
+                Ok(Some(meta))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_token_watch_metas(&self) -> Result<Vec<(Address, Address, TokenWatchMeta)>> {
+        let cf = self.get_cf("erc20_watch_meta")?;
+        let mut metas = Vec::new();
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, value) = item.context("Failed to read iterator")?;
+            let (token, owner) = decode_token_watch_meta_key(&key)?;
+            let meta: TokenWatchMeta =
+                postcard::from_bytes(&value).context("Failed to deserialize token watch meta")?;
+            metas.push((token, owner, meta));
+        }
+        Ok(metas)
+    }
+
+    fn put_token_metadata(&self, token: Address, metadata: &TokenMetadata) -> Result<()> {
+        let cf = self.get_cf("token_metadata")?;
+        let key = encode_token_metadata_key(token);
+        let value =
+            postcard::to_allocvec(metadata).context("Failed to serialize token metadata")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put token metadata")?;
+        Ok(())
+    }
+
+    fn get_token_metadata(&self, token: Address) -> Result<Option<TokenMetadata>> {
+        let cf = self.get_cf("token_metadata")?;
+        let key = encode_token_metadata_key(token);
+        match self.db.get_cf(cf, &key).context("Failed to get token metadata")? {
+            Some(bytes) => {
+                let metadata = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize token metadata")?;
+                Ok(Some(metadata))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_all_token_metadata(&self) -> Result<Vec<(Address, TokenMetadata)>> {
+        let cf = self.get_cf("token_metadata")?;
+        let mut tokens = Vec::new();
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, value) = item.context("Failed to read iterator")?;
+            let token = decode_token_metadata_key(&key)?;
+            let metadata: TokenMetadata =
+                postcard::from_bytes(&value).context("Failed to deserialize token metadata")?;
+            tokens.push((token, metadata));
+        }
+        Ok(tokens)
+    }
+
+    fn get_erc20_deltas_in_range_with_metadata(
+        &self,
+        token: Address,
+        owner: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<Erc20Delta>> {
+        let watch_meta = self
+            .get_token_watch_meta(token, owner)?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Token {:?} for owner {:?} is not being tracked",
+                    token,
+                    owner
+                )
+            })?;
+
+        let coverage = self.token_coverage(owner, &watch_meta)?;
+        let mut result = self.clamp_to_watch_coverage(
+            &coverage,
+            "token balance",
+            requested_start,
+            requested_end,
+        )?;
+        if result.effective_start > result.effective_end {
+            return Ok(result);
+        }
+
+        // Only include deltas from the owner's coverage segments
+        result.data = self
+            .get_erc20_deltas_in_range(token, owner, result.effective_start, result.effective_end)?
+            .into_iter()
+            .filter(|(block, _)| coverage.covers(*block))
+            .collect();
+        Ok(result)
+    }
+
+    fn get_erc20_balances_in_range_with_metadata(
+        &self,
+        token: Address,
+        owner: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<U256>> {
+        let watch_meta = self
+            .get_token_watch_meta(token, owner)?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Token {:?} for owner {:?} is not being tracked",
+                    token,
+                    owner
+                )
+            })?;
+
+        let coverage = self.token_coverage(owner, &watch_meta)?;
+        let mut result = self.clamp_to_watch_coverage(
+            &coverage,
+            "token balance",
+            requested_start,
+            requested_end,
+        )?;
+
+        // Each coverage segment is anchored at its own snapshot; gap blocks are skipped
+        for (segment_start, segment_end) in
+            coverage.segments_in(result.effective_start, result.effective_end)
+        {
+            let coverage_start = coverage
+                .segments_in(coverage.start_block, segment_start)
+                .last()
+                .map_or(coverage.start_block, |&(start, _)| start);
+
+            let anchor = self
+                .get_latest_erc20_snapshot_at_or_before(token, owner, segment_start)
+                .context("Failed to get anchor ERC20 snapshot")?;
+
+            let (anchor_block, mut balance) = match anchor {
+                Some((snapshot_block, bal)) => {
+                    if snapshot_block < coverage_start {
+                        anyhow::bail!(
+                            "No snapshot found at or after watch_start_block {} for token {:?} owner {:?}. \
+                            Found snapshot at block {} which is before coverage started. \
+                            Please reinitialize/backfill snapshots.",
+                            coverage_start, token, owner, snapshot_block
+                        );
+                    }
+                    (snapshot_block, bal)
+                }
+                None => {
+                    anyhow::bail!(
+                        "No snapshot found at watch_start_block {} for token {:?} owner {:?}. \
+                        Please reinitialize/backfill snapshots.",
+                        coverage_start, token, owner
+                    );
+                }
+            };
+
+            let deltas =
+                self.get_erc20_deltas_in_range(token, owner, segment_start, segment_end)?;
+            let delta_map: std::collections::HashMap<u64, Erc20Delta> =
+                deltas.into_iter().collect();
+
+            for block in segment_start..=segment_end {
+                // The anchor snapshot already includes its own block's delta
+                if let Some(delta) = delta_map.get(&block).filter(|_| block > anchor_block) {
+                    balance = balance
+                        .saturating_add(delta.delta_plus)
+                        .saturating_sub(delta.delta_minus);
+                }
+                result.data.push((block, balance));
+            }
+        }
+
+        Ok(result)
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC1155 implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_erc1155_delta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        block: u64,
+        delta: &Erc1155Delta,
+    ) -> Result<()> {
+        let cf = self.get_cf("erc1155_deltas")?;
+        let key = encode_erc1155_delta_key(contract, id, owner, block);
+        let value = postcard::to_allocvec(delta).context("Failed to serialize ERC1155 delta")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put ERC1155 delta")?;
+        Ok(())
+    }
+
+    fn get_erc1155_deltas_in_range(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<(u64, Erc1155Delta)>> {
+        let cf = self.get_cf("erc1155_deltas")?;
+        let start_key = encode_erc1155_delta_key(contract, id, owner, start_block);
+        let end_key = encode_erc1155_delta_key(contract, id, owner, end_block.saturating_add(1));
+
+        let mut deltas = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
+            }
+            let (_, _, _, block) =
+                decode_erc1155_delta_key(&key).context("Failed to decode ERC1155 delta key")?;
+            let delta: Erc1155Delta =
+                postcard::from_bytes(&value).context("Failed to deserialize ERC1155 delta")?;
+            deltas.push((block, delta));
+        }
+        Ok(deltas)
+    }
+
+    fn put_erc1155_snapshot(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        block: u64,
+        balance: Erc1155Snapshot,
+    ) -> Result<()> {
+        let cf = self.get_cf("erc1155_snapshots")?;
+        let key = encode_erc1155_snapshot_key(contract, id, owner, block);
+        let value = encode_u256(balance);
+        self.db
+            .put_cf(cf, &key, value)
+            .context("Failed to put ERC1155 snapshot")?;
+        Ok(())
+    }
+
+    fn get_latest_erc1155_snapshot_at_or_before(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        block: u64,
+    ) -> Result<Option<(u64, Erc1155Snapshot)>> {
+        let cf = self.get_cf("erc1155_snapshots")?;
+        let search_key = encode_erc1155_snapshot_key(contract, id, owner, block);
+        let mut iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&search_key, rocksdb::Direction::Reverse),
+        );
+
+        // Keys of one (contract, id, owner) are contiguous: the first key at or
+        // before the search key either belongs to it or there is no snapshot
+        if let Some(item) = iter.next() {
+            let (key, value) = item.context("Failed to read iterator")?;
+            let (k_contract, k_id, k_owner, key_block) = decode_erc1155_snapshot_key(&key)
+                .context("Failed to decode ERC1155 snapshot key")?;
+            if (k_contract, k_id, k_owner) == (contract, id, owner) {
+                let balance = decode_u256(&value).context("Failed to decode ERC1155 snapshot")?;
+                return Ok(Some((key_block, balance)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn put_erc1155_balance(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        balance: U256,
+    ) -> Result<()> {
+        let cf = self.get_cf("erc1155_balances")?;
+        let key = encode_erc1155_watch_meta_key(contract, id, owner); // Same layout as watch meta
+        let value = encode_u256(balance);
+        self.db
+            .put_cf(cf, &key, value)
+            .context("Failed to put ERC1155 balance")?;
+        Ok(())
+    }
+
+    fn get_erc1155_balance(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+    ) -> Result<Option<U256>> {
+        let cf = self.get_cf("erc1155_balances")?;
+        let key = encode_erc1155_watch_meta_key(contract, id, owner);
+        match self.db.get_cf(cf, &key).context("Failed to get ERC1155 balance")? {
+            Some(bytes) => {
+                let balance = decode_u256(&bytes).context("Failed to decode ERC1155 balance")?;
+                Ok(Some(balance))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put_erc1155_watch_meta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        meta: &TokenWatchMeta,
+    ) -> Result<()> {
+        let cf = self.get_cf("erc1155_watch_meta")?;
+        let key = encode_erc1155_watch_meta_key(contract, id, owner);
+        let value =
+            postcard::to_allocvec(meta).context("Failed to serialize ERC1155 watch meta")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put ERC1155 watch meta")?;
+        Ok(())
+    }
+
+    fn get_erc1155_watch_meta(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+    ) -> Result<Option<TokenWatchMeta>> {
+        let cf = self.get_cf("erc1155_watch_meta")?;
+        let key = encode_erc1155_watch_meta_key(contract, id, owner);
+        match self.db.get_cf(cf, &key).context("Failed to get ERC1155 watch meta")? {
+            Some(bytes) => {
+                let meta = postcard::from_bytes(&bytes)
+                    .context("Failed to deserialize ERC1155 watch meta")?;
+                Ok(Some(meta))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_erc1155_deltas_in_range_with_metadata(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<Erc1155Delta>> {
+        let watch_meta = self
+            .get_erc1155_watch_meta(contract, id, owner)?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "ERC1155 {:?} id {} for owner {:?} is not being tracked",
+                    contract,
+                    id,
+                    owner
+                )
+            })?;
+
+        let coverage = self.token_coverage(owner, &watch_meta)?;
+        let mut result = self.clamp_to_watch_coverage(
+            &coverage,
+            "token balance",
+            requested_start,
+            requested_end,
+        )?;
+        if result.effective_start <= result.effective_end {
+            result.data = self
+                .get_erc1155_deltas_in_range(
+                    contract,
+                    id,
+                    owner,
+                    result.effective_start,
+                    result.effective_end,
+                )?
+                .into_iter()
+                .filter(|(block, _)| coverage.covers(*block))
+                .collect();
+        }
+        Ok(result)
+    }
+
+    fn get_erc1155_balances_in_range_with_metadata(
+        &self,
+        contract: Address,
+        id: U256,
+        owner: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<U256>> {
+        let watch_meta = self
+            .get_erc1155_watch_meta(contract, id, owner)?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "ERC1155 {:?} id {} for owner {:?} is not being tracked",
+                    contract,
+                    id,
+                    owner
+                )
+            })?;
+
+        let coverage = self.token_coverage(owner, &watch_meta)?;
+        let mut result = self.clamp_to_watch_coverage(
+            &coverage,
+            "token balance",
+            requested_start,
+            requested_end,
+        )?;
+
+        // Each coverage segment is anchored at its own snapshot; gap blocks are skipped
+        for (segment_start, segment_end) in
+            coverage.segments_in(result.effective_start, result.effective_end)
+        {
+            let coverage_start = coverage
+                .segments_in(coverage.start_block, segment_start)
+                .last()
+                .map_or(coverage.start_block, |&(start, _)| start);
+
+            let (anchor_block, mut balance) = match self
+                .get_latest_erc1155_snapshot_at_or_before(contract, id, owner, segment_start)
+                .context("Failed to get anchor ERC1155 snapshot")?
+            {
+                Some((snapshot_block, bal)) if snapshot_block >= coverage_start => {
+                    (snapshot_block, bal)
+                }
+                _ => anyhow::bail!(
+                    "No snapshot found at watch_start_block {} for ERC1155 {:?} id {} owner {:?}. \
+                    Please reinitialize snapshots.",
+                    coverage_start,
+                    contract,
+                    id,
+                    owner
+                ),
+            };
+
+            let delta_map: std::collections::HashMap<u64, Erc1155Delta> = self
+                .get_erc1155_deltas_in_range(contract, id, owner, segment_start, segment_end)?
+                .into_iter()
+                .collect();
+
+            for block in segment_start..=segment_end {
+                // The anchor snapshot already includes its own block's delta
+                if let Some(delta) = delta_map.get(&block).filter(|_| block > anchor_block) {
+                    balance = balance
+                        .saturating_add(delta.delta_plus)
+                        .saturating_sub(delta.delta_minus);
+                }
+                result.data.push((block, balance));
+            }
+        }
+        Ok(result)
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Reorg undo implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_block_undo(&self, block: u64, undo: &BlockUndo) -> Result<()> {
+        let cf = self.get_cf("block_undo")?;
+        let key = encode_block_undo_key(block);
+        let value = postcard::to_allocvec(undo).context("Failed to serialize block undo")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put block undo")?;
+        Ok(())
+    }
+
+    fn get_block_undo(&self, block: u64) -> Result<Option<BlockUndo>> {
+        let cf = self.get_cf("block_undo")?;
+        let key = encode_block_undo_key(block);
+        match self.db.get_cf(cf, &key).context("Failed to get block undo")? {
+            Some(bytes) => {
+                let undo =
+                    postcard::from_bytes(&bytes).context("Failed to deserialize block undo")?;
+                Ok(Some(undo))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn delete_block_undo(&self, block: u64) -> Result<()> {
+        let cf = self.get_cf("block_undo")?;
+        let key = encode_block_undo_key(block);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete block undo")?;
+        Ok(())
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Drift record implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_drift_record(&self, record: &DriftRecord) -> Result<()> {
+        let cf = self.get_cf("drift")?;
+        let key = encode_drift_key(record.block, record.address, record.token);
+        let value = postcard::to_allocvec(record).context("Failed to serialize drift record")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put drift record")?;
+        Ok(())
+    }
+
+    fn get_drift_records_in_range(
+        &self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<DriftRecord>> {
+        let cf = self.get_cf("drift")?;
+        let start_key = encode_drift_key(start_block, Address::ZERO, None);
+        let end_key = encode_drift_key(end_block.saturating_add(1), Address::ZERO, None);
+
+        let mut records = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
+            }
+            let record: DriftRecord =
+                postcard::from_bytes(&value).context("Failed to deserialize drift record")?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Per-transaction history implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_tx_record(&self, addr: Address, seq: u16, record: &TxRecord) -> Result<()> {
+        let cf = self.get_cf("tx_history")?;
+        let key = encode_tx_record_key(addr, record.block, record.tx_index, seq);
+        let value = postcard::to_allocvec(record).context("Failed to serialize tx record")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put tx record")?;
+        Ok(())
+    }
+
+    fn get_tx_records_in_range(
+        &self,
+        addr: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<TxRecord>> {
+        let cf = self.get_cf("tx_history")?;
+        let start_key = encode_tx_record_key(addr, start_block, 0, 0);
+        let end_key = encode_tx_record_key(addr, end_block.saturating_add(1), 0, 0);
+
+        let mut records = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
+            }
+            let record: TxRecord =
+                postcard::from_bytes(&value).context("Failed to deserialize tx record")?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    fn get_tx_records_in_range_with_metadata(
+        &self,
+        addr: Address,
+        requested_start: u64,
+        requested_end: u64,
+    ) -> Result<QueryResult<TxRecord>> {
+        let watch_meta = self
+            .get_watch_meta(addr)?
+            .ok_or_else(|| anyhow::anyhow!("Address {:?} is not being tracked", addr))?;
+
+        // Clamp to coverage, like the delta and balance queries
+        let mut result = self.clamp_to_watch_coverage(
+            &watch_meta,
+            "transaction history",
+            requested_start,
+            requested_end,
+        )?;
+        if result.effective_start <= result.effective_end {
+            result.data = self
+                .get_tx_records_in_range(addr, result.effective_start, result.effective_end)?
+                .into_iter()
+                .filter(|record| watch_meta.covers(record.block))
+                .map(|record| (record.block, record))
+                .collect();
+        }
+        Ok(result)
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // Decoded event implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_event_record(&self, record: &EventRecord) -> Result<()> {
+        let cf = self.get_cf("events")?;
+        let key = encode_event_key(record.block, record.log_ordinal);
+        let value = postcard::to_allocvec(record).context("Failed to serialize event record")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put event record")?;
+        Ok(())
+    }
+
+    fn get_event_records_in_range(
+        &self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<EventRecord>> {
+        let cf = self.get_cf("events")?;
+        let start_key = encode_event_key(start_block, 0);
+        let end_key = encode_event_key(end_block.saturating_add(1), 0);
+
+        let mut records = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
+            }
+            let record: EventRecord =
+                postcard::from_bytes(&value).context("Failed to deserialize event record")?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    // ERC721 ownership implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_nft_holding(&self, owner: Address, holding: &NftHolding) -> Result<()> {
+        let cf = self.get_cf("nft_holdings")?;
+        let key = encode_nft_holding_key(owner, holding.contract, holding.token_id);
+        let value = postcard::to_allocvec(holding).context("Failed to serialize NFT holding")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put NFT holding")?;
+        Ok(())
+    }
+
+    fn delete_nft_holding(&self, owner: Address, contract: Address, token_id: U256) -> Result<()> {
+        let cf = self.get_cf("nft_holdings")?;
+        let key = encode_nft_holding_key(owner, contract, token_id);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete NFT holding")?;
+        Ok(())
+    }
+
+    fn get_nft_holdings(&self, owner: Address) -> Result<Vec<NftHolding>> {
+        let cf = self.get_cf("nft_holdings")?;
+        let start_key = encode_nft_holding_key(owner, Address::ZERO, U256::ZERO);
+        let prefix = &start_key[..21];
+
+        let mut holdings = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            let holding: NftHolding =
+                postcard::from_bytes(&value).context("Failed to deserialize NFT holding")?;
+            holdings.push(holding);
+        }
+        Ok(holdings)
+    }
+
+    fn put_nft_transfer(&self, owner: Address, transfer: &NftTransfer) -> Result<()> {
+        let cf = self.get_cf("nft_transfers")?;
+        let key = encode_nft_transfer_key(owner, transfer.block, transfer.log_ordinal);
+        let value = postcard::to_allocvec(transfer).context("Failed to serialize NFT transfer")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put NFT transfer")?;
+        Ok(())
+    }
+
+    fn get_nft_transfers_in_range(
+        &self,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<NftTransfer>> {
+        let cf = self.get_cf("nft_transfers")?;
+        let start_key = encode_nft_transfer_key(owner, start_block, 0);
+        let end_key = encode_nft_transfer_key(owner, end_block.saturating_add(1), 0);
+
+        let mut transfers = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
+            }
+            let transfer: NftTransfer =
+                postcard::from_bytes(&value).context("Failed to deserialize NFT transfer")?;
+            transfers.push(transfer);
+        }
+        Ok(transfers)
+    }
+
+    fn put_conversion(&self, owner: Address, conversion: &Conversion) -> Result<()> {
+        let cf = self.get_cf("conversions")?;
+        let key = encode_conversion_key(owner, conversion.block, conversion.log_ordinal);
+        let value = postcard::to_allocvec(conversion).context("Failed to serialize conversion")?;
+        self.db
+            .put_cf(cf, &key, &value)
+            .context("Failed to put conversion")?;
+        Ok(())
+    }
+
+    fn get_conversions_in_range(
+        &self,
+        owner: Address,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<Conversion>> {
+        let cf = self.get_cf("conversions")?;
+        let start_key = encode_conversion_key(owner, start_block, 0);
+        let end_key = encode_conversion_key(owner, end_block.saturating_add(1), 0);
+
+        let mut conversions = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
+            }
+            let conversion: Conversion =
+                postcard::from_bytes(&value).context("Failed to deserialize conversion")?;
+            conversions.push(conversion);
+        }
+        Ok(conversions)
     }
 
-    fn put_erc20_balance(&self, token: Address, owner: Address, balance: U256) -> Result<()> {
-        let cf = self.get_cf("erc20_balances")?;
-        let key = encode_token_watch_meta_key(token, owner); // Reuse same key layout: token+owner
-        let value = encode_u256(balance);
+    // ─────────────────────────────────────────────────────────────────
+    // Token approval implementations
+    // ─────────────────────────────────────────────────────────────────
+
+    fn put_allowance(&self, owner: Address, allowance: &Allowance) -> Result<()> {
+        let cf = self.get_cf("allowances")?;
+        let key = encode_allowance_key(owner, allowance.token, allowance.spender);
+        let value = postcard::to_allocvec(allowance).context("Failed to serialize allowance")?;
         self.db
             .put_cf(cf, &key, &value)
-            .context("Failed to put ERC20 balance")?;
+            .context("Failed to put allowance")?;
         Ok(())
     }
 
-    fn get_erc20_balance(&self, token: Address, owner: Address) -> Result<Option<U256>> {
-        let cf = self.get_cf("erc20_balances")?;
-        let key = encode_token_watch_meta_key(token, owner);
-        match self.db.get_cf(cf, &key).context("Failed to get ERC20 balance")? {
-            Some(bytes) => {
-                let balance = decode_u256(&bytes).context("Failed to decode ERC20 balance")?;
-                Ok(Some(balance))
+    fn delete_allowance(&self, owner: Address, token: Address, spender: Address) -> Result<()> {
+        let cf = self.get_cf("allowances")?;
+        let key = encode_allowance_key(owner, token, spender);
+        self.db
+            .delete_cf(cf, &key)
+            .context("Failed to delete allowance")?;
+        Ok(())
+    }
+
+    fn get_allowances(&self, owner: Address) -> Result<Vec<Allowance>> {
+        let cf = self.get_cf("allowances")?;
+        let start_key = encode_allowance_key(owner, Address::ZERO, Address::ZERO);
+        let prefix = &start_key[..21];
+
+        let mut allowances = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if !key.starts_with(prefix) {
+                break;
             }
-            None => Ok(None),
+            let allowance: Allowance =
+                postcard::from_bytes(&value).context("Failed to deserialize allowance")?;
+            allowances.push(allowance);
         }
+        Ok(allowances)
     }
 
-    fn put_token_watch_meta(
-        &self,
-        token: Address,
-        owner: Address,
-        meta: &TokenWatchMeta,
-    ) -> Result<()> {
-        let cf = self.get_cf("erc20_watch_meta")?;
-        let key = encode_token_watch_meta_key(token, owner);
-        let value =
-            postcard::to_allocvec(meta).context("Failed to serialize token watch meta")?;
+    fn put_allowance_change(&self, owner: Address, allowance: &Allowance) -> Result<()> {
+        let cf = self.get_cf("allowance_history")?;
+        let key = encode_allowance_change_key(owner, allowance.block, allowance.log_ordinal);
+        let value = postcard::to_allocvec(allowance).context("Failed to serialize allowance")?;
         self.db
             .put_cf(cf, &key, &value)
-            .context("Failed to put token watch meta")?;
+            .context("Failed to put allowance change")?;
         Ok(())
     }
 
-    fn get_token_watch_meta(
-        &self,
-        token: Address,
-        owner: Address,
-    ) -> Result<Option<TokenWatchMeta>> {
-        let cf = self.get_cf("erc20_watch_meta")?;
-        let key = encode_token_watch_meta_key(token, owner);
-        match self.db.get_cf(cf, &key).context("Failed to get token watch meta")? {
-            Some(bytes) => {
-                let meta =
-                    postcard::from_bytes(&bytes).context("Failed to deserialize token watch meta")?;
-                Ok(Some(meta))
-            }
-            None => Ok(None),
-        }
-    }
-
-    fn get_erc20_deltas_in_range_with_metadata(
+    fn get_allowance_history_in_range(
         &self,
-        token: Address,
         owner: Address,
-        requested_start: u64,
-        requested_end: u64,
-    ) -> Result<QueryResult<Erc20Delta>> {
-        let watch_meta = self
-            .get_token_watch_meta(token, owner)?
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Token {:?} for owner {:?} is not being tracked",
-                    token,
-                    owner
-                )
-            })?;
-
-        let head_block = self.get_head()?;
-        let effective_start = requested_start.max(watch_meta.start_block);
-        let effective_end = if let Some(head) = head_block {
-            requested_end.min(head)
-        } else {
-            requested_end
-        };
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<Allowance>> {
+        let cf = self.get_cf("allowance_history")?;
+        let start_key = encode_allowance_change_key(owner, start_block, 0);
+        let end_key = encode_allowance_change_key(owner, end_block.saturating_add(1), 0);
 
-        let mut message_parts = Vec::new();
-        if effective_start > requested_start {
-            message_parts.push(format!(
-                "Earliest known token balance starts at block {}.",
-                watch_meta.start_block
-            ));
-        }
-        if let Some(head) = head_block {
-            if effective_end < requested_end {
-                message_parts.push(format!("Latest available block is {}.", head));
+        let mut history = Vec::new();
+        let iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Forward),
+        );
+        for item in iter {
+            let (key, value) = item.context("Failed to read iterator")?;
+            if key.as_ref() >= end_key.as_slice() {
+                break;
             }
+            let allowance: Allowance =
+                postcard::from_bytes(&value).context("Failed to deserialize allowance")?;
+            history.push(allowance);
         }
-        let message = if message_parts.is_empty() {
-            None
-        } else {
-            Some(message_parts.join(" "))
-        };
+        Ok(history)
+    }
 
-        if effective_start > effective_end {
-            return Ok(QueryResult {
-                requested_start,
-                requested_end,
-                effective_start,
-                effective_end,
-                watch_start_block: watch_meta.start_block,
-                head_block,
-                message,
-                data: Vec::new(),
-            });
-        }
+    // ─────────────────────────────────────────────────────────────────
+    // Persisted watchlist implementations
+    // ─────────────────────────────────────────────────────────────────
 
-        let deltas = self.get_erc20_deltas_in_range(token, owner, effective_start, effective_end)?;
+    fn put_watched_address(&self, addr: Address) -> Result<()> {
+        let cf = self.get_cf("watchlist")?;
+        self.db
+            .put_cf(cf, encode_watched_address_key(addr), [])
+            .context("Failed to put watched address")?;
+        Ok(())
+    }
 
-        Ok(QueryResult {
-            requested_start,
-            requested_end,
-            effective_start,
-            effective_end,
-            watch_start_block: watch_meta.start_block,
-            head_block,
-            message,
-            data: deltas,
-        })
+    fn delete_watched_address(&self, addr: Address) -> Result<()> {
+        let cf = self.get_cf("watchlist")?;
+        self.db
+            .delete_cf(cf, encode_watched_address_key(addr))
+            .context("Failed to delete watched address")?;
+        Ok(())
     }
 
-    fn get_erc20_balances_in_range_with_metadata(
-        &self,
-        token: Address,
-        owner: Address,
-        requested_start: u64,
-        requested_end: u64,
-    ) -> Result<QueryResult<U256>> {
-        let watch_meta = self
-            .get_token_watch_meta(token, owner)?
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Token {:?} for owner {:?} is not being tracked",
-                    token,
-                    owner
-                )
-            })?;
+    fn get_watched_addresses(&self) -> Result<Vec<Address>> {
+        Ok(self
+            .scan_watchlist(b"Ya")?
+            .into_iter()
+            .map(|(addr, _)| addr)
+            .collect())
+    }
 
-        let head_block = self.get_head()?;
-        let effective_start = requested_start.max(watch_meta.start_block);
-        let effective_end = if let Some(head) = head_block {
-            requested_end.min(head)
-        } else {
-            requested_end
-        };
+    fn put_watched_token(&self, token: Address, strategy: TokenStrategy) -> Result<()> {
+        let cf = self.get_cf("watchlist")?;
+        self.db
+            .put_cf(cf, encode_watched_token_key(token), strategy.to_string())
+            .context("Failed to put watched token")?;
+        Ok(())
+    }
 
-        let mut message_parts = Vec::new();
-        if effective_start > requested_start {
-            message_parts.push(format!(
-                "Earliest known token balance starts at block {}.",
-                watch_meta.start_block
-            ));
-        }
-        if let Some(head) = head_block {
-            if effective_end < requested_end {
-                message_parts.push(format!("Latest available block is {}.", head));
-            }
-        }
-        let message = if message_parts.is_empty() {
-            None
-        } else {
-            Some(message_parts.join(" "))
-        };
+    fn delete_watched_token(&self, token: Address) -> Result<()> {
+        let cf = self.get_cf("watchlist")?;
+        self.db
+            .delete_cf(cf, encode_watched_token_key(token))
+            .context("Failed to delete watched token")?;
+        Ok(())
+    }
 
-        if effective_start > effective_end {
-            return Ok(QueryResult {
-                requested_start,
-                requested_end,
-                effective_start,
-                effective_end,
-                watch_start_block: watch_meta.start_block,
-                head_block,
-                message,
-                data: Vec::new(),
-            });
-        }
+    fn get_watched_tokens(&self) -> Result<Vec<(Address, TokenStrategy)>> {
+        self.scan_watchlist(b"Yt")?
+            .into_iter()
+            .map(|(token, value)| {
+                let strategy = std::str::from_utf8(&value)
+                    .context("Invalid token strategy encoding")?
+                    .parse()?;
+                Ok((token, strategy))
+            })
+            .collect()
+    }
 
-        let anchor = self
-            .get_latest_erc20_snapshot_at_or_before(token, owner, effective_start)
-            .context("Failed to get anchor ERC20 snapshot")?;
+    // ─────────────────────────────────────────────────────────────────
+    // Raw access and atomic batch implementations
+    // ─────────────────────────────────────────────────────────────────
 
-        let mut balance = match anchor {
-            Some((snapshot_block, bal)) => {
-                if snapshot_block < watch_meta.start_block {
-                    anyhow::bail!(
-                        "No snapshot found at or after watch_start_block {} for token {:?} owner {:?}. \
-                        Found snapshot at block {} which is before coverage started. \
-                        Please reinitialize/backfill snapshots.",
-                        watch_meta.start_block, token, owner, snapshot_block
-                    );
-                }
-                bal
-            }
-            None => {
-                anyhow::bail!(
-                    "No snapshot found at watch_start_block {} for token {:?} owner {:?}. \
-                    Please reinitialize/backfill snapshots.",
-                    watch_meta.start_block, token, owner
-                );
-            }
-        };
+    fn get_raw(&self, cf: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let handle = self.get_cf(cf)?;
+        self.db
+            .get_cf(handle, key)
+            .with_context(|| format!("Failed to get raw value from '{}'", cf))
+    }
 
-        let deltas =
-            self.get_erc20_deltas_in_range(token, owner, effective_start, effective_end)?;
-        let delta_map: std::collections::HashMap<u64, Erc20Delta> =
-            deltas.into_iter().collect();
-
-        let mut results = Vec::new();
-        for block in effective_start..=effective_end {
-            if let Some(delta) = delta_map.get(&block) {
-                balance = balance
-                    .saturating_add(delta.delta_plus)
-                    .saturating_sub(delta.delta_minus);
+    fn write_batch(&self, writes: &[RawWrite]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for write in writes {
+            let handle = self.get_cf(&write.cf)?;
+            match &write.value {
+                Some(value) => batch.put_cf(handle, &write.key, value),
+                None => batch.delete_cf(handle, &write.key),
             }
-            results.push((block, balance));
         }
-
-        Ok(QueryResult {
-            requested_start,
-            requested_end,
-            effective_start,
-            effective_end,
-            watch_start_block: watch_meta.start_block,
-            head_block,
-            message,
-            data: results,
-        })
+        self.db
+            .write(batch)
+            .context("Failed to write batch")?;
+        Ok(())
     }
 }
 
@@ -1233,6 +2650,13 @@ mod tests {
             sent_value: U256::from(300u64),
             fee_paid: U256::from(200u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1300,6 +2724,13 @@ mod tests {
             sent_value: U256::from(80u64),
             fee_paid: U256::from(20u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1311,6 +2742,13 @@ mod tests {
             sent_value: U256::ZERO,
             fee_paid: U256::ZERO,
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 0,
             tx_count: 1,
         };
@@ -1322,6 +2760,13 @@ mod tests {
             sent_value: U256::from(150u64),
             fee_paid: U256::from(50u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1349,7 +2794,7 @@ mod tests {
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
         // Set up: watch_start_block = 100, snapshot at block 100 with balance 10000
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
         store.put_snapshot(addr, 100, U256::from(10000u64)).unwrap();
 
@@ -1362,6 +2807,13 @@ mod tests {
             sent_value: U256::from(80u64),
             fee_paid: U256::from(20u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1373,6 +2825,13 @@ mod tests {
             sent_value: U256::ZERO,
             fee_paid: U256::ZERO,
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 0,
             tx_count: 1,
         };
@@ -1384,6 +2843,13 @@ mod tests {
             sent_value: U256::from(150u64),
             fee_paid: U256::from(50u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1431,7 +2897,7 @@ mod tests {
         let (store, _temp_dir) = create_test_store();
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
         let retrieved = store.get_watch_meta(addr).unwrap().unwrap();
         assert_eq!(watch_meta, retrieved);
@@ -1443,7 +2909,7 @@ mod tests {
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
         // Set watch_start_block = 100
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
 
         // Create snapshot at block 100
@@ -1466,7 +2932,7 @@ mod tests {
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
         // Set watch_start_block = 100, head_block = 150
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
         store.set_head(150).unwrap();
 
@@ -1494,7 +2960,7 @@ mod tests {
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
         // Set watch_start_block = 100, head_block = 150
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
         store.set_head(150).unwrap();
 
@@ -1514,6 +2980,125 @@ mod tests {
         assert!(msg.contains("Latest available block is 150"));
     }
 
+    #[test]
+    fn test_query_unwatched_and_rewatched_coverage() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x42);
+
+        // Watched 100..=120, unwatched, watched again from 130
+        let mut watch_meta = WatchMeta::new(100);
+        store.put_snapshot(addr, 100, U256::from(1000u64)).unwrap();
+        let mut delta = BlockDelta::new(110);
+        delta.delta_plus = U256::from(50u64);
+        store.put_delta(addr, 110, &delta).unwrap();
+        store.put_snapshot(addr, 110, U256::from(1050u64)).unwrap();
+        watch_meta.unwatch(120);
+        store.put_watch_meta(addr, &watch_meta).unwrap();
+        store.set_head(125).unwrap();
+
+        let result = store.get_balances_in_range_with_metadata(addr, 100, 125).unwrap();
+        assert_eq!(result.effective_end, 120);
+        assert_eq!(result.watch_end_block, Some(120));
+        assert_eq!(result.data.last(), Some(&(120, U256::from(1050u64))));
+        assert!(result.message.unwrap().contains("Address was unwatched after block 120"));
+
+        // The new segment is anchored at its own snapshot
+        watch_meta.rewatch(130);
+        store.put_watch_meta(addr, &watch_meta).unwrap();
+        store.put_snapshot(addr, 130, U256::from(7u64)).unwrap();
+        store.set_head(135).unwrap();
+
+        let result = store.get_balances_in_range_with_metadata(addr, 115, 135).unwrap();
+        assert_eq!((result.effective_start, result.effective_end), (115, 135));
+        assert_eq!(result.watch_end_block, None);
+        assert_eq!(result.coverage_gaps, vec![(121, 129)]);
+        assert_eq!(result.data.len(), 12); // 115..=120 and 130..=135
+        assert_eq!(result.data[5], (120, U256::from(1050u64)));
+        assert_eq!(result.data[6], (130, U256::from(7u64)));
+        assert!(result.message.unwrap().contains("Address was not watched in blocks 121-129"));
+
+        let deltas = store.get_deltas_in_range_with_metadata(addr, 100, 135).unwrap();
+        assert_eq!(deltas.data.len(), 1);
+        assert_eq!(deltas.coverage_gaps, vec![(121, 129)]);
+    }
+
+    #[test]
+    fn test_token_queries_follow_owner_coverage() {
+        let (store, _temp_dir) = create_test_store();
+        let owner = Address::repeat_byte(0x42);
+        let token = Address::repeat_byte(0xaa);
+        let (contract, id) = (Address::repeat_byte(0xbb), U256::from(7u64));
+        let pair_meta = TokenWatchMeta {
+            auto_discovered: true,
+            ..TokenWatchMeta::new(105)
+        };
+
+        // Owner watched 100..=120; token tracked from 105, ERC1155 balance from 100
+        let mut watch_meta = WatchMeta::new(100);
+        watch_meta.unwatch(120);
+        store.put_watch_meta(owner, &watch_meta).unwrap();
+        store.put_token_watch_meta(token, owner, &pair_meta).unwrap();
+        store.put_erc20_snapshot(token, owner, 105, U256::from(10u64)).unwrap();
+        let mut delta = Erc20Delta::new(110);
+        delta.delta_plus = U256::from(5u64);
+        store.put_erc20_delta(token, owner, 110, &delta).unwrap();
+        store.put_erc20_snapshot(token, owner, 110, U256::from(15u64)).unwrap();
+        let erc1155_meta = TokenWatchMeta::new(100);
+        store.put_erc1155_watch_meta(contract, id, owner, &erc1155_meta).unwrap();
+        store.put_erc1155_snapshot(contract, id, owner, 100, U256::from(3u64)).unwrap();
+        store.set_head(125).unwrap();
+
+        let result = store
+            .get_erc20_balances_in_range_with_metadata(token, owner, 100, 125)
+            .unwrap();
+        assert_eq!((result.effective_start, result.effective_end), (105, 120));
+        assert_eq!(result.watch_end_block, Some(120));
+        assert_eq!(result.data.last(), Some(&(120, U256::from(15u64))));
+        let message = result.message.unwrap();
+        assert!(message.contains("Earliest known token balance starts at block 105"));
+        assert!(message.contains("Address was unwatched after block 120"));
+
+        let deltas = store
+            .get_erc20_deltas_in_range_with_metadata(token, owner, 100, 125)
+            .unwrap();
+        assert_eq!(deltas.effective_end, 120);
+        assert_eq!(deltas.watch_end_block, Some(120));
+        assert_eq!(deltas.data.len(), 1);
+
+        let erc1155 = store
+            .get_erc1155_balances_in_range_with_metadata(contract, id, owner, 100, 125)
+            .unwrap();
+        assert_eq!(erc1155.effective_end, 120);
+        assert_eq!(erc1155.watch_end_block, Some(120));
+        assert_eq!(erc1155.data.len(), 21);
+
+        // Watched again from 130: the pair's new segment is anchored at its own snapshot
+        watch_meta.rewatch(130);
+        store.put_watch_meta(owner, &watch_meta).unwrap();
+        store.put_erc20_snapshot(token, owner, 130, U256::from(40u64)).unwrap();
+        store.put_erc1155_snapshot(contract, id, owner, 130, U256::from(1u64)).unwrap();
+        store.set_head(135).unwrap();
+
+        let result = store
+            .get_erc20_balances_in_range_with_metadata(token, owner, 115, 135)
+            .unwrap();
+        assert_eq!(result.watch_end_block, None);
+        assert_eq!(result.coverage_gaps, vec![(121, 129)]);
+        assert_eq!(result.data.len(), 12); // 115..=120 and 130..=135
+        assert_eq!(result.data[5], (120, U256::from(15u64)));
+        assert_eq!(result.data[6], (130, U256::from(40u64)));
+        assert!(result.message.unwrap().contains("Address was not watched in blocks 121-129"));
+
+        let erc1155 = store
+            .get_erc1155_deltas_in_range_with_metadata(contract, id, owner, 100, 135)
+            .unwrap();
+        assert_eq!(erc1155.coverage_gaps, vec![(121, 129)]);
+        let erc1155 = store
+            .get_erc1155_balances_in_range_with_metadata(contract, id, owner, 115, 135)
+            .unwrap();
+        assert_eq!(erc1155.data[6], (130, U256::from(1u64)));
+    }
+
     #[test]
     fn test_anchor_protection() {
         let (store, _temp_dir) = create_test_store();
@@ -1523,7 +3108,7 @@ mod tests {
         store.put_snapshot(addr, 50, U256::from(5000u64)).unwrap();
 
         // Set watch_start_block = 100
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
 
         // Query starting at 90 (will be clamped to 100)
@@ -1540,7 +3125,7 @@ mod tests {
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
         // Set watch_start_block = 100
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
 
         // Do NOT create snapshot at 100
@@ -1558,7 +3143,7 @@ mod tests {
         let addr = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
         // Set watch_start_block = 100, head_block = 150
-        let watch_meta = WatchMeta { start_block: 100 };
+        let watch_meta = WatchMeta::new(100);
         store.put_watch_meta(addr, &watch_meta).unwrap();
         store.set_head(150).unwrap();
 
@@ -1571,6 +3156,13 @@ mod tests {
             sent_value: U256::from(80u64),
             fee_paid: U256::from(20u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1582,6 +3174,13 @@ mod tests {
             sent_value: U256::ZERO,
             fee_paid: U256::ZERO,
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 0,
             tx_count: 1,
         };
@@ -1593,6 +3192,13 @@ mod tests {
             sent_value: U256::from(150u64),
             fee_paid: U256::from(50u64),
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 1,
             tx_count: 1,
         };
@@ -1604,6 +3210,13 @@ mod tests {
             sent_value: U256::ZERO,
             fee_paid: U256::ZERO,
             failed_fee: U256::ZERO,
+            blob_fee: U256::ZERO,
+            l1_fee: U256::ZERO,
+            minted_value: U256::ZERO,
+            withdrawal_value: U256::ZERO,
+            priority_fee_income: U256::ZERO,
+            correction_plus: U256::ZERO,
+            correction_minus: U256::ZERO,
             nonce_delta: 0,
             tx_count: 1,
         };
@@ -1638,10 +3251,19 @@ mod tests {
         let (store, _temp_dir) = create_test_store();
         let token = Address::from_slice(&hex::decode("dAC17F958D2ee523a2206206994597C13D831ec7").unwrap());
         let owner = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
-        let meta = TokenWatchMeta { start_block: 100 };
+        let meta = TokenWatchMeta::new(100);
         store.put_token_watch_meta(token, owner, &meta).unwrap();
         let retrieved = store.get_token_watch_meta(token, owner).unwrap().unwrap();
         assert_eq!(meta, retrieved);
+
+        let discovered = TokenWatchMeta {
+            auto_discovered: true,
+            ..TokenWatchMeta::new(120)
+        };
+        let other_token = Address::repeat_byte(0xaa);
+        store.put_token_watch_meta(other_token, owner, &discovered).unwrap();
+        let all = store.get_token_watch_metas().unwrap();
+        assert_eq!(all, vec![(other_token, owner, discovered), (token, owner, meta)]);
     }
 
     #[test]
@@ -1650,13 +3272,13 @@ mod tests {
         let token = Address::from_slice(&hex::decode("dAC17F958D2ee523a2206206994597C13D831ec7").unwrap());
         let owner = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
-        let meta = TokenWatchMeta { start_block: 100 };
+        let meta = TokenWatchMeta::new(100);
         store.put_token_watch_meta(token, owner, &meta).unwrap();
         store.put_erc20_snapshot(token, owner, 100, U256::from(10000u64)).unwrap();
 
-        let delta1 = Erc20Delta { block: 101, delta_plus: U256::ZERO, delta_minus: U256::from(100u64), tx_count: 1 };
-        let delta2 = Erc20Delta { block: 103, delta_plus: U256::from(500u64), delta_minus: U256::ZERO, tx_count: 1 };
-        let delta3 = Erc20Delta { block: 105, delta_plus: U256::ZERO, delta_minus: U256::from(200u64), tx_count: 1 };
+        let delta1 = Erc20Delta { block: 101, delta_plus: U256::ZERO, delta_minus: U256::from(100u64), correction_plus: U256::ZERO, correction_minus: U256::ZERO, rebase_plus: U256::ZERO, rebase_minus: U256::ZERO, adjustment_plus: U256::ZERO, adjustment_minus: U256::ZERO, tx_count: 1 };
+        let delta2 = Erc20Delta { block: 103, delta_plus: U256::from(500u64), delta_minus: U256::ZERO, correction_plus: U256::ZERO, correction_minus: U256::ZERO, rebase_plus: U256::ZERO, rebase_minus: U256::ZERO, adjustment_plus: U256::ZERO, adjustment_minus: U256::ZERO, tx_count: 1 };
+        let delta3 = Erc20Delta { block: 105, delta_plus: U256::ZERO, delta_minus: U256::from(200u64), correction_plus: U256::ZERO, correction_minus: U256::ZERO, rebase_plus: U256::ZERO, rebase_minus: U256::ZERO, adjustment_plus: U256::ZERO, adjustment_minus: U256::ZERO, tx_count: 1 };
 
         store.put_erc20_delta(token, owner, 101, &delta1).unwrap();
         store.put_erc20_delta(token, owner, 103, &delta2).unwrap();
@@ -1679,7 +3301,7 @@ mod tests {
         let token = Address::from_slice(&hex::decode("dAC17F958D2ee523a2206206994597C13D831ec7").unwrap());
         let owner = Address::from_slice(&hex::decode("0742d35Cc6634C0532925a3b844Bc9e7595f0bEb").unwrap());
 
-        let meta = TokenWatchMeta { start_block: 100 };
+        let meta = TokenWatchMeta::new(100);
         store.put_token_watch_meta(token, owner, &meta).unwrap();
         store.put_erc20_snapshot(token, owner, 100, U256::from(10000u64)).unwrap();
         store.set_head(150).unwrap();
@@ -1691,4 +3313,254 @@ mod tests {
         assert_eq!(result.effective_end, 150);
         assert_eq!(result.data.len(), 51);
     }
+
+    #[test]
+    fn test_erc1155_balances_fill_forward_and_clamping() {
+        let (store, _temp_dir) = create_test_store();
+        let contract = Address::repeat_byte(0xaa);
+        let owner = Address::repeat_byte(0x01);
+        let id = U256::from(7u64);
+        let other_id = U256::from(8u64);
+
+        store
+            .put_erc1155_watch_meta(
+                contract,
+                id,
+                owner,
+                &TokenWatchMeta::new(100),
+            )
+            .unwrap();
+        store.put_erc1155_snapshot(contract, id, owner, 100, U256::from(10u64)).unwrap();
+        // Another id of the same contract must not leak into the query
+        store.put_erc1155_snapshot(contract, other_id, owner, 100, U256::from(99u64)).unwrap();
+        store.set_head(104).unwrap();
+
+        let mut delta = Erc1155Delta::new(102);
+        delta.delta_plus = U256::from(5u64);
+        store.put_erc1155_delta(contract, id, owner, 102, &delta).unwrap();
+        let mut delta = Erc1155Delta::new(103);
+        delta.delta_minus = U256::from(3u64);
+        store.put_erc1155_delta(contract, other_id, owner, 103, &delta).unwrap();
+
+        let result = store
+            .get_erc1155_balances_in_range_with_metadata(contract, id, owner, 90, 200)
+            .unwrap();
+        assert_eq!(result.effective_start, 100);
+        assert_eq!(result.effective_end, 104);
+        assert!(result.message.is_some());
+        let balances: Vec<u64> = result.data.iter().map(|(_, b)| b.to::<u64>()).collect();
+        assert_eq!(balances, vec![10, 10, 15, 15, 15]);
+
+        let deltas = store
+            .get_erc1155_deltas_in_range_with_metadata(contract, id, owner, 0, 200)
+            .unwrap();
+        assert_eq!(deltas.data.len(), 1);
+        assert!(store
+            .get_erc1155_balances_in_range_with_metadata(contract, U256::from(9u64), owner, 100, 104)
+            .is_err());
+    }
+
+    #[test]
+    fn test_drift_records_in_range() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0xaa);
+        let record = |block: u64, token: Option<Address>| DriftRecord {
+            block,
+            address: addr,
+            token,
+            stored_balance: U256::from(100u64),
+            node_balance: U256::from(90u64),
+            stored_nonce: None,
+            node_nonce: None,
+            corrected: false,
+        };
+
+        store.put_drift_record(&record(100, None)).unwrap();
+        store.put_drift_record(&record(100, Some(token))).unwrap();
+        store.put_drift_record(&record(200, None)).unwrap();
+        store.put_drift_record(&record(300, None)).unwrap();
+
+        let records = store.get_drift_records_in_range(100, 200).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], record(100, None));
+        assert_eq!(records[1], record(100, Some(token)));
+        assert_eq!(records[2], record(200, None));
+        assert!(store.get_drift_records_in_range(301, 400).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_nft_holdings_and_history() {
+        let (store, _temp_dir) = create_test_store();
+        let owner = Address::repeat_byte(0x01);
+        let other = Address::repeat_byte(0x02);
+        let contract = Address::repeat_byte(0xaa);
+        let transfer = |block: u64, token_id: u64, direction: TxDirection| NftTransfer {
+            block,
+            log_ordinal: 0,
+            tx_hash: B256::repeat_byte(block as u8),
+            contract,
+            token_id: U256::from(token_id),
+            direction,
+            counterparty: other,
+        };
+
+        store.put_nft_transfer(owner, &transfer(100, 1, TxDirection::In)).unwrap();
+        store.put_nft_transfer(owner, &transfer(110, 2, TxDirection::In)).unwrap();
+        store.put_nft_transfer(owner, &transfer(120, 1, TxDirection::Out)).unwrap();
+        store
+            .put_nft_holding(
+                owner,
+                &NftHolding {
+                    contract,
+                    token_id: U256::from(2u64),
+                    since_block: 110,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(store.get_nft_transfers_in_range(owner, 105, 120).unwrap().len(), 2);
+        assert!(store.get_nft_transfers_in_range(other, 0, 200).unwrap().is_empty());
+
+        // Replayed history agrees with the current holdings at the head
+        let mut meta = WatchMeta::new(100);
+        store.put_watch_meta(owner, &meta).unwrap();
+        let at = |block: u64| -> Option<Vec<(u64, u64)>> {
+            store.get_nft_holdings_at(owner, block).unwrap().map(|holdings| {
+                holdings
+                    .iter()
+                    .map(|h| (h.token_id.to::<u64>(), h.since_block))
+                    .collect()
+            })
+        };
+        assert_eq!(at(99), None);
+        assert_eq!(at(100), Some(vec![(1, 100)]));
+        assert_eq!(at(115), Some(vec![(1, 100), (2, 110)]));
+        assert_eq!(at(120), Some(vec![(2, 110)]));
+
+        // Transfers in a coverage gap are unknown, so later holdings are too
+        meta.unwatch(130);
+        meta.rewatch(150);
+        store.put_watch_meta(owner, &meta).unwrap();
+        assert_eq!(at(125), Some(vec![(2, 110)]));
+        assert_eq!(at(140), None);
+        assert_eq!(at(160), None);
+        assert_eq!(store.get_nft_holdings(owner).unwrap().len(), 1);
+        assert!(store.get_nft_holdings(other).unwrap().is_empty());
+
+        store.delete_nft_holding(owner, contract, U256::from(2u64)).unwrap();
+        assert!(store.get_nft_holdings(owner).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_conversions_in_range() {
+        use crate::records::ConversionKind;
+
+        let (store, _temp_dir) = create_test_store();
+        let owner = Address::repeat_byte(0x01);
+        let weth = Address::repeat_byte(0xaa);
+        let conversion = |block: u64, log_ordinal: u32, kind| Conversion {
+            block,
+            log_ordinal,
+            tx_hash: B256::repeat_byte(block as u8),
+            token: weth,
+            kind,
+            amount: U256::from(1000u64),
+        };
+
+        store.put_conversion(owner, &conversion(100, 3, ConversionKind::Wrap)).unwrap();
+        store.put_conversion(owner, &conversion(100, 1, ConversionKind::Unwrap)).unwrap();
+        store.put_conversion(owner, &conversion(101, 0, ConversionKind::Unwrap)).unwrap();
+
+        let found = store.get_conversions_in_range(owner, 100, 100).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                conversion(100, 1, ConversionKind::Unwrap),
+                conversion(100, 3, ConversionKind::Wrap),
+            ]
+        );
+        assert_eq!(store.get_conversions_in_range(owner, 0, 200).unwrap().len(), 3);
+        assert!(store
+            .get_conversions_in_range(Address::repeat_byte(0x02), 0, 200)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_allowances_and_history() {
+        use crate::records::ApprovalKind;
+
+        let (store, _temp_dir) = create_test_store();
+        let owner = Address::repeat_byte(0x01);
+        let token = Address::repeat_byte(0xaa);
+        let allowance = |block: u64, spender: u8, value: U256| Allowance {
+            block,
+            log_ordinal: 0,
+            tx_hash: B256::repeat_byte(block as u8),
+            token,
+            spender: Address::repeat_byte(spender),
+            kind: ApprovalKind::Erc20,
+            value,
+        };
+
+        for approval in [
+            allowance(100, 0x22, U256::MAX),
+            allowance(105, 0x33, U256::from(50u64)),
+            allowance(110, 0x22, U256::ZERO),
+        ] {
+            store.put_allowance_change(owner, &approval).unwrap();
+            if approval.is_revoked() {
+                store.delete_allowance(owner, token, approval.spender).unwrap();
+            } else {
+                store.put_allowance(owner, &approval).unwrap();
+            }
+        }
+
+        assert_eq!(
+            store.get_allowances(owner).unwrap(),
+            vec![allowance(105, 0x33, U256::from(50u64))]
+        );
+        assert_eq!(store.get_allowance_history_in_range(owner, 100, 110).unwrap().len(), 3);
+        assert_eq!(store.get_allowance_history_in_range(owner, 101, 109).unwrap().len(), 1);
+        assert!(store.get_allowances(Address::repeat_byte(0x02)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_anchor_snapshot_includes_own_delta() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        store.put_watch_meta(addr, &WatchMeta::new(100)).unwrap();
+        store.put_snapshot(addr, 100, U256::from(1000u64)).unwrap();
+
+        // The watcher writes a delta and the resulting snapshot for block 101
+        let mut delta = BlockDelta::new(101);
+        delta.delta_plus = U256::from(500u64);
+        store.put_delta(addr, 101, &delta).unwrap();
+        store.put_snapshot(addr, 101, U256::from(1500u64)).unwrap();
+
+        // Anchored at the block-101 snapshot, the block-101 delta must not be applied again
+        let balances = store.get_balances_in_range(addr, 101, 102).unwrap();
+        assert_eq!(balances, vec![(101, U256::from(1500u64)), (102, U256::from(1500u64))]);
+
+        // Anchored at block 100, it is applied once
+        let balances = store.get_balances_in_range(addr, 100, 101).unwrap();
+        assert_eq!(balances, vec![(100, U256::from(1000u64)), (101, U256::from(1500u64))]);
+    }
+
+    #[test]
+    fn test_query_reports_head_finality() {
+        let (store, _temp_dir) = create_test_store();
+        let addr = Address::repeat_byte(0x01);
+        store.put_watch_meta(addr, &WatchMeta::new(100)).unwrap();
+        store.put_snapshot(addr, 100, U256::from(1000u64)).unwrap();
+        store.set_head(105).unwrap();
+
+        let result = store.get_balances_in_range_with_metadata(addr, 100, 105).unwrap();
+        assert_eq!(result.head_finality, None);
+
+        store.set_head_finality(FollowMode::Finalized).unwrap();
+        let result = store.get_balances_in_range_with_metadata(addr, 100, 105).unwrap();
+        assert_eq!(result.head_finality, Some(FollowMode::Finalized));
+    }
 }
@@ -0,0 +1,129 @@
+//! ERC20 token metadata (name, symbol, decimals)
+//!
+//! Reads `name()`, `symbol()` and `decimals()` with `eth_call`. The standard
+//! returns `string`, but some early tokens (e.g. MKR) return `bytes32`;
+//! both encodings are accepted. Also formats raw amounts with the decimals.
+
+use crate::records::TokenMetadata;
+use crate::rpc::RpcClient;
+use alloy_primitives::{Address, U256};
+use tracing::warn;
+
+/// `name()` selector
+const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+
+/// `symbol()` selector
+const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+
+/// `decimals()` selector
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Fetch a token's name, symbol and decimals at `block`.
+///
+/// Calls that fail or return undecodable data leave the field `None`.
+pub async fn fetch_token_metadata(rpc: &RpcClient, token: Address, block: &str) -> TokenMetadata {
+    let call = |selector: [u8; 4]| async move {
+        match rpc.eth_call(token, &selector, block).await {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!(
+                    "Token metadata call 0x{} failed for {:?}: {:?}",
+                    hex::encode(selector),
+                    token,
+                    e
+                );
+                None
+            }
+        }
+    };
+    TokenMetadata {
+        name: call(NAME_SELECTOR).await.and_then(|d| decode_string_result(&d)),
+        symbol: call(SYMBOL_SELECTOR).await.and_then(|d| decode_string_result(&d)),
+        decimals: call(DECIMALS_SELECTOR).await.and_then(|d| decode_decimals_result(&d)),
+        non_standard: false,
+    }
+}
+
+/// Decode a `string` or legacy `bytes32` return value.
+///
+/// `bytes32` values are right-padded with zero bytes, which are trimmed.
+fn decode_string_result(data: &[u8]) -> Option<String> {
+    let bytes = if data.len() == 32 {
+        let end = data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+        &data[..end]
+    } else {
+        let word = |offset: usize| data.get(offset..offset.checked_add(32)?);
+        let offset = usize::try_from(U256::from_be_slice(word(0)?)).ok()?;
+        let len = usize::try_from(U256::from_be_slice(word(offset)?)).ok()?;
+        let start = offset.checked_add(32)?;
+        data.get(start..start.checked_add(len)?)?
+    };
+    let s = String::from_utf8(bytes.to_vec()).ok()?;
+    (!s.is_empty()).then_some(s)
+}
+
+/// Decode a `uint8` return value.
+fn decode_decimals_result(data: &[u8]) -> Option<u8> {
+    let word = data.get(..32)?;
+    u8::try_from(U256::from_be_slice(word)).ok()
+}
+
+/// Format a raw amount with `decimals` fractional digits, e.g. 1500000 with 6 → "1.5".
+///
+/// Trailing zeros of the fraction are dropped. Returns None if `decimals`
+/// is too large for 10^decimals to fit in a U256.
+pub fn format_units(value: U256, decimals: u8) -> Option<String> {
+    let unit = U256::from(10u64).checked_pow(U256::from(decimals))?;
+    let whole = value / unit;
+    let fraction = value % unit;
+    if fraction.is_zero() {
+        return Some(whole.to_string());
+    }
+    let fraction = format!("{:0>width$}", fraction.to_string(), width = decimals as usize);
+    Some(format!("{}.{}", whole, fraction.trim_end_matches('0')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(keccak256("name()")[..4], NAME_SELECTOR);
+        assert_eq!(keccak256("symbol()")[..4], SYMBOL_SELECTOR);
+        assert_eq!(keccak256("decimals()")[..4], DECIMALS_SELECTOR);
+    }
+
+    #[test]
+    fn test_decode_string_and_bytes32() {
+        // ABI string "USDC": offset 0x20, length 4, padded data
+        let mut data = U256::from(32u64).to_be_bytes::<32>().to_vec();
+        data.extend_from_slice(&U256::from(4u64).to_be_bytes::<32>());
+        let mut padded = [0u8; 32];
+        padded[..4].copy_from_slice(b"USDC");
+        data.extend_from_slice(&padded);
+        assert_eq!(decode_string_result(&data), Some("USDC".to_string()));
+
+        // Legacy bytes32 "MKR"
+        let mut bytes32 = [0u8; 32];
+        bytes32[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_string_result(&bytes32), Some("MKR".to_string()));
+
+        // Empty and truncated results
+        assert_eq!(decode_string_result(&[]), None);
+        assert_eq!(decode_string_result(&data[..66]), None);
+
+        assert_eq!(decode_decimals_result(&U256::from(18u64).to_be_bytes::<32>()), Some(18));
+        assert_eq!(decode_decimals_result(&U256::from(256u64).to_be_bytes::<32>()), None);
+    }
+
+    #[test]
+    fn test_format_units() {
+        assert_eq!(format_units(U256::from(1_500_000u64), 6).unwrap(), "1.5");
+        assert_eq!(format_units(U256::from(1_000_000u64), 6).unwrap(), "1");
+        assert_eq!(format_units(U256::from(42u64), 6).unwrap(), "0.000042");
+        assert_eq!(format_units(U256::from(42u64), 0).unwrap(), "42");
+        assert_eq!(format_units(U256::MAX, 255), None);
+    }
+}
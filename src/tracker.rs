@@ -4,19 +4,24 @@
 //! can process blocks and persist their own state.
 //! Future-proofs the system for DeFi protocols that may need storage reading.
 
+use crate::batch::BlockWriteBatch;
 use crate::rpc::RpcClient;
 use crate::store::StateStore;
-use alloy_primitives::Address;
+use crate::types::{Log, Receipt};
+use alloy_primitives::{Address, B256};
 use anyhow::Result;
 use std::collections::HashSet;
 
 /// Shared context passed to trackers during block processing.
 ///
-/// Contains everything a tracker needs: store, RPC, watched addresses,
-/// watched tokens, and the current block number.
-pub struct TrackerContext<'a> {
-    /// State store for persisting deltas/snapshots
-    pub store: &'a dyn StateStore,
+/// Contains everything a tracker needs: store, the block's write batch, RPC,
+/// watched addresses, watched tokens, and the current block number.
+pub struct TrackerContext<'a, 's> {
+    /// State store for reading committed state (watch metadata, etc.)
+    pub store: &'s dyn StateStore,
+    /// Write batch for the current block; all tracker writes go through it
+    /// so they are committed atomically with the rest of the block
+    pub batch: &'a mut BlockWriteBatch<'s>,
     /// RPC client for balanceOf, storage reads, etc.
     pub rpc: &'a RpcClient,
     /// Set of watched EOA addresses
@@ -27,10 +32,40 @@ pub struct TrackerContext<'a> {
     pub block_number: u64,
 }
 
+impl TrackerContext<'_, '_> {
+    /// Whether `owner` is watched and its coverage includes the current block.
+    pub fn is_covered(&self, owner: Address) -> Result<bool> {
+        if !self.watched_eoas.contains(&owner) {
+            return Ok(false);
+        }
+        Ok(self
+            .store
+            .get_watch_meta(owner)?
+            .is_some_and(|meta| meta.covers(self.block_number)))
+    }
+}
+
+/// Logs of the receipts passed to `Tracker::process_block`, with their transaction hashes.
+///
+/// Each log comes with its position among all logs of those receipts (the
+/// block's successful ones), which keys the records trackers write per log.
+pub fn logs_with_ordinals<'r>(
+    receipts: &'r [(B256, &'r Receipt)],
+) -> impl Iterator<Item = (u32, &'r B256, &'r Log)> + 'r {
+    receipts
+        .iter()
+        .flat_map(|(tx_hash, receipt)| receipt.logs.iter().map(move |log| (tx_hash, log)))
+        .enumerate()
+        .map(|(ordinal, (tx_hash, log))| {
+            (u32::try_from(ordinal).unwrap_or(u32::MAX), tx_hash, log)
+        })
+}
+
 /// Block-processing tracker trait.
 ///
 /// Each tracker receives block context and receipts, and may persist
-/// its own deltas/snapshots. Trackers are called in sequence by the watcher.
+/// its own deltas/snapshots through `ctx.batch`. Trackers are called in
+/// sequence by the watcher.
 pub trait Tracker {
     /// Human-readable name for logging.
     fn name(&self) -> &'static str;
@@ -41,8 +76,8 @@ pub trait Tracker {
     /// transactions only. The tracker may fetch additional data via RPC if needed.
     fn process_block(
         &self,
-        _ctx: &TrackerContext<'_>,
-        _receipts: &[(alloy_primitives::B256, &crate::types::Receipt)],
+        _ctx: &mut TrackerContext<'_, '_>,
+        _receipts: &[(B256, &Receipt)],
     ) -> Result<()> {
         // Default no-op for optional processing
         Ok(())
@@ -0,0 +1,178 @@
+//! Token approval tracker
+//!
+//! Decodes ERC20 `Approval(owner, spender, value)` and ERC721/ERC1155
+//! `ApprovalForAll(owner, operator, approved)` logs from any contract and
+//! keeps, for each watched owner, its current allowances and an approval
+//! history. A zero allowance or `approved = false` removes the current
+//! entry. ERC721 single-token `Approval`s (token ID as 4th topic) are
+//! cleared on every transfer and are not tracked.
+//!
+//! Allowances spent by `transferFrom` are not observed (most tokens emit no
+//! event for it), so the current value is the last approved amount.
+//! Ignores logs from reverted transactions.
+
+use crate::records::{Allowance, ApprovalKind};
+use crate::tracker::{logs_with_ordinals, Tracker, TrackerContext};
+use crate::tracker_erc20::parse_address_from_topic;
+use crate::types::{Log, Receipt};
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{Context, Result};
+
+/// keccak256("Approval(address,address,uint256)")
+const APPROVAL_TOPIC: [u8; 32] = [
+    0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b, 0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e, 0x84,
+    0xf3, 0xdd, 0x03, 0x14, 0xc0, 0xf7, 0xb2, 0x29, 0x1e, 0x5b, 0x20, 0x0a, 0xc8, 0xc7, 0xc3,
+    0xb9, 0x25,
+];
+
+/// keccak256("ApprovalForAll(address,address,bool)")
+const APPROVAL_FOR_ALL_TOPIC: [u8; 32] = [
+    0x17, 0x30, 0x7e, 0xab, 0x39, 0xab, 0x61, 0x07, 0xe8, 0x89, 0x98, 0x45, 0xad, 0x3d, 0x59,
+    0xbd, 0x96, 0x53, 0xf2, 0x00, 0xf2, 0x20, 0x92, 0x04, 0x89, 0xca, 0x2b, 0x59, 0x37, 0x69,
+    0x6c, 0x31,
+];
+
+/// Approval tracker that records allowances granted by watched addresses.
+#[derive(Debug, Default)]
+pub struct ApprovalTracker;
+
+impl ApprovalTracker {
+    /// Create a new approval tracker.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parse owner, spender, kind and value from an `Approval` or `ApprovalForAll` log.
+    ///
+    /// Returns None for anything else, including ERC721 `Approval`s (4 topics).
+    fn parse_approval_log(&self, log: &Log) -> Option<(Address, Address, ApprovalKind, U256)> {
+        if log.topics.len() != 3 || log.data.len() < 32 {
+            return None;
+        }
+        let topic0 = log.topics[0].strip_prefix("0x").unwrap_or(&log.topics[0]);
+        let topic0 = hex::decode(topic0).ok()?;
+        let word = U256::from_be_slice(&log.data[0..32]);
+        let (kind, value) = if topic0.as_slice() == APPROVAL_TOPIC {
+            (ApprovalKind::Erc20, word)
+        } else if topic0.as_slice() == APPROVAL_FOR_ALL_TOPIC {
+            (ApprovalKind::ForAll, U256::from(!word.is_zero() as u8))
+        } else {
+            return None;
+        };
+        let owner = parse_address_from_topic(&log.topics[1]).ok()?;
+        let spender = parse_address_from_topic(&log.topics[2]).ok()?;
+        Some((owner, spender, kind, value))
+    }
+}
+
+impl Tracker for ApprovalTracker {
+    fn name(&self) -> &'static str {
+        "ApprovalTracker"
+    }
+
+    fn process_block(
+        &self,
+        ctx: &mut TrackerContext<'_, '_>,
+        receipts: &[(B256, &Receipt)],
+    ) -> Result<()> {
+        for (log_ordinal, tx_hash, log) in logs_with_ordinals(receipts) {
+            let Some((owner, spender, kind, value)) = self.parse_approval_log(log) else {
+                continue;
+            };
+            if !ctx.is_covered(owner)? {
+                continue;
+            }
+
+            let allowance = Allowance {
+                block: ctx.block_number,
+                log_ordinal,
+                tx_hash: *tx_hash,
+                token: log.address,
+                spender,
+                kind,
+                value,
+            };
+            ctx.batch.put_allowance_change(owner, &allowance)?;
+            if allowance.is_revoked() {
+                ctx.batch.delete_allowance(owner, log.address, spender)
+            } else {
+                ctx.batch.put_allowance(owner, &allowance)
+            }
+            .with_context(|| {
+                format!(
+                    "Failed to update allowance of {:?} for token {:?} spender {:?}",
+                    owner, log.address, spender
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    fn topic(addr: Address) -> String {
+        format!("0x{:0>64}", hex::encode(addr.as_slice()))
+    }
+
+    #[test]
+    fn test_parse_approval_logs() {
+        assert_eq!(keccak256("Approval(address,address,uint256)"), B256::from(APPROVAL_TOPIC));
+        assert_eq!(
+            keccak256("ApprovalForAll(address,address,bool)"),
+            B256::from(APPROVAL_FOR_ALL_TOPIC)
+        );
+
+        let tracker = ApprovalTracker::new();
+        let owner = Address::repeat_byte(0x11);
+        let spender = Address::repeat_byte(0x22);
+        let log = |topic0: [u8; 32], word: U256| Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![format!("0x{}", hex::encode(topic0)), topic(owner), topic(spender)],
+            data: word.to_be_bytes::<32>().to_vec(),
+        };
+
+        assert_eq!(
+            tracker.parse_approval_log(&log(APPROVAL_TOPIC, U256::MAX)),
+            Some((owner, spender, ApprovalKind::Erc20, U256::MAX))
+        );
+        assert_eq!(
+            tracker.parse_approval_log(&log(APPROVAL_FOR_ALL_TOPIC, U256::from(1u64))),
+            Some((owner, spender, ApprovalKind::ForAll, U256::from(1u64)))
+        );
+        assert_eq!(
+            tracker.parse_approval_log(&log(APPROVAL_FOR_ALL_TOPIC, U256::ZERO)),
+            Some((owner, spender, ApprovalKind::ForAll, U256::ZERO))
+        );
+
+        // ERC721 Approval: approved address and token ID indexed, no data
+        let mut erc721 = log(APPROVAL_TOPIC, U256::ZERO);
+        erc721.topics.push(format!("0x{:064x}", 7));
+        erc721.data.clear();
+        assert_eq!(tracker.parse_approval_log(&erc721), None);
+    }
+
+    #[test]
+    fn test_unlimited_allowance() {
+        let allowance = |kind, value: U256| Allowance {
+            block: 1,
+            log_ordinal: 0,
+            tx_hash: B256::ZERO,
+            token: Address::ZERO,
+            spender: Address::ZERO,
+            kind,
+            value,
+        };
+        // uint96 max (UNI, COMP) counts as unlimited, a large finite amount does not
+        let uint96_max = (U256::from(1u64) << 96) - U256::from(1u64);
+        assert!(allowance(ApprovalKind::Erc20, U256::MAX).is_unlimited());
+        assert!(allowance(ApprovalKind::Erc20, uint96_max).is_unlimited());
+        assert!(!allowance(ApprovalKind::Erc20, uint96_max - U256::from(1u64)).is_unlimited());
+        assert!(allowance(ApprovalKind::ForAll, U256::from(1u64)).is_unlimited());
+        assert!(!allowance(ApprovalKind::ForAll, U256::ZERO).is_unlimited());
+        assert!(allowance(ApprovalKind::ForAll, U256::ZERO).is_revoked());
+    }
+}
@@ -0,0 +1,305 @@
+//! ERC1155 TransferSingle/TransferBatch event tracker
+//!
+//! Mirrors the ERC20 tracker for multi-token contracts: parses
+//! `TransferSingle` and `TransferBatch` logs from receipts and updates
+//! per-(contract, id, owner) deltas and snapshots for the watched
+//! (contract, id) pairs. Mints (from=0x0) and burns (to=0x0) only affect
+//! the other side. Ignores logs from reverted transactions.
+
+use crate::records::Erc1155Delta;
+use crate::tracker::{Tracker, TrackerContext};
+use crate::tracker_erc20::parse_address_from_topic;
+use crate::types::{Log, Receipt};
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+
+/// keccak256("TransferSingle(address,address,address,uint256,uint256)")
+const TRANSFER_SINGLE_TOPIC: [u8; 32] = [
+    0xc3, 0xd5, 0x81, 0x68, 0xc5, 0xae, 0x73, 0x97, 0x73, 0x1d, 0x06, 0x3d, 0x5b, 0xbf, 0x3d,
+    0x65, 0x78, 0x54, 0x42, 0x73, 0x43, 0xf4, 0xc0, 0x83, 0x24, 0x0f, 0x7a, 0xac, 0xaa, 0x2d,
+    0x0f, 0x62,
+];
+
+/// keccak256("TransferBatch(address,address,address,uint256[],uint256[])")
+const TRANSFER_BATCH_TOPIC: [u8; 32] = [
+    0x4a, 0x39, 0xdc, 0x06, 0xd4, 0xc0, 0xdb, 0xc6, 0x4b, 0x70, 0xaf, 0x90, 0xfd, 0x69, 0x8a,
+    0x23, 0x3a, 0x51, 0x8a, 0xa5, 0xd0, 0x7e, 0x59, 0x5d, 0x98, 0x3b, 0x8c, 0x05, 0x26, 0xc8,
+    0xf7, 0xfb,
+];
+
+/// A decoded ERC1155 transfer: from, to and (id, value) entries.
+type Erc1155Transfer = (Address, Address, Vec<(U256, U256)>);
+
+/// ERC1155 tracker that parses transfer events and updates token balances.
+pub struct Erc1155Tracker {
+    /// Watched (contract, id) pairs
+    pairs: HashSet<(Address, U256)>,
+}
+
+impl Erc1155Tracker {
+    /// Create a new ERC1155 tracker for the given (contract, id) pairs.
+    pub fn new(pairs: Vec<(Address, U256)>) -> Self {
+        Self {
+            pairs: pairs.into_iter().collect(),
+        }
+    }
+
+    /// Parse a TransferSingle or TransferBatch log.
+    ///
+    /// topics[1] = operator, topics[2] = from, topics[3] = to; the data holds
+    /// `(id, value)` or `(uint256[] ids, uint256[] values)`.
+    fn parse_transfer_log(&self, log: &Log) -> Result<Option<Erc1155Transfer>> {
+        if log.topics.len() != 4 {
+            return Ok(None);
+        }
+        let topic0 = log.topics[0].strip_prefix("0x").unwrap_or(&log.topics[0]);
+        let topic0 = match hex::decode(topic0) {
+            Ok(bytes) => bytes,
+            Err(_) => return Ok(None),
+        };
+        let entries = if topic0.as_slice() == TRANSFER_SINGLE_TOPIC {
+            if log.data.len() < 64 {
+                anyhow::bail!("TransferSingle data too short: {} bytes", log.data.len());
+            }
+            vec![(
+                U256::from_be_slice(&log.data[0..32]),
+                U256::from_be_slice(&log.data[32..64]),
+            )]
+        } else if topic0.as_slice() == TRANSFER_BATCH_TOPIC {
+            let ids = decode_uint_array(&log.data, 0).context("Invalid TransferBatch ids")?;
+            let values = decode_uint_array(&log.data, 32).context("Invalid TransferBatch values")?;
+            if ids.len() != values.len() {
+                anyhow::bail!(
+                    "TransferBatch has {} ids but {} values",
+                    ids.len(),
+                    values.len()
+                );
+            }
+            ids.into_iter().zip(values).collect()
+        } else {
+            return Ok(None);
+        };
+
+        let from = parse_address_from_topic(&log.topics[2])?;
+        let to = parse_address_from_topic(&log.topics[3])?;
+        Ok(Some((from, to, entries)))
+    }
+
+    /// Process receipts for a block and accumulate ERC1155 deltas.
+    fn process_receipts(
+        &self,
+        ctx: &TrackerContext<'_, '_>,
+        receipts: &[(B256, &Receipt)],
+    ) -> HashMap<(Address, U256, Address), Erc1155Delta> {
+        let mut acc: HashMap<(Address, U256, Address), Erc1155Delta> = HashMap::new();
+
+        for (_tx_hash, receipt) in receipts {
+            // Only process successful transactions (reverted txs have no effect)
+            if !receipt.is_success() {
+                continue;
+            }
+
+            for log in &receipt.logs {
+                let (from, to, entries) = match self.parse_transfer_log(log) {
+                    Ok(Some(t)) => t,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!("Failed to parse ERC1155 transfer log: {:?}", e);
+                        continue;
+                    }
+                };
+
+                for (id, value) in entries {
+                    // Only process watched (contract, id) pairs
+                    if value == U256::ZERO || !self.pairs.contains(&(log.address, id)) {
+                        continue;
+                    }
+
+                    // Handle receiver (to)
+                    if to != Address::ZERO && ctx.watched_eoas.contains(&to) {
+                        let entry = acc
+                            .entry((log.address, id, to))
+                            .or_insert_with(|| Erc1155Delta::new(ctx.block_number));
+                        entry.delta_plus = entry.delta_plus.saturating_add(value);
+                        entry.tx_count = entry.tx_count.saturating_add(1);
+                    }
+
+                    // Handle sender (from)
+                    if from != Address::ZERO && ctx.watched_eoas.contains(&from) {
+                        let entry = acc
+                            .entry((log.address, id, from))
+                            .or_insert_with(|| Erc1155Delta::new(ctx.block_number));
+                        entry.delta_minus = entry.delta_minus.saturating_add(value);
+                        entry.tx_count = entry.tx_count.saturating_add(1);
+                    }
+                }
+            }
+        }
+
+        acc
+    }
+}
+
+impl Tracker for Erc1155Tracker {
+    fn name(&self) -> &'static str {
+        "Erc1155Tracker"
+    }
+
+    fn process_block(
+        &self,
+        ctx: &mut TrackerContext<'_, '_>,
+        receipts: &[(B256, &Receipt)],
+    ) -> Result<()> {
+        if self.pairs.is_empty() {
+            return Ok(());
+        }
+
+        let acc = self.process_receipts(ctx, receipts);
+
+        for ((contract, id, owner), delta) in acc {
+            if !delta.has_changes() {
+                continue;
+            }
+
+            // Check coverage: only persist if we have watch metadata for this (contract, id, owner)
+            let meta = match ctx.store.get_erc1155_watch_meta(contract, id, owner)? {
+                Some(m) => m,
+                None => continue,
+            };
+            if ctx.block_number < meta.start_block {
+                continue;
+            }
+
+            ctx.batch
+                .put_erc1155_delta(contract, id, owner, &delta)
+                .with_context(|| {
+                    format!(
+                        "Failed to store ERC1155 delta for {:?} id {} owner {:?}",
+                        contract, id, owner
+                    )
+                })?;
+
+            let current = ctx
+                .batch
+                .get_erc1155_balance(contract, id, owner)?
+                .unwrap_or(U256::ZERO);
+            let new_balance = current
+                .saturating_add(delta.delta_plus)
+                .saturating_sub(delta.delta_minus);
+
+            ctx.batch
+                .put_erc1155_balance(contract, id, owner, new_balance)
+                .with_context(|| {
+                    format!(
+                        "Failed to store ERC1155 balance for {:?} id {} owner {:?}",
+                        contract, id, owner
+                    )
+                })?;
+            ctx.batch
+                .put_erc1155_snapshot(contract, id, owner, new_balance)
+                .with_context(|| {
+                    format!(
+                        "Failed to store ERC1155 snapshot for {:?} id {} owner {:?}",
+                        contract, id, owner
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode a `uint256[]` whose offset is stored in the head word at `head`.
+fn decode_uint_array(data: &[u8], head: usize) -> Option<Vec<U256>> {
+    let word = |offset: usize| data.get(offset..offset.checked_add(32)?);
+    let offset = usize::try_from(U256::from_be_slice(word(head)?)).ok()?;
+    let len = usize::try_from(U256::from_be_slice(word(offset)?)).ok()?;
+    (0..len)
+        .map(|i| {
+            let at = offset.checked_add(32)?.checked_add(i.checked_mul(32)?)?;
+            word(at).map(U256::from_be_slice)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    fn topic(addr: Address) -> String {
+        format!("0x{:0>64}", hex::encode(addr.as_slice()))
+    }
+
+    fn word(value: u64) -> Vec<u8> {
+        U256::from(value).to_be_bytes::<32>().to_vec()
+    }
+
+    #[test]
+    fn test_transfer_topics() {
+        assert_eq!(
+            keccak256("TransferSingle(address,address,address,uint256,uint256)").0,
+            TRANSFER_SINGLE_TOPIC
+        );
+        assert_eq!(
+            keccak256("TransferBatch(address,address,address,uint256[],uint256[])").0,
+            TRANSFER_BATCH_TOPIC
+        );
+    }
+
+    #[test]
+    fn test_parse_single_and_batch() {
+        let tracker = Erc1155Tracker::new(Vec::new());
+        let operator = Address::repeat_byte(0x01);
+        let from = Address::repeat_byte(0x11);
+        let to = Address::repeat_byte(0x22);
+        let log = |topic0: [u8; 32], data: Vec<u8>| Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                format!("0x{}", hex::encode(topic0)),
+                topic(operator),
+                topic(from),
+                topic(to),
+            ],
+            data,
+        };
+
+        let single = log(TRANSFER_SINGLE_TOPIC, [word(7), word(3)].concat());
+        assert_eq!(
+            tracker.parse_transfer_log(&single).unwrap(),
+            Some((from, to, vec![(U256::from(7u64), U256::from(3u64))]))
+        );
+
+        // ids at 0x40: [1, 2]; values at 0xa0: [10, 20]
+        let data = [
+            word(0x40),
+            word(0xa0),
+            word(2),
+            word(1),
+            word(2),
+            word(2),
+            word(10),
+            word(20),
+        ]
+        .concat();
+        let batch = log(TRANSFER_BATCH_TOPIC, data);
+        assert_eq!(
+            tracker.parse_transfer_log(&batch).unwrap(),
+            Some((
+                from,
+                to,
+                vec![
+                    (U256::from(1u64), U256::from(10u64)),
+                    (U256::from(2u64), U256::from(20u64))
+                ]
+            ))
+        );
+
+        // Truncated batch data is an error, other events are skipped
+        let truncated = log(TRANSFER_BATCH_TOPIC, [word(0x40), word(0xa0), word(2)].concat());
+        assert!(tracker.parse_transfer_log(&truncated).is_err());
+        let other = log([0u8; 32], Vec::new());
+        assert_eq!(tracker.parse_transfer_log(&other).unwrap(), None);
+    }
+}
@@ -3,21 +3,53 @@
 //! Parses ERC20 Transfer logs from receipts and updates per-(token, owner)
 //! deltas and snapshots. Handles mint (from=0x0), burn (to=0x0), and normal transfers.
 //! Ignores logs from reverted transactions.
+//!
+//! WETH-style tokens mint and burn on `Deposit(dst, wad)` / `Withdrawal(src, wad)`
+//! without a Transfer event. For watched tokens these are applied to the
+//! balance as well and recorded as conversions, linking the token side to
+//! the ETH side of the same transaction.
+//!
+//! Fee-on-transfer tokens and tokens with inaccurate events break this
+//! accounting, so the balances a block changed can be verified with
+//! `balanceOf` afterwards (see `Erc20Tracker::verify_balances`).
 
-use crate::records::Erc20Delta;
-use crate::tracker::{Tracker, TrackerContext};
+use crate::batch::BlockWriteBatch;
+use crate::config::TokenStrategy;
+use crate::records::{Conversion, ConversionKind, Erc20Delta};
+use crate::rpc::RpcClient;
+use crate::tracker::{logs_with_ordinals, Tracker, TrackerContext};
 use crate::types::{Log, Receipt};
 use alloy_primitives::{Address, B256, U256};
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::warn;
 
 /// keccak256("Transfer(address,address,uint256)")
-const TRANSFER_TOPIC: [u8; 32] = [
+///
+/// Shared by ERC721, whose `Transfer` also indexes the token ID (4 topics).
+pub(crate) const TRANSFER_TOPIC: [u8; 32] = [
     0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d,
     0xaa, 0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23,
     0xb3, 0xef,
 ];
 
+/// keccak256("Deposit(address,uint256)") (WETH wrap)
+const DEPOSIT_TOPIC: [u8; 32] = [
+    0xe1, 0xff, 0xfc, 0xc4, 0x92, 0x3d, 0x04, 0xb5, 0x59, 0xf4, 0xd2, 0x9a, 0x8b, 0xfc, 0x6c,
+    0xda, 0x04, 0xeb, 0x5b, 0x0d, 0x3c, 0x46, 0x07, 0x51, 0xc2, 0x40, 0x2c, 0x5c, 0x5c, 0xc9,
+    0x10, 0x9c,
+];
+
+/// keccak256("Withdrawal(address,uint256)") (WETH unwrap)
+const WITHDRAWAL_TOPIC: [u8; 32] = [
+    0x7f, 0xcf, 0x53, 0x2c, 0x15, 0xf0, 0xa6, 0xdb, 0x0b, 0xd6, 0xd0, 0xe0, 0x38, 0xbe, 0xa7,
+    0x1d, 0x30, 0xd8, 0x08, 0xc7, 0xd9, 0x8c, 0xb3, 0xbf, 0x72, 0x68, 0xa9, 0x5b, 0xf5, 0x08,
+    0x1b, 0x65,
+];
+
+/// Per-block ERC20 deltas and the conversions of watched owners.
+type BlockChanges = (HashMap<(Address, Address), Erc20Delta>, Vec<(Address, Conversion)>);
+
 /// Zero address (mint sender, burn receiver)
 fn zero_address() -> Address {
     Address::ZERO
@@ -36,8 +68,8 @@ impl Erc20Tracker {
         Self { tokens }
     }
 
-    /// Check if a log is an ERC20 Transfer event.
-    fn is_transfer_event(&self, log: &Log) -> bool {
+    /// Check if a log is a Transfer event (ERC20 or ERC721).
+    pub(crate) fn is_transfer_event(&self, log: &Log) -> bool {
         if log.topics.is_empty() {
             return false;
         }
@@ -58,9 +90,12 @@ impl Erc20Tracker {
 
     /// Parse from, to, value from a Transfer log.
     /// topics[1] = from (indexed, padded to 32 bytes), topics[2] = to, data = value
-    fn parse_transfer_log(&self, log: &Log) -> Result<(Address, Address, U256)> {
-        if log.topics.len() < 3 {
-            anyhow::bail!("Transfer log has insufficient topics");
+    pub(crate) fn parse_transfer_log(&self, log: &Log) -> Result<(Address, Address, U256)> {
+        if log.topics.len() != 3 {
+            anyhow::bail!(
+                "ERC20 Transfer log must have 3 topics, got {}",
+                log.topics.len()
+            );
         }
         let from = parse_address_from_topic(&log.topics[1])?;
         let to = parse_address_from_topic(&log.topics[2])?;
@@ -72,70 +107,256 @@ impl Erc20Tracker {
         Ok((from, to, value))
     }
 
-    /// Process receipts for a block and accumulate ERC20 deltas.
+    /// Parse a WETH `Deposit(dst, wad)` or `Withdrawal(src, wad)` log.
+    ///
+    /// Returns the conversion kind, the indexed account and the amount.
+    fn parse_conversion_log(&self, log: &Log) -> Option<(ConversionKind, Address, U256)> {
+        if log.topics.len() != 2 || log.data.len() < 32 {
+            return None;
+        }
+        let topic0 = log.topics[0].strip_prefix("0x").unwrap_or(&log.topics[0]);
+        let topic0 = hex::decode(topic0).ok()?;
+        let kind = if topic0.as_slice() == DEPOSIT_TOPIC {
+            ConversionKind::Wrap
+        } else if topic0.as_slice() == WITHDRAWAL_TOPIC {
+            ConversionKind::Unwrap
+        } else {
+            return None;
+        };
+        let account = parse_address_from_topic(&log.topics[1]).ok()?;
+        Some((kind, account, U256::from_be_slice(&log.data[0..32])))
+    }
+
+    /// Process receipts for a block and accumulate ERC20 deltas and conversions.
     fn process_receipts(
         &self,
-        ctx: &TrackerContext<'_>,
+        ctx: &TrackerContext<'_, '_>,
         receipts: &[(B256, &Receipt)],
-    ) -> Result<HashMap<(Address, Address), Erc20Delta>> {
+    ) -> Result<BlockChanges> {
         let mut acc: HashMap<(Address, Address), Erc20Delta> = HashMap::new();
+        let mut conversions = Vec::new();
         let watched_tokens: std::collections::HashSet<Address> =
             ctx.watched_tokens.iter().copied().collect();
         let watched_eoas: std::collections::HashSet<Address> =
             ctx.watched_eoas.iter().copied().collect();
 
-        for (_tx_hash, receipt) in receipts {
-            // Only process successful transactions (reverted txs have no effect)
-            if !receipt.is_success() {
+        for (log_ordinal, tx_hash, log) in logs_with_ordinals(receipts) {
+            if let Some((kind, account, amount)) = self.parse_conversion_log(log) {
+                if amount.is_zero()
+                    || !watched_tokens.contains(&log.address)
+                    || !watched_eoas.contains(&account)
+                {
+                    continue;
+                }
+                let entry = acc
+                    .entry((log.address, account))
+                    .or_insert_with(|| Erc20Delta::new(ctx.block_number));
+                match kind {
+                    ConversionKind::Wrap => {
+                        entry.delta_plus = entry.delta_plus.saturating_add(amount)
+                    }
+                    ConversionKind::Unwrap => {
+                        entry.delta_minus = entry.delta_minus.saturating_add(amount)
+                    }
+                }
+                entry.tx_count = entry.tx_count.saturating_add(1);
+                conversions.push((
+                    account,
+                    Conversion {
+                        block: ctx.block_number,
+                        log_ordinal,
+                        tx_hash: *tx_hash,
+                        token: log.address,
+                        kind,
+                        amount,
+                    },
+                ));
                 continue;
             }
 
-            for log in &receipt.logs {
-                if !self.is_transfer_event(log) {
+            if !self.is_transfer_event(log) {
+                continue;
+            }
+            // ERC721 Transfer: the token ID is the 4th topic (see Erc721Tracker)
+            if log.topics.len() == 4 {
+                continue;
+            }
+            // Only process logs from watched tokens
+            if !watched_tokens.contains(&log.address) {
+                continue;
+            }
+            let token = log.address;
+
+            let (from, to, value) = match self.parse_transfer_log(log) {
+                Ok(t) => t,
+                Err(e) => {
+                    tracing::warn!("Failed to parse Transfer log: {:?}", e);
                     continue;
                 }
-                // Only process logs from watched tokens
-                if !watched_tokens.contains(&log.address) {
+            };
+
+            if value == U256::ZERO {
+                continue;
+            }
+
+            // Handle receiver (to)
+            if to != zero_address() && watched_eoas.contains(&to) {
+                let entry = acc
+                    .entry((token, to))
+                    .or_insert_with(|| Erc20Delta::new(ctx.block_number));
+                entry.delta_plus = entry.delta_plus.saturating_add(value);
+                entry.tx_count = entry.tx_count.saturating_add(1);
+            }
+
+            // Handle sender (from)
+            if from != zero_address() && watched_eoas.contains(&from) {
+                let entry = acc
+                    .entry((token, from))
+                    .or_insert_with(|| Erc20Delta::new(ctx.block_number));
+                entry.delta_minus = entry.delta_minus.saturating_add(value);
+                entry.tx_count = entry.tx_count.saturating_add(1);
+            }
+        }
+
+        Ok((acc, conversions))
+    }
+
+    /// Verify the balances this block's token events changed with `balanceOf` at the block.
+    ///
+    /// Runs after `process_block`. With `verify_all`, every changed covered
+    /// (token, owner) is checked; otherwise only those of tokens already
+    /// marked non-standard. A mismatch is recorded as an adjustment and the
+    /// token is marked non-standard in its metadata, so it is verified in
+    /// every later block. Tokens with a poll or shares strategy in
+    /// `strategies` are skipped: they rebase without events, which
+    /// `apply_token_strategies` accounts for. Returns the number of
+    /// adjustments written.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn verify_balances(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        rpc: &RpcClient,
+        watched_eoas: &HashSet<Address>,
+        watched_tokens: &HashSet<Address>,
+        strategies: &HashMap<Address, TokenStrategy>,
+        receipts: &[(B256, Receipt)],
+        verify_all: bool,
+    ) -> Result<usize> {
+        let block = batch.block();
+        let block_tag = format!("0x{:x}", block);
+
+        // (token, owner) pairs touched by this block's Transfers, Deposits and Withdrawals
+        let mut touched = BTreeSet::new();
+        for (_tx_hash, receipt) in receipts {
+            for log in &receipt.logs {
+                let rebasing = strategies
+                    .get(&log.address)
+                    .is_some_and(|strategy| *strategy != TokenStrategy::Transfers);
+                if !watched_tokens.contains(&log.address) || rebasing {
                     continue;
                 }
-                let token = log.address;
-
-                let (from, to, value) = match self.parse_transfer_log(log) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        tracing::warn!("Failed to parse Transfer log: {:?}", e);
-                        continue;
+                let owners = if let Some((_, account, _)) = self.parse_conversion_log(log) {
+                    [account, zero_address()]
+                } else if self.is_transfer_event(log) {
+                    match self.parse_transfer_log(log) {
+                        Ok((from, to, _)) => [from, to],
+                        Err(_) => continue,
                     }
-                };
-
-                if value == U256::ZERO {
+                } else {
                     continue;
+                };
+                for owner in owners {
+                    if owner != zero_address() && watched_eoas.contains(&owner) {
+                        touched.insert((log.address, owner));
+                    }
                 }
+            }
+        }
 
-                // Handle receiver (to)
-                if to != zero_address() && watched_eoas.contains(&to) {
-                    let entry = acc
-                        .entry((token, to))
-                        .or_insert_with(|| Erc20Delta::new(ctx.block_number));
-                    entry.delta_plus = entry.delta_plus.saturating_add(value);
-                    entry.tx_count = entry.tx_count.saturating_add(1);
-                }
+        let mut adjusted = 0;
+        for (token, owner) in touched {
+            if batch
+                .get_token_watch_meta(token, owner)?
+                .is_none_or(|meta| !meta.covers(block))
+            {
+                continue;
+            }
+            let mut metadata = batch.get_token_metadata(token)?.unwrap_or_default();
+            if !verify_all && !metadata.non_standard {
+                continue;
+            }
 
-                // Handle sender (from)
-                if from != zero_address() && watched_eoas.contains(&from) {
-                    let entry = acc
-                        .entry((token, from))
-                        .or_insert_with(|| Erc20Delta::new(ctx.block_number));
-                    entry.delta_minus = entry.delta_minus.saturating_add(value);
-                    entry.tx_count = entry.tx_count.saturating_add(1);
+            let node_balance = match rpc.erc20_balance_of(token, owner, &block_tag).await {
+                Ok(balance) => balance,
+                Err(e) => {
+                    warn!(
+                        "ERC20 verification: balanceOf failed for token {:?} owner {:?}: {:?}",
+                        token, owner, e
+                    );
+                    continue;
                 }
+            };
+            if !apply_adjustment(batch, token, owner, node_balance)? {
+                continue;
+            }
+            adjusted += 1;
+            warn!(
+                "ERC20 token {:?} owner {:?} at block {}: Transfer events disagree with balanceOf {}",
+                token, owner, block, node_balance
+            );
+
+            if !metadata.non_standard {
+                metadata.non_standard = true;
+                batch
+                    .put_token_metadata(token, &metadata)
+                    .context("Failed to mark token as non-standard")?;
             }
         }
 
-        Ok(acc)
+        Ok(adjusted)
     }
 }
 
+/// Record the difference between `node_balance` and the stored balance as an adjustment.
+///
+/// The difference is merged into this block's ERC20 delta, and the balance
+/// and snapshot are set to the node's value. Returns false if they agree.
+fn apply_adjustment(
+    batch: &mut BlockWriteBatch<'_>,
+    token: Address,
+    owner: Address,
+    node_balance: U256,
+) -> Result<bool> {
+    let stored = batch.get_erc20_balance(token, owner)?.unwrap_or(U256::ZERO);
+    if stored == node_balance {
+        return Ok(false);
+    }
+
+    let mut delta = batch
+        .get_erc20_delta(token, owner)?
+        .unwrap_or_else(|| Erc20Delta::new(batch.block()));
+    if node_balance > stored {
+        let diff = node_balance - stored;
+        delta.delta_plus = delta.delta_plus.saturating_add(diff);
+        delta.adjustment_plus = delta.adjustment_plus.saturating_add(diff);
+    } else {
+        let diff = stored - node_balance;
+        delta.delta_minus = delta.delta_minus.saturating_add(diff);
+        delta.adjustment_minus = delta.adjustment_minus.saturating_add(diff);
+    }
+
+    batch
+        .put_erc20_delta(token, owner, &delta)
+        .context("Failed to store ERC20 adjustment delta")?;
+    batch
+        .put_erc20_balance(token, owner, node_balance)
+        .context("Failed to store verified ERC20 balance")?;
+    batch
+        .put_erc20_snapshot(token, owner, node_balance)
+        .context("Failed to store verified ERC20 snapshot")?;
+    Ok(true)
+}
+
 impl Tracker for Erc20Tracker {
     fn name(&self) -> &'static str {
         "Erc20Tracker"
@@ -143,33 +364,47 @@ impl Tracker for Erc20Tracker {
 
     fn process_block(
         &self,
-        ctx: &TrackerContext<'_>,
+        ctx: &mut TrackerContext<'_, '_>,
         receipts: &[(B256, &Receipt)],
     ) -> Result<()> {
         if ctx.watched_tokens.is_empty() {
             return Ok(());
         }
 
-        let acc = self.process_receipts(ctx, receipts)?;
+        let (acc, conversions) = self.process_receipts(ctx, receipts)?;
+
+        for (owner, conversion) in conversions {
+            let covered = ctx
+                .batch
+                .get_token_watch_meta(conversion.token, owner)?
+                .is_some_and(|meta| meta.covers(ctx.block_number));
+            if covered {
+                ctx.batch.put_conversion(owner, &conversion).with_context(|| {
+                    format!("Failed to store conversion for {:?}", owner)
+                })?;
+            }
+        }
 
         for ((token, owner), delta) in acc {
             if !delta.has_changes() {
                 continue;
             }
 
-            // Check coverage: only persist if we have TokenWatchMeta for this (token, owner)
-            let meta = match ctx.store.get_token_watch_meta(token, owner)? {
+            // Check coverage: only persist if we have TokenWatchMeta for this (token, owner).
+            // Read through the batch: token discovery may have registered the pair in this block.
+            let meta = match ctx.batch.get_token_watch_meta(token, owner)? {
                 Some(m) => m,
                 None => continue, // Not tracking this (token, owner), skip
             };
 
-            if ctx.block_number < meta.start_block {
+            // Not before the pair's start, nor after its token was unwatched
+            if !meta.covers(ctx.block_number) {
                 continue;
             }
 
             // Persist delta
-            ctx.store
-                .put_erc20_delta(token, owner, ctx.block_number, &delta)
+            ctx.batch
+                .put_erc20_delta(token, owner, &delta)
                 .with_context(|| {
                     format!(
                         "Failed to store ERC20 delta for token {:?} owner {:?}",
@@ -179,7 +414,7 @@ impl Tracker for Erc20Tracker {
 
             // Get current balance and apply delta
             let current = ctx
-                .store
+                .batch
                 .get_erc20_balance(token, owner)?
                 .unwrap_or(U256::ZERO);
             let new_balance = current
@@ -187,7 +422,7 @@ impl Tracker for Erc20Tracker {
                 .saturating_sub(delta.delta_minus);
 
             // Update current balance and snapshot
-            ctx.store
+            ctx.batch
                 .put_erc20_balance(token, owner, new_balance)
                 .with_context(|| {
                     format!(
@@ -195,8 +430,8 @@ impl Tracker for Erc20Tracker {
                         token, owner
                     )
                 })?;
-            ctx.store
-                .put_erc20_snapshot(token, owner, ctx.block_number, new_balance)
+            ctx.batch
+                .put_erc20_snapshot(token, owner, new_balance)
                 .with_context(|| {
                     format!(
                         "Failed to store ERC20 snapshot for token {:?} owner {:?}",
@@ -210,7 +445,7 @@ impl Tracker for Erc20Tracker {
 }
 
 /// Parse a 32-byte hex topic into an Address (last 20 bytes).
-fn parse_address_from_topic(topic: &str) -> Result<Address> {
+pub(crate) fn parse_address_from_topic(topic: &str) -> Result<Address> {
     let s = topic.strip_prefix("0x").unwrap_or(topic);
     let s = if s.len() % 2 == 1 { format!("0{}", s) } else { s.to_string() };
     let bytes = hex::decode(&s).context("Invalid hex in topic")?;
@@ -243,6 +478,52 @@ mod tests {
         assert_eq!(addr, expected);
     }
 
+    #[test]
+    fn test_erc721_transfer_is_not_parsed_as_erc20() {
+        let tracker = Erc20Tracker::new(Vec::new());
+        let topic = |byte: &str| format!("0x{}", byte.repeat(32));
+        let log = Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                format!("0x{}", hex::encode(TRANSFER_TOPIC)),
+                topic("00"),
+                topic("11"),
+                topic("00"),
+            ],
+            data: Vec::new(),
+        };
+        assert!(tracker.is_transfer_event(&log));
+        assert!(tracker.parse_transfer_log(&log).is_err());
+    }
+
+    #[test]
+    fn test_parse_conversion_log() {
+        use alloy_primitives::keccak256;
+
+        assert_eq!(keccak256("Deposit(address,uint256)"), B256::from(DEPOSIT_TOPIC));
+        assert_eq!(keccak256("Withdrawal(address,uint256)"), B256::from(WITHDRAWAL_TOPIC));
+
+        let tracker = Erc20Tracker::new(Vec::new());
+        let account = Address::repeat_byte(0x11);
+        let log = |topic0: [u8; 32]| Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                format!("0x{}", hex::encode(topic0)),
+                format!("0x{:0>64}", hex::encode(account)),
+            ],
+            data: U256::from(5u64).to_be_bytes::<32>().to_vec(),
+        };
+        assert_eq!(
+            tracker.parse_conversion_log(&log(DEPOSIT_TOPIC)),
+            Some((ConversionKind::Wrap, account, U256::from(5u64)))
+        );
+        assert_eq!(
+            tracker.parse_conversion_log(&log(WITHDRAWAL_TOPIC)),
+            Some((ConversionKind::Unwrap, account, U256::from(5u64)))
+        );
+        assert_eq!(tracker.parse_conversion_log(&log(TRANSFER_TOPIC)), None);
+    }
+
     #[test]
     fn test_zero_address() {
         assert_eq!(zero_address(), Address::ZERO);
@@ -258,4 +539,99 @@ mod tests {
         d.delta_minus = U256::from(50u64);
         assert!(d.has_changes());
     }
+
+    #[test]
+    fn test_fee_on_transfer_adjustment() {
+        use crate::store::RocksStateStore;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let token = Address::repeat_byte(0xaa);
+        let owner = Address::repeat_byte(0x11);
+
+        // Transfer event said +100, but the token took a 2% fee
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        let mut delta = Erc20Delta::new(101);
+        delta.delta_plus = U256::from(100u64);
+        delta.tx_count = 1;
+        batch.put_erc20_delta(token, owner, &delta).unwrap();
+        batch.put_erc20_balance(token, owner, U256::from(100u64)).unwrap();
+
+        assert!(apply_adjustment(&mut batch, token, owner, U256::from(98u64)).unwrap());
+        assert!(!apply_adjustment(&mut batch, token, owner, U256::from(98u64)).unwrap());
+        let delta = batch.get_erc20_delta(token, owner).unwrap().unwrap();
+        assert_eq!(delta.delta_plus, U256::from(100u64));
+        assert_eq!(delta.delta_minus, U256::from(2u64));
+        assert_eq!(delta.adjustment_minus, U256::from(2u64));
+        assert_eq!(batch.get_erc20_balance(token, owner).unwrap(), Some(U256::from(98u64)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_balances_skips_rebasing_tokens() {
+        use crate::records::TokenWatchMeta;
+        use crate::rpc::mock::{mock_rpc, word};
+        use crate::store::{RocksStateStore, StateStore};
+        use serde_json::json;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let (plain, polled) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let (sender, owner) = (Address::repeat_byte(0x22), Address::repeat_byte(0x11));
+        for token in [plain, polled] {
+            store.put_token_watch_meta(token, owner, &TokenWatchMeta::new(100)).unwrap();
+        }
+
+        // Both tokens sent 100 to the owner; the node reports 150 after a rebase
+        let transfer = |token: Address| {
+            let topic = |addr: Address| format!("0x{:0>64}", hex::encode(addr));
+            Log {
+                address: token,
+                topics: vec![
+                    format!("0x{}", hex::encode(TRANSFER_TOPIC)),
+                    topic(sender),
+                    topic(owner),
+                ],
+                data: U256::from(100u64).to_be_bytes::<32>().to_vec(),
+            }
+        };
+        let receipt = Receipt {
+            status: 1,
+            gas_used: U256::from(21000u64),
+            effective_gas_price: None,
+            logs: vec![transfer(plain), transfer(polled)],
+            blob_gas_used: None,
+            blob_gas_price: None,
+            l1_fee: None,
+            transaction_hash: None,
+        };
+        let rpc = mock_rpc(|method, _| match method {
+            "eth_call" => Ok(word(150)),
+            _ => Err(json!({"code": -32601, "message": "unexpected"})),
+        });
+
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        for token in [plain, polled] {
+            batch.put_erc20_balance(token, owner, U256::from(100u64)).unwrap();
+        }
+        let tracker = Erc20Tracker::new(vec![plain, polled]);
+        let strategies = HashMap::from([(polled, TokenStrategy::Poll(10))]);
+        let adjusted = tracker
+            .verify_balances(
+                &mut batch,
+                &rpc,
+                &HashSet::from([owner]),
+                &HashSet::from([plain, polled]),
+                &strategies,
+                &[(B256::ZERO, receipt)],
+                true,
+            )
+            .await
+            .unwrap();
+
+        // Only the plain token is adjusted and marked non-standard
+        assert_eq!(adjusted, 1);
+        assert!(batch.get_token_metadata(plain).unwrap().unwrap().non_standard);
+        assert_eq!(batch.get_token_metadata(polled).unwrap(), None);
+        assert_eq!(batch.get_erc20_balance(polled, owner).unwrap(), Some(U256::from(100u64)));
+    }
 }
@@ -0,0 +1,241 @@
+//! ERC721 Transfer event tracker
+//!
+//! ERC721 `Transfer(address,address,uint256)` has the same topic0 as the
+//! ERC20 event, but indexes the token ID as well (4 topics, empty data).
+//! This tracker picks up those logs from any contract and maintains, for
+//! each watched address, its current holdings and a transfer history from
+//! which holdings at any earlier block can be replayed.
+//!
+//! Only transfers since an address's watch start are observed, so tokens it
+//! received earlier are not listed. Ignores logs from reverted transactions.
+
+use crate::records::{NftHolding, NftTransfer, TxDirection};
+use crate::tracker::{logs_with_ordinals, Tracker, TrackerContext};
+use crate::tracker_erc20::{parse_address_from_topic, TRANSFER_TOPIC};
+use crate::types::{Log, Receipt};
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{Context, Result};
+
+/// ERC721 tracker that records NFT ownership of watched addresses.
+#[derive(Debug, Default)]
+pub struct Erc721Tracker;
+
+impl Erc721Tracker {
+    /// Create a new ERC721 tracker.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parse from, to, token ID from an ERC721 Transfer log.
+    ///
+    /// Returns None for anything else, including ERC20 Transfers (3 topics).
+    fn parse_transfer_log(&self, log: &Log) -> Option<(Address, Address, U256)> {
+        if log.topics.len() != 4 {
+            return None;
+        }
+        let topic0 = log.topics[0].strip_prefix("0x").unwrap_or(&log.topics[0]);
+        if hex::decode(topic0).ok()?.as_slice() != TRANSFER_TOPIC {
+            return None;
+        }
+        let from = parse_address_from_topic(&log.topics[1]).ok()?;
+        let to = parse_address_from_topic(&log.topics[2]).ok()?;
+        let token_id = hex::decode(log.topics[3].strip_prefix("0x").unwrap_or(&log.topics[3]))
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .map(|bytes| U256::from_be_slice(&bytes))?;
+        Some((from, to, token_id))
+    }
+}
+
+impl Tracker for Erc721Tracker {
+    fn name(&self) -> &'static str {
+        "Erc721Tracker"
+    }
+
+    fn process_block(
+        &self,
+        ctx: &mut TrackerContext<'_, '_>,
+        receipts: &[(B256, &Receipt)],
+    ) -> Result<()> {
+        let block = ctx.block_number;
+        for (log_ordinal, tx_hash, log) in logs_with_ordinals(receipts) {
+            let Some((from, to, token_id)) = self.parse_transfer_log(log) else {
+                continue;
+            };
+            let transfer = |direction, counterparty| NftTransfer {
+                block,
+                log_ordinal,
+                tx_hash: *tx_hash,
+                contract: log.address,
+                token_id,
+                direction,
+                counterparty,
+            };
+
+            // Transfer to self: history only, ownership is unchanged
+            if from == to {
+                if ctx.is_covered(from)? {
+                    let record = transfer(TxDirection::SelfTransfer, from);
+                    ctx.batch.put_nft_transfer(from, &record)?;
+                }
+                continue;
+            }
+
+            if ctx.is_covered(to)? {
+                let record = transfer(TxDirection::In, from);
+                ctx.batch.put_nft_transfer(to, &record)?;
+                ctx.batch
+                    .put_nft_holding(
+                        to,
+                        &NftHolding {
+                            contract: log.address,
+                            token_id,
+                            since_block: block,
+                        },
+                    )
+                    .with_context(|| {
+                        format!("Failed to store NFT {:?} #{} for {:?}", log.address, token_id, to)
+                    })?;
+            }
+
+            if ctx.is_covered(from)? {
+                let record = transfer(TxDirection::Out, to);
+                ctx.batch.put_nft_transfer(from, &record)?;
+                ctx.batch
+                    .delete_nft_holding(from, log.address, token_id)
+                    .with_context(|| {
+                        format!("Failed to remove NFT {:?} #{} for {:?}", log.address, token_id, from)
+                    })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::BlockWriteBatch;
+    use crate::records::WatchMeta;
+    use crate::rpc::RpcClient;
+    use crate::store::{RocksStateStore, StateStore};
+    use std::collections::HashSet;
+    use tempfile::TempDir;
+
+    fn topic(addr: Address) -> String {
+        format!("0x{:0>64}", hex::encode(addr.as_slice()))
+    }
+
+    #[test]
+    fn test_parse_erc721_transfer() {
+        let tracker = Erc721Tracker::new();
+        let from = Address::repeat_byte(0x11);
+        let to = Address::repeat_byte(0x22);
+        let mut log = Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                format!("0x{}", hex::encode(TRANSFER_TOPIC)),
+                topic(from),
+                topic(to),
+                format!("0x{:064x}", 42),
+            ],
+            data: Vec::new(),
+        };
+        assert_eq!(tracker.parse_transfer_log(&log), Some((from, to, U256::from(42u64))));
+
+        // ERC20 Transfer: value in data, only 3 topics
+        log.topics.pop();
+        log.data = U256::from(42u64).to_be_bytes::<32>().to_vec();
+        assert_eq!(tracker.parse_transfer_log(&log), None);
+    }
+
+    #[test]
+    fn test_process_block_records_in_out_and_self_transfers() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let owner = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let contract = Address::repeat_byte(0xaa);
+        store.put_watch_meta(owner, &WatchMeta::new(100)).unwrap();
+        let held = NftHolding {
+            contract,
+            token_id: U256::from(2u64),
+            since_block: 100,
+        };
+        store.put_nft_holding(owner, &held).unwrap();
+
+        let log = |from: Address, to: Address, token_id: u64| Log {
+            address: contract,
+            topics: vec![
+                format!("0x{}", hex::encode(TRANSFER_TOPIC)),
+                topic(from),
+                topic(to),
+                format!("0x{:064x}", token_id),
+            ],
+            data: Vec::new(),
+        };
+        let receipt = Receipt {
+            status: 1,
+            gas_used: U256::from(21000u64),
+            effective_gas_price: None,
+            logs: vec![
+                log(Address::ZERO, owner, 1), // minted to owner
+                log(owner, other, 2),         // sent away
+                log(owner, owner, 3),         // to self
+                log(other, Address::ZERO, 4), // unwatched owner
+            ],
+            blob_gas_used: None,
+            blob_gas_price: None,
+            l1_fee: None,
+            transaction_hash: None,
+        };
+
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let watched_eoas = HashSet::from([owner]);
+        let watched_tokens = HashSet::new();
+        let mut batch = BlockWriteBatch::new(&store, 101);
+        let mut ctx = TrackerContext {
+            store: &store,
+            batch: &mut batch,
+            rpc: &rpc,
+            watched_eoas: &watched_eoas,
+            watched_tokens: &watched_tokens,
+            block_number: 101,
+        };
+        Erc721Tracker::new()
+            .process_block(&mut ctx, &[(B256::repeat_byte(0x01), &receipt)])
+            .unwrap();
+        batch.commit().unwrap();
+
+        // Only the minted token is held; the self-transfer does not change ownership
+        let holdings = store.get_nft_holdings(owner).unwrap();
+        assert_eq!(
+            holdings,
+            vec![NftHolding {
+                contract,
+                token_id: U256::from(1u64),
+                since_block: 101,
+            }]
+        );
+        let transfers = store.get_nft_transfers_in_range(owner, 101, 101).unwrap();
+        let summary: Vec<_> = transfers
+            .iter()
+            .map(|t| (t.log_ordinal, t.token_id.to::<u64>(), t.direction, t.counterparty))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 1, TxDirection::In, Address::ZERO),
+                (1, 2, TxDirection::Out, other),
+                (2, 3, TxDirection::SelfTransfer, owner),
+            ]
+        );
+        assert!(store.get_nft_transfers_in_range(other, 0, 200).unwrap().is_empty());
+
+        // Replaying the history gives the same holdings as the tracked state
+        assert_eq!(
+            store.get_nft_holdings_at(owner, 101).unwrap(),
+            Some(holdings)
+        );
+    }
+}
@@ -0,0 +1,625 @@
+//! Generic event-log tracker driven by user-supplied ABI definitions
+//!
+//! `Erc20Tracker` hard-codes one event. This tracker instead loads event
+//! definitions from a file, either as human-readable signatures:
+//!
+//! ```text
+//! # One event per line, optionally restricted to emitting contracts
+//! event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)
+//! Sync(uint112 reserve0, uint112 reserve1) @ 0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc
+//! ```
+//!
+//! or as a JSON ABI (an array of fragments; non-event entries are ignored,
+//! and an optional non-standard `"contracts"` array restricts the emitters).
+//!
+//! Topic hashes are computed from the canonical signatures. Matching logs of
+//! successful transactions are decoded (indexed parameters from the topics,
+//! the rest from the data) and stored in the `events` column family:
+//!
+//! - Definitions with `contracts` store every matching event those contracts emit
+//! - Definitions without `contracts` store events from any contract, but only
+//!   if one of their address parameters is a watched address
+//!
+//! Supported parameter types are `address`, `bool`, `uint<M>`, `int<M>`,
+//! `bytes<M>`, `bytes` and `string`. Indexed `bytes`/`string` parameters are
+//! stored as the topic hash, as the value itself is not in the log.
+
+use crate::records::{DecodedValue, EventParamValue, EventRecord};
+use crate::tracker::{logs_with_ordinals, Tracker, TrackerContext};
+use crate::types::{Log, Receipt};
+use alloy_primitives::{keccak256, Address, B256, U256};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// ABI parameter type supported by the decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbiType {
+    Address,
+    Bool,
+    /// Unsigned integer with the given bit size
+    Uint(u16),
+    /// Signed integer with the given bit size
+    Int(u16),
+    /// Fixed-size byte array (`bytes1`..`bytes32`)
+    FixedBytes(u8),
+    /// Dynamic byte array
+    Bytes,
+    /// Dynamic UTF-8 string
+    String,
+}
+
+impl AbiType {
+    /// Parse a Solidity type name.
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let bits = |rest: &str| -> Result<u16> {
+            if rest.is_empty() {
+                return Ok(256);
+            }
+            let bits: u16 = rest
+                .parse()
+                .with_context(|| format!("Invalid integer type: {}", s))?;
+            if bits == 0 || bits > 256 || !bits.is_multiple_of(8) {
+                anyhow::bail!("Invalid integer size in type: {}", s);
+            }
+            Ok(bits)
+        };
+
+        match s {
+            "address" => Ok(AbiType::Address),
+            "bool" => Ok(AbiType::Bool),
+            "bytes" => Ok(AbiType::Bytes),
+            "string" => Ok(AbiType::String),
+            _ if s.contains('[') || s.starts_with('(') || s.starts_with("tuple") => {
+                anyhow::bail!("Unsupported ABI type (arrays and tuples are not supported): {}", s)
+            }
+            _ if s.starts_with("uint") => Ok(AbiType::Uint(bits(&s[4..])?)),
+            _ if s.starts_with("int") => Ok(AbiType::Int(bits(&s[3..])?)),
+            _ if s.starts_with("bytes") => {
+                let size: u8 = s[5..]
+                    .parse()
+                    .with_context(|| format!("Invalid bytes type: {}", s))?;
+                if size == 0 || size > 32 {
+                    anyhow::bail!("Invalid bytes size in type: {}", s);
+                }
+                Ok(AbiType::FixedBytes(size))
+            }
+            _ => anyhow::bail!("Unsupported ABI type: {}", s),
+        }
+    }
+
+    /// Canonical type name used in the event signature.
+    pub fn canonical(&self) -> String {
+        match self {
+            AbiType::Address => "address".to_string(),
+            AbiType::Bool => "bool".to_string(),
+            AbiType::Uint(bits) => format!("uint{}", bits),
+            AbiType::Int(bits) => format!("int{}", bits),
+            AbiType::FixedBytes(size) => format!("bytes{}", size),
+            AbiType::Bytes => "bytes".to_string(),
+            AbiType::String => "string".to_string(),
+        }
+    }
+
+    /// Whether the value is stored out of line (head holds an offset).
+    fn is_dynamic(&self) -> bool {
+        matches!(self, AbiType::Bytes | AbiType::String)
+    }
+
+    /// Decode a single 32-byte word of a static type.
+    fn decode_word(&self, word: &[u8]) -> DecodedValue {
+        match self {
+            AbiType::Address => DecodedValue::Address(Address::from_slice(&word[12..32])),
+            AbiType::Bool => DecodedValue::Bool(word.iter().any(|b| *b != 0)),
+            AbiType::Uint(_) => DecodedValue::Uint(U256::from_be_slice(word)),
+            AbiType::Int(_) => DecodedValue::Int(U256::from_be_slice(word)),
+            AbiType::FixedBytes(size) => DecodedValue::Bytes(word[..*size as usize].to_vec()),
+            // Indexed dynamic values are only present as their keccak256 hash
+            AbiType::Bytes | AbiType::String => DecodedValue::Hash(B256::from_slice(word)),
+        }
+    }
+}
+
+/// One parameter of an event definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventParam {
+    pub name: String,
+    pub kind: AbiType,
+    pub indexed: bool,
+}
+
+/// A user-supplied event definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventDefinition {
+    /// Event name (e.g. "Swap")
+    pub name: String,
+    /// Canonical signature (e.g. "Swap(address,uint256,...)")
+    pub signature: String,
+    /// keccak256 of the canonical signature (topic0)
+    pub topic0: B256,
+    /// Parameters in declaration order
+    pub params: Vec<EventParam>,
+    /// Emitting contracts to track (empty = any contract with a watched participant)
+    pub contracts: Vec<Address>,
+}
+
+impl EventDefinition {
+    /// Build a definition from its name and parameters.
+    pub fn new(name: String, params: Vec<EventParam>, contracts: Vec<Address>) -> Self {
+        let types: Vec<String> = params.iter().map(|p| p.kind.canonical()).collect();
+        let signature = format!("{}({})", name, types.join(","));
+        let topic0 = keccak256(signature.as_bytes());
+        Self {
+            name,
+            signature,
+            topic0,
+            params,
+            contracts,
+        }
+    }
+
+    /// Parse a human-readable signature, e.g.
+    /// `event Transfer(address indexed from, address indexed to, uint256 value)`.
+    pub fn parse_signature(signature: &str, contracts: Vec<Address>) -> Result<Self> {
+        let s = signature.trim();
+        let s = s.strip_prefix("event ").unwrap_or(s).trim();
+        let open = s
+            .find('(')
+            .with_context(|| format!("Missing '(' in event signature: {}", signature))?;
+        let close = s
+            .rfind(')')
+            .with_context(|| format!("Missing ')' in event signature: {}", signature))?;
+        let name = s[..open].trim();
+        if name.is_empty() {
+            anyhow::bail!("Missing event name in signature: {}", signature);
+        }
+
+        let inner = s[open + 1..close].trim();
+        let mut params = Vec::new();
+        if !inner.is_empty() {
+            for (i, part) in inner.split(',').enumerate() {
+                let tokens: Vec<&str> = part.split_whitespace().collect();
+                let (kind, rest) = tokens
+                    .split_first()
+                    .with_context(|| format!("Empty parameter in event signature: {}", signature))?;
+                let indexed = rest.first() == Some(&"indexed");
+                let name = rest
+                    .iter()
+                    .find(|t| **t != "indexed")
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| format!("arg{}", i));
+                params.push(EventParam {
+                    name,
+                    kind: AbiType::parse(kind)?,
+                    indexed,
+                });
+            }
+        }
+
+        let definition = Self::new(name.to_string(), params, contracts);
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    /// Parse a JSON ABI event fragment (`{"type": "event", "name": ..., "inputs": [...]}`).
+    ///
+    /// An optional `"contracts"` array restricts the emitting contracts.
+    pub fn from_abi_fragment(fragment: &Value) -> Result<Self> {
+        let name = fragment
+            .get("name")
+            .and_then(|n| n.as_str())
+            .context("ABI event fragment has no name")?;
+        if fragment.get("anonymous").and_then(|a| a.as_bool()) == Some(true) {
+            anyhow::bail!("Anonymous event {} has no topic0 and cannot be tracked", name);
+        }
+
+        let mut params = Vec::new();
+        for (i, input) in fragment
+            .get("inputs")
+            .and_then(|i| i.as_array())
+            .map(|i| i.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+        {
+            let kind = input
+                .get("type")
+                .and_then(|t| t.as_str())
+                .with_context(|| format!("Input {} of event {} has no type", i, name))?;
+            let param_name = input
+                .get("name")
+                .and_then(|n| n.as_str())
+                .filter(|n| !n.is_empty())
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("arg{}", i));
+            params.push(EventParam {
+                name: param_name,
+                kind: AbiType::parse(kind)
+                    .with_context(|| format!("Unsupported input of event {}", name))?,
+                indexed: input.get("indexed").and_then(|x| x.as_bool()).unwrap_or(false),
+            });
+        }
+
+        let contracts = match fragment.get("contracts") {
+            Some(list) => list
+                .as_array()
+                .context("\"contracts\" must be an array")?
+                .iter()
+                .map(|c| {
+                    c.as_str()
+                        .context("Contract address must be a string")?
+                        .parse::<Address>()
+                        .with_context(|| format!("Invalid contract address: {}", c))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        let definition = Self::new(name.to_string(), params, contracts);
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let indexed = self.params.iter().filter(|p| p.indexed).count();
+        if indexed > 3 {
+            anyhow::bail!("Event {} has {} indexed parameters (max 3)", self.name, indexed);
+        }
+        Ok(())
+    }
+
+    /// Number of topics a matching log carries (topic0 + indexed parameters).
+    fn topic_count(&self) -> usize {
+        1 + self.params.iter().filter(|p| p.indexed).count()
+    }
+
+    /// Decode a log's parameters in declaration order.
+    pub fn decode(&self, topics: &[B256], data: &[u8]) -> Result<Vec<EventParamValue>> {
+        if topics.len() != self.topic_count() {
+            anyhow::bail!(
+                "Event {} expects {} topics, log has {}",
+                self.name,
+                self.topic_count(),
+                topics.len()
+            );
+        }
+
+        let mut topic_iter = topics[1..].iter();
+        let mut head = 0usize;
+        let mut values = Vec::with_capacity(self.params.len());
+        for param in &self.params {
+            let value = if param.indexed {
+                let topic = topic_iter.next().context("Missing indexed topic")?;
+                param.kind.decode_word(topic.as_slice())
+            } else {
+                let word = data_word(data, head)
+                    .with_context(|| format!("Data too short for parameter {}", param.name))?;
+                head += 32;
+                if param.kind.is_dynamic() {
+                    let bytes = dynamic_bytes(data, word)
+                        .with_context(|| format!("Invalid dynamic parameter {}", param.name))?;
+                    match param.kind {
+                        AbiType::String => {
+                            DecodedValue::String(String::from_utf8_lossy(&bytes).into_owned())
+                        }
+                        _ => DecodedValue::Bytes(bytes),
+                    }
+                } else {
+                    param.kind.decode_word(word)
+                }
+            };
+            values.push(EventParamValue {
+                name: param.name.clone(),
+                kind: param.kind.canonical(),
+                value,
+            });
+        }
+        Ok(values)
+    }
+}
+
+/// The 32-byte word at `offset` in `data`.
+fn data_word(data: &[u8], offset: usize) -> Option<&[u8]> {
+    data.get(offset..offset.checked_add(32)?)
+}
+
+/// Read a dynamic `bytes`/`string` value whose head word is `offset_word`.
+fn dynamic_bytes(data: &[u8], offset_word: &[u8]) -> Result<Vec<u8>> {
+    let offset = usize::try_from(U256::from_be_slice(offset_word)).context("Offset too large")?;
+    let len_word = data_word(data, offset).context("Offset out of range")?;
+    let len = usize::try_from(U256::from_be_slice(len_word)).context("Length too large")?;
+    let start = offset + 32;
+    data.get(start..start.checked_add(len).context("Length too large")?)
+        .map(|b| b.to_vec())
+        .context("Length out of range")
+}
+
+/// Parse an event definition file (JSON ABI or one signature per line).
+pub fn parse_event_definitions(content: &str) -> Result<Vec<EventDefinition>> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        let json: Value = serde_json::from_str(content).context("Invalid JSON ABI")?;
+        let fragments = match &json {
+            Value::Array(items) => items.clone(),
+            _ => vec![json],
+        };
+        return fragments
+            .iter()
+            .filter(|f| f.get("type").and_then(|t| t.as_str()) == Some("event"))
+            .map(EventDefinition::from_abi_fragment)
+            .collect();
+    }
+
+    let mut definitions = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (signature, contracts) = match line.split_once('@') {
+            Some((signature, contracts)) => (signature, contracts),
+            None => (line, ""),
+        };
+        let contracts = contracts
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                c.parse::<Address>()
+                    .with_context(|| format!("Invalid contract address on line {}: {}", line_no + 1, c))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        definitions.push(
+            EventDefinition::parse_signature(signature, contracts)
+                .with_context(|| format!("Invalid event definition on line {}", line_no + 1))?,
+        );
+    }
+    Ok(definitions)
+}
+
+/// Load event definitions from a file.
+pub fn load_event_definitions(path: &Path) -> Result<Vec<EventDefinition>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read event definitions from {:?}", path))?;
+    parse_event_definitions(&content)
+}
+
+/// Parse a 0x-prefixed 32-byte topic.
+fn parse_topic(topic: &str) -> Option<B256> {
+    let bytes = hex::decode(topic.strip_prefix("0x").unwrap_or(topic)).ok()?;
+    (bytes.len() == 32).then(|| B256::from_slice(&bytes))
+}
+
+/// Tracker that decodes and stores user-defined events.
+pub struct EventTracker {
+    /// topic0 -> definitions sharing it (e.g. ERC20 and ERC721 `Transfer`,
+    /// which differ only in how many parameters are indexed)
+    definitions: HashMap<B256, Vec<EventDefinition>>,
+}
+
+impl EventTracker {
+    /// Create a tracker for the given definitions.
+    pub fn new(definitions: Vec<EventDefinition>) -> Self {
+        let mut by_topic: HashMap<B256, Vec<EventDefinition>> = HashMap::new();
+        for definition in definitions {
+            by_topic.entry(definition.topic0).or_default().push(definition);
+        }
+        Self {
+            definitions: by_topic,
+        }
+    }
+
+    /// Number of loaded definitions.
+    pub fn len(&self) -> usize {
+        self.definitions.values().map(Vec::len).sum()
+    }
+
+    /// Whether no definitions are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    /// Match and decode a log; returns None if no definition applies or the
+    /// filters reject it.
+    fn decode_log(
+        &self,
+        log: &Log,
+        watched: &std::collections::HashSet<Address>,
+    ) -> Option<(&EventDefinition, Vec<EventParamValue>)> {
+        let topics: Vec<B256> = log.topics.iter().map(|t| parse_topic(t)).collect::<Option<_>>()?;
+        let candidates = self.definitions.get(topics.first()?)?;
+        let definition = candidates
+            .iter()
+            .find(|d| d.topic_count() == topics.len())?;
+
+        if !definition.contracts.is_empty() && !definition.contracts.contains(&log.address) {
+            return None;
+        }
+
+        let values = match definition.decode(&topics, &log.data) {
+            Ok(values) => values,
+            Err(e) => {
+                tracing::warn!("Failed to decode {} log: {:?}", definition.name, e);
+                return None;
+            }
+        };
+
+        if definition.contracts.is_empty() {
+            let involves_watched = values.iter().any(|p| match p.value {
+                DecodedValue::Address(addr) => watched.contains(&addr),
+                _ => false,
+            });
+            if !involves_watched {
+                return None;
+            }
+        }
+        Some((definition, values))
+    }
+}
+
+impl Tracker for EventTracker {
+    fn name(&self) -> &'static str {
+        "EventTracker"
+    }
+
+    fn process_block(
+        &self,
+        ctx: &mut TrackerContext<'_, '_>,
+        receipts: &[(B256, &Receipt)],
+    ) -> Result<()> {
+        if self.definitions.is_empty() {
+            return Ok(());
+        }
+
+        for (log_ordinal, tx_hash, log) in logs_with_ordinals(receipts) {
+            let Some((definition, params)) = self.decode_log(log, ctx.watched_eoas) else {
+                continue;
+            };
+            let record = EventRecord {
+                block: ctx.block_number,
+                log_ordinal,
+                tx_hash: *tx_hash,
+                contract: log.address,
+                event: definition.name.clone(),
+                signature: definition.signature.clone(),
+                params,
+            };
+            ctx.batch
+                .put_event_record(&record)
+                .with_context(|| format!("Failed to store {} event", definition.name))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u64) -> Vec<u8> {
+        U256::from(value).to_be_bytes::<32>().to_vec()
+    }
+
+    #[test]
+    fn test_parse_signature_and_topic() {
+        let definition = EventDefinition::parse_signature(
+            "event Transfer(address indexed from, address indexed to, uint value)",
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(definition.signature, "Transfer(address,address,uint256)");
+        assert_eq!(
+            definition.topic0,
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                .parse::<B256>()
+                .unwrap()
+        );
+        assert_eq!(definition.params[1].name, "to");
+        assert!(definition.params[1].indexed);
+        assert!(!definition.params[2].indexed);
+
+        assert!(EventDefinition::parse_signature("Foo(uint256[] xs)", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_definition_file_formats() {
+        let lines = "# comment\n\
+            Sync(uint112 reserve0, uint112 reserve1) @ 0x00000000000000000000000000000000000000aa\n\
+            Approval(address indexed owner, address indexed spender, uint256 value)\n";
+        let definitions = parse_event_definitions(lines).unwrap();
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(
+            definitions[0].contracts,
+            vec!["0x00000000000000000000000000000000000000aa".parse::<Address>().unwrap()]
+        );
+        assert!(definitions[1].contracts.is_empty());
+
+        let abi = r#"[
+            {"type": "function", "name": "swap", "inputs": []},
+            {"type": "event", "name": "Deposit", "anonymous": false, "inputs": [
+                {"name": "dst", "type": "address", "indexed": true},
+                {"name": "wad", "type": "uint256", "indexed": false}
+            ]}
+        ]"#;
+        let definitions = parse_event_definitions(abi).unwrap();
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].signature, "Deposit(address,uint256)");
+    }
+
+    #[test]
+    fn test_decode_indexed_static_and_dynamic() {
+        let definition = EventDefinition::parse_signature(
+            "Note(address indexed who, int256 delta, string memo, bytes4 tag)",
+            Vec::new(),
+        )
+        .unwrap();
+        let who = Address::repeat_byte(0x11);
+
+        // Head: delta, offset of memo (0x60), tag; tail: memo length + bytes
+        let mut data = (U256::MAX - U256::from(4u64)).to_be_bytes::<32>().to_vec(); // -5
+        data.extend(word(0x60));
+        let mut tag = [0u8; 32];
+        tag[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        data.extend(tag);
+        data.extend(word(2));
+        let mut memo = [0u8; 32];
+        memo[..2].copy_from_slice(b"hi");
+        data.extend(memo);
+
+        let mut who_topic = [0u8; 32];
+        who_topic[12..].copy_from_slice(who.as_slice());
+        let topics = vec![definition.topic0, B256::from(who_topic)];
+
+        let values = definition.decode(&topics, &data).unwrap();
+        assert_eq!(values[0].value, DecodedValue::Address(who));
+        assert_eq!(values[1].value, DecodedValue::Int(U256::MAX - U256::from(4u64)));
+        assert_eq!(values[2].value, DecodedValue::String("hi".to_string()));
+        assert_eq!(values[3].value, DecodedValue::Bytes(vec![0xde, 0xad, 0xbe, 0xef]));
+
+        // Wrong number of topics (e.g. a same-signature event with other indexing)
+        assert!(definition.decode(&topics[..1], &data).is_err());
+    }
+
+    #[test]
+    fn test_event_filters() {
+        let pair: Address = "0x00000000000000000000000000000000000000aa".parse().unwrap();
+        let watched_owner = Address::repeat_byte(0x11);
+        let tracker = EventTracker::new(vec![
+            EventDefinition::parse_signature(
+                "Approval(address indexed owner, address indexed spender, uint256 value)",
+                Vec::new(),
+            )
+            .unwrap(),
+            EventDefinition::parse_signature("Sync(uint112 reserve0, uint112 reserve1)", vec![pair])
+                .unwrap(),
+        ]);
+        let watched: std::collections::HashSet<Address> = [watched_owner].into_iter().collect();
+        let topic = |addr: Address| format!("0x{:0>64}", hex::encode(addr.as_slice()));
+        let approval_topic = format!("{:?}", keccak256("Approval(address,address,uint256)"));
+        let sync_topic = format!("{:?}", keccak256("Sync(uint112,uint112)"));
+
+        // Unrestricted definition: kept only when a watched address takes part
+        let approval = |owner: Address| Log {
+            address: Address::repeat_byte(0x77),
+            topics: vec![approval_topic.clone(), topic(owner), topic(Address::repeat_byte(0x22))],
+            data: word(5),
+        };
+        let (definition, params) = tracker.decode_log(&approval(watched_owner), &watched).unwrap();
+        assert_eq!(definition.name, "Approval");
+        assert_eq!(params[2].value, DecodedValue::Uint(U256::from(5u64)));
+        assert!(tracker.decode_log(&approval(Address::repeat_byte(0x33)), &watched).is_none());
+
+        // Restricted definition: kept for the listed contract only
+        let mut data = word(1);
+        data.extend(word(2));
+        let sync = |emitter: Address| Log {
+            address: emitter,
+            topics: vec![sync_topic.clone()],
+            data: data.clone(),
+        };
+        assert!(tracker.decode_log(&sync(pair), &watched).is_some());
+        assert!(tracker.decode_log(&sync(Address::repeat_byte(0x77)), &watched).is_none());
+    }
+}
@@ -17,13 +17,114 @@ pub struct Block {
     #[serde(rename = "hash", deserialize_with = "deserialize_hex_b256")]
     pub hash: B256,
 
+    /// Parent block hash (hex string in JSON)
+    #[serde(rename = "parentHash", deserialize_with = "deserialize_hex_b256")]
+    pub parent_hash: B256,
+
     /// Base fee per gas (EIP-1559, hex string in JSON)
     #[serde(rename = "baseFeePerGas", deserialize_with = "deserialize_hex_u256_opt")]
     pub base_fee_per_gas: Option<U256>,
 
+    /// Fee recipient (coinbase) of the block (hex string in JSON)
+    #[serde(rename = "miner", deserialize_with = "deserialize_hex_address")]
+    pub miner: Address,
+
     /// List of transactions in the block
     #[serde(rename = "transactions")]
     pub transactions: Vec<Transaction>,
+
+    /// Validator withdrawals (EIP-4895, absent before Shanghai)
+    #[serde(rename = "withdrawals", default)]
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+/// Block header as delivered by `eth_subscribe("newHeads")`.
+///
+/// Only the fields needed to order heads and detect reorgs are parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BlockHeader {
+    /// Block number (hex string in JSON, parsed to u64)
+    #[serde(rename = "number", deserialize_with = "deserialize_hex_u64")]
+    pub number: u64,
+
+    /// Block hash (hex string in JSON)
+    #[serde(rename = "hash", deserialize_with = "deserialize_hex_b256")]
+    pub hash: B256,
+
+    /// Parent block hash (hex string in JSON)
+    #[serde(rename = "parentHash", deserialize_with = "deserialize_hex_b256")]
+    pub parent_hash: B256,
+}
+
+/// Validator withdrawal credited to an execution-layer address (EIP-4895).
+///
+/// Withdrawals are not transactions: they pay no fees and do not change the nonce.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Withdrawal {
+    /// Monotonic withdrawal index (hex string in JSON)
+    #[serde(rename = "index", deserialize_with = "deserialize_hex_u64")]
+    pub index: u64,
+
+    /// Index of the validator being withdrawn from (hex string in JSON)
+    #[serde(rename = "validatorIndex", deserialize_with = "deserialize_hex_u64")]
+    pub validator_index: u64,
+
+    /// Recipient address (hex string in JSON)
+    #[serde(rename = "address", deserialize_with = "deserialize_hex_address")]
+    pub address: Address,
+
+    /// Amount in gwei (hex string in JSON)
+    #[serde(rename = "amount", deserialize_with = "deserialize_hex_u64")]
+    pub amount: u64,
+}
+
+impl Withdrawal {
+    /// Withdrawal amount converted from gwei to wei.
+    pub fn amount_wei(&self) -> U256 {
+        U256::from(self.amount) * U256::from(1_000_000_000u64)
+    }
+}
+
+/// EIP-2718 transaction type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TxType {
+    /// Type 0: legacy transaction (gasPrice)
+    #[default]
+    Legacy,
+    /// Type 1: EIP-2930 access list transaction (gasPrice)
+    AccessList,
+    /// Type 2: EIP-1559 dynamic fee transaction
+    Eip1559,
+    /// Type 3: EIP-4844 blob transaction (dynamic fee + blob gas)
+    Blob,
+    /// Type 0x7e: OP Stack deposit transaction (no fee, may mint value)
+    Deposit,
+    /// Any other type byte (chain-specific or future types)
+    Unknown(u8),
+}
+
+impl TxType {
+    /// Map a type byte to a `TxType`.
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => TxType::Legacy,
+            1 => TxType::AccessList,
+            2 => TxType::Eip1559,
+            3 => TxType::Blob,
+            0x7e => TxType::Deposit,
+            other => TxType::Unknown(other),
+        }
+    }
+
+    /// Whether this type is priced with a single `gasPrice`.
+    pub fn uses_gas_price(&self) -> bool {
+        matches!(self, TxType::Legacy | TxType::AccessList)
+    }
+
+    /// Whether this type is priced with `maxFeePerGas` / `maxPriorityFeePerGas`.
+    pub fn uses_dynamic_fee(&self) -> bool {
+        matches!(self, TxType::Eip1559 | TxType::Blob)
+    }
 }
 
 /// Ethereum transaction.
@@ -33,6 +134,10 @@ pub struct Transaction {
     #[serde(rename = "hash", deserialize_with = "deserialize_hex_b256")]
     pub hash: B256,
 
+    /// Transaction type (hex string in JSON, missing = legacy)
+    #[serde(rename = "type", default, deserialize_with = "deserialize_hex_tx_type")]
+    pub tx_type: TxType,
+
     /// Sender address (hex string in JSON)
     #[serde(rename = "from", deserialize_with = "deserialize_hex_address")]
     pub from: Address,
@@ -68,17 +173,31 @@ pub struct Transaction {
     /// Transaction nonce (hex string in JSON)
     #[serde(rename = "nonce", deserialize_with = "deserialize_hex_u64")]
     pub nonce: u64,
+
+    /// Value minted to the sender on L2 (OP Stack deposits only, hex string in JSON)
+    #[serde(rename = "mint", default, deserialize_with = "deserialize_hex_u256_opt")]
+    pub mint: Option<U256>,
 }
 
 impl Transaction {
-    /// Check if this is a legacy transaction (has gasPrice, no maxFeePerGas).
+    /// Check if this is a gasPrice-priced transaction (type 0 or 1).
     pub fn is_legacy(&self) -> bool {
-        self.gas_price.is_some() && self.max_fee_per_gas.is_none()
+        self.tx_type.uses_gas_price()
     }
 
-    /// Check if this is an EIP-1559 transaction (has maxFeePerGas).
+    /// Check if this is a dynamic fee transaction (type 2 or 3).
     pub fn is_eip1559(&self) -> bool {
-        self.max_fee_per_gas.is_some()
+        self.tx_type.uses_dynamic_fee()
+    }
+
+    /// Check if this is an EIP-4844 blob transaction (type 3).
+    pub fn is_blob(&self) -> bool {
+        self.tx_type == TxType::Blob
+    }
+
+    /// Check if this is an OP Stack deposit transaction (type 0x7e).
+    pub fn is_deposit(&self) -> bool {
+        self.tx_type == TxType::Deposit
     }
 
     /// Check if this is a contract creation transaction (to is None).
@@ -121,6 +240,22 @@ pub struct Receipt {
     /// Logs emitted during transaction execution (empty for reverted txs)
     #[serde(rename = "logs", default)]
     pub logs: Vec<Log>,
+
+    /// Blob gas used (EIP-4844, type-3 transactions only, hex string in JSON)
+    #[serde(rename = "blobGasUsed", default, deserialize_with = "deserialize_hex_u256_opt")]
+    pub blob_gas_used: Option<U256>,
+
+    /// Blob gas price (EIP-4844, type-3 transactions only, hex string in JSON)
+    #[serde(rename = "blobGasPrice", default, deserialize_with = "deserialize_hex_u256_opt")]
+    pub blob_gas_price: Option<U256>,
+
+    /// L1 data fee (OP Stack L2s only, hex string in JSON)
+    #[serde(rename = "l1Fee", default, deserialize_with = "deserialize_hex_u256_opt")]
+    pub l1_fee: Option<U256>,
+
+    /// Transaction hash (hex string in JSON), used to align block-level receipt results
+    #[serde(rename = "transactionHash", default, deserialize_with = "deserialize_hex_b256_opt")]
+    pub transaction_hash: Option<B256>,
 }
 
 impl Receipt {
@@ -235,6 +370,16 @@ where
     }
 }
 
+/// Deserialize a hex transaction type (e.g. "0x2") to TxType.
+fn deserialize_hex_tx_type<'de, D>(deserializer: D) -> Result<TxType, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = deserialize_hex_u64(deserializer)?;
+    let byte = u8::try_from(value).map_err(serde::de::Error::custom)?;
+    Ok(TxType::from_byte(byte))
+}
+
 /// Deserialize a hex string to B256.
 fn deserialize_hex_b256<'de, D>(deserializer: D) -> Result<B256, D::Error>
 where
@@ -253,6 +398,33 @@ where
     Ok(B256::from_slice(&bytes))
 }
 
+/// Deserialize an optional hex string to B256.
+fn deserialize_hex_b256_opt<'de, D>(deserializer: D) -> Result<Option<B256>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+    match s {
+        Some(s) => {
+            let s = s.strip_prefix("0x").unwrap_or(&s);
+            if s.is_empty() {
+                Ok(None)
+            } else {
+                let s = pad_hex_string(s);
+                let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+                if bytes.len() != 32 {
+                    return Err(serde::de::Error::custom(format!(
+                        "Expected 32 bytes for hash, got {}",
+                        bytes.len()
+                    )));
+                }
+                Ok(Some(B256::from_slice(&bytes)))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
 /// Deserialize a hex string to Address.
 fn deserialize_hex_address<'de, D>(deserializer: D) -> Result<Address, D::Error>
 where
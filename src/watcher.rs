@@ -3,20 +3,78 @@
 //! Orchestrates polling finalized blocks, processing transactions,
 //! and updating the state store for watched addresses.
 
-use crate::apply::{apply_internal_credit, apply_transaction, check_receiver_is_eoa, is_eoa_to_eoa_transfer};
+use crate::apply::{
+    apply_internal_credit, apply_priority_fee_credit, apply_transaction, apply_withdrawal_credit,
+    check_receiver_is_eoa, is_eoa_to_eoa_transfer,
+};
+use crate::batch::BlockWriteBatch;
 use crate::cache::ContractCache;
-use crate::config::{load_token_watchlist, load_watchlist};
-use crate::records::{AccountRecord, BlockDelta, TokenWatchMeta};
+use crate::config::{load_token_entries, load_watchlist, ChainProfile, FollowMode, TokenStrategy};
+use crate::fee::{calculate_effective_gas_price, calculate_priority_fee};
+use crate::heads::NewHeadsSubscription;
+use crate::rebasing::{apply_token_strategies, fetch_shares_of};
+use crate::reconcile::reconcile_block;
+use crate::records::{
+    AccountRecord, BlockDelta, TokenMetadata, TokenWatchMeta, TxDirection, TxRecord, TxSource,
+};
+use crate::reorg::{find_common_ancestor, prune_undo, rollback_to};
+use crate::rpc::{is_retryable_error, RpcClient};
+use crate::store::{RocksStateStore, StateStore};
+use crate::token_metadata::fetch_token_metadata;
 use crate::trace::{collect_internal_transfers, collect_senders};
 use crate::tracker::{Tracker, TrackerContext};
+use crate::tracker_approvals::ApprovalTracker;
+use crate::tracker_erc1155::Erc1155Tracker;
 use crate::tracker_erc20::Erc20Tracker;
-use crate::rpc::RpcClient;
-use crate::store::{RocksStateStore, StateStore};
-use alloy_primitives::{Address, B256};
+use crate::tracker_erc721::Erc721Tracker;
+use crate::tracker_events::EventTracker;
+use crate::types::{Block, CallTrace, Receipt};
+use crate::watchlist::{apply_queued_changes, end_removed_coverage};
+use alloy_primitives::{Address, B256, U256};
 use anyhow::{Context, Result};
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
-use tracing::info;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Which optional block-level RPC methods the node supports.
+///
+/// `None` means not probed yet. A method that fails on its first use is
+/// marked unsupported and the per-transaction fallback is used from then on.
+#[derive(Debug, Default)]
+struct NodeCapabilities {
+    /// `eth_getBlockReceipts`
+    block_receipts: Option<bool>,
+    /// JSON-RPC batch arrays
+    batch: Option<bool>,
+    /// `debug_traceBlockByNumber` with `callTracer`
+    trace_block: Option<bool>,
+}
+
+impl NodeCapabilities {
+    /// Record the outcome of using a method. Returns the value to use when it failed.
+    ///
+    /// The first failure marks the method unsupported unless it is a retryable
+    /// RPC error; failures after a success are treated as transient and only
+    /// skip it for the current block.
+    fn record<T>(flag: &mut Option<bool>, method: &str, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                *flag = Some(true);
+                Some(value)
+            }
+            Err(e) if flag.is_none() && !is_retryable_error(&e) => {
+                info!("{} not available, falling back to per-transaction calls: {:?}", method, e);
+                *flag = Some(false);
+                None
+            }
+            Err(e) => {
+                warn!("{} failed, falling back for this block: {:?}", method, e);
+                None
+            }
+        }
+    }
+}
 
 /// Main watcher that monitors and processes Ethereum blocks.
 pub struct Watcher {
@@ -26,11 +84,47 @@ pub struct Watcher {
     watchlist: Vec<Address>,
     /// Watched ERC20 token contract addresses (empty = no ERC20 tracking)
     token_watchlist: Vec<Address>,
-    /// ERC20 tracker (used when token_watchlist is non-empty)
+    /// ERC20 tracker (used when token_watchlist or discovered_tokens is non-empty)
     erc20_tracker: Erc20Tracker,
+    /// Accounting strategy of rebasing and share-based tokens (others apply Transfers only)
+    token_strategies: HashMap<Address, TokenStrategy>,
+    /// Verify ERC20 balances changed by Transfers with balanceOf
+    verify_erc20: bool,
+    /// Register (token, owner) pairs for Transfers touching watched addresses
+    token_discovery: bool,
+    /// Token contracts registered by discovery (this run or earlier ones)
+    discovered_tokens: HashSet<Address>,
+    /// Watched ERC1155 (contract, id) pairs (empty = no ERC1155 tracking)
+    erc1155_watchlist: Vec<(Address, U256)>,
+    /// ERC1155 tracker (used when erc1155_watchlist is non-empty)
+    erc1155_tracker: Erc1155Tracker,
+    /// Tracker for user-defined events (None = no event definitions loaded)
+    event_tracker: Option<EventTracker>,
+    /// ERC721 ownership tracker (None = NFT tracking disabled)
+    erc721_tracker: Option<Erc721Tracker>,
+    /// Token approval tracker (None = approval tracking disabled)
+    approval_tracker: Option<ApprovalTracker>,
+    /// Re-read the persisted watchlist between blocks (set by `initialize`)
+    persisted_watchlist: bool,
+    /// Queue file of `statectl watch` changes made while the watcher runs
+    watch_queue: Option<PathBuf>,
     /// Per-block delta accumulator: address -> BlockDelta
     /// Accumulates changes for the current block being processed
     block_deltas: HashMap<Address, BlockDelta>,
+    /// Chain-specific fee rules (L1 data fees, deposit transactions)
+    chain_profile: ChainProfile,
+    /// Reconcile balances against the node every N blocks (0 = disabled)
+    reconcile_every: u64,
+    /// Write corrective deltas when reconciliation finds drift
+    auto_correct: bool,
+    /// Which block to follow (latest, latest-N, safe, finalized)
+    follow_mode: FollowMode,
+    /// Optional block-level RPC methods the node supports
+    capabilities: NodeCapabilities,
+    /// Interval between polls (and fallback while the WebSocket is down)
+    poll_interval: Duration,
+    /// WebSocket URL for `eth_subscribe("newHeads")` (None = poll only)
+    ws_url: Option<String>,
 }
 
 impl Watcher {
@@ -43,42 +137,175 @@ impl Watcher {
             watchlist: Vec::new(),
             token_watchlist: Vec::new(),
             erc20_tracker: Erc20Tracker::new(Vec::new()),
+            token_strategies: HashMap::new(),
+            verify_erc20: false,
+            token_discovery: false,
+            discovered_tokens: HashSet::new(),
+            erc1155_watchlist: Vec::new(),
+            erc1155_tracker: Erc1155Tracker::new(Vec::new()),
+            event_tracker: None,
+            erc721_tracker: None,
+            approval_tracker: None,
+            persisted_watchlist: false,
+            watch_queue: None,
             block_deltas: HashMap::new(),
+            chain_profile: ChainProfile::default(),
+            reconcile_every: 0,
+            auto_correct: false,
+            follow_mode: FollowMode::default(),
+            capabilities: NodeCapabilities::default(),
+            poll_interval: Duration::from_secs(12),
+            ws_url: None,
+        }
+    }
+
+    /// Set the chain profile used for fee accounting.
+    pub fn with_chain_profile(mut self, chain_profile: ChainProfile) -> Self {
+        self.chain_profile = chain_profile;
+        self
+    }
+
+    /// Set the poll interval used when no new-head subscription is available.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Drive the loop from an `eth_subscribe("newHeads")` WebSocket subscription.
+    pub fn with_ws_url(mut self, ws_url: String) -> Self {
+        self.ws_url = Some(ws_url);
+        self
+    }
+
+    /// Decode and store logs matching user-supplied event definitions.
+    pub fn with_event_tracker(mut self, event_tracker: EventTracker) -> Self {
+        self.event_tracker = Some(event_tracker);
+        self
+    }
+
+    /// Track ERC1155 balances of the given (contract, id) pairs for every watched address.
+    pub fn with_erc1155_watchlist(mut self, pairs: Vec<(Address, U256)>) -> Self {
+        self.erc1155_tracker = Erc1155Tracker::new(pairs.clone());
+        self.erc1155_watchlist = pairs;
+        self
+    }
+
+    /// Verify every ERC20 balance changed by Transfer events with `balanceOf` at the block.
+    ///
+    /// Tokens found to disagree are marked non-standard and verified in every
+    /// later block, even when this is disabled.
+    pub fn with_erc20_verification(mut self, enabled: bool) -> Self {
+        self.verify_erc20 = enabled;
+        self
+    }
+
+    /// Automatically track ERC20 tokens that a watched address sends or receives.
+    ///
+    /// Any `Transfer` from a token the address is not tracked for registers
+    /// the (token, owner) pair, seeded with `balanceOf` at the previous block.
+    pub fn with_token_discovery(mut self, enabled: bool) -> Self {
+        self.token_discovery = enabled;
+        self
+    }
+
+    /// Track ERC721 ownership of watched addresses (any NFT contract).
+    pub fn with_nft_tracking(mut self, enabled: bool) -> Self {
+        self.erc721_tracker = enabled.then(Erc721Tracker::new);
+        self
+    }
+
+    /// Track ERC20 and ApprovalForAll approvals granted by watched addresses.
+    pub fn with_approval_tracking(mut self, enabled: bool) -> Self {
+        self.approval_tracker = enabled.then(ApprovalTracker::new);
+        self
+    }
+
+    /// Apply `statectl watch` changes queued in `path` before each block.
+    pub fn with_watch_queue(mut self, path: PathBuf) -> Self {
+        self.watch_queue = Some(path);
+        self
+    }
+
+    /// Set which block the watcher follows.
+    pub fn with_follow_mode(mut self, follow_mode: FollowMode) -> Self {
+        self.follow_mode = follow_mode;
+        self
+    }
+
+    /// Block number the watcher should process up to under its follow mode.
+    async fn follow_head(&self) -> Result<u64> {
+        match self.follow_mode {
+            FollowMode::Latest => self
+                .rpc
+                .get_block_number("latest")
+                .await
+                .context("Failed to get latest block number"),
+            FollowMode::Confirmations(n) => {
+                let latest = self
+                    .rpc
+                    .get_block_number("latest")
+                    .await
+                    .context("Failed to get latest block number")?;
+                Ok(latest.saturating_sub(n))
+            }
+            FollowMode::Safe => self
+                .rpc
+                .get_block_number("safe")
+                .await
+                .context("Failed to get safe block number (does the node support the 'safe' tag?)"),
+            FollowMode::Finalized => self
+                .rpc
+                .get_block_number("finalized")
+                .await
+                .context(
+                    "Failed to get finalized block number (does the node support the 'finalized' tag?)",
+                ),
         }
     }
 
+    /// Reconcile balances against the node every `every` blocks (0 disables it).
+    ///
+    /// With `auto_correct`, drift is also fixed by writing corrective deltas.
+    pub fn with_reconciliation(mut self, every: u64, auto_correct: bool) -> Self {
+        self.reconcile_every = every;
+        self.auto_correct = auto_correct;
+        self
+    }
+
     /// Ensure we have contract / EOA information for the given addresses.
     ///
     /// This populates the `ContractCache` by calling `eth_getCode` only for
-    /// addresses that are not already cached.
+    /// addresses that are not already cached. Takes the RPC client and cache
+    /// separately so it can run while the block's write batch borrows the store.
     async fn preload_contract_flags(
-        &mut self,
+        rpc: &RpcClient,
+        cache: &mut ContractCache,
         addrs: &HashSet<Address>,
         block_num: u64,
     ) -> Result<()> {
         for addr in addrs {
-            if self.cache.is_contract(*addr).is_some() {
+            if cache.is_contract(*addr).is_some() {
                 continue;
             }
 
-            let code = self
-                .rpc
+            let code = rpc
                 .get_code(*addr, &format!("0x{:x}", block_num))
                 .await
                 .with_context(|| format!("Failed to get code for address {:?}", addr))?;
 
             let is_contract = !code.is_empty();
-            self.cache.mark_contract(*addr, is_contract);
+            cache.mark_contract(*addr, is_contract);
         }
         Ok(())
     }
 
     /// Initialize the watcher.
     ///
-    /// Loads the watchlist, fetches initial state for all watched addresses,
-    /// and sets the head to the current finalized block.
-    /// If `tokens_path` is provided and the file exists, also initializes ERC20
-    /// tracking for (token, owner) pairs via balanceOf.
+    /// Seeds an empty persisted watchlist from the watchlist files (if they
+    /// exist), fetches initial state for all watched addresses,
+    /// and sets the head to the block selected by the follow mode. ERC20
+    /// tracking for (token, owner) pairs is initialized via balanceOf.
+    /// Afterwards the persisted watchlist is re-read between blocks.
     pub async fn initialize(
         &mut self,
         watchlist_path: &Path,
@@ -86,188 +313,679 @@ impl Watcher {
     ) -> Result<()> {
         info!("Initializing watcher...");
 
-        // Load watchlist
-        self.watchlist = load_watchlist(watchlist_path)
-            .context("Failed to load watchlist")?;
-        info!("Loaded {} addresses to watch", self.watchlist.len());
-
-        // Load token watchlist (optional)
-        if let Some(p) = tokens_path {
-            if p.exists() {
-                self.token_watchlist = load_token_watchlist(p)
-                    .context("Failed to load token watchlist")?;
-                self.erc20_tracker = Erc20Tracker::new(self.token_watchlist.clone());
-                info!("Loaded {} tokens to watch", self.token_watchlist.len());
+        // Seed the persisted watchlist from the files only while it is empty, so
+        // entries removed with `statectl watch remove` are not added back on restart
+        let persisted_empty = self.store.get_watched_addresses()?.is_empty()
+            && self.store.get_watched_tokens()?.is_empty();
+        if persisted_empty && watchlist_path.exists() {
+            for addr in load_watchlist(watchlist_path).context("Failed to load watchlist")? {
+                self.store.put_watched_address(addr)?;
             }
         }
+        if let Some(p) = tokens_path.filter(|p| persisted_empty && p.exists()) {
+            for (token, strategy) in
+                load_token_entries(p).context("Failed to load token watchlist")?
+            {
+                self.store.put_watched_token(token, strategy)?;
+            }
+        }
+        if let Some(queue) = &self.watch_queue {
+            apply_queued_changes(&self.store, queue)
+                .context("Failed to apply queued watchlist changes")?;
+        }
+
+        self.load_persisted_watchlist()?;
+        if self.watchlist.is_empty() {
+            anyhow::bail!(
+                "Watchlist is empty: add addresses to {:?} or with `statectl watch add`",
+                watchlist_path
+            );
+        }
+        self.persisted_watchlist = true;
+        info!(
+            "Watching {} addresses and {} tokens",
+            self.watchlist.len(),
+            self.token_watchlist.len()
+        );
+
+        // Keep tracking tokens discovered in earlier runs
+        self.load_discovered_tokens()?;
+        if !self.discovered_tokens.is_empty() {
+            info!("Loaded {} auto-discovered tokens", self.discovered_tokens.len());
+        }
 
         // Check if we already have a head block (resuming from existing state)
         let existing_head = self.store.get_head().context("Failed to get head")?;
-        
-        // Get current block number (use "latest" for consistency with balance fetching)
-        // This ensures we're using the same block reference for both balance and head
-        let current_block_num = self
-            .rpc
-            .get_block_number("latest")
-            .await
-            .context("Failed to get latest block number")?;
-        
+
+        // Get the block selected by the follow mode; balances are fetched at this
+        // exact block so the initial snapshot and the head agree
+        let current_block_num = self.follow_head().await?;
+
         if let Some(head) = existing_head {
-            info!("Resuming from existing state. Current head: {}, Latest block: {}", head, current_block_num);
-            // Don't overwrite existing state, just verify all addresses exist
-            for addr in &self.watchlist {
-                if self.store.get_account(*addr)?.is_none() {
-                    // Address not in DB, initialize it at latest block
-                    let balance = self
-                        .rpc
-                        .get_balance(*addr, "latest")
-                        .await
-                        .with_context(|| format!("Failed to get balance for {:?}", addr))?;
-                    let nonce = self
-                        .rpc
-                        .get_transaction_count(*addr, "latest")
-                        .await
-                        .with_context(|| format!("Failed to get transaction count for {:?}", addr))?;
-                    
-                    let account = AccountRecord {
-                        nonce,
-                        balance,
-                        code_hash: B256::ZERO,
-                    };
-                    self.store
-                        .put_account(*addr, &account)
-                        .with_context(|| format!("Failed to store account for {:?}", addr))?;
-
-                    // Write snapshot at initialization block
-                    self.store
-                        .put_snapshot(*addr, current_block_num, balance)
-                        .with_context(|| format!("Failed to store initial snapshot for {:?}", addr))?;
-
-                    // Write WatchMeta
-                    let watch_meta = crate::records::WatchMeta {
-                        start_block: current_block_num,
-                    };
-                    self.store
-                        .put_watch_meta(*addr, &watch_meta)
-                        .with_context(|| format!("Failed to store watch metadata for {:?}", addr))?;
+            info!(
+                "Resuming from existing state. Current head: {}, {} block: {}",
+                head, self.follow_mode, current_block_num
+            );
+            // Don't overwrite existing state; entries added while stopped are
+            // initialized at the head, so the blocks after it apply on top of them
+            self.initialize_new_entries(head).await?;
+        } else {
+            // First run: initialize all addresses (and ERC20/ERC1155 pairs) at the followed block
+            // This is a point-in-time snapshot - we only track changes going forward
+            info!(
+                "First run. Initializing all addresses at {} block {} (point-in-time snapshot)",
+                self.follow_mode, current_block_num
+            );
+            self.initialize_new_entries(current_block_num).await?;
+            self.initialize_head(current_block_num).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the watched addresses and tokens (with their strategies) from the persisted watchlist.
+    fn load_persisted_watchlist(&mut self) -> Result<()> {
+        self.watchlist = self
+            .store
+            .get_watched_addresses()
+            .context("Failed to read watched addresses")?;
+        let tokens = self
+            .store
+            .get_watched_tokens()
+            .context("Failed to read watched tokens")?;
+        self.token_watchlist = tokens.iter().map(|(token, _)| *token).collect();
+        self.token_strategies = tokens
+            .into_iter()
+            .filter(|(_, strategy)| *strategy != TokenStrategy::Transfers)
+            .collect();
+        self.erc20_tracker = Erc20Tracker::new(self.token_watchlist.clone());
+        Ok(())
+    }
 
-                    info!("Initialized new address {:?}: balance={:?}, nonce={}", addr, balance, nonce);
+    /// Load the tokens auto-discovered in committed blocks from their token watch metadata.
+    fn load_discovered_tokens(&mut self) -> Result<()> {
+        self.discovered_tokens = self
+            .store
+            .get_token_watch_metas()
+            .context("Failed to load token watch metadata")?
+            .into_iter()
+            .filter(|(_, _, meta)| meta.auto_discovered && meta.is_watched())
+            .map(|(token, _, _)| token)
+            .collect();
+        Ok(())
+    }
+
+    /// Pick up changes of the persisted watchlist made while the watcher runs.
+    ///
+    /// Applies queued `statectl watch` changes first. New addresses and
+    /// (token, owner) pairs are initialized at `head`, the last committed
+    /// block, so they are tracked from the next block on; a re-added address
+    /// or token starts a new coverage segment there. The initialization is
+    /// journaled with the head block and reverted if a reorg rolls it back.
+    /// Removed entries are no longer updated, but their history is kept
+    /// (their coverage ends at the head they were removed at).
+    async fn sync_watchlist(&mut self, head: u64) -> Result<()> {
+        if let Some(queue) = &self.watch_queue {
+            let applied = apply_queued_changes(&self.store, queue)
+                .context("Failed to apply queued watchlist changes")?;
+            if applied > 0 {
+                info!("Applied {} queued watchlist change(s)", applied);
+            }
+        }
+
+        let previous_addresses = std::mem::take(&mut self.watchlist);
+        let previous_tokens = std::mem::take(&mut self.token_watchlist);
+        let previous_strategies = std::mem::take(&mut self.token_strategies);
+        self.load_persisted_watchlist()?;
+        if self.watchlist == previous_addresses
+            && self.token_watchlist == previous_tokens
+            && self.token_strategies == previous_strategies
+        {
+            return Ok(());
+        }
+
+        let new_addresses = self
+            .watchlist
+            .iter()
+            .filter(|addr| !previous_addresses.contains(addr))
+            .count();
+        let new_tokens = self
+            .token_watchlist
+            .iter()
+            .filter(|token| !previous_tokens.contains(token))
+            .count();
+        info!(
+            "Watchlist changed after block {}: {} addresses ({} new), {} tokens ({} new)",
+            head,
+            self.watchlist.len(),
+            new_addresses,
+            self.token_watchlist.len(),
+            new_tokens
+        );
+
+        let result = self.initialize_new_entries(head).await;
+        if result.is_err() {
+            // Keep the old lists so the changes are picked up again before the next block
+            self.watchlist = previous_addresses;
+            self.token_watchlist = previous_tokens;
+            self.token_strategies = previous_strategies;
+            self.erc20_tracker = Erc20Tracker::new(self.token_watchlist.clone());
+        }
+        result
+    }
+
+    /// Initialize the watched entries without state at `head`.
+    ///
+    /// Covers addresses not in the store or watched again (with all their
+    /// token pairs, auto-discovered and ERC1155 ones included), and ERC20
+    /// and ERC1155 pairs that are uninitialized or whose token is watched
+    /// again. All writes are journaled in the undo
+    /// record of `head`, so rolling it back reverts them.
+    async fn initialize_new_entries(&self, head: u64) -> Result<()> {
+        let mut batch = BlockWriteBatch::amend(&self.store, head)?;
+        let mut new_addresses = Vec::new();
+        for addr in &self.watchlist {
+            let unwatched = batch
+                .get_watch_meta(*addr)?
+                .is_some_and(|meta| !meta.is_watched());
+            if unwatched || batch.get_account(*addr)?.is_none() {
+                self.initialize_address(&mut batch, *addr).await?;
+                new_addresses.push(*addr);
+            }
+        }
+        for owner in &new_addresses {
+            for token in &self.token_watchlist {
+                self.initialize_token_pair(&mut batch, *token, *owner).await?;
+            }
+            // Pairs tracked before the owner was removed resume from a fresh balance
+            for (token, pair_owner, meta) in self.store.get_token_watch_metas()? {
+                if pair_owner == *owner
+                    && meta.auto_discovered
+                    && !self.token_watchlist.contains(&token)
+                {
+                    self.initialize_token_pair(&mut batch, token, *owner).await?;
                 }
             }
-        } else {
-            // First run: initialize all addresses at current latest block
-            // This is a point-in-time snapshot - we only track changes going forward
-            info!("First run. Initializing all addresses at block {} (point-in-time snapshot)", current_block_num);
-            // Use "latest" to get the most current balance at initialization time
-            for addr in &self.watchlist {
-                // Fetch balance at latest block (most accurate for initialization)
-                let balance = self
-                    .rpc
-                    .get_balance(*addr, "latest")
-                    .await
-                    .with_context(|| format!("Failed to get balance for {:?}", addr))?;
+            for (contract, id) in &self.erc1155_watchlist {
+                self.initialize_erc1155_pair(&mut batch, *contract, *id, *owner).await?;
+            }
+        }
+        // Remaining pairs, token metadata, and share balances of tokens switched to `shares`
+        self.initialize_token_pairs(&mut batch).await?;
+        self.initialize_erc1155_pairs(&mut batch).await?;
 
-                // Fetch nonce at latest block
-                let nonce = self
-                    .rpc
-                    .get_transaction_count(*addr, "latest")
-                    .await
-                    .with_context(|| format!("Failed to get transaction count for {:?}", addr))?;
+        if batch.is_empty() {
+            return Ok(());
+        }
+        batch
+            .commit()
+            .with_context(|| format!("Failed to store watchlist entries initialized at {}", head))
+    }
 
-                // Store account
-                let account = AccountRecord {
-                    nonce,
-                    balance,
-                    code_hash: B256::ZERO, // EOA has no code
-                };
+    /// Initialize a fresh store at a fixed historical block.
+    ///
+    /// Used by backfill: every address and (token, owner) pair is anchored at
+    /// `block` with balances fetched at that block, and the head is set to it.
+    /// Requires an archive node for blocks outside the node's state window.
+    pub async fn initialize_at_block(
+        &mut self,
+        watchlist: Vec<Address>,
+        token_watchlist: Vec<Address>,
+        block: u64,
+    ) -> Result<()> {
+        self.watchlist = watchlist;
+        self.token_watchlist = token_watchlist;
+        self.erc20_tracker = Erc20Tracker::new(self.token_watchlist.clone());
 
-                self.store
-                    .put_account(*addr, &account)
-                    .with_context(|| format!("Failed to store account for {:?}", addr))?;
+        self.initialize_new_entries(block).await?;
+        self.initialize_head(block).await
+    }
 
-                // Write snapshot at initialization block
-                self.store
-                    .put_snapshot(*addr, current_block_num, balance)
-                    .with_context(|| format!("Failed to store initial snapshot for {:?}", addr))?;
+    /// Underlying state store.
+    pub fn store(&self) -> &RocksStateStore {
+        &self.store
+    }
 
-                // Write WatchMeta
-                let watch_meta = crate::records::WatchMeta {
-                    start_block: current_block_num,
-                };
-                self.store
-                    .put_watch_meta(*addr, &watch_meta)
-                    .with_context(|| format!("Failed to store watch metadata for {:?}", addr))?;
+    /// Fetch an address's balance and nonce at the batch's block and anchor it there.
+    ///
+    /// Writes the account record, the initial snapshot and `WatchMeta`.
+    async fn initialize_address(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        addr: Address,
+    ) -> Result<()> {
+        let block_num = batch.block();
+        let block_tag = format!("0x{:x}", block_num);
+        let balance = self
+            .rpc
+            .get_balance_quorum(addr, &block_tag)
+            .await
+            .with_context(|| format!("Failed to get balance for {:?}", addr))?;
+        let nonce = self
+            .rpc
+            .get_transaction_count(addr, &block_tag)
+            .await
+            .with_context(|| format!("Failed to get transaction count for {:?}", addr))?;
 
-                info!(
-                    "Initialized {:?}: balance={:?}, nonce={} (at block {})",
-                    addr, balance, nonce, current_block_num
-                );
+        let account = AccountRecord {
+            nonce,
+            balance,
+            code_hash: B256::ZERO, // EOA has no code
+        };
+        batch
+            .put_account(addr, &account)
+            .with_context(|| format!("Failed to store account for {:?}", addr))?;
+
+        // Write snapshot at initialization block
+        batch
+            .put_snapshot(addr, balance)
+            .with_context(|| format!("Failed to store initial snapshot for {:?}", addr))?;
+
+        // Write WatchMeta; a re-watched address gets a new coverage segment
+        let watch_meta = match batch.get_watch_meta(addr)? {
+            Some(mut meta) => {
+                meta.rewatch(block_num);
+                meta
             }
+            None => crate::records::WatchMeta::new(block_num),
+        };
+        batch
+            .put_watch_meta(addr, &watch_meta)
+            .with_context(|| format!("Failed to store watch metadata for {:?}", addr))?;
 
-            // Set head to current block number (the block we initialized from)
-            self.store
-                .set_head(current_block_num)
-                .context("Failed to set head block")?;
-            info!("Initialization complete. Head set to block {}", current_block_num);
+        info!(
+            "Initialized {:?}: balance={:?}, nonce={} (at block {})",
+            addr, balance, nonce, block_num
+        );
+        Ok(())
+    }
+
+    /// Set the head to `block_num` and record its hash.
+    async fn initialize_head(&self, block_num: u64) -> Result<()> {
+        // Set head to current block number (the block we initialized from)
+        self.store
+            .set_head(block_num)
+            .context("Failed to set head block")?;
+        self.store
+            .set_head_finality(self.follow_mode)
+            .context("Failed to set head finality")?;
+
+        // Record the head's hash so the first processed block can be checked for a reorg
+        let head_hash = self
+            .rpc
+            .get_block_hash(&format!("0x{:x}", block_num))
+            .await
+            .context("Failed to get initial block hash")?;
+        self.store
+            .put_block_hash(block_num, head_hash)
+            .context("Failed to store initial block hash")?;
+        info!("Initialization complete. Head set to block {}", block_num);
+        Ok(())
+    }
+
+    /// Initialize ERC20 tracking at the batch's block for every (token, owner) pair that is
+    /// uninitialized or was unwatched with its token.
+    async fn initialize_token_pairs(&self, batch: &mut BlockWriteBatch<'_>) -> Result<()> {
+        let block_str = format!("0x{:x}", batch.block());
+        for token in self.token_watchlist.iter().chain(&self.discovered_tokens) {
+            self.ensure_token_metadata(batch, *token, &block_str).await?;
+        }
+        for token in &self.token_watchlist {
+            let is_shares = self.token_strategies.get(token) == Some(&TokenStrategy::Shares);
+            for owner in &self.watchlist {
+                // Skip if already tracked (resuming)
+                if batch
+                    .get_token_watch_meta(*token, *owner)?
+                    .is_some_and(|meta| meta.is_watched())
+                {
+                    // A token switched to the shares strategy still needs share balances
+                    if is_shares && batch.get_erc20_shares(*token, *owner)?.is_none() {
+                        self.initialize_shares(batch, *token, *owner, &block_str).await?;
+                    }
+                    continue;
+                }
+                self.initialize_token_pair(batch, *token, *owner).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch the balance of (token, owner) with balanceOf and anchor it at the batch's block.
+    ///
+    /// Writes the current balance, the anchoring snapshot and `TokenWatchMeta`
+    /// (and the share balance for tokens with the shares strategy). A pair
+    /// tracked before keeps its start, history and discovery flag, and gets
+    /// a new coverage segment from here.
+    async fn initialize_token_pair(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        token: Address,
+        owner: Address,
+    ) -> Result<()> {
+        let block_num = batch.block();
+        let block_str = format!("0x{:x}", block_num);
+        let balance = self
+            .rpc
+            .erc20_balance_of_quorum(token, owner, &block_str)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to get ERC20 balance for token {:?} owner {:?}",
+                    token, owner
+                )
+            })?;
+        batch
+            .put_erc20_balance(token, owner, balance)
+            .context("Failed to store ERC20 balance")?;
+        batch
+            .put_erc20_snapshot(token, owner, balance)
+            .context("Failed to store ERC20 snapshot")?;
+        let meta = match batch.get_token_watch_meta(token, owner)? {
+            Some(mut meta) => {
+                meta.rewatch(block_num);
+                meta
+            }
+            None => TokenWatchMeta::new(block_num),
+        };
+        batch
+            .put_token_watch_meta(token, owner, &meta)
+            .context("Failed to store token watch meta")?;
+        if self.token_strategies.get(&token) == Some(&TokenStrategy::Shares) {
+            self.initialize_shares(batch, token, owner, &block_str).await?;
         }
+        info!(
+            "Initialized ERC20 token {:?} for owner {:?}: balance={:?}",
+            token, owner, balance
+        );
+        Ok(())
+    }
 
-        // Initialize ERC20 tracking for (token, owner) pairs if tokens are configured
-        if !self.token_watchlist.is_empty() {
-            let block_str = format!("0x{:x}", current_block_num);
-            for token in &self.token_watchlist {
-                for owner in &self.watchlist {
-                    // Skip if already initialized (resuming)
-                    if self.store.get_token_watch_meta(*token, *owner)?.is_some() {
+    /// Fetch and store the share balance of (token, owner) at `block` for a share-based token.
+    async fn initialize_shares(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        token: Address,
+        owner: Address,
+        block: &str,
+    ) -> Result<()> {
+        let shares = fetch_shares_of(&self.rpc, token, owner, block)
+            .await
+            .with_context(|| format!("Failed to get shares of {:?} for token {:?}", owner, token))?;
+        batch
+            .put_erc20_shares(token, owner, shares)
+            .context("Failed to store ERC20 shares")
+    }
+
+    /// Fetch and store a token's name, symbol and decimals unless already known.
+    ///
+    /// Nothing is stored when no field decodes (e.g. every call failed), so
+    /// the next call fetches again. A stored non-standard flag is kept.
+    async fn ensure_token_metadata(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        token: Address,
+        block: &str,
+    ) -> Result<()> {
+        let existing = batch.get_token_metadata(token)?;
+        if existing.as_ref().is_some_and(TokenMetadata::is_known) {
+            return Ok(());
+        }
+        let mut metadata = fetch_token_metadata(&self.rpc, token, block).await;
+        if !metadata.is_known() {
+            warn!("No metadata decoded for token {:?}; fetching again later", token);
+            return Ok(());
+        }
+        metadata.non_standard = existing.is_some_and(|existing| existing.non_standard);
+        batch
+            .put_token_metadata(token, &metadata)
+            .with_context(|| format!("Failed to store metadata for token {:?}", token))?;
+        info!(
+            "Token {:?}: symbol={:?}, decimals={:?}",
+            token, metadata.symbol, metadata.decimals
+        );
+        Ok(())
+    }
+
+    /// Register (token, owner) pairs for ERC20 Transfers that touch a watched
+    /// address from a token it is not tracked for yet.
+    ///
+    /// The balance is seeded with `balanceOf` at the previous block, so the
+    /// ERC20 tracker applies this block's transfers on top of it. All writes
+    /// go through the block's batch and are rolled back with it on reorgs.
+    /// Contracts whose `balanceOf` fails are skipped. Returns the newly
+    /// discovered token contracts.
+    async fn discover_tokens(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        receipts: &[(B256, Receipt)],
+        watched: &HashSet<Address>,
+    ) -> Result<Vec<Address>> {
+        let block_num = batch.block();
+        let prior_block = format!("0x{:x}", block_num.saturating_sub(1));
+        let mut discovered = Vec::new();
+        for (_tx_hash, receipt) in receipts {
+            for log in &receipt.logs {
+                // ERC20 only: ERC721 Transfers index the token ID as a 4th topic
+                if log.topics.len() != 3 || !self.erc20_tracker.is_transfer_event(log) {
+                    continue;
+                }
+                let Ok((from, to, _)) = self.erc20_tracker.parse_transfer_log(log) else {
+                    continue;
+                };
+                let token = log.address;
+                for owner in [from, to] {
+                    if owner == Address::ZERO
+                        || !watched.contains(&owner)
+                        || batch.get_token_watch_meta(token, owner)?.is_some()
+                    {
                         continue;
                     }
-                    let balance = self
-                        .rpc
-                        .erc20_balance_of(*token, *owner, &block_str)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Failed to get ERC20 balance for token {:?} owner {:?}",
-                                token, owner
-                            )
-                        })?;
-                    self.store
-                        .put_erc20_balance(*token, *owner, balance)
-                        .context("Failed to store ERC20 balance")?;
-                    self.store
-                        .put_erc20_snapshot(*token, *owner, current_block_num, balance)
-                        .context("Failed to store ERC20 snapshot")?;
-                    self.store
-                        .put_token_watch_meta(
-                            *token,
-                            *owner,
-                            &TokenWatchMeta {
-                                start_block: current_block_num,
-                            },
-                        )
-                        .context("Failed to store token watch meta")?;
+                    let balance = match self.rpc.erc20_balance_of(token, owner, &prior_block).await {
+                        Ok(balance) => balance,
+                        Err(e) => {
+                            warn!(
+                                "Token discovery: balanceOf failed for token {:?} owner {:?}, not tracking: {:?}",
+                                token, owner, e
+                            );
+                            continue;
+                        }
+                    };
+                    batch.put_erc20_balance(token, owner, balance)?;
+                    batch.put_erc20_snapshot(token, owner, balance)?;
+                    batch.put_token_watch_meta(
+                        token,
+                        owner,
+                        &TokenWatchMeta {
+                            auto_discovered: true,
+                            ..TokenWatchMeta::new(block_num)
+                        },
+                    )?;
                     info!(
-                        "Initialized ERC20 token {:?} for owner {:?}: balance={:?}",
-                        token, owner, balance
+                        "Discovered ERC20 token {:?} for owner {:?} at block {}: balance={:?}",
+                        token, owner, block_num, balance
                     );
+                    self.ensure_token_metadata(batch, token, &prior_block).await?;
+                    if !self.token_watchlist.contains(&token) && !discovered.contains(&token) {
+                        discovered.push(token);
+                    }
                 }
             }
         }
+        Ok(discovered)
+    }
 
+    /// Initialize ERC1155 tracking for every uninitialized (contract, id, owner) pair at the
+    /// batch's block.
+    async fn initialize_erc1155_pairs(&self, batch: &mut BlockWriteBatch<'_>) -> Result<()> {
+        for (contract, id) in &self.erc1155_watchlist {
+            for owner in &self.watchlist {
+                // Skip if already initialized (resuming)
+                if batch.get_erc1155_watch_meta(*contract, *id, *owner)?.is_some() {
+                    continue;
+                }
+                self.initialize_erc1155_pair(batch, *contract, *id, *owner).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch the balance of (contract, id, owner) with balanceOf and anchor it at the batch's
+    /// block.
+    ///
+    /// Writes the current balance, the anchoring snapshot and `TokenWatchMeta`.
+    /// A pair tracked before keeps its start and history, and gets a new
+    /// coverage segment from here.
+    async fn initialize_erc1155_pair(
+        &self,
+        batch: &mut BlockWriteBatch<'_>,
+        contract: Address,
+        id: U256,
+        owner: Address,
+    ) -> Result<()> {
+        let block_num = batch.block();
+        let block_str = format!("0x{:x}", block_num);
+        let balance = self
+            .rpc
+            .erc1155_balance_of_quorum(contract, owner, id, &block_str)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to get ERC1155 balance for {:?} id {} owner {:?}",
+                    contract, id, owner
+                )
+            })?;
+        batch
+            .put_erc1155_balance(contract, id, owner, balance)
+            .context("Failed to store ERC1155 balance")?;
+        batch
+            .put_erc1155_snapshot(contract, id, owner, balance)
+            .context("Failed to store ERC1155 snapshot")?;
+        let meta = match batch.get_erc1155_watch_meta(contract, id, owner)? {
+            Some(mut meta) => {
+                meta.rewatch(block_num);
+                meta
+            }
+            None => TokenWatchMeta::new(block_num),
+        };
+        batch
+            .put_erc1155_watch_meta(contract, id, owner, &meta)
+            .context("Failed to store ERC1155 watch meta")?;
+        info!(
+            "Initialized ERC1155 {:?} id {} for owner {:?}: balance={:?}",
+            contract, id, owner, balance
+        );
         Ok(())
     }
 
+    /// Fetch the receipts of every transaction in `block`, in transaction order.
+    ///
+    /// Prefers `eth_getBlockReceipts`, then a single batch request, and falls
+    /// back to one `eth_getTransactionReceipt` call per transaction.
+    async fn fetch_block_receipts(&mut self, block: &Block) -> Result<Vec<Receipt>> {
+        if block.transactions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if self.capabilities.block_receipts != Some(false) {
+            let result = self
+                .rpc
+                .get_block_receipts(&format!("0x{:x}", block.number))
+                .await
+                .and_then(|receipts| {
+                    check_receipts_match(block, &receipts)?;
+                    Ok(receipts)
+                });
+            if let Some(receipts) = NodeCapabilities::record(
+                &mut self.capabilities.block_receipts,
+                "eth_getBlockReceipts",
+                result,
+            ) {
+                return Ok(receipts);
+            }
+        }
+
+        let hashes: Vec<B256> = block.transactions.iter().map(|tx| tx.hash).collect();
+        if self.capabilities.batch != Some(false) {
+            let result = self
+                .rpc
+                .get_transaction_receipts_batch(&hashes)
+                .await
+                .and_then(|receipts| {
+                    check_receipts_match(block, &receipts)?;
+                    Ok(receipts)
+                });
+            if let Some(receipts) =
+                NodeCapabilities::record(&mut self.capabilities.batch, "JSON-RPC batch", result)
+            {
+                return Ok(receipts);
+            }
+        }
+
+        let mut receipts = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            let receipt = self
+                .rpc
+                .get_transaction_receipt(hash)
+                .await
+                .with_context(|| format!("Failed to fetch receipt for tx {:?}", hash))?;
+            receipts.push(receipt);
+        }
+        Ok(receipts)
+    }
+
+    /// Trace every transaction in `block` with `debug_traceBlockByNumber`.
+    ///
+    /// Returns `None` if block tracing is unavailable, in which case the
+    /// caller traces transactions one by one.
+    async fn fetch_block_traces(&mut self, block: &Block) -> Option<Vec<Option<CallTrace>>> {
+        if block.transactions.is_empty() || self.capabilities.trace_block == Some(false) {
+            return None;
+        }
+        let result = self
+            .rpc
+            .debug_trace_block_by_number_calltracer(&format!("0x{:x}", block.number), "10s")
+            .await
+            .and_then(|traces| {
+                if traces.len() != block.transactions.len() {
+                    anyhow::bail!(
+                        "debug_traceBlockByNumber returned {} traces for {} transactions",
+                        traces.len(),
+                        block.transactions.len()
+                    );
+                }
+                Ok(traces)
+            });
+        NodeCapabilities::record(
+            &mut self.capabilities.trace_block,
+            "debug_traceBlockByNumber",
+            result,
+        )
+    }
+
     /// Process a range of blocks sequentially.
     ///
     /// Fetches each block, filters relevant transactions, and applies them.
+    /// All writes for a block, including the head update, are collected in a
+    /// `BlockWriteBatch` and committed atomically, so a crash mid-block never
+    /// leaves a partially applied block behind.
+    /// If a block's parent hash does not match the stored hash of the previous
+    /// block, the store is rolled back to the common ancestor and processing
+    /// resumes from there.
     pub async fn process_block_range(&mut self, from: u64, to: u64) -> Result<()> {
         if from > to {
             return Ok(()); // Nothing to process
         }
 
         info!("Processing blocks {} to {}", from, to);
-        let watchlist_set: HashSet<Address> = self.watchlist.iter().copied().collect();
 
-        for block_num in from..=to {
+        let mut block_num = from;
+        while block_num <= to {
+            // Pick up watchlist edits (statectl watch) before each block
+            if self.persisted_watchlist {
+                self.sync_watchlist(block_num.saturating_sub(1)).await?;
+            }
+            let watchlist_set: HashSet<Address> = self.watchlist.iter().copied().collect();
+
             // Fetch full block with transactions
             let block_str = format!("0x{:x}", block_num);
             let block = self
@@ -276,6 +994,46 @@ impl Watcher {
                 .await
                 .with_context(|| format!("Failed to fetch block {}", block_num))?;
 
+            // Reorg check: the new block must build on the block we processed last
+            if block_num > 0 {
+                if let Some(stored_parent) = self.store.get_block_hash(block_num - 1)? {
+                    if stored_parent != block.parent_hash {
+                        warn!(
+                            "Reorg detected at block {}: parent {:?} does not match stored {:?}",
+                            block_num, block.parent_hash, stored_parent
+                        );
+                        let ancestor =
+                            find_common_ancestor(&self.store, &self.rpc, block_num - 1)
+                                .await
+                                .context("Failed to find common ancestor")?;
+                        rollback_to(&self.store, ancestor)
+                            .with_context(|| format!("Failed to roll back to block {}", ancestor))?;
+                        // Tokens discovered in the reverted blocks are no longer tracked
+                        self.load_discovered_tokens()?;
+                        // Watchlist additions initialized in them start over at the ancestor,
+                        // and addresses and tokens removed in them stop being covered there
+                        if self.persisted_watchlist {
+                            self.initialize_new_entries(ancestor).await?;
+                            end_removed_coverage(&self.store, ancestor)?;
+                        }
+                        info!("Rolled back to common ancestor {}, re-applying canonical chain", ancestor);
+                        block_num = ancestor + 1;
+                        continue;
+                    }
+                }
+            }
+
+            // Fetch receipts (and traces, if the node can trace whole blocks) up front
+            let receipts = self
+                .fetch_block_receipts(&block)
+                .await
+                .with_context(|| format!("Failed to fetch receipts for block {}", block_num))?;
+            let mut block_traces = self.fetch_block_traces(&block).await;
+
+            // Collect every write for this block; nothing reaches the store until commit
+            self.block_deltas.clear();
+            let mut batch = BlockWriteBatch::new(&self.store, block_num);
+
             info!(
                 "Processing block {} ({} transactions)",
                 block_num,
@@ -285,10 +1043,12 @@ impl Watcher {
             let mut traced_tx_count: u64 = 0;
             let mut internal_credit_count: u64 = 0;
             let mut trace_failures: u64 = 0;
-            let mut successful_receipts: Vec<(B256, crate::types::Receipt)> = Vec::new();
+            let mut successful_receipts: Vec<(B256, Receipt)> = Vec::new();
+            let miner_watched = watchlist_set.contains(&block.miner);
+            let mut priority_fee_income = U256::ZERO;
 
             // Process each transaction
-            for tx in &block.transactions {
+            for (tx_index, (tx, receipt)) in block.transactions.iter().zip(receipts).enumerate() {
                 // Check if transaction affects any watched address at the top level.
                 // Note: internal transfers from contracts to watched EOAs are handled
                 // separately via tracing and do not rely on this filter.
@@ -298,7 +1058,6 @@ impl Watcher {
                 // IMPORTANT: If sender is watched, we MUST process the transaction
                 // to deduct fees and update nonce, even if it's not an EOA→EOA transfer.
                 // For receiver-only transactions, we only process EOA→EOA transfers.
-                
                 let should_process = if sender_watched {
                     // Sender is watched: process ALL transactions (need to deduct fees)
                     true
@@ -307,35 +1066,55 @@ impl Watcher {
                     is_eoa_to_eoa_transfer(tx)
                 };
 
-                // Always fetch receipt (needed for fee calculation, status, and
-                // to decide whether we should consider internal transfers).
-                let receipt = self
-                    .rpc
-                    .get_transaction_receipt(tx.hash)
-                    .await
-                    .with_context(|| {
-                        format!("Failed to fetch receipt for tx {:?}", tx.hash)
-                    })?;
-
+                // The receipt is always needed (fee calculation, status, and
+                // deciding whether we should consider internal transfers).
                 if receipt.is_success() {
                     successful_receipts.push((tx.hash, receipt.clone()));
                 }
 
+                // Every transaction, failed or not, pays its priority fee to the fee recipient
+                // (deposit transactions pay no L2 fee at all)
+                if miner_watched && !tx.is_deposit() {
+                    let effective_gas_price =
+                        calculate_effective_gas_price(tx, &receipt, &block).with_context(|| {
+                            format!("Failed to calculate effective gas price for tx {:?}", tx.hash)
+                        })?;
+                    priority_fee_income = priority_fee_income.saturating_add(
+                        calculate_priority_fee(
+                            receipt.gas_used,
+                            effective_gas_price,
+                            block.base_fee_per_gas,
+                        ),
+                    );
+                }
+
                 // 1) Internal transfers via tracing (contract → watched EOA).
                 if receipt.is_success() {
                     traced_tx_count += 1;
 
-                    match self
-                        .rpc
-                        .debug_trace_transaction_calltracer(tx.hash, "10s")
-                        .await
-                    {
+                    // Use the block-level trace when available, otherwise trace this tx
+                    let trace = match block_traces.as_mut() {
+                        Some(traces) => traces[tx_index].take().ok_or_else(|| {
+                            anyhow::anyhow!("debug_traceBlockByNumber returned no trace")
+                        }),
+                        None => {
+                            self.rpc
+                                .debug_trace_transaction_calltracer(tx.hash, "10s")
+                                .await
+                        }
+                    };
+                    match trace {
                         Ok(trace) => {
                             // Preload contract flags for all senders in this trace so that
                             // the `sender_is_contract` predicate can be pure and fast.
                             let senders = collect_senders(&trace);
-                            self.preload_contract_flags(&senders, block_num)
-                                .await
+                            Self::preload_contract_flags(
+                                &self.rpc,
+                                &mut self.cache,
+                                &senders,
+                                block_num,
+                            )
+                            .await
                                 .with_context(|| {
                                     format!(
                                         "Failed to preload contract flags for trace of tx {:?}",
@@ -360,7 +1139,7 @@ impl Watcher {
 
                             for t in internal_transfers {
                                 apply_internal_credit(
-                                    &self.store,
+                                    &mut batch,
                                     t.to,
                                     t.value,
                                     block_num,
@@ -372,6 +1151,22 @@ impl Watcher {
                                         tx.hash
                                     )
                                 })?;
+                                batch
+                                    .put_tx_record(
+                                        t.to,
+                                        &TxRecord {
+                                            block: block_num,
+                                            tx_index: tx_index as u32,
+                                            tx_hash: Some(tx.hash),
+                                            counterparty: Some(t.from),
+                                            direction: TxDirection::In,
+                                            value: t.value,
+                                            fee: U256::ZERO,
+                                            success: true,
+                                            source: TxSource::Internal,
+                                        },
+                                    )
+                                    .context("Failed to save internal tx record")?;
                                 internal_credit_count += 1;
                             }
                         }
@@ -415,12 +1210,14 @@ impl Watcher {
                     }
 
                     apply_transaction(
-                        &self.store,
+                        &mut batch,
                         &self.rpc,
                         &mut self.cache,
                         tx,
+                        tx_index as u32,
                         &receipt,
                         &block,
+                        self.chain_profile,
                         &watchlist_set,
                         &mut self.block_deltas,
                     )
@@ -429,24 +1226,168 @@ impl Watcher {
                 }
             }
 
-            // Run ERC20 tracker on successful receipts
-            if !self.token_watchlist.is_empty() && !successful_receipts.is_empty() {
-                let watched_tokens: HashSet<Address> =
-                    self.token_watchlist.iter().copied().collect();
-                let receipt_refs: Vec<(B256, &crate::types::Receipt)> = successful_receipts
+            // Credit priority fees when a watched address is the block's fee recipient
+            if miner_watched {
+                apply_priority_fee_credit(
+                    &mut batch,
+                    block.miner,
+                    priority_fee_income,
+                    block_num,
+                    &mut self.block_deltas,
+                )
+                .context("Failed to apply priority fee income")?;
+            }
+
+            // Credit validator withdrawals (EIP-4895) to watched addresses.
+            // They are applied after all transactions, matching execution order.
+            for (position, w) in block.withdrawals.iter().enumerate() {
+                if !watchlist_set.contains(&w.address) {
+                    continue;
+                }
+                apply_withdrawal_credit(
+                    &mut batch,
+                    w.address,
+                    w.amount_wei(),
+                    block_num,
+                    &mut self.block_deltas,
+                )
+                .with_context(|| format!("Failed to apply withdrawal {}", w.index))?;
+                batch
+                    .put_tx_record(
+                        w.address,
+                        &TxRecord {
+                            block: block_num,
+                            tx_index: (block.transactions.len() + position) as u32,
+                            tx_hash: None,
+                            counterparty: None,
+                            direction: TxDirection::In,
+                            value: w.amount_wei(),
+                            fee: U256::ZERO,
+                            success: true,
+                            source: TxSource::Withdrawal,
+                        },
+                    )
+                    .context("Failed to save withdrawal tx record")?;
+            }
+
+            // Register tokens seen in Transfers of watched addresses before the ERC20 tracker runs
+            let mut discovered = Vec::new();
+            if self.token_discovery {
+                discovered = self
+                    .discover_tokens(&mut batch, &successful_receipts, &watchlist_set)
+                    .await
+                    .with_context(|| format!("Token discovery failed for block {}", block_num))?;
+            }
+            let watched_tokens: HashSet<Address> = self
+                .token_watchlist
+                .iter()
+                .chain(&self.discovered_tokens)
+                .chain(&discovered)
+                .copied()
+                .collect();
+
+            // Run log trackers (tokens, NFTs, approvals, custom events) on successful receipts
+            let run_erc20 = !watched_tokens.is_empty();
+            let run_erc1155 = !self.erc1155_watchlist.is_empty();
+            let run_log_trackers = run_erc20
+                || run_erc1155
+                || self.erc721_tracker.is_some()
+                || self.approval_tracker.is_some()
+                || self.event_tracker.is_some();
+            if run_log_trackers && !successful_receipts.is_empty() {
+                let receipt_refs: Vec<(B256, &Receipt)> = successful_receipts
                     .iter()
                     .map(|(h, r)| (*h, r))
                     .collect();
-                let ctx = TrackerContext {
+                let mut ctx = TrackerContext {
                     store: &self.store as &dyn StateStore,
+                    batch: &mut batch,
                     rpc: &self.rpc,
                     watched_eoas: &watchlist_set,
                     watched_tokens: &watched_tokens,
                     block_number: block_num,
                 };
-                self.erc20_tracker
-                    .process_block(&ctx, &receipt_refs)
-                    .with_context(|| format!("ERC20 tracker failed for block {}", block_num))?;
+                if run_erc20 {
+                    self.erc20_tracker
+                        .process_block(&mut ctx, &receipt_refs)
+                        .with_context(|| format!("ERC20 tracker failed for block {}", block_num))?;
+                }
+                if run_erc1155 {
+                    self.erc1155_tracker
+                        .process_block(&mut ctx, &receipt_refs)
+                        .with_context(|| format!("ERC1155 tracker failed for block {}", block_num))?;
+                }
+                if let Some(erc721_tracker) = &self.erc721_tracker {
+                    erc721_tracker
+                        .process_block(&mut ctx, &receipt_refs)
+                        .with_context(|| format!("ERC721 tracker failed for block {}", block_num))?;
+                }
+                if let Some(approval_tracker) = &self.approval_tracker {
+                    approval_tracker
+                        .process_block(&mut ctx, &receipt_refs)
+                        .with_context(|| format!("Approval tracker failed for block {}", block_num))?;
+                }
+                if let Some(event_tracker) = &self.event_tracker {
+                    event_tracker
+                        .process_block(&mut ctx, &receipt_refs)
+                        .with_context(|| format!("Event tracker failed for block {}", block_num))?;
+                }
+            }
+
+            // Check Transfer-derived balances (fee-on-transfer and other non-standard tokens)
+            if run_erc20 && !successful_receipts.is_empty() {
+                let adjusted = self
+                    .erc20_tracker
+                    .verify_balances(
+                        &mut batch,
+                        &self.rpc,
+                        &watchlist_set,
+                        &watched_tokens,
+                        &self.token_strategies,
+                        &successful_receipts,
+                        self.verify_erc20,
+                    )
+                    .await
+                    .with_context(|| format!("ERC20 verification failed for block {}", block_num))?;
+                if adjusted > 0 {
+                    warn!("Adjusted {} ERC20 balance(s) at block {}", adjusted, block_num);
+                }
+            }
+
+            // Rebasing and share-based tokens: record balance changes that transfers do not explain
+            if !self.token_strategies.is_empty() {
+                apply_token_strategies(
+                    &mut batch,
+                    &self.rpc,
+                    &self.token_strategies,
+                    &self.watchlist,
+                    &successful_receipts,
+                )
+                .await
+                .with_context(|| format!("Token strategies failed for block {}", block_num))?;
+            }
+
+            // Compare derived balances with the node at the configured cadence
+            if self.reconcile_every > 0 && block_num.is_multiple_of(self.reconcile_every) {
+                let tokens: Vec<Address> = watched_tokens.iter().copied().collect();
+                let drift = reconcile_block(
+                    &mut batch,
+                    &self.rpc,
+                    &self.watchlist,
+                    &tokens,
+                    self.auto_correct,
+                    &mut self.block_deltas,
+                )
+                .await
+                .with_context(|| format!("Reconciliation failed for block {}", block_num))?;
+                if !drift.is_empty() {
+                    warn!(
+                        "Reconciliation found {} drift record(s) at block {} (auto_correct={})",
+                        drift.len(),
+                        block_num,
+                        self.auto_correct
+                    );
+                }
             }
 
             // After processing all transactions in the block, persist deltas and snapshots
@@ -458,15 +1399,14 @@ impl Watcher {
                 }
 
                 // Store the delta
-                self.store
-                    .put_delta(*addr, block_num, delta)
+                batch
+                    .put_delta(*addr, delta)
                     .with_context(|| {
                         format!("Failed to store delta for {:?} at block {}", addr, block_num)
                     })?;
 
                 // Get the current balance after all transactions in this block
-                let account = self
-                    .store
+                let account = batch
                     .get_account(*addr)
                     .context("Failed to get account for snapshot")?
                     .ok_or_else(|| {
@@ -474,17 +1414,31 @@ impl Watcher {
                     })?;
 
                 // Store the snapshot (balance after this block)
-                self.store
-                    .put_snapshot(*addr, block_num, account.balance)
+                batch
+                    .put_snapshot(*addr, account.balance)
                     .with_context(|| {
                         format!("Failed to store snapshot for {:?} at block {}", addr, block_num)
                     })?;
             }
 
-            // Update head after processing block
-            self.store
+            // Store the block hash for reorg detection and advance the head
+            batch
+                .put_block_hash(block.hash)
+                .context("Failed to store block hash")?;
+            batch
                 .set_head(block_num)
                 .context("Failed to update head block")?;
+            batch
+                .set_head_finality(self.follow_mode)
+                .context("Failed to update head finality")?;
+            prune_undo(&mut batch).context("Failed to prune undo records")?;
+
+            // Commit the whole block (plus its undo record) atomically
+            batch
+                .commit()
+                .with_context(|| format!("Failed to commit block {}", block_num))?;
+            // Discovered tokens are tracked from here on only once their pairs are stored
+            self.discovered_tokens.extend(discovered);
 
             info!(
                 "Completed block {} ({} addresses changed, traced_tx_count={}, internal_credits={}, trace_failures={})",
@@ -494,6 +1448,7 @@ impl Watcher {
                 internal_credit_count,
                 trace_failures
             );
+            block_num += 1;
         }
 
         Ok(())
@@ -501,9 +1456,16 @@ impl Watcher {
 
     /// Run the main watcher loop.
     ///
-    /// Polls for new blocks every 12 seconds and processes them.
+    /// Processes new blocks up to the block selected by the follow mode,
+    /// then waits for the next head notification (with a WebSocket URL) or
+    /// for the poll interval.
     pub async fn run(&mut self) -> Result<()> {
-        info!("Starting watcher loop...");
+        info!("Starting watcher loop (following {})...", self.follow_mode);
+
+        let mut subscription = self
+            .ws_url
+            .clone()
+            .map(|url| NewHeadsSubscription::new(url, self.poll_interval));
 
         loop {
             // Get current local head
@@ -513,29 +1475,471 @@ impl Watcher {
                 .context("Failed to get local head")?
                 .unwrap_or(0);
 
-            // Get current latest block number (use same method as initialization for consistency)
-            let latest_head = self
-                .rpc
-                .get_block_number("latest")
-                .await
-                .context("Failed to get latest block number")?;
+            // Update endpoint heads so lagging endpoints are skipped
+            if self.rpc.endpoint_count() > 1 {
+                self.rpc.refresh_heads().await;
+            }
 
-            if local_head < latest_head {
+            // Get the followed block number (same method as initialization for consistency)
+            let target_head = match self.follow_head().await {
+                Ok(head) => Some(head),
+                Err(e) if is_retryable_error(&e) => {
+                    warn!("Failed to get {} head, retrying next poll: {:?}", self.follow_mode, e);
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+
+            if let Some(target_head) = target_head.filter(|target| local_head < *target) {
                 info!(
-                    "New blocks available: local={}, latest={}",
-                    local_head, latest_head
+                    "New blocks available: local={}, {}={}",
+                    local_head, self.follow_mode, target_head
                 );
 
-                // Process blocks from local_head + 1 to latest_head
-                self.process_block_range(local_head + 1, latest_head)
-                    .await
-                    .context("Failed to process block range")?;
-            } else {
-                info!("Up to date. Local head: {}, Latest: {}", local_head, latest_head);
+                // Process blocks from local_head + 1 to target_head. Each block
+                // commits atomically, so after a node outage that outlasted the
+                // RPC retries we resume from the last committed block.
+                match self.process_block_range(local_head + 1, target_head).await {
+                    Ok(()) => {}
+                    Err(e) if is_retryable_error(&e) => {
+                        warn!("Node unavailable, retrying next poll: {:?}", e);
+                    }
+                    Err(e) => return Err(e.context("Failed to process block range")),
+                }
+            } else if let Some(target_head) = target_head {
+                info!(
+                    "Up to date. Local head: {}, {}: {}",
+                    local_head, self.follow_mode, target_head
+                );
+            }
+
+            // Wait for the next head (or poll interval) before polling again
+            match subscription.as_mut() {
+                Some(subscription) => subscription.wait().await,
+                None => tokio::time::sleep(self.poll_interval).await,
+            }
+        }
+    }
+}
+
+/// Check that block-level receipts line up one-to-one with the block's transactions.
+fn check_receipts_match(block: &Block, receipts: &[Receipt]) -> Result<()> {
+    if receipts.len() != block.transactions.len() {
+        anyhow::bail!(
+            "Got {} receipts for {} transactions",
+            receipts.len(),
+            block.transactions.len()
+        );
+    }
+    for (tx, receipt) in block.transactions.iter().zip(receipts) {
+        if let Some(hash) = receipt.transaction_hash {
+            if hash != tx.hash {
+                anyhow::bail!("Receipt for {:?} does not match transaction {:?}", hash, tx.hash);
             }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::encode_erc20_snapshot_key;
+    use crate::records::{decode_u256, WatchMeta};
+    use crate::rpc::mock::{mock_rpc, word};
+    use crate::tracker_erc20::TRANSFER_TOPIC;
+    use crate::types::Log;
+    use crate::watchlist::WatchlistChange;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
 
-            // Wait 12 seconds before next poll
-            tokio::time::sleep(tokio::time::Duration::from_secs(12)).await;
+    fn transfer_log(token: Address, from: Address, to: Address, token_id: Option<u64>) -> Log {
+        let topic = |addr: Address| format!("0x{:0>64}", hex::encode(addr));
+        let mut topics = vec![format!("0x{}", hex::encode(TRANSFER_TOPIC)), topic(from), topic(to)];
+        let mut data = U256::from(50u64).to_be_bytes::<32>().to_vec();
+        if let Some(id) = token_id {
+            // ERC721: the token ID is indexed and the data is empty
+            topics.push(format!("0x{:0>64x}", id));
+            data.clear();
+        }
+        Log {
+            address: token,
+            topics,
+            data,
         }
     }
+
+    fn receipt(logs: Vec<Log>) -> (B256, Receipt) {
+        let receipt = Receipt {
+            status: 1,
+            gas_used: U256::from(21000u64),
+            effective_gas_price: None,
+            logs,
+            blob_gas_used: None,
+            blob_gas_price: None,
+            l1_fee: None,
+            transaction_hash: None,
+        };
+        (B256::ZERO, receipt)
+    }
+
+    #[tokio::test]
+    async fn test_discover_tokens_seeds_untracked_pairs_at_previous_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let owner = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let new_token = Address::repeat_byte(0xaa);
+        let sent_token = Address::repeat_byte(0xee);
+        let tracked_token = Address::repeat_byte(0xbb);
+        let nft = Address::repeat_byte(0xcc);
+        let reverting_token = Address::repeat_byte(0xdd);
+        let tracked_meta = TokenWatchMeta::new(50);
+        store.put_token_watch_meta(tracked_token, owner, &tracked_meta).unwrap();
+        store.set_head(100).unwrap();
+
+        let rpc = mock_rpc(move |method, params| {
+            assert_eq!(method, "eth_call");
+            let to: Address = params[0]["to"].as_str().unwrap().parse().unwrap();
+            let data = params[0]["data"].as_str().unwrap();
+            if to == reverting_token {
+                return Err(json!({"code": 3, "message": "execution reverted"}));
+            }
+            if data.starts_with("0x70a08231") {
+                // balanceOf: 500 at the block before the transfers, 550 after
+                return Ok(if params[1] == "0x64" { word(500) } else { word(550) });
+            }
+            if data == "0x95d89b41" {
+                let mut symbol = [0u8; 32];
+                symbol[..3].copy_from_slice(b"TKN");
+                return Ok(json!(format!("0x{}", hex::encode(symbol))));
+            }
+            Ok(json!("0x"))
+        });
+        let mut watcher = Watcher::new(store, rpc);
+        let watched: HashSet<Address> = [owner].into_iter().collect();
+        let receipts = vec![
+            receipt(vec![
+                transfer_log(new_token, other, owner, None),
+                transfer_log(new_token, owner, other, None),
+                transfer_log(sent_token, owner, other, None),
+            ]),
+            receipt(vec![
+                transfer_log(tracked_token, other, owner, None),
+                transfer_log(nft, other, owner, Some(7)),
+                transfer_log(reverting_token, other, owner, None),
+            ]),
+        ];
+
+        let discovered = {
+            let mut batch = BlockWriteBatch::new(watcher.store(), 101);
+            let discovered = watcher
+                .discover_tokens(&mut batch, &receipts, &watched)
+                .await
+                .unwrap();
+            batch.set_head(101).unwrap();
+            batch.commit().unwrap();
+            discovered
+        };
+        assert_eq!(discovered, vec![new_token, sent_token]);
+        watcher.discovered_tokens.extend(discovered);
+
+        // Seeded with balanceOf at block 100, so the tracker can apply block 101 on top
+        let store = watcher.store();
+        assert_eq!(store.get_erc20_balance(new_token, owner).unwrap(), Some(U256::from(500u64)));
+        let snapshot = store
+            .get_raw("erc20_snapshots", &encode_erc20_snapshot_key(new_token, owner, 101))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decode_u256(&snapshot).unwrap(), U256::from(500u64));
+        assert_eq!(
+            store.get_token_watch_meta(new_token, owner).unwrap(),
+            Some(TokenWatchMeta {
+                auto_discovered: true,
+                ..TokenWatchMeta::new(101)
+            })
+        );
+        let metadata = store.get_token_metadata(new_token).unwrap().unwrap();
+        assert_eq!(metadata.symbol.as_deref(), Some("TKN"));
+        // Sending a token discovers it as well
+        assert!(store.get_token_watch_meta(sent_token, owner).unwrap().unwrap().auto_discovered);
+
+        // Tracked pairs, ERC721 Transfers and tokens without balanceOf are skipped
+        assert_eq!(store.get_token_watch_meta(tracked_token, owner).unwrap(), Some(tracked_meta));
+        assert_eq!(store.get_token_watch_meta(nft, owner).unwrap(), None);
+        assert_eq!(store.get_token_watch_meta(reverting_token, owner).unwrap(), None);
+        assert_eq!(store.get_token_metadata(reverting_token).unwrap(), None);
+
+        // Rolling the block back forgets the discovery, metadata included
+        rollback_to(store, 100).unwrap();
+        assert_eq!(store.get_token_watch_meta(new_token, owner).unwrap(), None);
+        assert_eq!(store.get_token_metadata(new_token).unwrap(), None);
+        watcher.load_discovered_tokens().unwrap();
+        assert!(watcher.discovered_tokens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_reverts_watchlist_addition() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let addr = Address::repeat_byte(0x11);
+        let token = Address::repeat_byte(0xaa);
+        store.set_head(99).unwrap();
+        let mut batch = BlockWriteBatch::new(&store, 100);
+        batch.set_head(100).unwrap();
+        batch.commit().unwrap();
+
+        // Balances differ per block, so the anchor block can be told apart
+        let rpc = mock_rpc(|method, params| match method {
+            "eth_getBalance" => Ok(json!(if params[1] == "0x64" { "0x3e8" } else { "0x384" })),
+            "eth_getTransactionCount" => Ok(json!("0x5")),
+            "eth_call" if params[0]["data"].as_str().unwrap().starts_with("0x70a08231") => {
+                Ok(if params[1] == "0x64" { word(500) } else { word(450) })
+            }
+            _ => Ok(json!("0x")),
+        });
+        let mut watcher = Watcher::new(store, rpc);
+        watcher.persisted_watchlist = true;
+
+        // Added with statectl while block 100 is the head
+        watcher.store().put_watched_address(addr).unwrap();
+        watcher.store().put_watched_token(token, TokenStrategy::Transfers).unwrap();
+        watcher.sync_watchlist(100).await.unwrap();
+        let store = watcher.store();
+        assert_eq!(store.get_account(addr).unwrap().unwrap().balance, U256::from(1000u64));
+        assert_eq!(store.get_snapshot(addr, 100).unwrap(), Some(U256::from(1000u64)));
+        assert_eq!(store.get_watch_meta(addr).unwrap(), Some(WatchMeta::new(100)));
+        assert_eq!(store.get_erc20_balance(token, addr).unwrap(), Some(U256::from(500u64)));
+
+        // The initialization is journaled with block 100, next to its own writes
+        let undo = store.get_block_undo(100).unwrap().unwrap();
+        assert!(undo.entries.iter().any(|e| e.cf == "meta"));
+        assert!(undo.entries.iter().any(|e| e.cf == "watch_meta"));
+
+        // A reorg of block 100 reverts it; the entries are still watched and start over at 99
+        rollback_to(store, 99).unwrap();
+        assert_eq!(store.get_account(addr).unwrap(), None);
+        assert_eq!(store.get_snapshot(addr, 100).unwrap(), None);
+        assert_eq!(store.get_watch_meta(addr).unwrap(), None);
+        assert_eq!(store.get_token_watch_meta(token, addr).unwrap(), None);
+        assert_eq!(store.get_erc20_balance(token, addr).unwrap(), None);
+        assert_eq!(store.get_token_metadata(token).unwrap(), None);
+        assert_eq!(store.get_head().unwrap(), Some(99));
+
+        watcher.initialize_new_entries(99).await.unwrap();
+        let store = watcher.store();
+        assert_eq!(store.get_snapshot(addr, 99).unwrap(), Some(U256::from(900u64)));
+        assert_eq!(store.get_watch_meta(addr).unwrap(), Some(WatchMeta::new(99)));
+        assert_eq!(
+            store.get_token_watch_meta(token, addr).unwrap().unwrap().start_block,
+            99
+        );
+        assert_eq!(store.get_erc20_balance(token, addr).unwrap(), Some(U256::from(450u64)));
+    }
+
+    #[tokio::test]
+    async fn test_readded_token_gets_new_coverage_segment() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let addr = Address::repeat_byte(0x11);
+        let token = Address::repeat_byte(0xaa);
+        store.put_watched_address(addr).unwrap();
+        store.put_watched_token(token, TokenStrategy::Transfers).unwrap();
+        let account = AccountRecord {
+            nonce: 0,
+            balance: U256::ZERO,
+            code_hash: B256::ZERO,
+        };
+        store.put_account(addr, &account).unwrap();
+        store.put_watch_meta(addr, &WatchMeta::new(100)).unwrap();
+        let pair = TokenWatchMeta {
+            auto_discovered: true,
+            ..TokenWatchMeta::new(100)
+        };
+        store.put_token_watch_meta(token, addr, &pair).unwrap();
+        store.put_erc20_snapshot(token, addr, 100, U256::from(500u64)).unwrap();
+        store.put_erc20_balance(token, addr, U256::from(500u64)).unwrap();
+        let commit_head = |head: u64| {
+            let mut batch = BlockWriteBatch::new(&store, head);
+            batch.set_head(head).unwrap();
+            batch.commit().unwrap();
+        };
+
+        // Removed while block 150 is the head
+        commit_head(150);
+        WatchlistChange::RemoveToken(token).apply(&store).unwrap();
+        commit_head(200);
+        assert_eq!(
+            store.get_token_watch_meta(token, addr).unwrap().unwrap().end_block,
+            Some(150)
+        );
+        let balances = store
+            .get_erc20_balances_in_range_with_metadata(token, addr, 100, 200)
+            .unwrap();
+        assert_eq!(balances.effective_end, 150);
+        assert_eq!(balances.watch_end_block, Some(150));
+
+        // Added back while block 200 is the head
+        WatchlistChange::AddToken(token, TokenStrategy::Transfers).apply(&store).unwrap();
+        let rpc = mock_rpc(|method, params| match method {
+            "eth_call" if params[0]["data"].as_str().unwrap().starts_with("0x70a08231") => {
+                Ok(word(700))
+            }
+            _ => Ok(json!("0x")),
+        });
+        let mut watcher = Watcher::new(store, rpc);
+        watcher.persisted_watchlist = true;
+        watcher.sync_watchlist(200).await.unwrap();
+
+        // The pair keeps its start, history and discovery flag; the removal is a gap
+        let store = watcher.store();
+        let pair = store.get_token_watch_meta(token, addr).unwrap().unwrap();
+        assert_eq!(pair.start_block, 100);
+        assert!(pair.auto_discovered && pair.is_watched());
+        assert_eq!(pair.gaps, vec![(151, 199)]);
+        assert_eq!(store.get_erc20_balance(token, addr).unwrap(), Some(U256::from(700u64)));
+
+        let balances = store
+            .get_erc20_balances_in_range_with_metadata(token, addr, 100, 200)
+            .unwrap();
+        assert_eq!(balances.coverage_gaps, vec![(151, 199)]);
+        assert_eq!(balances.data.len(), 52);
+        assert_eq!(balances.data[0], (100, U256::from(500u64)));
+        assert_eq!(balances.data[50], (150, U256::from(500u64)));
+        assert_eq!(balances.data[51], (200, U256::from(700u64)));
+    }
+
+    #[tokio::test]
+    async fn test_readded_address_reseeds_discovered_and_erc1155_pairs() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let addr = Address::repeat_byte(0x11);
+        let (token, contract) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xcc));
+        let id = U256::from(7u64);
+        store.put_watched_address(addr).unwrap();
+        let account = AccountRecord {
+            nonce: 0,
+            balance: U256::ZERO,
+            code_hash: B256::ZERO,
+        };
+        store.put_account(addr, &account).unwrap();
+        store.put_watch_meta(addr, &WatchMeta::new(100)).unwrap();
+        let pair = TokenWatchMeta {
+            auto_discovered: true,
+            ..TokenWatchMeta::new(100)
+        };
+        store.put_token_watch_meta(token, addr, &pair).unwrap();
+        store.put_erc20_snapshot(token, addr, 100, U256::from(500u64)).unwrap();
+        store.put_erc20_balance(token, addr, U256::from(500u64)).unwrap();
+        store.put_erc1155_watch_meta(contract, id, addr, &TokenWatchMeta::new(100)).unwrap();
+        store.put_erc1155_snapshot(contract, id, addr, 100, U256::from(2u64)).unwrap();
+        store.put_erc1155_balance(contract, id, addr, U256::from(2u64)).unwrap();
+        let commit_head = |head: u64| {
+            let mut batch = BlockWriteBatch::new(&store, head);
+            batch.set_head(head).unwrap();
+            batch.commit().unwrap();
+        };
+
+        // Removed while block 150 is the head, added back while block 200 is
+        commit_head(150);
+        WatchlistChange::RemoveAddress(addr).apply(&store).unwrap();
+        commit_head(200);
+        WatchlistChange::AddAddress(addr).apply(&store).unwrap();
+        let rpc = mock_rpc(|method, params| match method {
+            "eth_getBalance" => Ok(json!("0x0")),
+            "eth_getTransactionCount" => Ok(json!("0x0")),
+            "eth_call" => {
+                let data = params[0]["data"].as_str().unwrap();
+                if data.starts_with("0x70a08231") {
+                    Ok(word(700))
+                } else if data.starts_with("0x00fdd58e") {
+                    Ok(word(3))
+                } else {
+                    Ok(json!("0x"))
+                }
+            }
+            _ => Ok(json!("0x")),
+        });
+        let mut watcher = Watcher::new(store, rpc).with_erc1155_watchlist(vec![(contract, id)]);
+        watcher.persisted_watchlist = true;
+        watcher.sync_watchlist(200).await.unwrap();
+
+        // Both pairs resume from a fresh balance anchored at the new segment's start
+        let store = watcher.store();
+        assert_eq!(store.get_erc20_balance(token, addr).unwrap(), Some(U256::from(700u64)));
+        let balances = store
+            .get_erc20_balances_in_range_with_metadata(token, addr, 100, 200)
+            .unwrap();
+        assert_eq!(balances.coverage_gaps, vec![(151, 199)]);
+        assert_eq!(balances.data.last(), Some(&(200, U256::from(700u64))));
+
+        assert_eq!(
+            store.get_erc1155_balance(contract, id, addr).unwrap(),
+            Some(U256::from(3u64))
+        );
+        let balances = store
+            .get_erc1155_balances_in_range_with_metadata(contract, id, addr, 100, 200)
+            .unwrap();
+        assert_eq!(balances.coverage_gaps, vec![(151, 199)]);
+        assert_eq!(balances.data[50], (150, U256::from(2u64)));
+        assert_eq!(balances.data.last(), Some(&(200, U256::from(3u64))));
+    }
+
+    #[tokio::test]
+    async fn test_token_metadata_is_not_stored_when_nothing_decodes() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path()).unwrap();
+        let (failing, flagged) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let flagged_metadata = TokenMetadata {
+            non_standard: true,
+            ..TokenMetadata::default()
+        };
+        store.put_token_metadata(flagged, &flagged_metadata).unwrap();
+        let rpc = mock_rpc(move |method, params| match method {
+            "eth_call" if params[0]["to"] == json!(format!("0x{:x}", flagged)) => Ok(word(18)),
+            _ => Err(json!({"code": 3, "message": "execution reverted"})),
+        });
+        let watcher = Watcher::new(store, rpc);
+
+        let mut batch = BlockWriteBatch::new(watcher.store(), 100);
+        watcher.ensure_token_metadata(&mut batch, failing, "0x64").await.unwrap();
+        assert_eq!(batch.get_token_metadata(failing).unwrap(), None);
+
+        // Metadata without any decoded field is fetched again; the flag is kept
+        watcher.ensure_token_metadata(&mut batch, flagged, "0x64").await.unwrap();
+        let metadata = batch.get_token_metadata(flagged).unwrap().unwrap();
+        assert_eq!(metadata.decimals, Some(18));
+        assert!(metadata.non_standard);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_seeds_only_an_empty_watchlist() {
+        let temp_dir = TempDir::new().unwrap();
+        let (kept, removed) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        let watchlist_path = temp_dir.path().join("watchlist.txt");
+        std::fs::write(&watchlist_path, format!("0x{:x}\n0x{:x}\n", kept, removed)).unwrap();
+        let handler = |method: &str, _: &Value| match method {
+            "eth_getBlockByNumber" => {
+                Ok(json!({"number": "0x96", "hash": format!("{:?}", B256::ZERO)}))
+            }
+            "eth_getBalance" => Ok(json!("0x3e8")),
+            "eth_getTransactionCount" => Ok(json!("0x0")),
+            _ => Ok(json!("0x")),
+        };
+
+        // First start: the files seed the empty persisted watchlist
+        let store = RocksStateStore::open(temp_dir.path().join("state_db")).unwrap();
+        let mut watcher = Watcher::new(store, mock_rpc(handler));
+        watcher.initialize(&watchlist_path, None).await.unwrap();
+        assert_eq!(watcher.store().get_watched_addresses().unwrap(), vec![kept, removed]);
+
+        // Removed with statectl: a restart does not add it back from the file
+        WatchlistChange::RemoveAddress(removed).apply(watcher.store()).unwrap();
+        drop(watcher);
+        let store = RocksStateStore::open(temp_dir.path().join("state_db")).unwrap();
+        let mut watcher = Watcher::new(store, mock_rpc(handler));
+        watcher.initialize(&watchlist_path, None).await.unwrap();
+        assert_eq!(watcher.store().get_watched_addresses().unwrap(), vec![kept]);
+        assert_eq!(watcher.watchlist, vec![kept]);
+    }
 }
@@ -3,12 +3,18 @@
 //! Monitors finalized blocks and updates local state for watched EOA addresses.
 //! Handles EOA→EOA ETH transfers with correct gas/fee accounting.
 
-use kage::rpc::RpcClient;
+use kage::backfill::{backfill, BackfillTarget};
+use kage::config::{load_erc1155_watchlist, ChainProfile, FollowMode};
+use kage::rpc::{RpcClient, RpcConfig};
 use kage::store::RocksStateStore;
+use kage::tracker_events::{load_event_definitions, EventTracker};
 use kage::watcher::Watcher;
+use kage::watchlist::watch_queue_path;
+use alloy_primitives::Address;
 use anyhow::{Context, Result};
 use clap::Parser;
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing::{info, Level};
 use tracing_subscriber;
 
@@ -17,11 +23,12 @@ use tracing_subscriber;
 #[command(name = "watcher")]
 #[command(about = "Monitor Ethereum blocks and update state for watched addresses")]
 struct Args {
-    /// RPC endpoint URL (e.g., https://eth.llamarpc.com)
-    #[arg(short, long, default_value = "http://127.0.0.1:8545")]
-    rpc_url: String,
+    /// RPC endpoint URL (e.g., https://eth.llamarpc.com); repeat or comma-separate
+    /// several for failover, in order of preference
+    #[arg(short, long, default_value = "http://127.0.0.1:8545", value_delimiter = ',')]
+    rpc_url: Vec<String>,
 
-    /// Path to watchlist file (one address per line)
+    /// Path to watchlist file (one address per line), added to the persisted watchlist
     #[arg(short, long, default_value = "watchlist.txt")]
     watchlist: PathBuf,
 
@@ -29,9 +36,110 @@ struct Args {
     #[arg(short, long)]
     tokens: Option<PathBuf>,
 
+    /// Path to ERC1155 watchlist file (one "<contract> <id>" per line, optional)
+    #[arg(long)]
+    erc1155: Option<PathBuf>,
+
+    /// Track ERC721 NFT ownership of watched addresses
+    #[arg(long)]
+    nfts: bool,
+
+    /// Track token approvals (ERC20 Approval, ApprovalForAll) granted by watched addresses
+    #[arg(long)]
+    approvals: bool,
+
+    /// Automatically track ERC20 tokens sent to or from watched addresses
+    #[arg(long)]
+    discover_tokens: bool,
+
+    /// Verify ERC20 balances changed by Transfer events with balanceOf
+    /// (detects fee-on-transfer and other non-standard tokens)
+    #[arg(long)]
+    verify_erc20: bool,
+
+    /// Path to event definitions to decode and store (JSON ABI or one
+    /// `event Name(type [indexed] name, ...) [@ 0xcontract,...]` per line)
+    #[arg(long)]
+    events: Option<PathBuf>,
+
     /// Path to RocksDB database directory
     #[arg(short, long, default_value = "./state_db")]
     db_path: PathBuf,
+
+    /// Chain profile for fee accounting (ethereum, op-stack)
+    #[arg(long, value_enum, default_value_t = ChainProfile::Ethereum)]
+    chain_profile: ChainProfile,
+
+    /// WebSocket URL for eth_subscribe("newHeads"); polls on every new head instead of a fixed interval
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Seconds between polls (and fallback interval while the WebSocket is down)
+    #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u64).range(1..))]
+    poll_interval_secs: u64,
+
+    /// Block to follow: latest, latest-N (N confirmations), safe or finalized
+    #[arg(long, default_value = "latest")]
+    follow: FollowMode,
+
+    /// Reconcile balances against the node every N blocks (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    reconcile_every: u64,
+
+    /// Write corrective deltas when reconciliation finds drift
+    #[arg(long)]
+    auto_correct: bool,
+
+    /// Backfill mode: replay history for this address from --backfill-from, then exit
+    #[arg(long, requires = "backfill_from")]
+    backfill_address: Option<String>,
+
+    /// Backfill the (token, backfill-address) pair instead of ETH
+    #[arg(long, requires = "backfill_address")]
+    backfill_token: Option<String>,
+
+    /// Historical block to backfill from (requires an archive node)
+    #[arg(long, requires = "backfill_address")]
+    backfill_from: Option<u64>,
+
+    /// Retries for retryable RPC errors (timeouts, 429/5xx, rate-limit codes)
+    #[arg(long, default_value_t = 5)]
+    rpc_max_retries: u32,
+
+    /// Default RPC request timeout in seconds
+    #[arg(long, default_value_t = 30)]
+    rpc_timeout_secs: u64,
+
+    /// Per-method RPC timeout as METHOD=SECS (repeatable, e.g. debug_traceTransaction=90)
+    #[arg(long, value_parser = parse_method_timeout)]
+    rpc_method_timeout: Vec<(String, u64)>,
+
+    /// Maximum RPC requests per second (0 = unlimited)
+    #[arg(long, default_value_t = 0.0)]
+    rpc_rate_limit: f64,
+
+    /// Burst size for --rpc-rate-limit
+    #[arg(long, default_value_t = 10)]
+    rpc_burst: u32,
+
+    /// Skip endpoints whose head trails the best endpoint by more than N blocks
+    #[arg(long, default_value_t = 5)]
+    rpc_max_lag: u64,
+
+    /// Number of endpoints that must agree on initial balances (1 = no quorum)
+    #[arg(long, default_value_t = 1)]
+    rpc_quorum: usize,
+}
+
+/// Parse a METHOD=SECS per-method timeout.
+fn parse_method_timeout(s: &str) -> Result<(String, u64), String> {
+    let (method, secs) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected METHOD=SECS, got '{}'", s))?;
+    let secs = secs
+        .parse()
+        .map_err(|_| format!("invalid timeout seconds '{}'", secs))?;
+    Ok((method.to_string(), secs))
 }
 
 #[tokio::main]
@@ -44,19 +152,104 @@ async fn main() -> Result<()> {
     let args = Args::parse();
 
     info!("Starting Ethereum address watcher");
-    info!("RPC URL: {}", args.rpc_url);
+    info!("RPC URL: {}", args.rpc_url.join(", "));
     info!("Watchlist: {:?}", args.watchlist);
     info!("Database: {:?}", args.db_path);
+    info!("Chain profile: {:?}", args.chain_profile);
+    info!("Follow mode: {}", args.follow);
+    if args.reconcile_every > 0 {
+        info!(
+            "Reconciling every {} blocks (auto-correct: {})",
+            args.reconcile_every, args.auto_correct
+        );
+    }
 
     // Create RPC client
-    let rpc = RpcClient::new(args.rpc_url);
+    let mut rpc_config = RpcConfig {
+        max_retries: args.rpc_max_retries,
+        default_timeout: Duration::from_secs(args.rpc_timeout_secs),
+        requests_per_second: Some(args.rpc_rate_limit).filter(|rps| *rps > 0.0),
+        burst: args.rpc_burst,
+        max_head_lag: args.rpc_max_lag,
+        quorum: args.rpc_quorum,
+        ..RpcConfig::default()
+    };
+    for (method, secs) in args.rpc_method_timeout {
+        rpc_config
+            .method_timeouts
+            .insert(method, Duration::from_secs(secs));
+    }
+    if let Some(rps) = rpc_config.requests_per_second {
+        info!("RPC rate limit: {} req/s (burst {})", rps, rpc_config.burst);
+    }
+    if rpc_config.quorum > 1 {
+        info!("Initial balances require {} agreeing endpoints", rpc_config.quorum);
+    }
+    let rpc = RpcClient::with_endpoints(args.rpc_url, rpc_config)
+        .context("Invalid RPC endpoint configuration")?;
 
     // Open state store
     let store = RocksStateStore::open(&args.db_path)
         .with_context(|| format!("Failed to open database at {:?}", args.db_path))?;
 
+    // Backfill mode: extend coverage of one address or pair backwards, then exit
+    if let (Some(address), Some(from)) = (&args.backfill_address, args.backfill_from) {
+        let owner: Address = address
+            .parse()
+            .with_context(|| format!("Invalid backfill address: {}", address))?;
+        let target = match &args.backfill_token {
+            Some(token) => BackfillTarget::Erc20 {
+                token: token
+                    .parse()
+                    .with_context(|| format!("Invalid backfill token: {}", token))?,
+                owner,
+            },
+            None => BackfillTarget::Eth(owner),
+        };
+        let scratch_path = args.db_path.with_extension("backfill");
+        let report = backfill(&store, rpc, &scratch_path, target, from, args.chain_profile)
+            .await
+            .context("Backfill failed")?;
+        info!(
+            "Backfilled blocks {}..={}; coverage now starts at block {}",
+            report.from_block + 1,
+            report.join_block,
+            report.from_block
+        );
+        return Ok(());
+    }
+
     // Create watcher
-    let mut watcher = Watcher::new(store, rpc);
+    let mut watcher = Watcher::new(store, rpc)
+        .with_chain_profile(args.chain_profile)
+        .with_follow_mode(args.follow)
+        .with_reconciliation(args.reconcile_every, args.auto_correct)
+        .with_poll_interval(Duration::from_secs(args.poll_interval_secs))
+        .with_nft_tracking(args.nfts)
+        .with_approval_tracking(args.approvals)
+        .with_watch_queue(watch_queue_path(&args.db_path))
+        .with_token_discovery(args.discover_tokens)
+        .with_erc20_verification(args.verify_erc20);
+    if let Some(path) = &args.erc1155 {
+        let pairs = load_erc1155_watchlist(path)
+            .with_context(|| format!("Failed to load ERC1155 watchlist from {:?}", path))?;
+        info!("Loaded {} ERC1155 (contract, id) pairs to watch", pairs.len());
+        watcher = watcher.with_erc1155_watchlist(pairs);
+    }
+    if let Some(path) = &args.events {
+        let definitions = load_event_definitions(path)
+            .with_context(|| format!("Failed to load event definitions from {:?}", path))?;
+        for definition in &definitions {
+            info!("Tracking event {} ({:?})", definition.signature, definition.topic0);
+        }
+        watcher = watcher.with_event_tracker(EventTracker::new(definitions));
+    }
+    if let Some(ws_url) = args.ws_url {
+        info!("New heads: WebSocket subscription at {}", ws_url);
+        watcher = watcher.with_ws_url(ws_url);
+    } else {
+        info!("New heads: polling every {}s", args.poll_interval_secs);
+    }
 
     // Initialize (load watchlist, fetch initial state, optionally ERC20 tokens)
     watcher
@@ -0,0 +1,491 @@
+//! Persisted watchlist and its change queue
+//!
+//! Watched addresses and ERC20 tokens live in the store's `watchlist` column
+//! family; `watchlist.txt` and `tokens.txt` only seed it while it is empty.
+//! The watcher re-reads it between blocks and initializes new entries at its
+//! head.
+//!
+//! Removing an address ends its coverage at the store's head block
+//! (`WatchMeta.end_block`), and removing a token ends that of each of its
+//! (token, owner) pairs (`TokenWatchMeta.end_block`); their history stays
+//! queryable. Watching them again starts a new coverage segment when the
+//! watcher initializes them. The end is journaled with the head block, and
+//! after a rollback the watcher ends the coverage of removed entries at the
+//! new head instead.
+//!
+//! RocksDB allows a single writer, so while a watcher holds the database
+//! `statectl watch` appends changes to a queue file next to it instead
+//! (`<db_path>.watch-queue`, one change per line). The watcher applies queued
+//! changes before each block, and `statectl` applies them whenever it can
+//! open the database itself.
+
+use crate::batch::BlockWriteBatch;
+use crate::config::{parse_address, TokenStrategy};
+use crate::records::{TokenWatchMeta, WatchMeta};
+use crate::store::StateStore;
+use alloy_primitives::Address;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// One edit of the persisted watchlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchlistChange {
+    /// Start watching an address
+    AddAddress(Address),
+    /// Stop watching an address
+    RemoveAddress(Address),
+    /// Start watching an ERC20 token (or change its strategy)
+    AddToken(Address, TokenStrategy),
+    /// Stop watching an ERC20 token
+    RemoveToken(Address),
+}
+
+impl WatchlistChange {
+    /// Apply the change to the persisted watchlist in `store`.
+    ///
+    /// Removing an address or token records the head block as the end of
+    /// its (or its pairs') coverage. Adding it back before another block is
+    /// processed reopens that coverage segment. The coverage change and the
+    /// watchlist edit commit in one batch.
+    pub fn apply(&self, store: &dyn StateStore) -> Result<()> {
+        let Some(head) = store.get_head()? else {
+            // Nothing is covered yet, so only the watchlist changes
+            return match *self {
+                WatchlistChange::AddAddress(addr) => store.put_watched_address(addr),
+                WatchlistChange::RemoveAddress(addr) => store.delete_watched_address(addr),
+                WatchlistChange::AddToken(token, strategy) => {
+                    store.put_watched_token(token, strategy)
+                }
+                WatchlistChange::RemoveToken(token) => store.delete_watched_token(token),
+            };
+        };
+
+        let mut batch = BlockWriteBatch::amend(store, head)?;
+        match *self {
+            WatchlistChange::AddAddress(addr) => {
+                update_watch_meta(&mut batch, addr, |meta, head| {
+                    let reopen = meta.end_block == Some(head);
+                    if reopen {
+                        meta.end_block = None;
+                    }
+                    reopen
+                })?;
+                batch.put_watched_address(addr);
+            }
+            WatchlistChange::RemoveAddress(addr) => {
+                update_watch_meta(&mut batch, addr, |meta, head| {
+                    let watched = meta.is_watched();
+                    meta.unwatch(head);
+                    watched
+                })?;
+                batch.delete_watched_address(addr);
+            }
+            WatchlistChange::AddToken(token, strategy) => {
+                update_token_watch_metas(&mut batch, token, |meta, head| {
+                    let reopen = meta.end_block == Some(head);
+                    if reopen {
+                        meta.end_block = None;
+                    }
+                    reopen
+                })?;
+                batch.put_watched_token(token, strategy);
+            }
+            WatchlistChange::RemoveToken(token) => {
+                update_token_watch_metas(&mut batch, token, |meta, head| {
+                    let watched = meta.is_watched();
+                    meta.unwatch(head);
+                    watched
+                })?;
+                batch.delete_watched_token(token);
+            }
+        }
+        batch
+            .commit()
+            .with_context(|| format!("Failed to apply watchlist change '{}'", self))
+    }
+}
+
+/// Update the `WatchMeta` of `addr` in `batch`, which amends the head block.
+///
+/// `update` returns whether it changed the metadata. The write is journaled
+/// in the head block's undo record, so rolling the head back reverts it.
+/// Does nothing without metadata.
+fn update_watch_meta(
+    batch: &mut BlockWriteBatch<'_>,
+    addr: Address,
+    update: impl FnOnce(&mut WatchMeta, u64) -> bool,
+) -> Result<()> {
+    let Some(mut meta) = batch.get_watch_meta(addr)? else {
+        return Ok(());
+    };
+    if update(&mut meta, batch.block()) {
+        batch.put_watch_meta(addr, &meta)?;
+    }
+    Ok(())
+}
+
+/// Update the `TokenWatchMeta` of every (token, owner) pair of `token` in `batch`.
+///
+/// Journaled like `update_watch_meta`; `update` returns whether it changed
+/// the metadata.
+fn update_token_watch_metas(
+    batch: &mut BlockWriteBatch<'_>,
+    token: Address,
+    mut update: impl FnMut(&mut TokenWatchMeta, u64) -> bool,
+) -> Result<()> {
+    for (pair_token, owner, _) in batch.store().get_token_watch_metas()? {
+        if pair_token != token {
+            continue;
+        }
+        let Some(mut meta) = batch.get_token_watch_meta(token, owner)? else {
+            continue;
+        };
+        if update(&mut meta, batch.block()) {
+            batch.put_token_watch_meta(token, owner, &meta)?;
+        }
+    }
+    Ok(())
+}
+
+/// End the coverage of unwatched addresses and tokens at `head` after a rollback to it.
+///
+/// Rolling back the block an entry was removed at also reverts the end of
+/// its coverage, and no coverage may extend past the head. Auto-discovered
+/// pairs are tracked without their token being watched and stay open. The
+/// writes are journaled with `head`.
+pub fn end_removed_coverage(store: &dyn StateStore, head: u64) -> Result<()> {
+    let end_at = |end_block: Option<u64>| end_block.map_or(head, |end| end.min(head));
+    let watched = store.get_watched_addresses()?;
+    let mut batch = BlockWriteBatch::amend(store, head)?;
+    for (addr, mut meta) in store.get_watch_metas()? {
+        let end = end_at(meta.end_block);
+        if watched.contains(&addr) || meta.end_block == Some(end) {
+            continue;
+        }
+        meta.end_block = Some(end);
+        batch.put_watch_meta(addr, &meta)?;
+    }
+
+    let watched_tokens = store.get_watched_tokens()?;
+    for (token, owner, mut meta) in store.get_token_watch_metas()? {
+        let tracked = watched_tokens.iter().any(|(watched, _)| *watched == token)
+            || (meta.auto_discovered && meta.is_watched());
+        let end = end_at(meta.end_block);
+        if tracked || meta.end_block == Some(end) {
+            continue;
+        }
+        meta.end_block = Some(end);
+        batch.put_token_watch_meta(token, owner, &meta)?;
+    }
+    if batch.is_empty() {
+        return Ok(());
+    }
+    batch
+        .commit()
+        .with_context(|| format!("Failed to end coverage of removed entries at {}", head))
+}
+
+impl FromStr for WatchlistChange {
+    type Err = anyhow::Error;
+
+    /// Parse `add address <addr>`, `remove address <addr>`,
+    /// `add token <addr> [strategy]` or `remove token <addr>`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        match parts.as_slice() {
+            ["add", "address", addr] => Ok(WatchlistChange::AddAddress(parse_address(addr)?)),
+            ["remove", "address", addr] => Ok(WatchlistChange::RemoveAddress(parse_address(addr)?)),
+            ["add", "token", token] => Ok(WatchlistChange::AddToken(
+                parse_address(token)?,
+                TokenStrategy::default(),
+            )),
+            ["add", "token", token, strategy] => Ok(WatchlistChange::AddToken(
+                parse_address(token)?,
+                strategy.parse()?,
+            )),
+            ["remove", "token", token] => Ok(WatchlistChange::RemoveToken(parse_address(token)?)),
+            _ => anyhow::bail!("Invalid watchlist change '{}'", s),
+        }
+    }
+}
+
+impl fmt::Display for WatchlistChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchlistChange::AddAddress(addr) => write!(f, "add address 0x{:x}", addr),
+            WatchlistChange::RemoveAddress(addr) => write!(f, "remove address 0x{:x}", addr),
+            WatchlistChange::AddToken(token, strategy) => {
+                write!(f, "add token 0x{:x} {}", token, strategy)
+            }
+            WatchlistChange::RemoveToken(token) => write!(f, "remove token 0x{:x}", token),
+        }
+    }
+}
+
+/// Queue file used for changes while the database at `db_path` is held by a watcher.
+pub fn watch_queue_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("watch-queue")
+}
+
+/// Append a change to the queue file.
+pub fn queue_change(queue: &Path, change: &WatchlistChange) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(queue)
+        .with_context(|| format!("Failed to open watchlist queue {:?}", queue))?;
+    writeln!(file, "{}", change)
+        .with_context(|| format!("Failed to write watchlist queue {:?}", queue))
+}
+
+/// Changes in the queue file, oldest first (empty if there is none).
+///
+/// Fails on the first line that is not a valid change.
+pub fn queued_changes(queue: &Path) -> Result<Vec<WatchlistChange>> {
+    if !queue.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(queue)
+        .with_context(|| format!("Failed to read watchlist queue {:?}", queue))?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse().with_context(|| {
+                format!("Invalid entry {} '{}' in watchlist queue {:?}", i + 1, line, queue)
+            })
+        })
+        .collect()
+}
+
+/// File a queue is moved to while its changes are being applied.
+fn applying_path(queue: &Path) -> PathBuf {
+    queue.with_extension("watch-queue-applying")
+}
+
+/// Changes not yet applied, oldest first: those left over from an
+/// interrupted application, then those in the queue file.
+pub fn pending_changes(queue: &Path) -> Result<Vec<WatchlistChange>> {
+    let mut changes = queued_changes(&applying_path(queue))?;
+    changes.extend(queued_changes(queue)?);
+    Ok(changes)
+}
+
+/// Apply and remove all queued changes. Returns the number applied.
+///
+/// The queue is moved aside first, so changes appended meanwhile start a
+/// new queue file and are picked up next time. If applying stops at an
+/// error, the changes not yet applied stay in the moved-aside file and are
+/// finished before any newer queue is taken.
+pub fn apply_queued_changes(store: &dyn StateStore, queue: &Path) -> Result<usize> {
+    let taken = applying_path(queue);
+    let mut applied = 0;
+    if taken.exists() {
+        applied += apply_taken_changes(store, &taken)?;
+    }
+    if queue.exists() {
+        fs::rename(queue, &taken)
+            .with_context(|| format!("Failed to take watchlist queue {:?}", queue))?;
+        applied += apply_taken_changes(store, &taken)?;
+    }
+    Ok(applied)
+}
+
+/// Apply the changes of a moved-aside queue file, then remove it.
+///
+/// On a failed change the file is rewritten with it and the changes after it.
+fn apply_taken_changes(store: &dyn StateStore, taken: &Path) -> Result<usize> {
+    let changes = queued_changes(taken)?;
+    for (i, change) in changes.iter().enumerate() {
+        if let Err(e) = change.apply(store) {
+            let remaining: String = changes[i..]
+                .iter()
+                .map(|change| format!("{}\n", change))
+                .collect();
+            fs::write(taken, remaining)
+                .with_context(|| format!("Failed to rewrite watchlist queue {:?}", taken))?;
+            return Err(e.context(format!("Failed to apply queued watchlist change '{}'", change)));
+        }
+    }
+    fs::remove_file(taken)
+        .with_context(|| format!("Failed to remove applied watchlist queue {:?}", taken))?;
+    Ok(changes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reorg::rollback_to;
+    use crate::store::RocksStateStore;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_change_roundtrip() {
+        let addr = Address::repeat_byte(0x11);
+        for change in [
+            WatchlistChange::AddAddress(addr),
+            WatchlistChange::RemoveAddress(addr),
+            WatchlistChange::AddToken(addr, TokenStrategy::Poll(10)),
+            WatchlistChange::RemoveToken(addr),
+        ] {
+            assert_eq!(change.to_string().parse::<WatchlistChange>().unwrap(), change);
+        }
+        assert_eq!(
+            format!("add token {:?}", addr).parse::<WatchlistChange>().unwrap(),
+            WatchlistChange::AddToken(addr, TokenStrategy::Transfers)
+        );
+        assert!("add nothing".parse::<WatchlistChange>().is_err());
+    }
+
+    #[test]
+    fn test_apply_queued_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("state_db");
+        let store = RocksStateStore::open(&db_path).unwrap();
+        let queue = watch_queue_path(&db_path);
+        assert_eq!(queue, temp_dir.path().join("state_db.watch-queue"));
+
+        let (a, b, token) = (
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x02),
+            Address::repeat_byte(0xaa),
+        );
+        for change in [
+            WatchlistChange::AddAddress(a),
+            WatchlistChange::AddAddress(b),
+            WatchlistChange::AddToken(token, TokenStrategy::Shares),
+            WatchlistChange::RemoveAddress(a),
+        ] {
+            queue_change(&queue, &change).unwrap();
+        }
+        assert_eq!(queued_changes(&queue).unwrap().len(), 4);
+
+        assert_eq!(apply_queued_changes(&store, &queue).unwrap(), 4);
+        assert!(!queue.exists());
+        assert_eq!(apply_queued_changes(&store, &queue).unwrap(), 0);
+        assert_eq!(store.get_watched_addresses().unwrap(), vec![b]);
+        assert_eq!(
+            store.get_watched_tokens().unwrap(),
+            vec![(token, TokenStrategy::Shares)]
+        );
+    }
+
+    #[test]
+    fn test_leftover_queue_is_finished_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("state_db");
+        let store = RocksStateStore::open(&db_path).unwrap();
+        let queue = watch_queue_path(&db_path);
+        let (a, b) = (Address::repeat_byte(0x01), Address::repeat_byte(0x02));
+
+        // An interrupted application left its remaining changes behind
+        queue_change(&applying_path(&queue), &WatchlistChange::AddAddress(a)).unwrap();
+        queue_change(&queue, &WatchlistChange::AddAddress(b)).unwrap();
+        queue_change(&queue, &WatchlistChange::RemoveAddress(a)).unwrap();
+        assert_eq!(pending_changes(&queue).unwrap().len(), 3);
+
+        assert_eq!(apply_queued_changes(&store, &queue).unwrap(), 3);
+        assert!(!applying_path(&queue).exists());
+        assert_eq!(store.get_watched_addresses().unwrap(), vec![b]);
+
+        // An invalid line fails and keeps the file for the next attempt
+        fs::write(&queue, "add adress 0x01\n").unwrap();
+        let err = apply_queued_changes(&store, &queue).unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid entry 1"));
+        assert!(applying_path(&queue).exists());
+        assert!(apply_queued_changes(&store, &queue).is_err());
+    }
+
+    #[test]
+    fn test_remove_address_ends_coverage() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path().join("state_db")).unwrap();
+        let addr = Address::repeat_byte(0x01);
+        store.put_watch_meta(addr, &WatchMeta::new(100)).unwrap();
+        store.set_head(150).unwrap();
+
+        // Removing and adding back before the next block keeps one segment
+        WatchlistChange::RemoveAddress(addr).apply(&store).unwrap();
+        assert_eq!(store.get_watch_meta(addr).unwrap().unwrap().end_block, Some(150));
+        WatchlistChange::AddAddress(addr).apply(&store).unwrap();
+        assert_eq!(store.get_watch_meta(addr).unwrap().unwrap(), WatchMeta::new(100));
+
+        // Adding back later leaves the new segment to the watcher
+        WatchlistChange::RemoveAddress(addr).apply(&store).unwrap();
+        store.set_head(160).unwrap();
+        WatchlistChange::AddAddress(addr).apply(&store).unwrap();
+        assert_eq!(store.get_watch_meta(addr).unwrap().unwrap().end_block, Some(150));
+        assert_eq!(store.get_watched_addresses().unwrap(), vec![addr]);
+    }
+
+    #[test]
+    fn test_rollback_of_removal_ends_coverage_at_new_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path().join("state_db")).unwrap();
+        let (removed, kept) = (Address::repeat_byte(0x01), Address::repeat_byte(0x02));
+        for addr in [removed, kept] {
+            store.put_watch_meta(addr, &WatchMeta::new(100)).unwrap();
+            store.put_watched_address(addr).unwrap();
+        }
+        store.set_head(149).unwrap();
+        let mut batch = BlockWriteBatch::new(&store, 150);
+        batch.set_head(150).unwrap();
+        batch.commit().unwrap();
+
+        // Removed at head 150: the end is journaled with block 150
+        WatchlistChange::RemoveAddress(removed).apply(&store).unwrap();
+        assert_eq!(store.get_watch_meta(removed).unwrap().unwrap().end_block, Some(150));
+        rollback_to(&store, 149).unwrap();
+        assert_eq!(store.get_watch_meta(removed).unwrap().unwrap(), WatchMeta::new(100));
+        assert_eq!(store.get_watched_addresses().unwrap(), vec![kept]);
+
+        end_removed_coverage(&store, 149).unwrap();
+        assert_eq!(store.get_watch_meta(removed).unwrap().unwrap().end_block, Some(149));
+        assert_eq!(store.get_watch_meta(kept).unwrap().unwrap(), WatchMeta::new(100));
+    }
+
+    #[test]
+    fn test_token_removal_ends_pair_coverage() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RocksStateStore::open(temp_dir.path().join("state_db")).unwrap();
+        let (token, other) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let owner = Address::repeat_byte(0x01);
+        store.put_watched_token(token, TokenStrategy::Transfers).unwrap();
+        for pair_token in [token, other] {
+            store.put_token_watch_meta(pair_token, owner, &TokenWatchMeta::new(100)).unwrap();
+        }
+        store.set_head(149).unwrap();
+        let mut batch = BlockWriteBatch::new(&store, 150);
+        batch.set_head(150).unwrap();
+        batch.commit().unwrap();
+
+        // Removed at head 150: every pair of the token ends there, journaled with block 150
+        WatchlistChange::RemoveToken(token).apply(&store).unwrap();
+        let pair = store.get_token_watch_meta(token, owner).unwrap().unwrap();
+        assert_eq!(pair.end_block, Some(150));
+        assert!(store.get_watched_tokens().unwrap().is_empty());
+
+        // Added back at the same head: the segment is reopened
+        WatchlistChange::AddToken(token, TokenStrategy::Transfers).apply(&store).unwrap();
+        let pair = store.get_token_watch_meta(token, owner).unwrap().unwrap();
+        assert_eq!(pair, TokenWatchMeta::new(100));
+
+        // A rollback reverts the end; the watcher then ends it at the new head
+        WatchlistChange::RemoveToken(token).apply(&store).unwrap();
+        rollback_to(&store, 149).unwrap();
+        let pair = store.get_token_watch_meta(token, owner).unwrap().unwrap();
+        assert_eq!(pair, TokenWatchMeta::new(100));
+        end_removed_coverage(&store, 149).unwrap();
+        let pair = store.get_token_watch_meta(token, owner).unwrap().unwrap();
+        assert_eq!(pair.end_block, Some(149));
+        // A pair of an unwatched, non-discovered token also ends there
+        let other_pair = store.get_token_watch_meta(other, owner).unwrap().unwrap();
+        assert_eq!(other_pair.end_block, Some(149));
+    }
+}